@@ -0,0 +1,263 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use crate::graph::Attributes;
+use crate::graph::Edge;
+use crate::graph::Graph;
+use crate::graph::Vertex;
+
+// What happens to the value of an attribute that is not kept.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AttributePolicy {
+    // Drop the attribute.
+    Strip,
+    // Replace the value by a salted hash, so that equal values stay equal but cannot be read.
+    Hash,
+}
+
+/**
+ * How to anonymize a graph, e.g., to share it in a bug report. By default every attribute and
+ * graph property is hashed, and vertices and edges are renumbered in a shuffled order so that
+ * ids do not reveal the order the graph was built in.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Anonymization {
+    pub policy: AttributePolicy,
+    // Attribute keys whose values are kept as they are, e.g., `weight`.
+    pub keep: BTreeSet<String>,
+    pub renumber: bool,
+    // The number of random double edge swaps to make. A swap turns `a -> b` and `c -> d` into
+    // `a -> d` and `c -> b`, so it preserves every vertex's in- and out-degree.
+    pub swaps: usize,
+    // Seeds the shuffling and the swaps, and salts the hashes.
+    pub seed: u64,
+}
+
+impl Default for Anonymization {
+    fn default() -> Self {
+        Anonymization {
+            policy: AttributePolicy::Hash,
+            keep: BTreeSet::new(),
+            renumber: true,
+            swaps: 0,
+            seed: 0,
+        }
+    }
+}
+
+impl Anonymization {
+    // Parse options of the form `--strip`, `--keep=weight,pos`, `--swaps=10` or `--seed=3`.
+    pub fn parse<'a, I: Iterator<Item = &'a str>>(options: I) -> Result<Anonymization, String> {
+        let mut anonymization = Anonymization::default();
+        for option in options {
+            let (name, value) = option.split_once('=').unwrap_or((option, ""));
+            let number = || {
+                value
+                    .parse::<u64>()
+                    .map_err(|_| format!("Expected a number in {}", option))
+            };
+            match name {
+                "--strip" => anonymization.policy = AttributePolicy::Strip,
+                "--keep-ids" => anonymization.renumber = false,
+                "--keep" => anonymization.keep.extend(
+                    value
+                        .split(',')
+                        .filter(|k| !k.is_empty())
+                        .map(str::to_string),
+                ),
+                "--swaps" => anonymization.swaps = number()? as usize,
+                "--seed" => anonymization.seed = number()?,
+                _ => return Err(format!("Unknown anonymization option: {}", option)),
+            }
+        }
+        Ok(anonymization)
+    }
+}
+
+// A small deterministic generator, so that anonymized exports are reproducible.
+struct SplitMix(u64);
+
+impl SplitMix {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+// FNV-1a, salted by the seed. It is not cryptographic, but the point is to hide values from
+// casual readers, not from someone willing to guess values and compare hashes.
+fn hash(seed: u64, value: &str) -> String {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325 ^ seed;
+    for byte in value.bytes() {
+        h ^= byte as u64;
+        h = h.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("h{:016x}", h)
+}
+
+fn anonymize_attributes(attributes: &Attributes, options: &Anonymization) -> Attributes {
+    attributes
+        .iter()
+        .filter_map(|(key, value)| {
+            if options.keep.contains(key) {
+                Some((key.clone(), value.clone()))
+            } else {
+                match options.policy {
+                    AttributePolicy::Strip => None,
+                    AttributePolicy::Hash => Some((key.clone(), hash(options.seed, value))),
+                }
+            }
+        })
+        .collect()
+}
+
+/**
+ * A copy of the graph with its attributes hidden, and optionally its ids shuffled and its edges
+ * randomly swapped, but otherwise the same structure. Attribute keys are kept, since they rarely
+ * say anything sensitive and bugs often depend on them.
+ */
+pub fn anonymize(graph: &Graph, options: &Anonymization) -> Graph {
+    let mut rng = SplitMix(options.seed);
+
+    let mut vertex_ids: Vec<i64> = graph.vertices.keys().copied().collect();
+    vertex_ids.sort_unstable();
+    let mut edge_ids: Vec<i64> = graph.edges.keys().copied().collect();
+    edge_ids.sort_unstable();
+
+    let mut new_vertex_ids: BTreeMap<i64, i64> = vertex_ids.iter().map(|id| (*id, *id)).collect();
+    let mut new_edge_ids: BTreeMap<i64, i64> = edge_ids.iter().map(|id| (*id, *id)).collect();
+    if options.renumber {
+        let mut order = vertex_ids.clone();
+        rng.shuffle(&mut order);
+        new_vertex_ids = order.into_iter().zip(0..).collect();
+        let mut order = edge_ids.clone();
+        rng.shuffle(&mut order);
+        new_edge_ids = order.into_iter().zip(0..).collect();
+    }
+
+    let mut endpoints: Vec<(i64, i64)> = edge_ids
+        .iter()
+        .map(|id| (graph.edges[id].source, graph.edges[id].target))
+        .collect();
+    if endpoints.len() >= 2 {
+        for _ in 0..options.swaps {
+            let (i, j) = (rng.below(endpoints.len()), rng.below(endpoints.len()));
+            let (a, b) = endpoints[i];
+            let (c, d) = endpoints[j];
+            endpoints[i] = (a, d);
+            endpoints[j] = (c, b);
+        }
+    }
+
+    let mut anonymized = Graph::new();
+    anonymized.properties = anonymize_attributes(&graph.properties, options);
+    for id in vertex_ids.iter() {
+        let mut v = Vertex::new(new_vertex_ids[id]);
+        v.attributes = anonymize_attributes(&graph.vertices[id].attributes, options);
+        anonymized.add_vertex(v);
+    }
+    for (id, (source, target)) in edge_ids.iter().zip(endpoints) {
+        let mut e = Edge::new(
+            new_edge_ids[id],
+            new_vertex_ids[&source],
+            new_vertex_ids[&target],
+        );
+        e.attributes = anonymize_attributes(&graph.edges[id].attributes, options);
+        anonymized.add_edge(e);
+    }
+    anonymized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn degrees(g: &Graph) -> Vec<(usize, usize)> {
+        let mut degrees: Vec<(usize, usize)> = g
+            .vertices
+            .keys()
+            .map(|id| {
+                (
+                    g.edges.values().filter(|e| e.source == *id).count(),
+                    g.edges.values().filter(|e| e.target == *id).count(),
+                )
+            })
+            .collect();
+        degrees.sort_unstable();
+        degrees
+    }
+
+    fn network() -> Graph {
+        let mut g = Graph::new();
+        g.properties
+            .insert("name".to_string(), "payroll".to_string());
+        for id in 10..16 {
+            g.add_vertex(Vertex::new(id).with_attribute("label", "alice"));
+        }
+        for (i, (s, t)) in [(10, 11), (11, 12), (12, 10), (13, 14), (14, 15), (10, 15)]
+            .iter()
+            .enumerate()
+        {
+            g.add_edge(Edge::new(i as i64, *s, *t).with_attribute("weight", &i.to_string()));
+        }
+        g
+    }
+
+    #[test]
+    fn hides_values_and_keeps_structure() {
+        let g = network();
+        let options =
+            Anonymization::parse(["--keep=weight", "--swaps=20", "--seed=7"].iter().copied())
+                .unwrap();
+        let anonymized = anonymize(&g, &options);
+
+        assert_eq!(degrees(&g), degrees(&anonymized));
+        assert_eq!(
+            (0..6).collect::<BTreeSet<i64>>(),
+            anonymized.vertices.keys().copied().collect()
+        );
+        let labels: BTreeSet<&str> = anonymized
+            .vertices
+            .values()
+            .map(|v| v.attribute("label").unwrap())
+            .collect();
+        assert_eq!(1, labels.len());
+        assert!(!labels.contains("alice"));
+        assert_ne!(
+            Some(&"payroll".to_string()),
+            anonymized.properties.get("name")
+        );
+        let mut weights: Vec<&str> = anonymized
+            .edges
+            .values()
+            .map(|e| e.attribute("weight").unwrap())
+            .collect();
+        weights.sort_unstable();
+        assert_eq!(vec!["0", "1", "2", "3", "4", "5"], weights);
+
+        assert_eq!(anonymized, anonymize(&g, &options));
+    }
+
+    #[test]
+    fn strip_without_renumbering() {
+        let g = network();
+        let options = Anonymization::parse(["--strip", "--keep-ids"].iter().copied()).unwrap();
+        let anonymized = anonymize(&g, &options);
+        assert!(anonymized.vertices[&10].attributes.is_empty());
+        assert_eq!(g.edges[&5].source, anonymized.edges[&5].source);
+        assert!(Anonymization::parse(["--swaps=many"].iter().copied()).is_err());
+    }
+}
@@ -0,0 +1,347 @@
+use crate::graph::Diff;
+use crate::graph::Edge;
+use crate::graph::Graph;
+use crate::graph::GraphOperation;
+use crate::graph::Vertex;
+
+/**
+ * A GraphBackend stores a graph document and applies operations to it. Backends may differ in
+ * how the document is represented, but must be indistinguishable through this trait: the same
+ * sequence of operations produces the same diffs and the same vertices and edges.
+ */
+pub trait GraphBackend {
+    fn apply(&mut self, operation: GraphOperation) -> Diff;
+
+    // All vertices, in increasing order of id.
+    fn sorted_vertices(&self) -> Vec<Vertex>;
+
+    // All edges, in increasing order of id.
+    fn sorted_edges(&self) -> Vec<Edge>;
+}
+
+impl GraphBackend for Graph {
+    fn apply(&mut self, operation: GraphOperation) -> Diff {
+        Graph::apply(self, operation)
+    }
+
+    fn sorted_vertices(&self) -> Vec<Vertex> {
+        let mut vertices: Vec<Vertex> = self.vertices.values().cloned().collect();
+        vertices.sort_unstable_by_key(|v| v.id);
+        vertices
+    }
+
+    fn sorted_edges(&self) -> Vec<Edge> {
+        let mut edges: Vec<Edge> = self.edges.values().cloned().collect();
+        edges.sort_unstable_by_key(|e| e.id);
+        edges
+    }
+}
+
+// Backends may report the operations of a single diff in any order (e.g., the edges removed
+// along with a vertex), so diffs are compared after sorting.
+fn normalized(diff: Diff) -> Vec<GraphOperation> {
+    let mut operations: Vec<GraphOperation> = diff.primitives().cloned().collect();
+    operations.sort_by_key(|operation| match operation {
+        GraphOperation::AddVertex(v) => (0, v.id),
+        GraphOperation::RemoveVertex(v) => (1, v.id),
+        GraphOperation::ModifyVertex { new, .. } => (2, new.id),
+        GraphOperation::AddEdge(e) => (3, e.id),
+        GraphOperation::RemoveEdge(e) => (4, e.id),
+        GraphOperation::ModifyEdge { new, .. } => (5, new.id),
+        _ => unreachable!(),
+    });
+    operations
+}
+
+/**
+ * Apply the same sequence of operations to every backend, panicking at the first step where any
+ * backend's observable behavior differs from the first backend's. Each backend is given a name
+ * that is used to report which backend diverged.
+ */
+pub fn assert_backends_agree(
+    operations: &[GraphOperation],
+    backends: &mut [(&str, &mut dyn GraphBackend)],
+) {
+    if backends.is_empty() {
+        return;
+    }
+
+    for (step, operation) in operations.iter().enumerate() {
+        let diffs: Vec<Vec<GraphOperation>> = backends
+            .iter_mut()
+            .map(|(_, backend)| normalized(backend.apply(operation.clone())))
+            .collect();
+
+        let (reference_name, reference) = &backends[0];
+        for ((name, backend), diff) in backends.iter().zip(diffs.iter()).skip(1) {
+            assert_eq!(
+                diffs[0], *diff,
+                "Step {} ({:?}): diff from {} differs from {}",
+                step, operation, name, reference_name
+            );
+            assert_eq!(
+                reference.sorted_vertices(),
+                backend.sorted_vertices(),
+                "Step {} ({:?}): vertices of {} differ from {}",
+                step,
+                operation,
+                name,
+                reference_name
+            );
+            assert_eq!(
+                reference.sorted_edges(),
+                backend.sorted_edges(),
+                "Step {} ({:?}): edges of {} differ from {}",
+                step,
+                operation,
+                name,
+                reference_name
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::GraphOperation::*;
+
+    // A deliberately naive backend that stores everything in vectors, serving as an executable
+    // specification to compare real backends against.
+    #[derive(Default)]
+    struct VecBackend {
+        vertices: Vec<Vertex>,
+        edges: Vec<Edge>,
+    }
+
+    impl GraphBackend for VecBackend {
+        fn apply(&mut self, operation: GraphOperation) -> Diff {
+            let mut operations = Vec::new();
+            match operation {
+                AddVertex(v) => {
+                    if !self.vertices.iter().any(|x| x.id == v.id) {
+                        self.vertices.push(v.clone());
+                        operations.push(AddVertex(v));
+                    }
+                }
+                RemoveVertex(v) => {
+                    if let Some(removed) = self.vertices.iter().find(|x| x.id == v.id).cloned() {
+                        self.vertices.retain(|x| x.id != v.id);
+                        operations.push(RemoveVertex(removed));
+                        for e in self
+                            .edges
+                            .iter()
+                            .filter(|e| e.source == v.id || e.target == v.id)
+                        {
+                            operations.push(RemoveEdge(e.clone()));
+                        }
+                        self.edges.retain(|e| e.source != v.id && e.target != v.id);
+                    }
+                }
+                AddEdge(e) => {
+                    if !self.edges.iter().any(|x| x.id == e.id) {
+                        self.edges.push(e.clone());
+                        operations.push(AddEdge(e));
+                    }
+                }
+                RemoveEdge(e) => {
+                    if let Some(removed) = self.edges.iter().find(|x| x.id == e.id).cloned() {
+                        self.edges.retain(|x| x.id != e.id);
+                        operations.push(RemoveEdge(removed));
+                    }
+                }
+                ModifyVertex { new, .. } => {
+                    if let Some(stored) = self.vertices.iter_mut().find(|x| x.id == new.id) {
+                        if *stored != new {
+                            let old = std::mem::replace(stored, new.clone());
+                            operations.push(ModifyVertex { old, new });
+                        }
+                    }
+                }
+                ModifyEdge { new, .. } => {
+                    if let Some(stored) = self.edges.iter_mut().find(|x| x.id == new.id) {
+                        if *stored != new {
+                            let old = std::mem::replace(stored, new.clone());
+                            operations.push(ModifyEdge { old, new });
+                        }
+                    }
+                }
+                ContractEdge(e) => {
+                    if let Some(e) = self.edges.iter().find(|x| x.id == e.id).cloned() {
+                        operations.extend(self.apply(RemoveEdge(e.clone())).operations);
+                        operations.extend(
+                            self.apply(MergeVertices {
+                                keep: Vertex::new(e.source),
+                                remove: Vertex::new(e.target),
+                            })
+                            .operations,
+                        );
+                    }
+                }
+                MergeVertices { keep, remove } => {
+                    let present = |id| self.vertices.iter().any(|x| x.id == id);
+                    if keep.id != remove.id && present(keep.id) && present(remove.id) {
+                        let rewire = |id| if id == remove.id { keep.id } else { id };
+                        for e in self.edges.iter_mut() {
+                            if e.source == remove.id || e.target == remove.id {
+                                let old = e.clone();
+                                e.source = rewire(e.source);
+                                e.target = rewire(e.target);
+                                operations.push(RemoveEdge(old));
+                                operations.push(AddEdge(e.clone()));
+                            }
+                        }
+                        operations.extend(self.apply(RemoveVertex(remove)).operations);
+                    }
+                }
+            }
+            Diff { operations }
+        }
+
+        fn sorted_vertices(&self) -> Vec<Vertex> {
+            let mut vertices = self.vertices.clone();
+            vertices.sort_unstable_by_key(|v| v.id);
+            vertices
+        }
+
+        fn sorted_edges(&self) -> Vec<Edge> {
+            let mut edges = self.edges.clone();
+            edges.sort_unstable_by_key(|e| e.id);
+            edges
+        }
+    }
+
+    // Generate a reproducible sequence of valid operations from a seed, mixing additions and
+    // removals of a small pool of ids so that collisions and cascades are common.
+    fn random_operations(seed: u64, count: usize) -> Vec<GraphOperation> {
+        let mut state = seed;
+        let mut next = move |bound: i64| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((state >> 33) as i64) % bound
+        };
+
+        let mut reference = Graph::new();
+        let mut operations = Vec::new();
+        while operations.len() < count {
+            let operation = match next(8) {
+                0 => AddVertex(Vertex::new(next(10))),
+                1 => RemoveVertex(Vertex::new(next(10))),
+                2 => MergeVertices {
+                    keep: Vertex::new(next(10)),
+                    remove: Vertex::new(next(10)),
+                },
+                3 => match reference.sorted_edges().last() {
+                    Some(e) => ContractEdge(e.clone()),
+                    None => continue,
+                },
+                5 => {
+                    let new = Vertex::new(next(10)).with_attribute("label", &next(3).to_string());
+                    ModifyVertex {
+                        old: Vertex::new(new.id),
+                        new,
+                    }
+                }
+                6 => match reference.sorted_edges().first() {
+                    Some(e) => ModifyEdge {
+                        old: e.clone(),
+                        new: e.clone().with_attribute("weight", &next(3).to_string()),
+                    },
+                    None => continue,
+                },
+                4 => {
+                    let vertices: Vec<i64> =
+                        reference.sorted_vertices().iter().map(|v| v.id).collect();
+                    if vertices.is_empty() {
+                        continue;
+                    }
+                    AddEdge(Edge::new(
+                        next(20),
+                        vertices[next(vertices.len() as i64) as usize],
+                        vertices[next(vertices.len() as i64) as usize],
+                    ))
+                }
+                _ => match reference.sorted_edges().first() {
+                    Some(e) => RemoveEdge(e.clone()),
+                    None => continue,
+                },
+            };
+            reference.apply(operation.clone());
+            operations.push(operation);
+        }
+        operations
+    }
+
+    #[test]
+    fn hash_map_backend_agrees_with_reference() {
+        for seed in 0..20 {
+            let operations = random_operations(seed, 200);
+            let mut graph = Graph::new();
+            let mut reference = VecBackend::default();
+            assert_backends_agree(
+                &operations,
+                &mut [("HashMap", &mut graph), ("reference", &mut reference)],
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "vertices of broken differ from reference")]
+    fn divergence_is_reported() {
+        // A backend that forgets every vertex it is given.
+        struct Forgetful;
+        impl GraphBackend for Forgetful {
+            fn apply(&mut self, operation: GraphOperation) -> Diff {
+                Diff {
+                    operations: vec![operation],
+                }
+            }
+            fn sorted_vertices(&self) -> Vec<Vertex> {
+                Vec::new()
+            }
+            fn sorted_edges(&self) -> Vec<Edge> {
+                Vec::new()
+            }
+        }
+
+        assert_backends_agree(
+            &[AddVertex(Vertex::new(1))],
+            &mut [
+                ("reference", &mut VecBackend::default()),
+                ("broken", &mut Forgetful),
+            ],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "vertices of broken differ from reference")]
+    fn attribute_divergence_is_reported() {
+        // A backend that reports the right diffs but loses the attributes of its vertices.
+        #[derive(Default)]
+        struct Unlabelled(Graph);
+        impl GraphBackend for Unlabelled {
+            fn apply(&mut self, operation: GraphOperation) -> Diff {
+                self.0.apply(operation)
+            }
+            fn sorted_vertices(&self) -> Vec<Vertex> {
+                self.0
+                    .sorted_vertices()
+                    .iter()
+                    .map(|v| Vertex::new(v.id))
+                    .collect()
+            }
+            fn sorted_edges(&self) -> Vec<Edge> {
+                self.0.sorted_edges()
+            }
+        }
+
+        assert_backends_agree(
+            &[AddVertex(Vertex::new(1).with_attribute("label", "a"))],
+            &mut [
+                ("reference", &mut VecBackend::default()),
+                ("broken", &mut Unlabelled::default()),
+            ],
+        );
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::graph::Attributes;
+use crate::graph::Graph;
+use crate::graph::Vertex;
+
+type Expression = Arc<dyn Fn(&Graph, &Vertex) -> Option<String> + Send + Sync>;
+
+/**
+ * A ComputedAttribute is a vertex attribute derived from the document rather than stored in it.
+ * Computed attributes are evaluated when they are read, so they can never go stale, and since
+ * they are not part of the document they never appear in a Diff.
+ */
+#[derive(Clone)]
+pub enum ComputedAttribute {
+    // The number of incident edges, counting a self-loop twice.
+    Degree,
+    InDegree,
+    OutDegree,
+    // The smallest vertex id in the vertex's weakly connected component.
+    ComponentId,
+    // A user-provided function of the document and the vertex.
+    Expression(Expression),
+}
+
+impl fmt::Debug for ComputedAttribute {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ComputedAttribute::Degree => write!(f, "Degree"),
+            ComputedAttribute::InDegree => write!(f, "InDegree"),
+            ComputedAttribute::OutDegree => write!(f, "OutDegree"),
+            ComputedAttribute::ComponentId => write!(f, "ComponentId"),
+            ComputedAttribute::Expression(_) => write!(f, "Expression(..)"),
+        }
+    }
+}
+
+impl ComputedAttribute {
+    pub fn expression<F>(f: F) -> ComputedAttribute
+    where
+        F: Fn(&Graph, &Vertex) -> Option<String> + Send + Sync + 'static,
+    {
+        ComputedAttribute::Expression(Arc::new(f))
+    }
+
+    // The built-in computed attribute with the given name, if any.
+    pub fn builtin(name: &str) -> Option<ComputedAttribute> {
+        match name {
+            "degree" => Some(ComputedAttribute::Degree),
+            "in_degree" => Some(ComputedAttribute::InDegree),
+            "out_degree" => Some(ComputedAttribute::OutDegree),
+            "component_id" => Some(ComputedAttribute::ComponentId),
+            _ => None,
+        }
+    }
+
+    fn evaluate(&self, pass: &mut Pass, v: &Vertex) -> Option<String> {
+        match self {
+            ComputedAttribute::Degree => {
+                let (in_degree, out_degree) = pass.degree(v.id);
+                Some((in_degree + out_degree).to_string())
+            }
+            ComputedAttribute::InDegree => Some(pass.degree(v.id).0.to_string()),
+            ComputedAttribute::OutDegree => Some(pass.degree(v.id).1.to_string()),
+            ComputedAttribute::ComponentId => pass.component_id(v.id).map(|id| id.to_string()),
+            ComputedAttribute::Expression(f) => f(pass.graph, v),
+        }
+    }
+}
+
+/**
+ * What the built-in attributes are computed from, worked out at most once for each evaluation of
+ * a graph, and then looked up for each vertex, so that materializing the whole graph takes time
+ * linear in its size.
+ */
+struct Pass<'a> {
+    graph: &'a Graph,
+    // The in- and out-degree of each vertex with an incident edge.
+    degrees: Option<HashMap<i64, (usize, usize)>>,
+    components: Option<HashMap<i64, i64>>,
+}
+
+impl<'a> Pass<'a> {
+    fn new(graph: &'a Graph) -> Pass<'a> {
+        Pass {
+            graph,
+            degrees: None,
+            components: None,
+        }
+    }
+
+    fn degree(&mut self, id: i64) -> (usize, usize) {
+        let graph = self.graph;
+        let degrees = self.degrees.get_or_insert_with(|| {
+            let mut degrees: HashMap<i64, (usize, usize)> = HashMap::new();
+            for e in graph.edges.values() {
+                degrees.entry(e.target).or_default().0 += 1;
+                degrees.entry(e.source).or_default().1 += 1;
+            }
+            degrees
+        });
+        degrees.get(&id).copied().unwrap_or_default()
+    }
+
+    fn component_id(&mut self, id: i64) -> Option<i64> {
+        let graph = self.graph;
+        self.components
+            .get_or_insert_with(|| component_ids(graph))
+            .get(&id)
+            .copied()
+    }
+}
+
+// Map each vertex to the smallest vertex id in its weakly connected component.
+fn component_ids(graph: &Graph) -> HashMap<i64, i64> {
+    let mut neighbors: HashMap<i64, Vec<i64>> = HashMap::new();
+    for e in graph.edges.values() {
+        neighbors.entry(e.source).or_default().push(e.target);
+        neighbors.entry(e.target).or_default().push(e.source);
+    }
+
+    let mut ids: Vec<i64> = graph.vertices.keys().copied().collect();
+    ids.sort_unstable();
+
+    let mut components = HashMap::new();
+    let mut seen = HashSet::new();
+    for root in ids {
+        if !seen.insert(root) {
+            continue;
+        }
+        let mut stack = vec![root];
+        while let Some(id) = stack.pop() {
+            components.insert(id, root);
+            for next in neighbors.get(&id).into_iter().flatten() {
+                if seen.insert(*next) {
+                    stack.push(*next);
+                }
+            }
+        }
+    }
+    components
+}
+
+// The set of computed attributes declared for a document, by attribute name.
+#[derive(Debug, Clone, Default)]
+pub struct ComputedAttributes {
+    attributes: BTreeMap<String, ComputedAttribute>,
+}
+
+impl ComputedAttributes {
+    pub fn new() -> ComputedAttributes {
+        ComputedAttributes::default()
+    }
+
+    pub fn declare(&mut self, name: &str, attribute: ComputedAttribute) {
+        self.attributes.insert(name.to_string(), attribute);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<ComputedAttribute> {
+        self.attributes.remove(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.attributes.keys().map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.attributes.is_empty()
+    }
+
+    // The values of all computed attributes for one vertex.
+    pub fn evaluate(&self, graph: &Graph, vertex_id: i64) -> Attributes {
+        match graph.vertices.get(&vertex_id) {
+            Some(v) => self.evaluate_in(&mut Pass::new(graph), v),
+            None => Attributes::new(),
+        }
+    }
+
+    fn evaluate_in(&self, pass: &mut Pass, v: &Vertex) -> Attributes {
+        let mut values = Attributes::new();
+        for (name, attribute) in self.attributes.iter() {
+            if let Some(value) = attribute.evaluate(pass, v) {
+                values.insert(name.clone(), value);
+            }
+        }
+        values
+    }
+
+    /**
+     * A copy of the graph in which every vertex also carries its computed attributes, e.g., for
+     * export. A computed attribute takes precedence over a stored attribute of the same name.
+     */
+    pub fn materialize(&self, graph: &Graph) -> Graph {
+        let mut materialized = graph.clone();
+        if self.is_empty() {
+            return materialized;
+        }
+        let mut pass = Pass::new(graph);
+        for v in materialized.vertices.values_mut() {
+            v.attributes
+                .extend(self.evaluate_in(&mut pass, &graph.vertices[&v.id]));
+        }
+        materialized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Edge;
+
+    fn two_components() -> Graph {
+        // 0 -> 1 -> 1, and 2 -> 3
+        let mut g = Graph::new();
+        for id in 0..4 {
+            g.add_vertex(Vertex::new(id));
+        }
+        g.add_edge(Edge::new(0, 0, 1));
+        g.add_edge(Edge::new(1, 1, 1));
+        g.add_edge(Edge::new(2, 3, 2));
+        g
+    }
+
+    #[test]
+    fn builtin_attributes() {
+        let g = two_components();
+        let mut computed = ComputedAttributes::new();
+        for name in ["degree", "in_degree", "out_degree", "component_id"].iter() {
+            computed.declare(name, ComputedAttribute::builtin(name).unwrap());
+        }
+
+        let values = computed.evaluate(&g, 1);
+        assert_eq!(Some("3"), values.get("degree").map(String::as_str));
+        assert_eq!(Some("2"), values.get("in_degree").map(String::as_str));
+        assert_eq!(Some("1"), values.get("out_degree").map(String::as_str));
+        assert_eq!(Some("0"), values.get("component_id").map(String::as_str));
+        assert_eq!(
+            Some("2"),
+            computed
+                .evaluate(&g, 3)
+                .get("component_id")
+                .map(String::as_str)
+        );
+    }
+
+    #[test]
+    fn materialize_does_not_change_document() {
+        let g = two_components();
+        let mut computed = ComputedAttributes::new();
+        computed.declare(
+            "parity",
+            ComputedAttribute::expression(|_, v| Some((v.id % 2).to_string())),
+        );
+
+        let materialized = computed.materialize(&g);
+
+        assert_eq!(Some("1"), materialized.vertices[&3].attribute("parity"));
+        assert_eq!(None, g.vertices[&3].attribute("parity"));
+    }
+
+    #[test]
+    fn materialize_agrees_with_evaluate() {
+        let g = two_components();
+        let mut computed = ComputedAttributes::new();
+        for name in ["degree", "in_degree", "out_degree", "component_id"].iter() {
+            computed.declare(name, ComputedAttribute::builtin(name).unwrap());
+        }
+
+        let materialized = computed.materialize(&g);
+
+        for id in 0..4 {
+            assert_eq!(
+                computed.evaluate(&g, id),
+                materialized.vertices[&id].attributes
+            );
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+
+use crate::graph::Attributes;
+use crate::graph::Diff;
+use crate::graph::GraphOperation;
+use crate::schema::ElementKind;
+
+// One change of one attribute's value. A value of None means the attribute was absent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AttributeChange {
+    pub old: Option<String>,
+    pub new: Option<String>,
+    pub at: SystemTime,
+}
+
+/**
+ * The values each attribute of each element has had, in the order the document went through
+ * them. Undoing an edit changes the document too, so it is recorded like any other change.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct AttributeHistory {
+    changes: BTreeMap<(ElementKind, i64, String), Vec<AttributeChange>>,
+}
+
+impl AttributeHistory {
+    pub fn new() -> AttributeHistory {
+        AttributeHistory::default()
+    }
+
+    // The changes of an attribute, from oldest to newest.
+    pub fn changes(&self, kind: ElementKind, id: i64, key: &str) -> &[AttributeChange] {
+        self.changes
+            .get(&(kind, id, key.to_string()))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub fn record(&mut self, diff: &Diff) {
+        let at = SystemTime::now();
+        let empty = Attributes::new();
+        for op in diff.primitives() {
+            let (kind, id, old, new) = match op {
+                GraphOperation::AddVertex(v) => (ElementKind::Vertex, v.id, &empty, &v.attributes),
+                GraphOperation::RemoveVertex(v) => {
+                    (ElementKind::Vertex, v.id, &v.attributes, &empty)
+                }
+                GraphOperation::ModifyVertex { old, new } => (
+                    ElementKind::Vertex,
+                    new.id,
+                    &old.attributes,
+                    &new.attributes,
+                ),
+                GraphOperation::AddEdge(e) => (ElementKind::Edge, e.id, &empty, &e.attributes),
+                GraphOperation::RemoveEdge(e) => (ElementKind::Edge, e.id, &e.attributes, &empty),
+                GraphOperation::ModifyEdge { old, new } => {
+                    (ElementKind::Edge, new.id, &old.attributes, &new.attributes)
+                }
+                _ => continue,
+            };
+            for key in old
+                .keys()
+                .chain(new.keys().filter(|k| !old.contains_key(*k)))
+            {
+                let (before, after) = (old.get(key), new.get(key));
+                if before != after {
+                    self.changes
+                        .entry((kind, id, key.clone()))
+                        .or_default()
+                        .push(AttributeChange {
+                            old: before.cloned(),
+                            new: after.cloned(),
+                            at,
+                        });
+                }
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.changes.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Edge;
+
+    #[test]
+    fn records_each_change_of_an_attribute() {
+        let e = Edge::new(3, 0, 1).with_attribute("label", "x");
+        let weighted = |w: &str| e.clone().with_attribute("weight", w);
+        let mut history = AttributeHistory::new();
+        history.record(&Diff {
+            operations: vec![GraphOperation::AddEdge(e.clone())],
+        });
+        history.record(&Diff {
+            operations: vec![GraphOperation::ModifyEdge {
+                old: e.clone(),
+                new: weighted("2"),
+            }],
+        });
+        history.record(&Diff {
+            operations: vec![GraphOperation::RemoveEdge(weighted("2"))],
+        });
+
+        let values: Vec<(Option<&str>, Option<&str>)> = history
+            .changes(ElementKind::Edge, 3, "weight")
+            .iter()
+            .map(|c| (c.old.as_deref(), c.new.as_deref()))
+            .collect();
+        assert_eq!(vec![(None, Some("2")), (Some("2"), None)], values);
+        assert_eq!(2, history.changes(ElementKind::Edge, 3, "label").len());
+        assert!(history.changes(ElementKind::Vertex, 3, "weight").is_empty());
+    }
+}
@@ -0,0 +1,126 @@
+use std::fmt;
+
+use crate::editor::keymap::key_notation;
+use crate::editor::keymap::Keymap;
+use crate::editor::keymap::KeymapMode;
+use crate::editor::keys::*;
+use crate::editor::mode::EditorMode;
+use crate::editor::mode::ModalOperation;
+use crate::editor::mode::Selection;
+use crate::editor::mode::TransitionResult;
+use crate::editor::state::Input;
+
+// What typing keys does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    // A mapping of the keymap types other keys instead.
+    Mapped(Vec<char>),
+    Operation(ModalOperation),
+    // The keys begin a longer command, which the mode entered awaits the rest of.
+    Enters(EditorMode),
+}
+
+// Keys that do something in a mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding {
+    pub keys: Vec<char>,
+    pub action: Action,
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ", key_notation(&self.keys))?;
+        match &self.action {
+            Action::Mapped(to) => write!(f, "types {}", key_notation(to)),
+            Action::Operation(op) => write!(f, "{:?}", op),
+            Action::Enters(mode) => write!(f, "enters {}", mode_name(mode)),
+        }
+    }
+}
+
+/**
+ * The bindings in effect in a mode: the keymap's mappings, then every key the mode itself
+ * responds to, found by trying each key on the mode, so that help is generated from the state
+ * machine rather than kept beside it. A key the keymap maps is left out of the mode's own, since
+ * the mapping takes its place. Keys that are only typed into text, such as an element chosen in
+ * Visual mode, are left out too.
+ */
+pub fn bindings(mode: KeymapMode, keymap: &Keymap) -> Vec<Binding> {
+    let mut bindings: Vec<Binding> = keymap
+        .mappings()
+        .filter(|(mapped_mode, _, _)| *mapped_mode == mode)
+        .map(|(_, keys, to)| Binding {
+            keys: keys.to_vec(),
+            action: Action::Mapped(to.to_vec()),
+        })
+        .collect();
+    let editor_mode = match mode {
+        KeymapMode::Command => EditorMode::Command,
+        KeymapMode::Insert => EditorMode::Insert,
+        KeymapMode::Visual => EditorMode::Visual(Selection::default()),
+    };
+    for key in candidate_keys() {
+        if keymap.get(mode, &[key]).is_some() {
+            continue;
+        }
+        let action = match editor_mode.clone().transition(Input::Key(key)) {
+            TransitionResult::Apply(op, _) => Action::Operation(op),
+            TransitionResult::ModeChange(next) if next == editor_mode => continue,
+            TransitionResult::ModeChange(next) if next.pending_text() == Some(&key.to_string()) => {
+                continue
+            }
+            TransitionResult::ModeChange(next) => Action::Enters(next),
+            TransitionResult::Error(_, _) => continue,
+        };
+        bindings.push(Binding {
+            keys: vec![key],
+            action,
+        });
+    }
+    bindings
+}
+
+// Every printable ASCII key, the control keys and the named keys.
+fn candidate_keys() -> impl Iterator<Item = char> {
+    const NAMED: [char; 7] = [ENTER, ESC, BACKSPACE, LEFT, RIGHT, UP, DOWN];
+    (' '..='~')
+        .chain('\u{1}'..='\u{1a}')
+        .chain(NAMED.iter().copied())
+}
+
+// The name of a mode's variant, without what has been typed in it.
+fn mode_name(mode: &EditorMode) -> String {
+    format!("{:?}", mode)
+        .chars()
+        .take_while(char::is_ascii_alphanumeric)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_come_from_the_modes_and_the_keymap() {
+        let mut keymap = Keymap::new();
+        keymap.map(KeymapMode::Command, vec!['u'], vec!['U']);
+        let listed: Vec<String> = bindings(KeymapMode::Command, &keymap)
+            .iter()
+            .map(Binding::to_string)
+            .collect();
+        assert_eq!("u types U", listed[0]);
+        assert!(listed.contains(&"U Redo".to_string()));
+        assert!(listed.contains(&"<C-r> Redo".to_string()));
+        assert!(listed.contains(&"i enters Insert".to_string()));
+        assert!(!listed.contains(&"u Undo".to_string()));
+
+        let visual = bindings(KeymapMode::Visual, &keymap);
+        assert!(visual.iter().all(|binding| binding.keys != vec!['v']));
+        assert!(visual.contains(&Binding {
+            keys: vec![ESC],
+            action: Action::Enters(EditorMode::Command),
+        }));
+        let insert = bindings(KeymapMode::Insert, &keymap);
+        assert!(insert.iter().any(|binding| binding.keys == vec!['2']));
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::BTreeSet;
+
+use crate::editor::mode::ModalOperation;
+use crate::editor::mode::Motion;
+use crate::editor::mode::Operator;
+use crate::editor::mode::Target;
+use crate::editor::references::ElementRef;
+use crate::graph::Attributes;
+
+/**
+ * An edit or movement for a program embedding the editor to carry out directly, instead of typing
+ * the keys for it. Commands leave the mode as it is, but are interpreted like the operations keys
+ * emit, so an edit is recorded in the history, can be undone, and is repeated by `.`. Vertices are
+ * given by id or by label, and edges by id.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Command {
+    // Create a vertex, labeled if a label is given.
+    AddVertex { label: Option<String> },
+    // Create an edge from the first vertex to the second.
+    AddEdgeBetween(String, String),
+    ContractEdge(String),
+    // Merge the second vertex into the first.
+    MergeVertices(String, String),
+    // Remove the elements, and the edges of removed vertices. Like `d`, this puts the subgraph
+    // they induce in the unnamed register.
+    Delete(BTreeSet<ElementRef>),
+    // Replace the attributes of an element, given like `v3` or `e0`.
+    SetAttributes(String, Attributes),
+    // Set an attribute of every element to a value.
+    SetAttribute(BTreeSet<ElementRef>, String, String),
+    // Adjust the weight of an edge by a number of steps, which may be negative.
+    AdjustWeight(String, i64),
+    MoveCursor(Motion),
+    // Move the cursor to the first vertex matching a search.
+    Search(String),
+    Undo,
+    Redo,
+    UndoAll,
+    RedoToTip,
+    // An ex command, as typed after `:`.
+    Ex(String),
+}
+
+impl From<Command> for ModalOperation {
+    fn from(command: Command) -> ModalOperation {
+        match command {
+            Command::AddVertex { label } => ModalOperation::CreateNewVertex { label },
+            Command::AddEdgeBetween(source, target) => {
+                ModalOperation::CreateNewEdge(format!("{},{}", source, target))
+            }
+            Command::ContractEdge(edge) => ModalOperation::ContractEdge(edge),
+            Command::MergeVertices(keep, remove) => {
+                ModalOperation::MergeVertices(format!("{},{}", keep, remove))
+            }
+            Command::Delete(elements) => {
+                ModalOperation::Operate(Operator::Delete, Target::Elements(elements), None)
+            }
+            Command::SetAttributes(element, attributes) => {
+                ModalOperation::SetAttributes(element, attributes)
+            }
+            Command::SetAttribute(elements, key, value) => {
+                ModalOperation::SetAttribute(Target::Elements(elements), key, value)
+            }
+            Command::AdjustWeight(edge, steps) => ModalOperation::AdjustWeight(edge, steps),
+            Command::MoveCursor(motion) => ModalOperation::MoveCursor(motion),
+            Command::Search(query) => ModalOperation::Search(query),
+            Command::Undo => ModalOperation::Undo,
+            Command::Redo => ModalOperation::Redo,
+            Command::UndoAll => ModalOperation::UndoAll,
+            Command::RedoToTip => ModalOperation::RedoToTip,
+            Command::Ex(command) => ModalOperation::ExCommand(command),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use crate::graph::Graph;
+
+// A vertex that could complete a partially typed vertex reference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub vertex: i64,
+    // The text that replaces the partial reference when the completion is accepted.
+    pub text: String,
+    pub label: Option<String>,
+}
+
+// Limits on how many candidates are offered, so completion stays cheap and readable.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CompletionBudget {
+    pub max_candidates: usize,
+}
+
+impl Default for CompletionBudget {
+    fn default() -> Self {
+        CompletionBudget { max_candidates: 10 }
+    }
+}
+
+// How well a vertex matches a prefix; smaller is better.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum MatchQuality {
+    ExactId,
+    IdPrefix,
+    LabelPrefix,
+}
+
+/**
+ * Rank the vertices matching `prefix` by id or by label (case-insensitively). Better matches come
+ * first; among equally good matches, recently used vertices come first, most recent first, and
+ * the rest are ordered by id. `recent` lists vertices from least to most recently used.
+ */
+pub fn complete(
+    graph: &Graph,
+    recent: &[i64],
+    prefix: &str,
+    budget: CompletionBudget,
+) -> Vec<Completion> {
+    let prefix = prefix.trim().to_lowercase();
+    let recency = |id: i64| {
+        recent
+            .iter()
+            .rev()
+            .position(|r| *r == id)
+            .unwrap_or(usize::MAX)
+    };
+
+    let mut ranked: Vec<(MatchQuality, usize, i64, Completion)> = graph
+        .vertices
+        .values()
+        .filter_map(|v| {
+            let id = v.id.to_string();
+            let label = v.attribute("label").map(str::to_string);
+            let quality = if id == prefix {
+                MatchQuality::ExactId
+            } else if id.starts_with(&prefix) {
+                MatchQuality::IdPrefix
+            } else if label
+                .as_ref()
+                .is_some_and(|l| l.to_lowercase().starts_with(&prefix))
+            {
+                MatchQuality::LabelPrefix
+            } else {
+                return None;
+            };
+            Some((
+                quality,
+                recency(v.id),
+                v.id,
+                Completion {
+                    vertex: v.id,
+                    text: id,
+                    label,
+                },
+            ))
+        })
+        .collect();
+
+    ranked.sort_by_key(|a| (a.0, a.1, a.2));
+    ranked.truncate(budget.max_candidates);
+    ranked.into_iter().map(|(_, _, _, c)| c).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Vertex;
+
+    fn vertices(completions: &[Completion]) -> Vec<i64> {
+        completions.iter().map(|c| c.vertex).collect()
+    }
+
+    #[test]
+    fn ranks_by_match_then_recency() {
+        let mut g = Graph::new();
+        for id in [1, 10, 11, 12, 2].iter() {
+            g.add_vertex(Vertex::new(*id));
+        }
+        g.add_vertex(Vertex::new(3).with_attribute("label", "1st street"));
+
+        let budget = CompletionBudget::default();
+        assert_eq!(
+            vec![1, 12, 10, 11, 3],
+            vertices(&complete(&g, &[12], "1", budget))
+        );
+        assert_eq!(vec![3], vertices(&complete(&g, &[], "1ST", budget)));
+        assert!(complete(&g, &[], "x", budget).is_empty());
+    }
+
+    #[test]
+    fn respects_budget() {
+        let mut g = Graph::new();
+        for id in 0..100 {
+            g.add_vertex(Vertex::new(id));
+        }
+
+        let completions = complete(&g, &[], "", CompletionBudget { max_candidates: 3 });
+
+        assert_eq!(vec![0, 1, 2], vertices(&completions));
+    }
+}
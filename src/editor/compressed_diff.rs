@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+use std::mem::size_of;
+
+use crate::graph::Attributes;
+use crate::graph::Diff;
+use crate::graph::Edge;
+use crate::graph::GraphOperation;
+use crate::graph::Vertex;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Kind {
+    AddVertex,
+    RemoveVertex,
+    ModifyVertex,
+    MergeVertices,
+    AddEdge,
+    RemoveEdge,
+    ModifyEdge,
+    ContractEdge,
+}
+
+/**
+ * A diff stored compactly, for keeping many of them in the history. Rather than a struct per
+ * operation, the fields of the operations are kept in a few flat arrays, and each distinct
+ * attribute key or value is stored once however many elements of the diff have it, so that,
+ * e.g., the attributes a modified element kept are not stored twice. Operations are rebuilt on
+ * demand by `to_diff`.
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "Diff", into = "Diff")
+)]
+pub struct CompressedDiff {
+    kinds: Vec<Kind>,
+    // For each element an operation mentions, in order: its id, then for an edge its source and
+    // target.
+    ids: Vec<i64>,
+    // For each element, the number of its attributes.
+    attribute_counts: Vec<u32>,
+    // For each attribute, in order, the positions of its key and value in `strings`.
+    attributes: Vec<(u32, u32)>,
+    strings: Vec<Box<str>>,
+}
+
+struct Encoder<'a> {
+    compressed: CompressedDiff,
+    positions: HashMap<&'a str, u32>,
+}
+
+impl<'a> Encoder<'a> {
+    fn intern(&mut self, s: &'a str) -> u32 {
+        let strings = &mut self.compressed.strings;
+        *self.positions.entry(s).or_insert_with(|| {
+            strings.push(s.into());
+            (strings.len() - 1) as u32
+        })
+    }
+
+    fn attributes(&mut self, attributes: &'a Attributes) {
+        self.compressed
+            .attribute_counts
+            .push(attributes.len() as u32);
+        for (key, value) in attributes.iter() {
+            let pair = (self.intern(key), self.intern(value));
+            self.compressed.attributes.push(pair);
+        }
+    }
+
+    fn vertex(&mut self, v: &'a Vertex) {
+        self.compressed.ids.push(v.id);
+        self.attributes(&v.attributes);
+    }
+
+    fn edge(&mut self, e: &'a Edge) {
+        self.compressed
+            .ids
+            .extend([e.id, e.source, e.target].iter().copied());
+        self.attributes(&e.attributes);
+    }
+}
+
+struct Decoder<'a> {
+    compressed: &'a CompressedDiff,
+    ids: std::slice::Iter<'a, i64>,
+    attribute_counts: std::slice::Iter<'a, u32>,
+    attributes: std::slice::Iter<'a, (u32, u32)>,
+}
+
+impl<'a> Decoder<'a> {
+    fn id(&mut self) -> i64 {
+        *self.ids.next().expect("Compressed diff is missing an id")
+    }
+
+    fn attributes(&mut self) -> Attributes {
+        let count = *self
+            .attribute_counts
+            .next()
+            .expect("Compressed diff is missing attributes");
+        let strings = &self.compressed.strings;
+        (&mut self.attributes)
+            .take(count as usize)
+            .map(|(key, value)| {
+                (
+                    strings[*key as usize].to_string(),
+                    strings[*value as usize].to_string(),
+                )
+            })
+            .collect()
+    }
+
+    fn vertex(&mut self) -> Vertex {
+        let id = self.id();
+        Vertex {
+            id,
+            attributes: self.attributes(),
+        }
+    }
+
+    fn edge(&mut self) -> Edge {
+        let (id, source, target) = (self.id(), self.id(), self.id());
+        Edge {
+            id,
+            source,
+            target,
+            attributes: self.attributes(),
+        }
+    }
+}
+
+impl CompressedDiff {
+    pub fn new(diff: &Diff) -> CompressedDiff {
+        let mut encoder = Encoder {
+            compressed: CompressedDiff::default(),
+            positions: HashMap::new(),
+        };
+        for op in diff.operations.iter() {
+            let kind = match op {
+                GraphOperation::AddVertex(v) => {
+                    encoder.vertex(v);
+                    Kind::AddVertex
+                }
+                GraphOperation::RemoveVertex(v) => {
+                    encoder.vertex(v);
+                    Kind::RemoveVertex
+                }
+                GraphOperation::ModifyVertex { old, new } => {
+                    encoder.vertex(old);
+                    encoder.vertex(new);
+                    Kind::ModifyVertex
+                }
+                GraphOperation::MergeVertices { keep, remove } => {
+                    encoder.vertex(keep);
+                    encoder.vertex(remove);
+                    Kind::MergeVertices
+                }
+                GraphOperation::AddEdge(e) => {
+                    encoder.edge(e);
+                    Kind::AddEdge
+                }
+                GraphOperation::RemoveEdge(e) => {
+                    encoder.edge(e);
+                    Kind::RemoveEdge
+                }
+                GraphOperation::ModifyEdge { old, new } => {
+                    encoder.edge(old);
+                    encoder.edge(new);
+                    Kind::ModifyEdge
+                }
+                GraphOperation::ContractEdge(e) => {
+                    encoder.edge(e);
+                    Kind::ContractEdge
+                }
+            };
+            encoder.compressed.kinds.push(kind);
+        }
+        let mut compressed = encoder.compressed;
+        compressed.shrink_to_fit();
+        compressed
+    }
+
+    // The number of operations.
+    pub fn len(&self) -> usize {
+        self.kinds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.kinds.is_empty()
+    }
+
+    pub fn to_diff(&self) -> Diff {
+        let mut decoder = Decoder {
+            compressed: self,
+            ids: self.ids.iter(),
+            attribute_counts: self.attribute_counts.iter(),
+            attributes: self.attributes.iter(),
+        };
+        let operations = self
+            .kinds
+            .iter()
+            .map(|kind| match kind {
+                Kind::AddVertex => GraphOperation::AddVertex(decoder.vertex()),
+                Kind::RemoveVertex => GraphOperation::RemoveVertex(decoder.vertex()),
+                Kind::ModifyVertex => GraphOperation::ModifyVertex {
+                    old: decoder.vertex(),
+                    new: decoder.vertex(),
+                },
+                Kind::MergeVertices => GraphOperation::MergeVertices {
+                    keep: decoder.vertex(),
+                    remove: decoder.vertex(),
+                },
+                Kind::AddEdge => GraphOperation::AddEdge(decoder.edge()),
+                Kind::RemoveEdge => GraphOperation::RemoveEdge(decoder.edge()),
+                Kind::ModifyEdge => GraphOperation::ModifyEdge {
+                    old: decoder.edge(),
+                    new: decoder.edge(),
+                },
+                Kind::ContractEdge => GraphOperation::ContractEdge(decoder.edge()),
+            })
+            .collect();
+        Diff { operations }
+    }
+
+    // The memory the diff occupies, including its strings.
+    pub fn estimated_bytes(&self) -> usize {
+        size_of::<CompressedDiff>()
+            + self.kinds.len() * size_of::<Kind>()
+            + self.ids.len() * size_of::<i64>()
+            + self.attribute_counts.len() * size_of::<u32>()
+            + self.attributes.len() * size_of::<(u32, u32)>()
+            + self
+                .strings
+                .iter()
+                .map(|s| s.len() + size_of::<Box<str>>())
+                .sum::<usize>()
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.kinds.shrink_to_fit();
+        self.ids.shrink_to_fit();
+        self.attribute_counts.shrink_to_fit();
+        self.attributes.shrink_to_fit();
+        self.strings.shrink_to_fit();
+    }
+}
+
+impl From<Diff> for CompressedDiff {
+    fn from(diff: Diff) -> CompressedDiff {
+        CompressedDiff::new(&diff)
+    }
+}
+
+impl From<CompressedDiff> for Diff {
+    fn from(compressed: CompressedDiff) -> Diff {
+        compressed.to_diff()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_shares_strings() {
+        let old = Vertex::new(3)
+            .with_attribute("label", "hub")
+            .with_attribute("color", "red");
+        let new = old.clone().with_attribute("color", "blue");
+        let e = Edge::new(0, 3, 4).with_attribute("label", "hub");
+        let diff = Diff {
+            operations: vec![
+                GraphOperation::AddVertex(Vertex::new(4)),
+                GraphOperation::ModifyVertex {
+                    old: old.clone(),
+                    new: new.clone(),
+                },
+                GraphOperation::AddEdge(e.clone()),
+                GraphOperation::ContractEdge(e.clone()),
+                GraphOperation::MergeVertices {
+                    keep: old.clone(),
+                    remove: Vertex::new(4),
+                },
+                GraphOperation::ModifyEdge {
+                    old: e.clone(),
+                    new: e.clone().with_attribute("weight", "2"),
+                },
+                GraphOperation::RemoveEdge(e),
+                GraphOperation::RemoveVertex(new),
+            ],
+        };
+
+        let compressed = CompressedDiff::new(&diff);
+        assert_eq!(diff.operations, compressed.to_diff().operations);
+        assert_eq!(8, compressed.len());
+        // label, hub, color, red, blue, weight and 2.
+        assert_eq!(7, compressed.strings.len());
+    }
+}
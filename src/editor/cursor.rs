@@ -0,0 +1,103 @@
+use std::collections::BTreeSet;
+use std::ops::Bound;
+
+use crate::editor::mode::Motion;
+use crate::graph::Graph;
+
+/**
+ * The vertex a motion moves the cursor to from a vertex, if it can move. Without a vertex to
+ * move from, every motion but a jump moves to the vertex with the smallest id. Where a vertex has
+ * several edges to follow, the one with the smallest id is followed.
+ */
+pub fn motion_target(graph: &Graph, from: Option<i64>, motion: Motion) -> Option<i64> {
+    let from = from.filter(|id| graph.vertices.contains_key(id));
+    let ids = || graph.vertices.keys().copied();
+    match (motion, from) {
+        (Motion::FirstVertex, _) => ids().min(),
+        (Motion::LastVertex, _) => ids().max(),
+        (Motion::NextSource, _) => next_matching(ids(), from, |id| {
+            !graph.edges.values().any(|e| e.target == id)
+        }),
+        (Motion::NextSink, _) => next_matching(ids(), from, |id| {
+            !graph.edges.values().any(|e| e.source == id)
+        }),
+        (_, None) => ids().min(),
+        (Motion::NextVertex, Some(from)) => ids().filter(|id| *id > from).min(),
+        (Motion::PreviousVertex, Some(from)) => ids().filter(|id| *id < from).max(),
+        (Motion::OutgoingEdge, Some(from)) => graph
+            .edges
+            .values()
+            .filter(|e| e.source == from)
+            .min_by_key(|e| e.id)
+            .map(|e| e.target),
+        (Motion::IncomingEdge, Some(from)) => graph
+            .edges
+            .values()
+            .filter(|e| e.target == from)
+            .min_by_key(|e| e.id)
+            .map(|e| e.source),
+    }
+}
+
+// The matching vertex with the next larger id than a vertex, wrapping around to the smallest
+// like vim's search.
+fn next_matching(
+    ids: impl Iterator<Item = i64>,
+    from: Option<i64>,
+    matching: impl Fn(i64) -> bool,
+) -> Option<i64> {
+    let matches: BTreeSet<i64> = ids.filter(|id| matching(*id)).collect();
+    from.and_then(|from| {
+        matches
+            .range((Bound::Excluded(from), Bound::Unbounded))
+            .next()
+            .copied()
+    })
+    .or_else(|| matches.iter().next().copied())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Edge;
+    use crate::graph::Vertex;
+
+    #[test]
+    fn motions_follow_ids_and_edges() {
+        let mut g = Graph::new();
+        for id in [2, 5, 9].iter() {
+            g.add_vertex(Vertex::new(*id));
+        }
+        g.add_edge(Edge::new(1, 5, 9));
+        g.add_edge(Edge::new(0, 5, 2));
+
+        assert_eq!(Some(2), motion_target(&g, None, Motion::OutgoingEdge));
+        assert_eq!(Some(9), motion_target(&g, Some(5), Motion::NextVertex));
+        assert_eq!(None, motion_target(&g, Some(9), Motion::NextVertex));
+        assert_eq!(Some(2), motion_target(&g, Some(5), Motion::PreviousVertex));
+        assert_eq!(Some(2), motion_target(&g, Some(5), Motion::OutgoingEdge));
+        assert_eq!(Some(5), motion_target(&g, Some(9), Motion::IncomingEdge));
+        assert_eq!(None, motion_target(&g, Some(5), Motion::IncomingEdge));
+    }
+
+    #[test]
+    fn jumps_to_extremes() {
+        let mut g = Graph::new();
+        for id in [2, 5, 9, 11].iter() {
+            g.add_vertex(Vertex::new(*id));
+        }
+        g.add_edge(Edge::new(0, 2, 5));
+        g.add_edge(Edge::new(1, 11, 5));
+
+        assert_eq!(Some(2), motion_target(&g, Some(9), Motion::FirstVertex));
+        assert_eq!(Some(11), motion_target(&g, None, Motion::LastVertex));
+        // Sources and sinks are visited in order of id, wrapping around.
+        assert_eq!(Some(2), motion_target(&g, None, Motion::NextSource));
+        assert_eq!(Some(9), motion_target(&g, Some(2), Motion::NextSource));
+        assert_eq!(Some(11), motion_target(&g, Some(9), Motion::NextSource));
+        assert_eq!(Some(2), motion_target(&g, Some(11), Motion::NextSource));
+        assert_eq!(Some(5), motion_target(&g, Some(11), Motion::NextSink));
+        assert_eq!(Some(9), motion_target(&g, Some(5), Motion::NextSink));
+        assert_eq!(Some(5), motion_target(&g, Some(9), Motion::NextSink));
+    }
+}
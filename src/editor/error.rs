@@ -0,0 +1,65 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::graph::GraphError;
+use crate::schema::SchemaError;
+
+// Why an operation typed in the editor could not be carried out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditorError {
+    // A vertex reference, as typed, that names no vertex.
+    UnknownVertex(String),
+    // A vertex reference, as typed, that is the label of each of the vertices given.
+    AmbiguousVertex(String, Vec<i64>),
+    // An edge reference, as typed, that names no edge.
+    UnknownEdge(String),
+    // An element, as typed, e.g., `v3`, that is not in the document.
+    UnknownElement(String),
+    // An operation on the vertex under the cursor, without one.
+    NoCursor,
+    // Repeating the target of the last edge created, without one.
+    NoEdgeTarget,
+    // An operation on the vertex created last, without one.
+    NoCreatedVertex,
+    // A plugin could not carry out an operation, or was not allowed to.
+    Plugin(String),
+    // A change to the document while the `readonly` option is set.
+    ReadOnly,
+    // Text that is not what the operation expects, e.g., an edge without a target.
+    Malformed {
+        typed: String,
+        expected: &'static str,
+    },
+    // A change that would not apply to the document while the `strict` option is set.
+    Graph(GraphError),
+    // A change that the document's schema does not allow.
+    Schema(SchemaError),
+}
+
+impl fmt::Display for EditorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditorError::UnknownVertex(vertex) => write!(f, "Could not find vertex {}", vertex),
+            EditorError::AmbiguousVertex(label, ids) => {
+                let ids: Vec<String> = ids.iter().map(i64::to_string).collect();
+                write!(f, "{} labels vertices {}", label, ids.join(", "))
+            }
+            EditorError::UnknownEdge(edge) => write!(f, "Could not find edge {}", edge),
+            EditorError::UnknownElement(element) => write!(f, "Could not find {}", element),
+            EditorError::NoCursor => write!(f, "No vertex under the cursor"),
+            EditorError::NoEdgeTarget => {
+                write!(f, "No edge has been created to take the target of")
+            }
+            EditorError::NoCreatedVertex => write!(f, "No vertex has been created"),
+            EditorError::Plugin(reason) => write!(f, "{}", reason),
+            EditorError::ReadOnly => write!(f, "The document is read-only"),
+            EditorError::Malformed { typed, expected } => {
+                write!(f, "Unable to parse '{}' as {}", typed, expected)
+            }
+            EditorError::Graph(err) => write!(f, "{}", err),
+            EditorError::Schema(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for EditorError {}
@@ -0,0 +1,43 @@
+use std::sync::mpsc;
+
+use crate::editor::completion::Completion;
+use crate::editor::history::HistoryEvent;
+use crate::editor::message::Message;
+use crate::editor::mode::EditorMode;
+use crate::graph::Diff;
+
+// Notifications an EditorState sends to its subscribers as it evaluates input.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditorEvent {
+    // The completion candidates for the vertex reference being typed changed. An empty list
+    // means there is nothing to complete, e.g., because the pending mode was left.
+    Completions(Vec<Completion>),
+    // The history tree changed, e.g., because an edit was made or undone.
+    History(HistoryEvent),
+    // An error or notice was reported.
+    Message(Message),
+    // The document changed, as the diff describes, so that it can be followed without comparing
+    // whole graphs.
+    Document(Diff),
+    // The mode changed, including what is typed in a pending mode.
+    Mode(EditorMode),
+}
+
+// The set of channels events are broadcast to. Subscribers that hang up are dropped.
+#[derive(Debug, Default)]
+pub struct Subscribers {
+    senders: Vec<mpsc::Sender<EditorEvent>>,
+}
+
+impl Subscribers {
+    pub fn subscribe(&mut self) -> mpsc::Receiver<EditorEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.senders.push(sender);
+        receiver
+    }
+
+    pub fn emit(&mut self, event: EditorEvent) {
+        self.senders
+            .retain(|sender| sender.send(event.clone()).is_ok());
+    }
+}
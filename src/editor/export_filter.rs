@@ -0,0 +1,154 @@
+use crate::editor::references::ElementRef;
+use crate::editor::references::Reference;
+use crate::editor::references::References;
+use crate::graph::Attributes;
+use crate::graph::Graph;
+
+// A test that a vertex or edge passes to be exported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElementFilter {
+    // Elements with the attribute, whatever its value.
+    Has(String),
+    Equals(String, String),
+    // Elements the reference names, e.g., the selection.
+    Named(Reference),
+}
+
+impl ElementFilter {
+    /**
+     * Parse a filter of the form `key`, `key=value`, or a reference: `@selection`, `@pins`,
+     * `@cursor`, `@alias:<name>`, `@mark:<letter>` or `@cluster:<name>`.
+     */
+    pub fn parse(filter: &str) -> Result<ElementFilter, String> {
+        let reference = match filter.strip_prefix('@') {
+            Some(reference) => reference,
+            None => {
+                return match filter.split_once('=') {
+                    Some(("", _)) => Err(format!("No attribute name in {}", filter)),
+                    Some((key, value)) => {
+                        Ok(ElementFilter::Equals(key.to_string(), value.to_string()))
+                    }
+                    None if filter.is_empty() => Err("Empty filter".to_string()),
+                    None => Ok(ElementFilter::Has(filter.to_string())),
+                }
+            }
+        };
+        let (kind, name) = reference.split_once(':').unwrap_or((reference, ""));
+        let mark: Vec<char> = name.chars().collect();
+        let reference = match (kind, name) {
+            ("selection", "") => Reference::Selection,
+            ("pins", "") => Reference::Pin,
+            ("cursor", "") => Reference::Cursor,
+            ("alias", name) if !name.is_empty() => Reference::Alias(name.to_string()),
+            ("mark", _) if mark.len() == 1 => Reference::Mark(mark[0]),
+            ("cluster", name) if !name.is_empty() => Reference::Cluster(name.to_string()),
+            _ => return Err(format!("Unknown reference: {}", filter)),
+        };
+        Ok(ElementFilter::Named(reference))
+    }
+
+    fn matches(
+        &self,
+        references: &References,
+        element: ElementRef,
+        attributes: &Attributes,
+    ) -> bool {
+        match self {
+            ElementFilter::Has(key) => attributes.contains_key(key),
+            ElementFilter::Equals(key, value) => attributes.get(key) == Some(value),
+            ElementFilter::Named(reference) => {
+                references.elements(reference).any(|named| named == element)
+            }
+        }
+    }
+}
+
+/**
+ * Which part of the document to export. The vertices passing the vertex filter are exported,
+ * with the edges among them that pass the edge filter. A missing filter passes everything, so
+ * that, e.g., exporting the selected vertices also exports the edges among them.
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExportFilter {
+    pub vertices: Option<ElementFilter>,
+    pub edges: Option<ElementFilter>,
+}
+
+impl ExportFilter {
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_none() && self.edges.is_none()
+    }
+
+    pub fn apply(&self, graph: &Graph, references: &References) -> Graph {
+        let passes = |filter: &Option<ElementFilter>, element, attributes: &Attributes| {
+            filter
+                .as_ref()
+                .is_none_or(|filter| filter.matches(references, element, attributes))
+        };
+        graph.filtered(
+            |v| passes(&self.vertices, ElementRef::Vertex(v.id), &v.attributes),
+            |e| passes(&self.edges, ElementRef::Edge(e.id), &e.attributes),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Edge;
+    use crate::graph::Vertex;
+
+    #[test]
+    fn parse_filters() {
+        assert_eq!(
+            Ok(ElementFilter::Equals(
+                "color".to_string(),
+                "red".to_string()
+            )),
+            ElementFilter::parse("color=red")
+        );
+        assert_eq!(
+            Ok(ElementFilter::Has("weight".to_string())),
+            ElementFilter::parse("weight")
+        );
+        assert_eq!(
+            Ok(ElementFilter::Named(Reference::Mark('a'))),
+            ElementFilter::parse("@mark:a")
+        );
+        assert_eq!(
+            Ok(ElementFilter::Named(Reference::Cluster("core".to_string()))),
+            ElementFilter::parse("@cluster:core")
+        );
+        assert!(ElementFilter::parse("@alias").is_err());
+        assert!(ElementFilter::parse("=red").is_err());
+    }
+
+    #[test]
+    fn selected_vertices_and_filtered_edges() {
+        let mut g = Graph::new();
+        for id in 0..3 {
+            g.add_vertex(Vertex::new(id));
+        }
+        g.add_edge(Edge::new(0, 0, 1).with_attribute("weight", "2"));
+        g.add_edge(Edge::new(1, 1, 0));
+        g.add_edge(Edge::new(2, 1, 2).with_attribute("weight", "1"));
+        let mut references = References::new();
+        references.add(Reference::Selection, ElementRef::Vertex(0));
+        references.add(Reference::Selection, ElementRef::Vertex(1));
+
+        let filter = ExportFilter {
+            vertices: Some(ElementFilter::Named(Reference::Selection)),
+            edges: None,
+        };
+        let exported = filter.apply(&g, &references);
+        assert_eq!(2, exported.vertices.len());
+        assert_eq!(2, exported.edges.len());
+
+        let filter = ExportFilter {
+            edges: Some(ElementFilter::Has("weight".to_string())),
+            ..filter
+        };
+        let exported = filter.apply(&g, &references);
+        assert_eq!(vec![&0], exported.edges.keys().collect::<Vec<_>>());
+    }
+}
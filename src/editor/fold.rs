@@ -0,0 +1,276 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+
+use crate::graph::Diff;
+use crate::graph::Graph;
+use crate::graph::GraphOperation;
+
+// What a fold collapses behind its anchor vertex.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FoldKind {
+    // Every vertex reachable from the anchor by following outgoing edges, e.g., a subtree.
+    Descendants,
+    // A fixed set of vertices.
+    Cluster(BTreeSet<i64>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Fold {
+    kind: FoldKind,
+    open: bool,
+    // The vertices the fold hides while closed, kept up to date as the document changes.
+    members: BTreeSet<i64>,
+}
+
+// What a renderer shows on a vertex that anchors a fold.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FoldIndicator {
+    pub open: bool,
+    pub hidden: usize,
+}
+
+/**
+ * Folds are a property of the view rather than the document: they hide vertices without changing
+ * them, and are not part of the history. Each fold is anchored at a vertex, which stays visible.
+ * A fold whose anchor is removed is kept, hiding nothing, so that it reappears when the removal
+ * is undone.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Folds {
+    folds: BTreeMap<i64, Fold>,
+}
+
+impl Folds {
+    pub fn new() -> Folds {
+        Folds::default()
+    }
+
+    // Create a closed fold, replacing any fold already anchored at the vertex.
+    pub fn create(&mut self, graph: &Graph, anchor: i64, kind: FoldKind) {
+        let mut fold = Fold {
+            kind,
+            open: false,
+            members: BTreeSet::new(),
+        };
+        fold.members = members(graph, &OutEdges::new(graph), anchor, &fold.kind);
+        self.folds.insert(anchor, fold);
+    }
+
+    pub fn delete(&mut self, anchor: i64) -> bool {
+        self.folds.remove(&anchor).is_some()
+    }
+
+    pub fn is_fold(&self, anchor: i64) -> bool {
+        self.folds.contains_key(&anchor)
+    }
+
+    // Open or close the fold anchored at the vertex, returning false if there is none.
+    pub fn set_open(&mut self, anchor: i64, open: bool) -> bool {
+        match self.folds.get_mut(&anchor) {
+            Some(fold) => {
+                fold.open = open;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn toggle(&mut self, anchor: i64) -> bool {
+        match self.folds.get(&anchor) {
+            Some(fold) => {
+                let open = fold.open;
+                self.set_open(anchor, !open)
+            }
+            None => false,
+        }
+    }
+
+    pub fn set_all_open(&mut self, open: bool) {
+        for fold in self.folds.values_mut() {
+            fold.open = open;
+        }
+    }
+
+    pub fn indicator(&self, anchor: i64) -> Option<FoldIndicator> {
+        self.folds.get(&anchor).map(|fold| FoldIndicator {
+            open: fold.open,
+            hidden: fold.members.len(),
+        })
+    }
+
+    // The vertices hidden by closed folds. An anchor is hidden if another closed fold hides it.
+    pub fn hidden(&self) -> BTreeSet<i64> {
+        self.folds
+            .values()
+            .filter(|fold| !fold.open)
+            .flat_map(|fold| fold.members.iter().copied())
+            .collect()
+    }
+
+    pub fn is_hidden(&self, vertex: i64) -> bool {
+        self.folds
+            .values()
+            .any(|fold| !fold.open && fold.members.contains(&vertex))
+    }
+
+    // The part of the graph that is not folded away.
+    pub fn visible(&self, graph: &Graph) -> Graph {
+        let hidden = self.hidden();
+        graph.subgraph_by(|v| !hidden.contains(&v.id))
+    }
+
+    /**
+     * Bring the folds up to date with a diff that was just applied to the graph. Only folds whose
+     * anchor or members the diff touches are recomputed, where an edge touches its source for the
+     * purpose of descendant folds.
+     */
+    pub fn update(&mut self, graph: &Graph, diff: &Diff) {
+        let mut touched = BTreeSet::new();
+        for op in diff.primitives() {
+            match op {
+                GraphOperation::AddVertex(v)
+                | GraphOperation::RemoveVertex(v)
+                | GraphOperation::ModifyVertex { new: v, .. } => {
+                    touched.insert(v.id);
+                }
+                GraphOperation::AddEdge(e)
+                | GraphOperation::RemoveEdge(e)
+                | GraphOperation::ModifyEdge { new: e, .. } => {
+                    touched.insert(e.source);
+                }
+                _ => {}
+            }
+        }
+
+        let stale: Vec<i64> = self
+            .folds
+            .iter()
+            .filter(|(anchor, fold)| {
+                touched.contains(anchor)
+                    || !fold.members.is_disjoint(&touched)
+                    || matches!(&fold.kind, FoldKind::Cluster(ids) if !ids.is_disjoint(&touched))
+            })
+            .map(|(anchor, _)| *anchor)
+            .collect();
+        if stale.is_empty() {
+            return;
+        }
+        let out_edges = OutEdges::new(graph);
+        for anchor in stale {
+            let fold = self.folds.get_mut(&anchor).unwrap();
+            fold.members = members(graph, &out_edges, anchor, &fold.kind);
+        }
+    }
+}
+
+struct OutEdges {
+    targets: HashMap<i64, Vec<i64>>,
+}
+
+impl OutEdges {
+    fn new(graph: &Graph) -> OutEdges {
+        let mut targets: HashMap<i64, Vec<i64>> = HashMap::new();
+        for e in graph.edges.values() {
+            targets.entry(e.source).or_default().push(e.target);
+        }
+        OutEdges { targets }
+    }
+}
+
+fn members(graph: &Graph, out_edges: &OutEdges, anchor: i64, kind: &FoldKind) -> BTreeSet<i64> {
+    if !graph.vertices.contains_key(&anchor) {
+        return BTreeSet::new();
+    }
+    match kind {
+        FoldKind::Descendants => {
+            let mut reached = BTreeSet::new();
+            let mut stack = vec![anchor];
+            while let Some(id) = stack.pop() {
+                for target in out_edges.targets.get(&id).into_iter().flatten() {
+                    if *target != anchor && reached.insert(*target) {
+                        stack.push(*target);
+                    }
+                }
+            }
+            reached
+        }
+        FoldKind::Cluster(ids) => ids
+            .iter()
+            .filter(|id| **id != anchor && graph.vertices.contains_key(id))
+            .copied()
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Edge;
+    use crate::graph::Vertex;
+
+    // 0 -> 1 -> 2, 1 -> 3, 4
+    fn tree() -> Graph {
+        let mut g = Graph::new();
+        for id in 0..5 {
+            g.add_vertex(Vertex::new(id));
+        }
+        g.add_edge(Edge::new(0, 0, 1));
+        g.add_edge(Edge::new(1, 1, 2));
+        g.add_edge(Edge::new(2, 1, 3));
+        g
+    }
+
+    #[test]
+    fn closed_fold_hides_descendants() {
+        let g = tree();
+        let mut folds = Folds::new();
+        folds.create(&g, 1, FoldKind::Descendants);
+
+        assert_eq!(BTreeSet::from([2, 3]), folds.hidden());
+        assert_eq!(
+            Some(FoldIndicator {
+                open: false,
+                hidden: 2
+            }),
+            folds.indicator(1)
+        );
+        assert_eq!(
+            vec![0, 1, 4],
+            folds
+                .visible(&g)
+                .vertices
+                .keys()
+                .copied()
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+
+        assert!(folds.toggle(1));
+        assert!(folds.hidden().is_empty());
+        assert!(!folds.toggle(4));
+    }
+
+    #[test]
+    fn folds_follow_document_changes() {
+        let mut g = tree();
+        let mut folds = Folds::new();
+        folds.create(&g, 1, FoldKind::Descendants);
+        folds.create(&g, 4, FoldKind::Cluster(BTreeSet::from([0, 4])));
+
+        let diff = g.apply(GraphOperation::AddEdge(Edge::new(3, 3, 4)));
+        folds.update(&g, &diff);
+        assert_eq!(BTreeSet::from([0, 2, 3, 4]), folds.hidden());
+
+        // Removing an anchor empties its fold until the anchor comes back.
+        let removal = g.apply(GraphOperation::RemoveVertex(Vertex::new(1)));
+        folds.update(&g, &removal);
+        assert_eq!(BTreeSet::from([0]), folds.hidden());
+        assert!(folds.is_fold(1));
+
+        let restore = g.apply_all(removal.invert().operations);
+        folds.update(&g, &restore);
+        assert_eq!(BTreeSet::from([0, 2, 3, 4]), folds.hidden());
+    }
+}
@@ -0,0 +1,181 @@
+use crate::editor::keys::BACKSPACE;
+use crate::graph::Attributes;
+
+// Text being typed into a form, which the form applies once it is accepted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FieldPrompt {
+    // A new value for the selected field.
+    Value(String),
+    // A new field, typed as `key=value`.
+    NewField(String),
+}
+
+impl FieldPrompt {
+    pub fn text(&self) -> &str {
+        match self {
+            FieldPrompt::Value(text) | FieldPrompt::NewField(text) => text,
+        }
+    }
+}
+
+/**
+ * A form listing the attributes of one element as fields, like a properties panel. Fields are
+ * edited one at a time through a prompt, and the form only changes the document when it is
+ * closed, as a single modification of the element.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AttributeForm {
+    // The element being edited, as typed, e.g., `v3`.
+    pub element: String,
+    fields: Vec<(String, String)>,
+    selected: usize,
+    prompt: Option<FieldPrompt>,
+}
+
+impl AttributeForm {
+    pub fn new(element: &str, attributes: &Attributes) -> AttributeForm {
+        AttributeForm {
+            element: element.to_string(),
+            fields: attributes
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            selected: 0,
+            prompt: None,
+        }
+    }
+
+    pub fn fields(&self) -> &[(String, String)] {
+        &self.fields
+    }
+
+    // The index of the selected field. It is meaningless if there are no fields.
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn prompt(&self) -> Option<&FieldPrompt> {
+        self.prompt.as_ref()
+    }
+
+    pub fn attributes(&self) -> Attributes {
+        self.fields.iter().cloned().collect()
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.fields.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn delete_selected(&mut self) {
+        if self.selected < self.fields.len() {
+            self.fields.remove(self.selected);
+            self.selected = self.selected.min(self.fields.len().saturating_sub(1));
+        }
+    }
+
+    // Start editing the selected field, returning false if there is none.
+    pub fn edit_selected(&mut self) -> bool {
+        if self.selected < self.fields.len() {
+            self.prompt = Some(FieldPrompt::Value("".to_string()));
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn add_field(&mut self) {
+        self.prompt = Some(FieldPrompt::NewField("".to_string()));
+    }
+
+    pub fn type_key(&mut self, key: char) {
+        match &mut self.prompt {
+            Some(FieldPrompt::Value(text)) | Some(FieldPrompt::NewField(text)) => match key {
+                BACKSPACE => {
+                    text.pop();
+                }
+                key => text.push(key),
+            },
+            None => {}
+        }
+    }
+
+    pub fn cancel_prompt(&mut self) {
+        self.prompt = None;
+    }
+
+    // Apply the prompt's text to the form, or explain why it cannot be applied.
+    pub fn accept_prompt(&mut self) -> Result<(), String> {
+        match self.prompt.take() {
+            Some(FieldPrompt::Value(value)) => {
+                self.fields[self.selected].1 = value;
+                Ok(())
+            }
+            Some(FieldPrompt::NewField(text)) => {
+                let (key, value) = match text.split_once('=') {
+                    Some((key, value)) if !key.trim().is_empty() => (key.trim(), value),
+                    _ => {
+                        let err = format!("Expected a field as key=value, not '{}'", text);
+                        self.prompt = Some(FieldPrompt::NewField(text));
+                        return Err(err);
+                    }
+                };
+                match self.fields.iter().position(|(k, _)| k == key) {
+                    Some(index) => {
+                        self.fields[index].1 = value.to_string();
+                        self.selected = index;
+                    }
+                    None => {
+                        self.fields.push((key.to_string(), value.to_string()));
+                        self.selected = self.fields.len() - 1;
+                    }
+                }
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_add_and_delete_fields() {
+        let mut attributes = Attributes::new();
+        attributes.insert("label".to_string(), "a".to_string());
+        attributes.insert("weight".to_string(), "1".to_string());
+        let mut form = AttributeForm::new("e0", &attributes);
+
+        form.select_next();
+        assert!(form.edit_selected());
+        "2.5".chars().for_each(|c| form.type_key(c));
+        assert_eq!(Ok(()), form.accept_prompt());
+
+        form.add_field();
+        "color".chars().for_each(|c| form.type_key(c));
+        assert!(form.accept_prompt().is_err());
+        "=red".chars().for_each(|c| form.type_key(c));
+        assert_eq!(Ok(()), form.accept_prompt());
+        assert_eq!(2, form.selected());
+
+        form.select_previous();
+        form.select_previous();
+        form.delete_selected();
+
+        let expected: Vec<(String, String)> = vec![
+            ("weight".to_string(), "2.5".to_string()),
+            ("color".to_string(), "red".to_string()),
+        ];
+        assert_eq!(expected.as_slice(), form.fields());
+        assert_eq!(None, form.prompt());
+    }
+}
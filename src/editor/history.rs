@@ -0,0 +1,911 @@
+use indextree::Arena;
+use indextree::NodeId;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::mem::size_of;
+use std::num::NonZeroUsize;
+use std::time::SystemTime;
+
+use crate::editor::compressed_diff::CompressedDiff;
+use crate::editor::ids::add_ids;
+use crate::format::dot::to_dot;
+use crate::graph::Diff;
+use crate::graph::DiffSummary;
+use crate::graph::Edge;
+use crate::graph::Graph;
+use crate::graph::Vertex;
+
+// One edit in the history tree.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HistoryEntry {
+    // Edits are kept compressed, since a long session's history may hold many copies of the same
+    // attributes.
+    diff: CompressedDiff,
+    pub created: SystemTime,
+    // The last time the edit was made, undone or redone.
+    pub last_touched: SystemTime,
+    // A description of the edit given by the user, like a commit message.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub message: Option<String>,
+}
+
+impl HistoryEntry {
+    fn new(diff: Diff) -> HistoryEntry {
+        let now = SystemTime::now();
+        HistoryEntry {
+            diff: CompressedDiff::new(&diff),
+            created: now,
+            last_touched: now,
+            message: None,
+        }
+    }
+
+    // The edit's changes, rebuilt from their compressed form.
+    pub fn diff(&self) -> Diff {
+        self.diff.to_diff()
+    }
+
+    // A rough estimate of the memory the entry occupies, including its attributes.
+    pub fn estimated_bytes(&self) -> usize {
+        size_of::<HistoryEntry>() - size_of::<CompressedDiff>() + self.diff.estimated_bytes()
+    }
+
+    fn coalesce(&mut self, later: Diff) {
+        self.diff = CompressedDiff::new(&self.diff().coalesce(later));
+    }
+}
+
+// Statistics about a branch of the history tree: an edit and everything done after it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BranchStats {
+    // The id of the branch's first edit.
+    pub id: usize,
+    pub node_count: usize,
+    pub estimated_bytes: usize,
+    pub last_touched: SystemTime,
+}
+
+// A read-only description of a node of the history tree, e.g., for drawing an undo tree panel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HistoryNodeView {
+    // The node's user-facing id, as `History::id_of` returns.
+    pub id: usize,
+    pub parent: Option<usize>,
+    // The edits made after this one, in time order.
+    pub children: Vec<usize>,
+    pub summary: DiffSummary,
+    pub created: SystemTime,
+    pub last_touched: SystemTime,
+    pub is_current: bool,
+    // Whether the node is on the path from the root to the current node, i.e., its edit is part
+    // of the current document.
+    pub is_applied: bool,
+    pub bookmarks: Vec<String>,
+    pub message: Option<String>,
+}
+
+// A cap on the size of the history tree. Whichever limit is reached first triggers eviction.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HistoryLimit {
+    pub max_nodes: Option<usize>,
+    // As estimated by `HistoryEntry::estimated_bytes`.
+    pub max_bytes: Option<usize>,
+}
+
+/**
+ * A change to the shape of the history tree or the position in it, for mirroring the tree
+ * elsewhere, e.g., in a history panel, without re-reading it after every edit. Nodes are named
+ * by their user-facing ids, as `History::id_of` returns.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HistoryEvent {
+    NodeCreated {
+        node: usize,
+        parent: usize,
+    },
+    // A node was created beside existing children of its parent. Follows its NodeCreated.
+    BranchCreated {
+        node: usize,
+        parent: usize,
+    },
+    // Later changes were folded into the node's edit.
+    NodeAmended(usize),
+    MessageChanged {
+        node: usize,
+        message: Option<String>,
+    },
+    // The node's edit was folded into its parent's to keep the history under its limit. Its
+    // children are now the parent's.
+    NodeMerged {
+        node: usize,
+        into: usize,
+    },
+    // The nodes, a branch, were deleted.
+    NodesRemoved(Vec<usize>),
+    CurrentMoved {
+        from: usize,
+        to: usize,
+    },
+    // The whole history was replaced, e.g., by opening a document, and must be read again.
+    Reset,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HistoryError {
+    UnknownNode(usize),
+    // The branch contains the current position in the history, so deleting it would lose the
+    // document's state.
+    CurrentBranch(usize),
+}
+
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HistoryError::UnknownNode(id) => write!(f, "No history node {}", id),
+            HistoryError::CurrentBranch(id) => {
+                write!(f, "History node {} leads to the current document", id)
+            }
+        }
+    }
+}
+
+impl Error for HistoryError {}
+
+/**
+ * A history as lists, e.g., to save it: the nodes in preorder, each with the position of its
+ * parent in the list, and the positions of the current node and of each bookmarked node. The root
+ * comes first, with no parent.
+ */
+#[derive(Debug, Clone)]
+pub struct FlatHistory {
+    pub nodes: Vec<(Option<usize>, HistoryEntry)>,
+    pub current: usize,
+    pub bookmarks: Vec<(String, usize)>,
+    pub limit: HistoryLimit,
+}
+
+/**
+ * The "undo tree" of an editing session. Children of a node are the edits made after it, in time
+ * order. The root is a sentinel with an empty diff standing for the empty document, so that
+ * every edit has a parent to undo to.
+ */
+#[derive(Debug, Clone)]
+pub struct History {
+    tree: Arena<HistoryEntry>,
+    root: NodeId,
+    current: NodeId,
+    // Names given to nodes, e.g., "before-refactor", to jump back to.
+    bookmarks: BTreeMap<String, NodeId>,
+    limit: HistoryLimit,
+    // The changes not yet taken with `take_events`.
+    events: Vec<HistoryEvent>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History::new()
+    }
+}
+
+impl History {
+    pub fn new() -> History {
+        let mut tree = Arena::new();
+        let root = tree.new_node(HistoryEntry::new(Diff {
+            operations: Vec::new(),
+        }));
+        History {
+            tree,
+            root,
+            current: root,
+            bookmarks: BTreeMap::new(),
+            limit: HistoryLimit::default(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn root(&self) -> NodeId {
+        self.root
+    }
+
+    // The node whose edit produced the current document.
+    pub fn current(&self) -> NodeId {
+        self.current
+    }
+
+    pub fn entry(&self, node: NodeId) -> Option<&HistoryEntry> {
+        self.tree
+            .get(node)
+            .filter(|n| !n.is_removed())
+            .map(|n| n.get())
+    }
+
+    pub fn parent(&self, node: NodeId) -> Option<NodeId> {
+        node.parent(&self.tree)
+    }
+
+    // The edits made after the node, in time order.
+    pub fn children(&self, node: NodeId) -> Vec<NodeId> {
+        node.children(&self.tree).collect()
+    }
+
+    // The node with the given user-facing id, i.e., the id `id_of` returns.
+    pub fn node(&self, id: usize) -> Option<NodeId> {
+        self.tree.get_node_id_at(NonZeroUsize::new(id)?)
+    }
+
+    pub fn id_of(node: NodeId) -> usize {
+        usize::from(node)
+    }
+
+    // The number of edits, not counting the root.
+    pub fn len(&self) -> usize {
+        self.tree.live_count() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Add an edit after the current node and move to it.
+    pub fn record(&mut self, diff: Diff) -> NodeId {
+        let parent = self.current;
+        let node = parent.append_value(HistoryEntry::new(diff), &mut self.tree);
+        let (node_id, parent_id) = (History::id_of(node), History::id_of(parent));
+        self.events.push(HistoryEvent::NodeCreated {
+            node: node_id,
+            parent: parent_id,
+        });
+        if node.preceding_siblings(&self.tree).nth(1).is_some() {
+            self.events.push(HistoryEvent::BranchCreated {
+                node: node_id,
+                parent: parent_id,
+            });
+        }
+        self.move_to(node);
+        self.evict();
+        node
+    }
+
+    // The changes made to the tree since the last call, oldest first.
+    pub fn take_events(&mut self) -> Vec<HistoryEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn limit(&self) -> HistoryLimit {
+        self.limit
+    }
+
+    pub fn set_limit(&mut self, limit: HistoryLimit) {
+        self.limit = limit;
+        self.evict();
+    }
+
+    pub fn estimated_bytes(&self) -> usize {
+        self.root
+            .descendants(&self.tree)
+            .map(|node| self.tree[node].get().estimated_bytes())
+            .sum()
+    }
+
+    fn over_limit(&self) -> bool {
+        self.limit.max_nodes.is_some_and(|max| self.len() > max)
+            || self
+                .limit
+                .max_bytes
+                .is_some_and(|max| self.estimated_bytes() > max)
+    }
+
+    /**
+     * Shrink the tree to its limit by collapsing linear chains, oldest first: an edit whose only
+     * child is the next edit is merged with it into one checkpoint edit, undone and redone as a
+     * whole. Branch points, bookmarked nodes and the current node are never merged away, so the
+     * shape of the tree and the states that can be returned to by name are preserved. Stops when
+     * nothing more can be collapsed, even if the tree is still over its limit.
+     */
+    fn evict(&mut self) {
+        while self.over_limit() {
+            let bookmarked: HashSet<NodeId> = self.bookmarks.values().copied().collect();
+            let kept = |node: &NodeId| *node == self.current || bookmarked.contains(node);
+            let oldest = self
+                .root
+                .descendants(&self.tree)
+                .filter(|node| *node != self.root && !kept(node))
+                .filter_map(|node| {
+                    let mut children = node.children(&self.tree);
+                    match (children.next(), children.next()) {
+                        (Some(only), None) if !kept(&only) => Some((node, only)),
+                        _ => None,
+                    }
+                })
+                .min_by_key(|(node, _)| (self.tree[*node].get().created, *node));
+            let (node, only) = match oldest {
+                Some(pair) => pair,
+                None => return,
+            };
+            let later = self.tree[only].get().clone();
+            let entry = self.tree[node].get_mut();
+            entry.coalesce(later.diff());
+            entry.last_touched = entry.last_touched.max(later.last_touched);
+            entry.message = match (entry.message.take(), later.message) {
+                (Some(earlier), Some(later)) => Some(format!("{}; {}", earlier, later)),
+                (earlier, later) => earlier.or(later),
+            };
+            // The child's children take its place.
+            only.remove(&mut self.tree);
+            self.events.push(HistoryEvent::NodeMerged {
+                node: History::id_of(only),
+                into: History::id_of(node),
+            });
+        }
+    }
+
+    // The changes that undo the current edit, and the node they lead to.
+    pub fn undo_target(&self) -> Option<(Diff, NodeId)> {
+        let parent = self.current.parent(&self.tree)?;
+        Some((self.entry(self.current)?.diff().invert(), parent))
+    }
+
+    // The changes that redo the most recent edit made after the current one, and its node.
+    pub fn redo_target(&self) -> Option<(Diff, NodeId)> {
+        let child = self.current.last_child(&self.tree)?;
+        Some((self.entry(child)?.diff(), child))
+    }
+
+    // The changes that redo the given edit made after the current one.
+    pub fn redo_target_into(&self, child: NodeId) -> Option<(Diff, NodeId)> {
+        if self.parent(child) != Some(self.current) {
+            return None;
+        }
+        Some((self.entry(child)?.diff(), child))
+    }
+
+    // Fold a later diff into the current edit, so that both are undone and redone together.
+    pub fn amend(&mut self, later: Diff) {
+        if let Some(n) = self.tree.get_mut(self.current) {
+            let entry = n.get_mut();
+            entry.coalesce(later);
+            entry.last_touched = SystemTime::now();
+            self.events
+                .push(HistoryEvent::NodeAmended(History::id_of(self.current)));
+        }
+    }
+
+    // Record that the document now reflects the given node.
+    pub fn move_to(&mut self, node: NodeId) {
+        if node != self.current {
+            self.events.push(HistoryEvent::CurrentMoved {
+                from: History::id_of(self.current),
+                to: History::id_of(node),
+            });
+        }
+        self.current = node;
+        if let Some(n) = self.tree.get_mut(node) {
+            n.get_mut().last_touched = SystemTime::now();
+        }
+    }
+
+    // Describe a node's edit, or remove its description. Returns false if there is no such node.
+    pub fn set_message(&mut self, node: NodeId, message: Option<String>) -> bool {
+        match self.tree.get_mut(node).filter(|n| !n.is_removed()) {
+            Some(n) => {
+                n.get_mut().message = message.clone();
+                self.events.push(HistoryEvent::MessageChanged {
+                    node: History::id_of(node),
+                    message,
+                });
+                true
+            }
+            None => false,
+        }
+    }
+
+    // The ids of every vertex and edge mentioned by an edit in the tree.
+    pub fn element_ids(&self) -> (HashSet<i64>, HashSet<i64>) {
+        let (mut vertices, mut edges) = (HashSet::new(), HashSet::new());
+        for node in self.root.descendants(&self.tree) {
+            for op in self.tree[node].get().diff().operations.iter() {
+                add_ids(op, &mut vertices, &mut edges);
+            }
+        }
+        (vertices, edges)
+    }
+
+    // The nearest node both nodes descend from, which may be one of them.
+    pub fn common_ancestor(&self, a: NodeId, b: NodeId) -> Option<NodeId> {
+        self.entry(a)?;
+        self.entry(b)?;
+        let b_ancestors: HashSet<NodeId> = b.ancestors(&self.tree).collect();
+        a.ancestors(&self.tree)
+            .find(|node| b_ancestors.contains(node))
+    }
+
+    /**
+     * The changes that turn the document at one node into the document at another: the edits
+     * from the first node up to the nearest node both descend from, undone, then the edits from
+     * there down to the second node, redone.
+     */
+    pub fn path_diff(&self, from: NodeId, to: NodeId) -> Option<Diff> {
+        let common = self.common_ancestor(from, to)?;
+        let to_ancestors: Vec<NodeId> = to.ancestors(&self.tree).collect();
+        let mut diff = Diff {
+            operations: Vec::new(),
+        };
+        for node in from
+            .ancestors(&self.tree)
+            .take_while(|node| *node != common)
+        {
+            diff = diff.coalesce(self.tree[node].get().diff().invert());
+        }
+        let down: Vec<NodeId> = to_ancestors
+            .into_iter()
+            .take_while(|node| *node != common)
+            .collect();
+        for node in down.into_iter().rev() {
+            diff = diff.coalesce(self.tree[node].get().diff());
+        }
+        Some(diff)
+    }
+
+    // Name the current node, moving the name if another node had it.
+    pub fn bookmark(&mut self, name: &str) {
+        self.bookmarks.insert(name.to_string(), self.current);
+    }
+
+    pub fn remove_bookmark(&mut self, name: &str) -> bool {
+        self.bookmarks.remove(name).is_some()
+    }
+
+    pub fn bookmarked(&self, name: &str) -> Option<NodeId> {
+        self.bookmarks.get(name).copied()
+    }
+
+    // Every bookmark and the node it names, in order of name.
+    pub fn bookmarks(&self) -> impl Iterator<Item = (&str, NodeId)> {
+        self.bookmarks
+            .iter()
+            .map(|(name, node)| (name.as_str(), *node))
+    }
+
+    fn branch_stats(&self, first: NodeId) -> BranchStats {
+        let entries: Vec<&HistoryEntry> = first
+            .descendants(&self.tree)
+            .filter_map(|node| self.entry(node))
+            .collect();
+        BranchStats {
+            id: History::id_of(first),
+            node_count: entries.len(),
+            estimated_bytes: entries.iter().map(|e| e.estimated_bytes()).sum(),
+            last_touched: entries
+                .iter()
+                .map(|e| e.last_touched)
+                .max()
+                .unwrap_or(SystemTime::UNIX_EPOCH),
+        }
+    }
+
+    /**
+     * Statistics for each branch that is not on the path from the root to the current node, i.e.,
+     * the edits that were undone and then abandoned for others. These are the branches that can
+     * be deleted, in order of id.
+     */
+    pub fn branches(&self) -> Vec<BranchStats> {
+        let path: HashSet<NodeId> = self.current.ancestors(&self.tree).collect();
+        let mut branches: Vec<BranchStats> = path
+            .iter()
+            .flat_map(|node| node.children(&self.tree))
+            .filter(|child| !path.contains(child))
+            .map(|child| self.branch_stats(child))
+            .collect();
+        branches.sort_by_key(|b| b.id);
+        branches
+    }
+
+    // Delete the branch starting at the node with the given id, reclaiming its memory.
+    pub fn delete_branch(&mut self, id: usize) -> Result<BranchStats, HistoryError> {
+        let first = self.node(id).ok_or(HistoryError::UnknownNode(id))?;
+        if self.current.ancestors(&self.tree).any(|node| node == first) {
+            return Err(HistoryError::CurrentBranch(id));
+        }
+        let stats = self.branch_stats(first);
+        let removed: Vec<NodeId> = first.descendants(&self.tree).collect();
+        self.bookmarks.retain(|_, node| !removed.contains(node));
+        first.remove_subtree(&mut self.tree);
+        self.events.push(HistoryEvent::NodesRemoved(
+            removed.into_iter().map(History::id_of).collect(),
+        ));
+        Ok(stats)
+    }
+
+    // A description of every node, the root first, in preorder.
+    pub fn view(&self) -> Vec<HistoryNodeView> {
+        let applied: HashSet<NodeId> = self.current.ancestors(&self.tree).collect();
+        self.root
+            .descendants(&self.tree)
+            .map(|node| {
+                let entry = self.tree[node].get();
+                HistoryNodeView {
+                    id: History::id_of(node),
+                    parent: self.parent(node).map(History::id_of),
+                    children: node.children(&self.tree).map(History::id_of).collect(),
+                    summary: entry.diff().summary(),
+                    created: entry.created,
+                    last_touched: entry.last_touched,
+                    is_current: node == self.current,
+                    is_applied: applied.contains(&node),
+                    bookmarks: self
+                        .bookmarks()
+                        .filter(|(_, bookmarked)| *bookmarked == node)
+                        .map(|(name, _)| name.to_string())
+                        .collect(),
+                    message: entry.message.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /**
+     * Render the tree in Graphviz's DOT language, e.g., to see how an editing session branched.
+     * Each node is labeled with its id, a summary of its edit, the time it was made (UTC), and
+     * its message and bookmarks, if any. The current node is filled, and the path to it is bold.
+     */
+    pub fn to_dot(&self) -> String {
+        let mut graph = Graph::new();
+        for node in self.view() {
+            let mut lines = match node.parent {
+                Some(_) => vec![
+                    format!("node {}", node.id),
+                    node.summary.to_string(),
+                    time_of_day(node.created),
+                ],
+                None => vec!["root".to_string()],
+            };
+            lines.extend(node.message);
+            lines.extend(
+                node.bookmarks
+                    .iter()
+                    .map(|name| format!("bookmark {}", name)),
+            );
+            let mut vertex = Vertex::new(node.id as i64).with_attribute("label", &lines.join("\n"));
+            if node.is_current {
+                vertex = vertex
+                    .with_attribute("style", "filled")
+                    .with_attribute("fillcolor", "yellow");
+            }
+            graph.add_vertex(vertex);
+            if let Some(parent) = node.parent {
+                let mut edge = Edge::new(node.id as i64, parent as i64, node.id as i64);
+                if node.is_applied {
+                    edge = edge.with_attribute("style", "bold");
+                }
+                graph.add_edge(edge);
+            }
+        }
+        to_dot(&graph)
+    }
+
+    // The history as lists. Node ids are not preserved by `unflatten`.
+    pub fn flatten(&self) -> FlatHistory {
+        let mut positions = HashMap::new();
+        let mut nodes = Vec::new();
+        for node in self.root.descendants(&self.tree) {
+            positions.insert(node, nodes.len());
+            let parent = node.parent(&self.tree).map(|parent| positions[&parent]);
+            nodes.push((parent, self.tree[node].get().clone()));
+        }
+        FlatHistory {
+            nodes,
+            current: positions[&self.current],
+            bookmarks: self
+                .bookmarks()
+                .map(|(name, node)| (name.to_string(), positions[&node]))
+                .collect(),
+            limit: self.limit,
+        }
+    }
+
+    // Rebuild a history from `flatten`'s output, or None if it does not describe a tree.
+    pub fn unflatten(flat: FlatHistory) -> Option<History> {
+        let mut nodes = flat.nodes.into_iter();
+        let (root_parent, root_entry) = nodes.next()?;
+        if root_parent.is_some() {
+            return None;
+        }
+        let mut tree = Arena::new();
+        let mut ids = vec![tree.new_node(root_entry)];
+        for (parent, entry) in nodes {
+            // Parents come before their children in preorder.
+            let parent = *ids.get(parent?)?;
+            ids.push(parent.append_value(entry, &mut tree));
+        }
+        let mut bookmarks = BTreeMap::new();
+        for (name, position) in flat.bookmarks {
+            bookmarks.insert(name, *ids.get(position)?);
+        }
+        Some(History {
+            tree,
+            root: ids[0],
+            current: *ids.get(flat.current)?,
+            bookmarks,
+            limit: flat.limit,
+            events: Vec::new(),
+        })
+    }
+}
+
+// The time of day, as HH:MM:SS in UTC.
+fn time_of_day(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+        % 86400;
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::GraphOperation;
+    use crate::graph::Vertex;
+
+    fn add_vertex(id: i64) -> Diff {
+        Diff {
+            operations: vec![GraphOperation::AddVertex(
+                Vertex::new(id).with_attribute("label", "a vertex"),
+            )],
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_targets() {
+        let mut history = History::new();
+        assert_eq!(None, history.undo_target().map(|(_, node)| node));
+
+        let first = history.record(add_vertex(0));
+        let (undo, parent) = history.undo_target().unwrap();
+        assert_eq!(history.root(), parent);
+        assert_eq!(add_vertex(0).invert().operations, undo.operations);
+
+        history.move_to(parent);
+        assert_eq!(Some(first), history.redo_target().map(|(_, node)| node));
+    }
+
+    #[test]
+    fn abandoned_branches_can_be_deleted() {
+        // root -> 0 -> 1, then undo twice and make a different edit.
+        let mut history = History::new();
+        let zero = history.record(add_vertex(0));
+        history.record(add_vertex(1));
+        history.move_to(history.root());
+        let two = history.record(add_vertex(2));
+        assert_eq!(3, history.len());
+
+        let branches = history.branches();
+        assert_eq!(1, branches.len());
+        assert_eq!(History::id_of(zero), branches[0].id);
+        assert_eq!(2, branches[0].node_count);
+        assert_eq!(
+            history.entry(zero).unwrap().estimated_bytes() * 2,
+            branches[0].estimated_bytes
+        );
+
+        assert_eq!(
+            Err(HistoryError::CurrentBranch(History::id_of(two))),
+            history.delete_branch(History::id_of(two))
+        );
+        assert_eq!(
+            Ok(branches[0].clone()),
+            history.delete_branch(branches[0].id)
+        );
+        assert_eq!(1, history.len());
+        assert!(history.branches().is_empty());
+        assert_eq!(
+            Err(HistoryError::UnknownNode(branches[0].id)),
+            history.delete_branch(branches[0].id)
+        );
+    }
+
+    #[test]
+    fn path_diff_goes_through_the_common_ancestor() {
+        // root -> 0 -> 1, and 0 -> 2.
+        let mut history = History::new();
+        let zero = history.record(add_vertex(0));
+        let one = history.record(add_vertex(1));
+        history.move_to(zero);
+        let two = history.record(add_vertex(2));
+
+        let diff = history.path_diff(one, two).unwrap();
+        let mut expected = add_vertex(1).invert().operations;
+        expected.extend(add_vertex(2).operations);
+        assert_eq!(expected, diff.operations);
+        assert!(history.path_diff(two, two).unwrap().operations.is_empty());
+        assert_eq!(
+            add_vertex(1).invert().operations,
+            history.path_diff(one, zero).unwrap().operations
+        );
+    }
+
+    #[test]
+    fn bookmarks_are_dropped_with_their_branch() {
+        let mut history = History::new();
+        let zero = history.record(add_vertex(0));
+        history.bookmark("first");
+        history.record(add_vertex(1));
+        history.bookmark("second");
+        history.bookmark("first");
+        assert_eq!(history.bookmarked("second"), history.bookmarked("first"));
+
+        history.move_to(history.root());
+        history.record(add_vertex(2));
+        history.delete_branch(History::id_of(zero)).unwrap();
+        assert_eq!(None, history.bookmarked("first"));
+        assert_eq!(0, history.bookmarks().count());
+    }
+
+    #[test]
+    fn eviction_collapses_old_chains_and_keeps_branches() {
+        // root -> 0 -> 1 -> 2 -> 3, and 1 -> 4, with 4 current.
+        let mut history = History::new();
+        let zero = history.record(add_vertex(0));
+        let one = history.record(add_vertex(1));
+        history.record(add_vertex(2));
+        history.record(add_vertex(3));
+        history.move_to(one);
+        history.record(add_vertex(4));
+        history.set_message(zero, Some("start".to_string()));
+        history.set_message(one, Some("hub".to_string()));
+
+        history.set_limit(HistoryLimit {
+            max_nodes: Some(3),
+            max_bytes: None,
+        });
+        assert_eq!(3, history.len());
+        // The chain 0 -> 1 became one edit, and so did 2 -> 3; the branch point survives.
+        let shape: Vec<usize> = history
+            .view()
+            .iter()
+            .map(|node| node.summary.added_vertices)
+            .collect();
+        assert_eq!(vec![0, 2, 2, 1], shape);
+        // Messages of merged edits are kept.
+        assert_eq!(Some("start; hub".to_string()), history.view()[1].message);
+        assert_eq!(
+            2,
+            history.children(history.children(history.root())[0]).len()
+        );
+
+        // Nothing more can be collapsed without losing a branch or the current node.
+        history.set_limit(HistoryLimit {
+            max_nodes: Some(1),
+            max_bytes: None,
+        });
+        assert_eq!(3, history.len());
+        let (undo, _) = history.undo_target().unwrap();
+        assert_eq!(add_vertex(4).invert().operations, undo.operations);
+    }
+
+    #[test]
+    fn dot_highlights_the_current_node() {
+        // root -> 0 -> 1, and 0 -> 2, with 2 current.
+        let mut history = History::new();
+        let zero = history.record(add_vertex(0));
+        history.record(add_vertex(1));
+        history.move_to(zero);
+        let two = history.record(add_vertex(2));
+        history.set_message(two, Some("retry".to_string()));
+
+        let dot = history.to_dot();
+        let id = History::id_of;
+        assert!(dot.contains(&format!("  {} [label=root];", id(history.root()))));
+        // Labels span several lines, so find the node's whole statement.
+        let current = dot
+            .split("];")
+            .find(|statement| {
+                statement
+                    .trim_start()
+                    .starts_with(&format!("{} [", id(two)))
+            })
+            .unwrap();
+        assert!(current.contains("added 1 vertex"));
+        assert!(current.contains("retry"));
+        assert!(current.contains("fillcolor=yellow"));
+        assert!(dot.contains(&format!("  {} -> {} [style=bold];", id(zero), id(two))));
+        assert!(dot.contains(&format!("  {} -> {};", id(zero), id(two) - 1)));
+    }
+
+    #[test]
+    fn view_describes_the_tree() {
+        // root -> 0 -> 1, and 0 -> 2, with 2 current.
+        let mut history = History::new();
+        let zero = history.record(add_vertex(0));
+        let one = history.record(add_vertex(1));
+        history.move_to(zero);
+        let two = history.record(add_vertex(2));
+        history.bookmark("tip");
+
+        let view = history.view();
+        let ids: Vec<usize> = view.iter().map(|node| node.id).collect();
+        let id = History::id_of;
+        assert_eq!(vec![id(history.root()), id(zero), id(one), id(two)], ids);
+        assert_eq!(None, view[0].parent);
+        assert_eq!(vec![id(one), id(two)], view[1].children);
+        assert_eq!(Some(id(zero)), view[3].parent);
+        assert_eq!(1, view[3].summary.added_vertices);
+        assert_eq!(vec!["tip".to_string()], view[3].bookmarks);
+        assert_eq!(
+            vec![true, true, false, true],
+            view.iter().map(|node| node.is_applied).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![false, false, false, true],
+            view.iter().map(|node| node.is_current).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn flatten_and_unflatten() {
+        let mut history = History::new();
+        let zero = history.record(add_vertex(0));
+        history.record(add_vertex(1));
+        history.move_to(zero);
+        history.record(add_vertex(2));
+        history.bookmark("two");
+        history.move_to(zero);
+        let limit = HistoryLimit {
+            max_nodes: Some(8),
+            max_bytes: None,
+        };
+        history.set_limit(limit);
+
+        let flat = history.flatten();
+        let parents: Vec<Option<usize>> = flat.nodes.iter().map(|(parent, _)| *parent).collect();
+        assert_eq!(vec![None, Some(0), Some(1), Some(1)], parents);
+        assert_eq!(1, flat.current);
+        assert_eq!(vec![("two".to_string(), 3)], flat.bookmarks);
+
+        let restored = History::unflatten(flat.clone()).unwrap();
+        let restored_flat = restored.flatten();
+        assert_eq!(
+            (flat.nodes.len(), flat.current, &flat.bookmarks, flat.limit),
+            (
+                restored_flat.nodes.len(),
+                restored_flat.current,
+                &restored_flat.bookmarks,
+                restored_flat.limit
+            )
+        );
+        assert_eq!(
+            add_vertex(2).operations,
+            restored.redo_target().unwrap().0.operations
+        );
+        assert!(History::unflatten(FlatHistory {
+            current: 4,
+            ..flat.clone()
+        })
+        .is_none());
+        assert!(History::unflatten(FlatHistory {
+            bookmarks: vec![("gone".to_string(), 4)],
+            ..flat.clone()
+        })
+        .is_none());
+        assert!(History::unflatten(FlatHistory {
+            nodes: flat.nodes[1..].to_vec(),
+            current: 0,
+            bookmarks: Vec::new(),
+            ..flat
+        })
+        .is_none());
+    }
+}
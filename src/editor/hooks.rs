@@ -0,0 +1,258 @@
+use std::fmt;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::editor::mode::EditorMode;
+use crate::editor::state::EditorState;
+use crate::graph::Diff;
+use crate::graph::GraphOperation;
+
+pub type HookId = usize;
+
+// A point in the editor's life that code can run at, like one of vim's autocommand events.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Hook {
+    VertexAdded,
+    VertexRemoved,
+    EdgeAdded,
+    EdgeRemoved,
+    ModeChanged,
+    // Before the document is written to a file. A callback that fails keeps it from being written.
+    PreSave,
+    // After an undo, counted or not, or `:undo-all` moved the document back through the history.
+    // Other moves back, e.g., to a node picked from the history, are not undos.
+    PostUndo,
+}
+
+pub const HOOKS: [Hook; 7] = [
+    Hook::VertexAdded,
+    Hook::VertexRemoved,
+    Hook::EdgeAdded,
+    Hook::EdgeRemoved,
+    Hook::ModeChanged,
+    Hook::PreSave,
+    Hook::PostUndo,
+];
+
+impl Hook {
+    // The hook named as `:autocmd` takes it, e.g., `VertexAdded`.
+    pub fn parse(name: &str) -> Option<Hook> {
+        HOOKS.iter().copied().find(|hook| hook.to_string() == name)
+    }
+}
+
+impl fmt::Display for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+// What happened, for the callbacks of its hook.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookEvent {
+    VertexAdded(i64),
+    VertexRemoved(i64),
+    EdgeAdded(i64),
+    EdgeRemoved(i64),
+    ModeChanged { from: EditorMode, to: EditorMode },
+    // The path the document is about to be written to.
+    PreSave(String),
+    PostUndo,
+}
+
+impl HookEvent {
+    pub fn hook(&self) -> Hook {
+        match self {
+            HookEvent::VertexAdded(_) => Hook::VertexAdded,
+            HookEvent::VertexRemoved(_) => Hook::VertexRemoved,
+            HookEvent::EdgeAdded(_) => Hook::EdgeAdded,
+            HookEvent::EdgeRemoved(_) => Hook::EdgeRemoved,
+            HookEvent::ModeChanged { .. } => Hook::ModeChanged,
+            HookEvent::PreSave(_) => Hook::PreSave,
+            HookEvent::PostUndo => Hook::PostUndo,
+        }
+    }
+
+    // An autocommand's command, with `<id>` replaced by the element's id and `<path>` by the path.
+    pub fn expand(&self, command: &str) -> String {
+        match self {
+            HookEvent::VertexAdded(id)
+            | HookEvent::VertexRemoved(id)
+            | HookEvent::EdgeAdded(id)
+            | HookEvent::EdgeRemoved(id) => command.replace("<id>", &id.to_string()),
+            HookEvent::PreSave(path) => command.replace("<path>", path),
+            _ => command.to_string(),
+        }
+    }
+}
+
+// The element events of a diff applied to the document, in order. Diffs hold primitive
+// operations only, so a contracted edge is a removed edge, and so on.
+pub fn element_events(diff: &Diff) -> Vec<HookEvent> {
+    diff.operations
+        .iter()
+        .filter_map(|op| match op {
+            GraphOperation::AddVertex(v) => Some(HookEvent::VertexAdded(v.id)),
+            GraphOperation::RemoveVertex(v) => Some(HookEvent::VertexRemoved(v.id)),
+            GraphOperation::AddEdge(e) => Some(HookEvent::EdgeAdded(e.id)),
+            GraphOperation::RemoveEdge(e) => Some(HookEvent::EdgeRemoved(e.id)),
+            _ => None,
+        })
+        .collect()
+}
+
+/**
+ * Code run at a hook, given the editor and the event. Callbacks may change the editor, e.g., lay
+ * out a vertex that was just added, but the hooks those changes would run are not run, like vim's
+ * autocommands without `++nested`, so that a callback cannot trigger itself.
+ */
+pub type HookCallback = Box<dyn FnMut(&mut EditorState, &HookEvent) -> Result<(), String> + Send>;
+
+// What a hook runs: a callback, or an ex command, as typed after `:`.
+#[derive(Clone)]
+pub enum HookAction {
+    Callback(Arc<Mutex<HookCallback>>),
+    Autocommand(String),
+}
+
+impl fmt::Debug for HookAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HookAction::Callback(_) => write!(f, "Callback"),
+            HookAction::Autocommand(command) => write!(f, "Autocommand({:?})", command),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    id: HookId,
+    hook: Hook,
+    action: HookAction,
+    // The plugin that added the entry, if any, which removes it when it is unregistered.
+    owner: Option<String>,
+}
+
+// What runs at each hook, in the order it was added.
+#[derive(Debug, Clone, Default)]
+pub struct Hooks {
+    entries: Vec<Entry>,
+    next_id: HookId,
+}
+
+impl Hooks {
+    pub fn new() -> Hooks {
+        Hooks::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn add(&mut self, hook: Hook, action: HookAction, owner: Option<String>) -> HookId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.entries.push(Entry {
+            id,
+            hook,
+            action,
+            owner,
+        });
+        id
+    }
+
+    pub fn remove(&mut self, id: HookId) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|entry| entry.id != id);
+        self.entries.len() != before
+    }
+
+    // Remove the autocommands added by `:autocmd`, at the hook or at every hook.
+    pub fn remove_autocommands(&mut self, hook: Option<Hook>) {
+        self.entries.retain(|entry| {
+            entry.owner.is_some()
+                || !matches!(entry.action, HookAction::Autocommand(_))
+                || hook.is_some_and(|hook| hook != entry.hook)
+        });
+    }
+
+    pub fn remove_owned_by(&mut self, plugin: &str) {
+        self.entries
+            .retain(|entry| entry.owner.as_deref() != Some(plugin));
+    }
+
+    pub fn actions(&self, hook: Hook) -> Vec<HookAction> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.hook == hook)
+            .map(|entry| entry.action.clone())
+            .collect()
+    }
+
+    // Every autocommand, by hook, with the plugin that added it, if any.
+    pub fn autocommands(&self) -> Vec<(Hook, &str, Option<&str>)> {
+        let mut autocommands: Vec<(Hook, &str, Option<&str>)> = self
+            .entries
+            .iter()
+            .filter_map(|entry| match &entry.action {
+                HookAction::Autocommand(command) => {
+                    Some((entry.hook, command.as_str(), entry.owner.as_deref()))
+                }
+                HookAction::Callback(_) => None,
+            })
+            .collect();
+        autocommands.sort_by_key(|(hook, _, _)| *hook);
+        autocommands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Edge;
+    use crate::graph::Vertex;
+
+    #[test]
+    fn autocommands_are_removed_by_hook_or_owner() {
+        let mut hooks = Hooks::new();
+        let autocommand = |command: &str| HookAction::Autocommand(command.to_string());
+        hooks.add(Hook::VertexAdded, autocommand("a <id>"), None);
+        hooks.add(Hook::PreSave, autocommand("b <path>"), None);
+        hooks.add(Hook::VertexAdded, autocommand("c"), Some("p".to_string()));
+        let callback: HookCallback = Box::new(|_, _| Ok(()));
+        let id = hooks.add(
+            Hook::VertexAdded,
+            HookAction::Callback(Arc::new(Mutex::new(callback))),
+            None,
+        );
+        assert_eq!(3, hooks.actions(Hook::VertexAdded).len());
+
+        hooks.remove_autocommands(Some(Hook::VertexAdded));
+        assert_eq!(
+            vec![
+                (Hook::VertexAdded, "c", Some("p")),
+                (Hook::PreSave, "b <path>", None)
+            ],
+            hooks.autocommands()
+        );
+        hooks.remove_owned_by("p");
+        assert!(hooks.remove(id));
+        assert_eq!(
+            vec![(Hook::PreSave, "b <path>", None)],
+            hooks.autocommands()
+        );
+
+        assert_eq!(Some(Hook::PostUndo), Hook::parse("PostUndo"));
+        assert_eq!("a 3", HookEvent::VertexAdded(3).expand("a <id>"));
+        let diff = Diff {
+            operations: vec![
+                GraphOperation::AddVertex(Vertex::new(1)),
+                GraphOperation::RemoveEdge(Edge::new(2, 0, 1)),
+            ],
+        };
+        assert_eq!(
+            vec![HookEvent::VertexAdded(1), HookEvent::EdgeRemoved(2)],
+            element_events(&diff)
+        );
+    }
+}
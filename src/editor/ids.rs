@@ -0,0 +1,444 @@
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeSet;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::hash::Hasher;
+
+use crate::editor::import::IdMapping;
+use crate::graph::Graph;
+use crate::graph::GraphOperation;
+
+/**
+ * Hands out the ids of new vertices or of new edges. The editor keeps one allocator for vertices
+ * and one for edges, and tells them of every id that comes into use, whether they handed it out
+ * or not, so that an allocator never hands out an id in use. Embedders that give ids a meaning of
+ * their own, e.g., to match the keys of an external database, can supply their own allocators.
+ */
+pub trait IdAllocator: fmt::Debug + Send + Sync {
+    // The id the next call to `allocate` returns.
+    fn peek(&self) -> i64;
+
+    // Record that the id is in use, e.g., by an element added by other means than `allocate`.
+    fn observe(&mut self, id: i64);
+
+    fn allocate(&mut self) -> i64 {
+        let id = self.peek();
+        self.observe(id);
+        id
+    }
+
+    // Start over for a newly opened document, whose elements have the ids.
+    fn reset(&mut self, in_use: &HashSet<i64>);
+
+    // The lowest id above every id handed out or observed, e.g., to save alongside the document.
+    fn next_unused(&self) -> i64;
+
+    fn is_recycling(&self) -> bool {
+        false
+    }
+
+    // Returns whether the allocator can recycle ids. Ones that cannot ignore the setting.
+    fn set_recycling(&mut self, _recycling: bool) -> bool {
+        false
+    }
+
+    // While recycling, make every id not in use free to hand out again.
+    fn recycle(&mut self, _in_use: &HashSet<i64>) {}
+
+    // Take back an id handed out for an element that never came into use, e.g., one added by an
+    // edit that was rejected, so that it is handed out again. Ids are released in the reverse of
+    // the order they were handed out. Allocators that cannot take ids back ignore this.
+    fn release(&mut self, _id: i64) {}
+}
+
+/**
+ * Hands out ids in increasing order, from 0 by default. Ids are normally never reused, so that an
+ * id names at most one element over an editing session, including the elements of undone edits.
+ * With recycling, the ids that appear nowhere, neither in the document nor in its history, are
+ * handed out again, smallest first, as found by the last call to `recycle`.
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sequential {
+    next: i64,
+    // Ids below `next` that may be handed out again, while recycling.
+    free: BTreeSet<i64>,
+    recycling: bool,
+}
+
+impl Sequential {
+    pub fn starting_at(next: i64) -> Sequential {
+        Sequential {
+            next,
+            ..Sequential::default()
+        }
+    }
+}
+
+impl IdAllocator for Sequential {
+    fn peek(&self) -> i64 {
+        self.free.iter().next().copied().unwrap_or(self.next)
+    }
+
+    fn observe(&mut self, id: i64) {
+        self.free.remove(&id);
+        self.next = self.next.max(id + 1);
+    }
+
+    // Keeps whether ids are recycled.
+    fn reset(&mut self, in_use: &HashSet<i64>) {
+        self.next = in_use.iter().max().map_or(0, |id| id + 1);
+        self.free.clear();
+    }
+
+    fn next_unused(&self) -> i64 {
+        self.next
+    }
+
+    fn is_recycling(&self) -> bool {
+        self.recycling
+    }
+
+    fn set_recycling(&mut self, recycling: bool) -> bool {
+        self.recycling = recycling;
+        self.free.clear();
+        true
+    }
+
+    fn recycle(&mut self, in_use: &HashSet<i64>) {
+        if !self.recycling {
+            return;
+        }
+        self.next = in_use.iter().map(|id| id + 1).max().unwrap_or(0).max(0);
+        self.free = (0..self.next).filter(|id| !in_use.contains(id)).collect();
+    }
+
+    fn release(&mut self, id: i64) {
+        if id + 1 == self.next {
+            self.next = id;
+        } else if id < self.next {
+            self.free.insert(id);
+        }
+    }
+}
+
+// Random ids are below 2^53, so that they are exact as JSON numbers read into a double.
+const RANDOM_ID_BITS: u32 = 53;
+
+/**
+ * Hands out non-negative ids at random, like UUIDs, e.g., so that documents edited apart can be
+ * combined without renumbering. Every id observed is remembered, so that none is handed out twice.
+ * The same seed gives the same ids.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Random {
+    state: u64,
+    next: i64,
+    in_use: HashSet<i64>,
+}
+
+impl Random {
+    pub fn with_seed(seed: u64) -> Random {
+        let mut random = Random {
+            state: seed,
+            next: 0,
+            in_use: HashSet::new(),
+        };
+        random.draw();
+        random
+    }
+
+    // Seeded differently every time.
+    pub fn new() -> Random {
+        Random::with_seed(RandomState::new().build_hasher().finish())
+    }
+
+    // Pick the next id with splitmix64, until one is found that is not in use.
+    fn draw(&mut self) {
+        loop {
+            self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = self.state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^= z >> 31;
+            self.next = (z >> (64 - RANDOM_ID_BITS)) as i64;
+            if !self.in_use.contains(&self.next) {
+                return;
+            }
+        }
+    }
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Random::new()
+    }
+}
+
+impl IdAllocator for Random {
+    fn peek(&self) -> i64 {
+        self.next
+    }
+
+    fn observe(&mut self, id: i64) {
+        self.in_use.insert(id);
+        if id == self.next {
+            self.draw();
+        }
+    }
+
+    fn reset(&mut self, in_use: &HashSet<i64>) {
+        self.in_use = in_use.clone();
+        if self.in_use.contains(&self.next) {
+            self.draw();
+        }
+    }
+
+    fn next_unused(&self) -> i64 {
+        self.in_use.iter().max().map_or(0, |id| id + 1)
+    }
+
+    fn release(&mut self, id: i64) {
+        self.in_use.remove(&id);
+    }
+}
+
+// The number of low bits of a prefixed id that number it within its namespace.
+const NAMESPACE_SHIFT: u32 = 32;
+
+/**
+ * Hands out ids in increasing order within a namespace, which makes up the high bits of each id,
+ * e.g., one namespace per user or per database shard, so that ids handed out for different
+ * namespaces never collide. Ids observed in other namespaces are ignored.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Prefixed {
+    namespace: u16,
+    ids: Sequential,
+}
+
+impl Prefixed {
+    pub fn new(namespace: u16) -> Prefixed {
+        Prefixed {
+            namespace,
+            ids: Sequential::starting_at(Prefixed::first(namespace)),
+        }
+    }
+
+    pub fn namespace(&self) -> u16 {
+        self.namespace
+    }
+
+    // The namespace of an id handed out by any prefixed allocator.
+    pub fn namespace_of(id: i64) -> u16 {
+        (id >> NAMESPACE_SHIFT) as u16
+    }
+
+    fn first(namespace: u16) -> i64 {
+        i64::from(namespace) << NAMESPACE_SHIFT
+    }
+
+    fn owns(&self, id: i64) -> bool {
+        id >= 0 && Prefixed::namespace_of(id) == self.namespace
+    }
+}
+
+impl IdAllocator for Prefixed {
+    fn peek(&self) -> i64 {
+        self.ids.peek()
+    }
+
+    fn observe(&mut self, id: i64) {
+        if self.owns(id) {
+            self.ids.observe(id);
+        }
+    }
+
+    fn reset(&mut self, in_use: &HashSet<i64>) {
+        let owned: HashSet<i64> = in_use.iter().copied().filter(|id| self.owns(*id)).collect();
+        self.ids.reset(&owned);
+        self.ids.observe(Prefixed::first(self.namespace) - 1);
+    }
+
+    fn next_unused(&self) -> i64 {
+        self.ids.next_unused()
+    }
+
+    fn release(&mut self, id: i64) {
+        if self.owns(id) {
+            self.ids.release(id);
+        }
+    }
+}
+
+// Add the ids of the vertices and edges an operation mentions to the sets.
+pub fn add_ids(op: &GraphOperation, vertices: &mut HashSet<i64>, edges: &mut HashSet<i64>) {
+    match op {
+        GraphOperation::AddVertex(v) | GraphOperation::RemoveVertex(v) => {
+            vertices.insert(v.id);
+        }
+        GraphOperation::ModifyVertex { old, new } => {
+            vertices.insert(old.id);
+            vertices.insert(new.id);
+        }
+        GraphOperation::MergeVertices { keep, remove } => {
+            vertices.insert(keep.id);
+            vertices.insert(remove.id);
+        }
+        GraphOperation::AddEdge(e)
+        | GraphOperation::RemoveEdge(e)
+        | GraphOperation::ContractEdge(e) => {
+            edges.insert(e.id);
+            vertices.extend([e.source, e.target].iter().copied());
+        }
+        GraphOperation::ModifyEdge { old, new } => {
+            edges.insert(old.id);
+            edges.insert(new.id);
+            vertices.extend(
+                [old.source, old.target, new.source, new.target]
+                    .iter()
+                    .copied(),
+            );
+        }
+    }
+}
+
+/**
+ * The graph with its vertices numbered 0, 1, 2, ... and its edges likewise, in order of their
+ * ids, so that there are no gaps left by removed elements. Also returns the new id of each
+ * element, by its old id.
+ */
+pub fn compact(graph: &Graph) -> (Graph, IdMapping) {
+    let mut mapping = IdMapping::default();
+    let mut vertex_ids: Vec<i64> = graph.vertices.keys().copied().collect();
+    vertex_ids.sort_unstable();
+    let mut edge_ids: Vec<i64> = graph.edges.keys().copied().collect();
+    edge_ids.sort_unstable();
+
+    let mut compacted = Graph::new();
+    compacted.properties = graph.properties.clone();
+    for (new_id, id) in vertex_ids.into_iter().enumerate() {
+        let mut v = graph.vertices[&id].clone();
+        v.id = new_id as i64;
+        mapping.vertices.insert(id, v.id);
+        compacted.vertices.insert(v.id, v);
+    }
+    for id in edge_ids {
+        let mut e = graph.edges[&id].clone();
+        e.id = compacted.edges.len() as i64;
+        match (mapping.vertex(e.source), mapping.vertex(e.target)) {
+            (Some(source), Some(target)) => {
+                e.source = source;
+                e.target = target;
+            }
+            // Graphs built by hand may have edges to missing vertices.
+            _ => {
+                mapping.dangling_edges.push(id);
+                continue;
+            }
+        }
+        mapping.edges.insert(id, e.id);
+        compacted.edges.insert(e.id, e);
+    }
+    (compacted, mapping)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Edge;
+    use crate::graph::Vertex;
+
+    #[test]
+    fn recycled_ids_are_handed_out_smallest_first() {
+        let mut ids = Sequential::starting_at(3);
+        let in_use: HashSet<i64> = [1, 4].iter().copied().collect();
+        ids.recycle(&in_use);
+        assert_eq!(3, ids.allocate());
+
+        assert!(ids.set_recycling(true));
+        ids.recycle(&in_use);
+        assert_eq!(5, ids.next_unused());
+        assert_eq!(
+            vec![0, 2, 3, 5],
+            (0..4).map(|_| ids.allocate()).collect::<Vec<_>>()
+        );
+        ids.observe(9);
+        assert_eq!(10, ids.allocate());
+    }
+
+    #[test]
+    fn released_ids_are_handed_out_again() {
+        let mut ids = Sequential::starting_at(3);
+        let allocated: Vec<i64> = (0..3).map(|_| ids.allocate()).collect();
+        allocated.iter().rev().for_each(|id| ids.release(*id));
+        assert_eq!(3, ids.allocate());
+
+        // An id released after a later one came into use is handed out before the next.
+        ids.observe(4);
+        ids.release(3);
+        assert_eq!(vec![3, 5], vec![ids.allocate(), ids.allocate()]);
+
+        let mut ids = Prefixed::new(1);
+        let first = ids.allocate();
+        ids.release(first);
+        ids.release(7);
+        assert_eq!(first, ids.peek());
+    }
+
+    #[test]
+    fn random_ids_are_never_handed_out_twice() {
+        let mut ids = Random::with_seed(7);
+        let taken = ids.peek();
+        ids.observe(taken);
+        assert_ne!(taken, ids.peek());
+        let allocated: HashSet<i64> = (0..100).map(|_| ids.allocate()).collect();
+        assert_eq!(100, allocated.len());
+        assert!(!allocated.contains(&taken));
+        assert!(allocated
+            .iter()
+            .all(|id| (0..1 << RANDOM_ID_BITS).contains(id)));
+        assert_eq!(
+            Random::with_seed(7).allocate(),
+            taken,
+            "the same seed gives the same ids"
+        );
+        assert!(!ids.set_recycling(true));
+    }
+
+    #[test]
+    fn prefixed_ids_stay_in_their_namespace() {
+        let mut ids = Prefixed::new(3);
+        let first = ids.allocate();
+        assert_eq!(3 << 32, first);
+        ids.observe(Prefixed::new(4).peek());
+        ids.observe(12);
+        assert_eq!(first + 1, ids.allocate());
+        assert_eq!(3, Prefixed::namespace_of(ids.peek()));
+
+        let in_use: HashSet<i64> = [5, first + 9].iter().copied().collect();
+        ids.reset(&in_use);
+        assert_eq!(first + 10, ids.peek());
+        ids.reset(&HashSet::new());
+        assert_eq!(first, ids.peek());
+    }
+
+    #[test]
+    fn compact_removes_gaps() {
+        let mut g = Graph::new();
+        g.add_vertex(Vertex::new(2));
+        g.add_vertex(Vertex::new(7).with_attribute("label", "b"));
+        g.add_edge(Edge::new(5, 7, 2));
+        g.edges.insert(1, Edge::new(1, 2, 42));
+
+        let (compacted, mapping) = compact(&g);
+        let mut expected = Graph::new();
+        expected.add_vertex(Vertex::new(0));
+        expected.add_vertex(Vertex::new(1).with_attribute("label", "b"));
+        expected.add_edge(Edge::new(0, 1, 0));
+        assert_eq!(expected, compacted);
+        assert_eq!(Some(1), mapping.vertex(7));
+        assert_eq!(Some(0), mapping.edge(5));
+        assert_eq!(vec![1], mapping.dangling_edges);
+    }
+}
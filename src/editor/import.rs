@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+
+use crate::editor::ids::IdAllocator;
+use crate::graph::Edge;
+use crate::graph::Graph;
+use crate::graph::GraphOperation;
+use crate::graph::Vertex;
+
+// The ids an imported graph's vertices and edges were given in the document, by their ids in the
+// imported graph.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IdMapping {
+    pub vertices: BTreeMap<i64, i64>,
+    pub edges: BTreeMap<i64, i64>,
+    // Edges of the imported graph left out because an endpoint is not in it.
+    pub dangling_edges: Vec<i64>,
+}
+
+impl IdMapping {
+    pub fn vertex(&self, imported: i64) -> Option<i64> {
+        self.vertices.get(&imported).copied()
+    }
+
+    pub fn edge(&self, imported: i64) -> Option<i64> {
+        self.edges.get(&imported).copied()
+    }
+}
+
+/**
+ * The operations that add a graph to a document, with every vertex and edge given a fresh id
+ * from the document's allocators, so that none can collide with the document's own. Elements are
+ * renumbered in order of id, so the mapping is deterministic.
+ */
+pub fn renumber(
+    graph: &Graph,
+    vertex_ids: &mut dyn IdAllocator,
+    edge_ids: &mut dyn IdAllocator,
+) -> (Vec<GraphOperation>, IdMapping) {
+    let mut vertices: Vec<&Vertex> = graph.vertices.values().collect();
+    vertices.sort_by_key(|v| v.id);
+    let mut edges: Vec<&Edge> = graph.edges.values().collect();
+    edges.sort_by_key(|e| e.id);
+
+    let mut mapping = IdMapping::default();
+    let mut operations = Vec::new();
+    for v in vertices {
+        let mut renumbered = v.clone();
+        renumbered.id = vertex_ids.allocate();
+        mapping.vertices.insert(v.id, renumbered.id);
+        operations.push(GraphOperation::AddVertex(renumbered));
+    }
+    for e in edges {
+        let (source, target) = match (mapping.vertex(e.source), mapping.vertex(e.target)) {
+            (Some(source), Some(target)) => (source, target),
+            _ => {
+                mapping.dangling_edges.push(e.id);
+                continue;
+            }
+        };
+        let mut renumbered = e.clone();
+        renumbered.id = edge_ids.allocate();
+        renumbered.source = source;
+        renumbered.target = target;
+        mapping.edges.insert(e.id, renumbered.id);
+        operations.push(GraphOperation::AddEdge(renumbered));
+    }
+    (operations, mapping)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::ids::Sequential;
+
+    #[test]
+    fn ids_are_allocated_from_the_allocators() {
+        let mut g = Graph::new();
+        g.add_vertex(Vertex::new(7));
+        g.add_vertex(Vertex::new(-1));
+        g.add_edge(Edge::new(3, 7, -1).with_attribute("weight", "2"));
+        g.add_edge(Edge::new(0, -1, 7));
+        // Parsers do not produce edges to missing vertices, but a graph built by hand might.
+        g.edges.insert(9, Edge::new(9, 7, 42));
+
+        let (mut vertex_ids, mut edge_ids) =
+            (Sequential::starting_at(5), Sequential::starting_at(2));
+        let (operations, mapping) = renumber(&g, &mut vertex_ids, &mut edge_ids);
+        assert_eq!((7, 4), (vertex_ids.peek(), edge_ids.peek()));
+        assert_eq!(Some(5), mapping.vertex(-1));
+        assert_eq!(Some(6), mapping.vertex(7));
+        assert_eq!(Some(2), mapping.edge(0));
+        assert_eq!(Some(3), mapping.edge(3));
+        assert_eq!(vec![9], mapping.dangling_edges);
+        assert_eq!(
+            GraphOperation::AddEdge(Edge::new(3, 6, 5).with_attribute("weight", "2")),
+            operations[3]
+        );
+    }
+}
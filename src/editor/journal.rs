@@ -0,0 +1,244 @@
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Write;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::format::ParseError;
+use crate::graph::Diff;
+use crate::graph::GraphOperation;
+
+// The version of the journal layout. Readers reject journals of a newer version.
+pub const VERSION: u32 = 1;
+
+// The recovery journal kept alongside a document saved at the path, like vim's swap file.
+pub fn journal_path(document_path: &str) -> String {
+    format!("{}.journal", document_path)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    version: u32,
+    document_hash: u64,
+}
+
+/**
+ * The changes made to a document since it was last saved, as found in its journal. A journal is
+ * a line of JSON describing the saved document, followed by a line of JSON for each diff, so that
+ * appending never rewrites what is already there. A crash while appending leaves a partial last
+ * line, which is ignored.
+ */
+#[derive(Debug, Clone)]
+pub struct RecoveryJournal {
+    // The hash of the saved document the diffs apply to, as computed by `document_hash`.
+    pub document_hash: u64,
+    pub diffs: Vec<Diff>,
+}
+
+impl RecoveryJournal {
+    pub fn read(path: &str) -> Result<RecoveryJournal, ParseError> {
+        let text =
+            std::fs::read_to_string(path).map_err(|err| ParseError::new(1, &err.to_string()))?;
+        let mut lines = text.lines();
+        let header: Header = serde_json::from_str(lines.next().unwrap_or(""))
+            .map_err(|err| ParseError::new(1, &err.to_string()))?;
+        if header.version > VERSION {
+            return Err(ParseError::new(
+                1,
+                &format!(
+                    "Journal version {} is newer than the supported version {}",
+                    header.version, VERSION
+                ),
+            ));
+        }
+        let lines: Vec<&str> = lines.collect();
+        let mut diffs = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            match serde_json::from_str(line) {
+                Ok(diff) => diffs.push(diff),
+                Err(_) if i + 1 == lines.len() && !text.ends_with('\n') => break,
+                Err(err) => return Err(ParseError::new(i + 2, &err.to_string())),
+            }
+        }
+        Ok(RecoveryJournal {
+            document_hash: header.document_hash,
+            diffs,
+        })
+    }
+
+    // All the journal's changes, in order, as the operations of a single diff.
+    pub fn operations(&self) -> Vec<GraphOperation> {
+        self.diffs
+            .iter()
+            .flat_map(|diff| diff.operations.iter().cloned())
+            .collect()
+    }
+}
+
+fn append_line<T: Serialize>(file: &mut std::fs::File, value: &T) -> io::Result<()> {
+    let mut line = serde_json::to_string(value).unwrap();
+    line.push('\n');
+    file.write_all(line.as_bytes())
+}
+
+/**
+ * Autosave keeps the journal of the document being edited. Diffs are buffered as they are made,
+ * and appended to the journal every `interval` diffs, or whenever the editor is asked to
+ * autosave, e.g., by a frontend's idle timer. Saving the document deletes its journal.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Autosave {
+    // The document the journal belongs to, once it has been opened or saved.
+    path: Option<String>,
+    interval: Option<usize>,
+    saved_hash: u64,
+    pending: Vec<Diff>,
+    // Whether the journal has been started since the document was last saved.
+    started: bool,
+    // Whether journaling waits for a journal found on opening the document to be recovered or
+    // discarded, so that it is not overwritten.
+    suspended: bool,
+}
+
+impl Autosave {
+    pub fn set_interval(&mut self, interval: Option<usize>) {
+        self.interval = interval;
+        if interval.is_none() {
+            self.pending.clear();
+        }
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    pub fn record(&mut self, diff: &Diff) -> io::Result<()> {
+        let interval = match (self.interval, &self.path) {
+            (Some(interval), Some(_)) if !self.suspended => interval,
+            _ => return Ok(()),
+        };
+        self.pending.push(diff.clone());
+        if self.pending.len() >= interval {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    // Append the buffered diffs to the journal.
+    pub fn flush(&mut self) -> io::Result<()> {
+        let path = match &self.path {
+            Some(path) if !self.pending.is_empty() => journal_path(path),
+            _ => return Ok(()),
+        };
+        let mut file = if self.started {
+            OpenOptions::new().append(true).open(&path)?
+        } else {
+            let mut file = std::fs::File::create(&path)?;
+            append_line(
+                &mut file,
+                &Header {
+                    version: VERSION,
+                    document_hash: self.saved_hash,
+                },
+            )?;
+            self.started = true;
+            file
+        };
+        for diff in self.pending.drain(..) {
+            append_line(&mut file, &diff)?;
+        }
+        file.sync_data()
+    }
+
+    // Record that the document was saved to the path, which makes its journal unnecessary.
+    pub fn saved(&mut self, path: &str, document_hash: u64) {
+        self.discard();
+        self.path = Some(path.to_string());
+        self.saved_hash = document_hash;
+        self.suspended = false;
+    }
+
+    /**
+     * Record that the document at the path was opened, journaling the previous document's
+     * buffered diffs so that they survive in its journal. If the opened document already has a
+     * journal, e.g., after a crash, journaling is suspended until `resume`.
+     */
+    pub fn opened(&mut self, path: &str, document_hash: u64) -> io::Result<()> {
+        let flushed = self.flush();
+        self.path = Some(path.to_string());
+        self.saved_hash = document_hash;
+        self.pending.clear();
+        self.started = false;
+        self.suspended = std::path::Path::new(&journal_path(path)).exists();
+        flushed
+    }
+
+    // Start journaling again, replacing the journal the document was opened with.
+    pub fn resume(&mut self) {
+        self.suspended = false;
+    }
+
+    // Delete the journal and forget the buffered diffs, e.g., because they are saved.
+    pub fn discard(&mut self) {
+        if self.started || self.suspended {
+            if let Some(path) = &self.path {
+                let _ = std::fs::remove_file(journal_path(path));
+            }
+        }
+        self.pending.clear();
+        self.started = false;
+        self.suspended = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Vertex;
+
+    fn add_vertex(id: i64) -> Diff {
+        Diff {
+            operations: vec![GraphOperation::AddVertex(Vertex::new(id))],
+        }
+    }
+
+    #[test]
+    fn journal_survives_a_torn_write() {
+        let document = std::env::temp_dir()
+            .join(format!("gri-journal-{}.json", std::process::id()))
+            .display()
+            .to_string();
+        let mut autosave = Autosave::default();
+        autosave.set_interval(Some(2));
+        autosave.saved(&document, 7);
+        for id in 0..3 {
+            autosave.record(&add_vertex(id)).unwrap();
+        }
+
+        let read = RecoveryJournal::read(&journal_path(&document)).unwrap();
+        assert_eq!(7, read.document_hash);
+        assert_eq!(2, read.diffs.len());
+
+        autosave.flush().unwrap();
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(journal_path(&document))
+            .unwrap();
+        file.write_all(b"{\"operations\": [{\"AddVer").unwrap();
+        let read = RecoveryJournal::read(&journal_path(&document)).unwrap();
+        assert_eq!(
+            vec![0, 1, 2],
+            read.operations()
+                .iter()
+                .map(|op| match op {
+                    GraphOperation::AddVertex(v) => v.id,
+                    _ => panic!("Unexpected operation {:?}", op),
+                })
+                .collect::<Vec<_>>()
+        );
+
+        autosave.saved(&document, 8);
+        assert!(RecoveryJournal::read(&journal_path(&document)).is_err());
+    }
+}
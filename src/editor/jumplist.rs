@@ -0,0 +1,93 @@
+// The number of jumps remembered, like vim's jumplist.
+const MAX_JUMPS: usize = 100;
+
+/**
+ * The vertices the cursor jumped from, like vim's jumplist, so that Ctrl-O and Ctrl-I can retrace
+ * where the cursor has been. Unlike the history, moving through it does not change the document.
+ * Jumping from somewhere in the middle of the list forgets the newer jumps, as a new edit forgets
+ * the redo branch of a linear undo.
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Jumplist {
+    entries: Vec<i64>,
+    // The entry the cursor is at while moving through the list, or the number of entries if it
+    // is past the newest one.
+    position: usize,
+}
+
+impl Jumplist {
+    pub fn new() -> Jumplist {
+        Jumplist::default()
+    }
+
+    // Remember a vertex the cursor jumped from.
+    pub fn record(&mut self, from: i64) {
+        self.entries.truncate(self.position);
+        if self.entries.last() != Some(&from) {
+            self.entries.push(from);
+        }
+        if self.entries.len() > MAX_JUMPS {
+            self.entries.remove(0);
+        }
+        self.position = self.entries.len();
+    }
+
+    // The vertex of the next older jump from the cursor at a vertex, if any.
+    pub fn back(&mut self, current: Option<i64>) -> Option<i64> {
+        if let Some(current) = current.filter(|_| self.position == self.entries.len()) {
+            // Remember where the cursor is, so that Ctrl-I can come back to it.
+            if self.entries.last() != Some(&current) {
+                self.entries.push(current);
+            }
+            self.position = self.entries.len() - 1;
+        }
+        if self.position == 0 {
+            return None;
+        }
+        self.position -= 1;
+        Some(self.entries[self.position])
+    }
+
+    // The vertex of the next newer jump, if the cursor has moved back through the list.
+    pub fn forward(&mut self) -> Option<i64> {
+        if self.position + 1 >= self.entries.len() {
+            return None;
+        }
+        self.position += 1;
+        Some(self.entries[self.position])
+    }
+
+    pub fn entries(&self) -> &[i64] {
+        &self.entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retrace_jumps() {
+        let mut jumps = Jumplist::new();
+        assert_eq!(None, jumps.back(Some(0)));
+        jumps.record(0);
+        jumps.record(4);
+        jumps.record(4);
+        assert_eq!(&[0, 4], jumps.entries());
+
+        assert_eq!(Some(4), jumps.back(Some(7)));
+        assert_eq!(Some(0), jumps.back(Some(4)));
+        assert_eq!(None, jumps.back(Some(0)));
+        assert_eq!(Some(4), jumps.forward());
+        assert_eq!(Some(7), jumps.forward());
+        assert_eq!(None, jumps.forward());
+
+        // A jump from the middle of the list forgets the newer ones.
+        jumps.back(Some(7));
+        jumps.back(Some(4));
+        jumps.record(0);
+        assert_eq!(&[0], jumps.entries());
+        // Without a cursor, e.g., after its vertex is removed, there is nothing to come back to.
+        assert_eq!(Some(0), jumps.back(None));
+    }
+}
@@ -0,0 +1,384 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::editor::keys::BACKSPACE;
+use crate::editor::keys::DOWN;
+use crate::editor::keys::ENTER;
+use crate::editor::keys::ESC;
+use crate::editor::keys::LEFT;
+use crate::editor::keys::RIGHT;
+use crate::editor::keys::TAB;
+use crate::editor::keys::UP;
+use crate::format::ParseError;
+
+// The modes keys can be mapped in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum KeymapMode {
+    Command,
+    Insert,
+    // Visual mode while nothing is being typed.
+    Visual,
+}
+
+impl KeymapMode {
+    pub fn parse(name: &str) -> Option<KeymapMode> {
+        match name {
+            "command" => Some(KeymapMode::Command),
+            "insert" => Some(KeymapMode::Insert),
+            "visual" => Some(KeymapMode::Visual),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for KeymapMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeymapMode::Command => write!(f, "command"),
+            KeymapMode::Insert => write!(f, "insert"),
+            KeymapMode::Visual => write!(f, "visual"),
+        }
+    }
+}
+
+// The leader key `<leader>` stands for unless another is set, as in vim.
+pub const DEFAULT_LEADER: char = '\\';
+
+// What the keys typed so far stand for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    // The keys begin a longer mapping, so more keys are needed to decide.
+    Pending,
+    // The given number of keys, at the start, stand for the keys given. The keys after them are
+    // left to be resolved again.
+    Mapped(usize, Vec<char>),
+    // The first key is not mapped, and stands for itself.
+    Unmapped,
+}
+
+/**
+ * Key sequences mapped to the keys they stand for, by mode, consulted before the built-in
+ * bindings. Like vim's `noremap`, the keys a mapping stands for are not mapped again, so `v` can be
+ * mapped to `vv` without recursing.
+ *
+ * Typed keys are resolved deterministically, as vim resolves them: while they begin a longer
+ * mapping, more keys are awaited, even when they are a complete mapping themselves; once they
+ * cannot, the longest mapping they begin with is used, or else the first key stands for itself,
+ * and the keys that follow are resolved afresh.
+ *
+ * A keymap file is a subset of TOML: an optional `leader` key, then a table per mode, like
+ * `[insert]`, each mapping keys to a string of keys, like `n = "v"` or
+ * `"<leader>T" = "ve0,1<Enter>"`. Keys are written like vim's, with `<Enter>`, `<Esc>`, `<BS>`,
+ * `<Tab>`, `<Left>`, `<Right>`, `<Up>`, `<Down>`, `<Space>`, `<lt>` and `<C-x>` for keys that are
+ * not printable, and `<leader>` for the leader key at the time the mapping is made.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    mappings: BTreeMap<(KeymapMode, Vec<char>), Vec<char>>,
+    leader: char,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap {
+            mappings: BTreeMap::new(),
+            leader: DEFAULT_LEADER,
+        }
+    }
+}
+
+impl Keymap {
+    pub fn new() -> Keymap {
+        Keymap::default()
+    }
+
+    pub fn leader(&self) -> char {
+        self.leader
+    }
+
+    // Set the key `<leader>` stands for in mappings made from now on.
+    pub fn set_leader(&mut self, leader: char) {
+        self.leader = leader;
+    }
+
+    pub fn map(&mut self, mode: KeymapMode, keys: Vec<char>, to: Vec<char>) {
+        self.mappings.insert((mode, keys), to);
+    }
+
+    pub fn unmap(&mut self, mode: KeymapMode, keys: &[char]) -> bool {
+        self.mappings.remove(&(mode, keys.to_vec())).is_some()
+    }
+
+    pub fn get(&self, mode: KeymapMode, keys: &[char]) -> Option<&[char]> {
+        self.mappings.get(&(mode, keys.to_vec())).map(Vec::as_slice)
+    }
+
+    // The mappings, ordered by mode and keys.
+    pub fn mappings(&self) -> impl Iterator<Item = (KeymapMode, &[char], &[char])> + '_ {
+        self.mappings
+            .iter()
+            .map(|((mode, keys), to)| (*mode, keys.as_slice(), to.as_slice()))
+    }
+
+    // Keys in vim's notation, with `<leader>` standing for the leader key.
+    pub fn parse_keys(&self, notation: &str) -> Result<Vec<char>, String> {
+        parse_keys(notation, self.leader)
+    }
+
+    // What the keys typed in a mode stand for.
+    pub fn resolve(&self, mode: KeymapMode, typed: &[char]) -> Resolution {
+        if self
+            .mappings_in(mode)
+            .any(|(keys, _)| keys.len() > typed.len() && keys.starts_with(typed))
+        {
+            return Resolution::Pending;
+        }
+        self.resolve_now(mode, typed)
+    }
+
+    // What the keys typed in a mode stand for if no more keys are coming, e.g., once the timeout
+    // for typing the rest of a mapping has passed: never `Pending`.
+    pub fn resolve_now(&self, mode: KeymapMode, typed: &[char]) -> Resolution {
+        match self
+            .mappings_in(mode)
+            .filter(|(keys, _)| typed.starts_with(keys))
+            .max_by_key(|(keys, _)| keys.len())
+        {
+            Some((keys, to)) => Resolution::Mapped(keys.len(), to.clone()),
+            None => Resolution::Unmapped,
+        }
+    }
+
+    fn mappings_in(&self, mode: KeymapMode) -> impl Iterator<Item = (&Vec<char>, &Vec<char>)> {
+        self.mappings
+            .iter()
+            .filter(move |((mapped_mode, _), _)| *mapped_mode == mode)
+            .map(|((_, keys), to)| (keys, to))
+    }
+
+    // Add the mappings of a keymap file, replacing existing mappings of the same keys.
+    pub fn extend_from_toml(&mut self, text: &str) -> Result<(), ParseError> {
+        let mut mode = None;
+        for (i, line) in text.lines().enumerate() {
+            let error = |message: &str| ParseError::new(i + 1, message);
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let leader = |line: &str| -> Result<Option<char>, String> {
+                let (key, rest) = toml_key(line)?;
+                if key != "leader" {
+                    return Ok(None);
+                }
+                let rest = rest.trim_start().strip_prefix('=');
+                let (leader, _) = toml_string(rest.unwrap_or("").trim_start())?;
+                match parse_keys(&leader, DEFAULT_LEADER)?.as_slice() {
+                    [leader] => Ok(Some(*leader)),
+                    _ => Err(format!("Expected a single leader key, got '{}'", leader)),
+                }
+            };
+            if mode.is_none() {
+                if let Some(key) = leader(line).map_err(|err| error(&err))? {
+                    self.leader = key;
+                    continue;
+                }
+            }
+            if let Some(table) = line.strip_prefix('[') {
+                let name = table
+                    .strip_suffix(']')
+                    .ok_or_else(|| error("Unterminated table header"))?;
+                mode = Some(
+                    KeymapMode::parse(name.trim())
+                        .ok_or_else(|| error(&format!("Unknown mode: {}", name)))?,
+                );
+                continue;
+            }
+            let mode = mode.ok_or_else(|| error("Mapping outside of a mode's table"))?;
+            let (key, rest) = toml_key(line).map_err(|err| error(&err))?;
+            let rest = rest
+                .trim_start()
+                .strip_prefix('=')
+                .ok_or_else(|| error("Expected '=' after the key"))?;
+            let (keys, rest) = toml_string(rest.trim_start()).map_err(|err| error(&err))?;
+            let rest = rest.trim_start();
+            if !rest.is_empty() && !rest.starts_with('#') {
+                return Err(error(&format!("Unexpected text after the keys: {}", rest)));
+            }
+            let key = self.parse_keys(&key).map_err(|err| error(&err))?;
+            if key.is_empty() {
+                return Err(error("Expected keys to map"));
+            }
+            let keys = self.parse_keys(&keys).map_err(|err| error(&err))?;
+            self.map(mode, key, keys);
+        }
+        Ok(())
+    }
+}
+
+// A bare or quoted TOML key at the start of a line, and the rest of the line.
+fn toml_key(line: &str) -> Result<(String, &str), String> {
+    if line.starts_with('"') {
+        return toml_string(line);
+    }
+    let end = line
+        .find(|c: char| c.is_whitespace() || c == '=')
+        .unwrap_or(line.len());
+    if end == 0 {
+        return Err("Expected a key".to_string());
+    }
+    Ok((line[..end].to_string(), &line[end..]))
+}
+
+// A TOML basic string at the start of the text, and the text after it.
+fn toml_string(text: &str) -> Result<(String, &str), String> {
+    let mut chars = text.char_indices();
+    if chars.next().map(|(_, c)| c) != Some('"') {
+        return Err("Expected a quoted string".to_string());
+    }
+    let mut value = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, &text[i + 1..])),
+            '\\' => match chars.next().map(|(_, c)| c) {
+                Some('"') => value.push('"'),
+                Some('\\') => value.push('\\'),
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                other => return Err(format!("Unsupported escape: \\{}", other.unwrap_or(' '))),
+            },
+            _ => value.push(c),
+        }
+    }
+    Err("Unterminated string".to_string())
+}
+
+// The keys written in vim's notation, e.g., `ve0,1<Enter>`.
+pub fn parse_keys(notation: &str, leader: char) -> Result<Vec<char>, String> {
+    let mut keys = Vec::new();
+    let mut rest = notation;
+    while let Some(c) = rest.chars().next() {
+        let name = match (c, rest.find('>')) {
+            ('<', Some(end)) if end > 1 => &rest[1..end],
+            _ => {
+                keys.push(c);
+                rest = &rest[c.len_utf8()..];
+                continue;
+            }
+        };
+        let key = match name.to_ascii_lowercase().as_str() {
+            "enter" | "cr" => ENTER,
+            "esc" => ESC,
+            "bs" => BACKSPACE,
+            "tab" => TAB,
+            "left" => LEFT,
+            "right" => RIGHT,
+            "up" => UP,
+            "down" => DOWN,
+            "space" => ' ',
+            "lt" => '<',
+            "leader" => leader,
+            control => match control.strip_prefix("c-").map(str::as_bytes) {
+                Some([letter]) if letter.is_ascii_lowercase() => (letter & 0x1f) as char,
+                _ => return Err(format!("Unknown key: <{}>", name)),
+            },
+        };
+        keys.push(key);
+        rest = &rest[name.len() + 2..];
+    }
+    Ok(keys)
+}
+
+// The keys in the notation `parse_keys` reads.
+pub fn key_notation(keys: &[char]) -> String {
+    keys.iter()
+        .map(|key| match *key {
+            ENTER => "<Enter>".to_string(),
+            ESC => "<Esc>".to_string(),
+            BACKSPACE => "<BS>".to_string(),
+            TAB => "<Tab>".to_string(),
+            LEFT => "<Left>".to_string(),
+            RIGHT => "<Right>".to_string(),
+            UP => "<Up>".to_string(),
+            DOWN => "<Down>".to_string(),
+            ' ' => "<Space>".to_string(),
+            '<' => "<lt>".to_string(),
+            control @ '\u{1}'..='\u{1a}' => {
+                format!("<C-{}>", (control as u8 + b'a' - 1) as char)
+            }
+            key => key.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::keys::CTRL_R;
+
+    #[test]
+    fn parse_keymap_file() {
+        let mut keymap = Keymap::new();
+        let text = r#"
+            leader = "<Space>"
+
+            # Mappings for a keyboard without a V key.
+            [insert]
+            n = "v"
+            "<leader>T" = "ve0,1<Enter>"  # A vertex and an edge.
+
+            [command]
+            "<lt>" = "<C-r>"
+            r = "\"ap"
+        "#;
+        keymap.extend_from_toml(text).unwrap();
+        assert_eq!(' ', keymap.leader());
+        assert_eq!(Some(&['v'][..]), keymap.get(KeymapMode::Insert, &['n']));
+        assert_eq!(
+            Some(&['v', 'e', '0', ',', '1', ENTER][..]),
+            keymap.get(KeymapMode::Insert, &[' ', 'T'])
+        );
+        assert_eq!(Some(&[CTRL_R][..]), keymap.get(KeymapMode::Command, &['<']));
+        assert_eq!(
+            Some(&['"', 'a', 'p'][..]),
+            keymap.get(KeymapMode::Command, &['r'])
+        );
+        assert_eq!(None, keymap.get(KeymapMode::Command, &['n']));
+        assert_eq!(
+            "ve0,1<Enter>",
+            key_notation(&['v', 'e', '0', ',', '1', ENTER])
+        );
+
+        assert_eq!(
+            Err(ParseError::new(1, "Mapping outside of a mode's table")),
+            Keymap::new().extend_from_toml("n = \"v\"")
+        );
+        assert!(parse_keys("<F1>", DEFAULT_LEADER).is_err());
+    }
+
+    #[test]
+    fn resolve_prefers_longer_mappings() {
+        let mut keymap = Keymap::new();
+        let keys = |notation: &str| parse_keys(notation, DEFAULT_LEADER).unwrap();
+        keymap.map(KeymapMode::Command, keys("g"), keys("x"));
+        keymap.map(KeymapMode::Command, keys("gab"), keys("y"));
+        keymap.map(KeymapMode::Command, keys("<leader>d"), keys("dd"));
+
+        let resolve = |typed: &str| keymap.resolve(KeymapMode::Command, &keys(typed));
+        assert_eq!(Resolution::Pending, resolve("g"));
+        assert_eq!(Resolution::Pending, resolve("ga"));
+        assert_eq!(Resolution::Mapped(3, keys("y")), resolve("gab"));
+        assert_eq!(Resolution::Mapped(1, keys("x")), resolve("gac"));
+        assert_eq!(Resolution::Mapped(2, keys("dd")), resolve("\\d"));
+        assert_eq!(Resolution::Unmapped, resolve("\\x"));
+        assert_eq!(Resolution::Unmapped, resolve("x"));
+        assert_eq!(
+            Resolution::Unmapped,
+            keymap.resolve(KeymapMode::Insert, &keys("g"))
+        );
+
+        // Once no more keys are coming, the keys typed resolve as far as they can.
+        let resolve_now = |typed: &str| keymap.resolve_now(KeymapMode::Command, &keys(typed));
+        assert_eq!(Resolution::Mapped(1, keys("x")), resolve_now("ga"));
+        assert_eq!(Resolution::Unmapped, resolve_now("\\"));
+    }
+}
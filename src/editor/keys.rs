@@ -1,11 +1,185 @@
+pub const AT: char = '@';
+pub const APOSTROPHE: char = '\'';
+pub const A_LOWER: char = 'a';
+pub const BACKSPACE: char = '\u{e003}';
+pub const B_UPPER: char = 'B';
+pub const COLON: char = ':';
 pub const COMMA: char = ',';
+pub const CTRL_O: char = '\u{000f}';
+pub const CTRL_R: char = '\u{0012}';
+pub const CTRL_V: char = '\u{0016}';
+pub const C_LOWER: char = 'c';
+pub const C_UPPER: char = 'C';
 pub const DIGIT_0: char = '0';
 pub const DIGIT_1: char = '1';
 pub const DIGIT_2: char = '2';
+pub const DOT: char = '.';
+pub const DOWN: char = '\u{e015}';
+pub const D_LOWER: char = 'd';
 pub const ENTER: char = '\u{e007}';
 pub const ESC: char = '\u{001b}';
 pub const E_LOWER: char = 'e';
+pub const E_UPPER: char = 'E';
+pub const G_LOWER: char = 'g';
+pub const G_UPPER: char = 'G';
+pub const H_LOWER: char = 'h';
 pub const I_LOWER: char = 'i';
+pub const J_LOWER: char = 'j';
+pub const K_LOWER: char = 'k';
+pub const LEFT: char = '\u{e012}';
+pub const L_LOWER: char = 'l';
+pub const MINUS: char = '-';
+pub const M_UPPER: char = 'M';
+pub const N_LOWER: char = 'n';
+pub const N_UPPER: char = 'N';
+pub const O_LOWER: char = 'o';
+pub const PLUS: char = '+';
+pub const P_LOWER: char = 'p';
+pub const RIGHT: char = '\u{e014}';
+pub const QUOTE: char = '"';
+pub const Q_LOWER: char = 'q';
+pub const SLASH: char = '/';
+pub const S_LOWER: char = 's';
+pub const TAB: char = '\t';
+pub const T_LOWER: char = 't';
+pub const UP: char = '\u{e013}';
 pub const U_LOWER: char = 'u';
 pub const U_UPPER: char = 'U';
 pub const V_LOWER: char = 'v';
+pub const V_UPPER: char = 'V';
+pub const W_LOWER: char = 'w';
+pub const X_LOWER: char = 'x';
+pub const Y_LOWER: char = 'y';
+pub const Z_LOWER: char = 'z';
+
+// A key as a frontend reports it, with the modifiers held while it was pressed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeyEvent {
+    pub code: KeyCode,
+    pub modifiers: Modifiers,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeyCode {
+    // A printable key, e.g., `r`, which with Shift is `R`.
+    Char(char),
+    Enter,
+    Esc,
+    Backspace,
+    Tab,
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+}
+
+impl KeyEvent {
+    pub fn new(code: KeyCode) -> KeyEvent {
+        KeyEvent {
+            code,
+            modifiers: Modifiers::default(),
+        }
+    }
+
+    pub fn with_ctrl(mut self) -> KeyEvent {
+        self.modifiers.ctrl = true;
+        self
+    }
+
+    pub fn with_alt(mut self) -> KeyEvent {
+        self.modifiers.alt = true;
+        self
+    }
+
+    pub fn with_shift(mut self) -> KeyEvent {
+        self.modifiers.shift = true;
+        self
+    }
+
+    /**
+     * The key as the editor encodes it, which keymaps, macros and the mode machine work with:
+     * a printable key as itself, uppercase with Shift if it is a letter, Ctrl with a letter as
+     * its control character, like vim, and the other keys as their WebDriver codes. None for a
+     * combination the editor has no key for, e.g., anything with Alt or Ctrl with an arrow.
+     */
+    pub fn key(self) -> Option<char> {
+        let Modifiers { ctrl, alt, shift } = self.modifiers;
+        if alt {
+            return None;
+        }
+        match self.code {
+            KeyCode::Char(c) if ctrl => Some(c.to_ascii_lowercase())
+                .filter(char::is_ascii_lowercase)
+                .map(|c| (c as u8 & 0x1f) as char),
+            KeyCode::Char(c) if shift => Some(c.to_ascii_uppercase()),
+            KeyCode::Char(c) => Some(c),
+            _ if ctrl || shift => None,
+            KeyCode::Enter => Some(ENTER),
+            KeyCode::Esc => Some(ESC),
+            KeyCode::Backspace => Some(BACKSPACE),
+            KeyCode::Tab => Some(TAB),
+            KeyCode::Left => Some(LEFT),
+            KeyCode::Right => Some(RIGHT),
+            KeyCode::Up => Some(UP),
+            KeyCode::Down => Some(DOWN),
+        }
+    }
+
+    // The event the editor's encoding of a key stands for.
+    pub fn from_key(key: char) -> KeyEvent {
+        let code = match key {
+            ENTER => KeyCode::Enter,
+            ESC => KeyCode::Esc,
+            BACKSPACE => KeyCode::Backspace,
+            TAB => KeyCode::Tab,
+            LEFT => KeyCode::Left,
+            RIGHT => KeyCode::Right,
+            UP => KeyCode::Up,
+            DOWN => KeyCode::Down,
+            control @ '\u{1}'..='\u{1a}' => {
+                return KeyEvent::new(KeyCode::Char((control as u8 + b'a' - 1) as char)).with_ctrl()
+            }
+            letter if letter.is_ascii_uppercase() => {
+                return KeyEvent::new(KeyCode::Char(letter.to_ascii_lowercase())).with_shift()
+            }
+            key => KeyCode::Char(key),
+        };
+        KeyEvent::new(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_events_are_encoded_as_keys() {
+        let r = KeyEvent::new(KeyCode::Char('r'));
+        assert_eq!(Some(CTRL_R), r.with_ctrl().key());
+        assert_eq!(Some(CTRL_R), r.with_ctrl().with_shift().key());
+        assert_eq!(
+            Some(U_UPPER),
+            KeyEvent::new(KeyCode::Char('u')).with_shift().key()
+        );
+        assert_eq!(Some(UP), KeyEvent::new(KeyCode::Up).key());
+        assert_eq!(None, r.with_alt().key());
+        assert_eq!(None, KeyEvent::new(KeyCode::Left).with_ctrl().key());
+        assert_eq!(None, KeyEvent::new(KeyCode::Char('1')).with_ctrl().key());
+
+        for key in "aZ9:\u{12}\u{f}\t\u{e007}\u{1b}\u{e003}\u{e012}\u{e014}\u{e013}\u{e015}".chars()
+        {
+            assert_eq!(Some(key), KeyEvent::from_key(key).key());
+        }
+        assert_eq!(r.with_ctrl(), KeyEvent::from_key(CTRL_R));
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+
+use crate::graph::Edge;
+use crate::graph::Graph;
+use crate::graph::Vertex;
+
+// A change the two sides of a merge made differently. None stands for the element being absent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeConflict {
+    Vertex {
+        id: i64,
+        ours: Option<Vertex>,
+        theirs: Option<Vertex>,
+    },
+    Edge {
+        id: i64,
+        ours: Option<Edge>,
+        theirs: Option<Edge>,
+    },
+    // An edge one side kept or added refers to a vertex the other side removed. The edge is left
+    // out of the merge.
+    DanglingEdge {
+        edge: i64,
+        vertex: i64,
+    },
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let describe = |present: bool| if present { "changed" } else { "removed" };
+        match self {
+            MergeConflict::Vertex { id, ours, theirs } => write!(
+                f,
+                "Vertex {} was {} on one branch and {} on the other; keeping the first",
+                id,
+                describe(ours.is_some()),
+                describe(theirs.is_some())
+            ),
+            MergeConflict::Edge { id, ours, theirs } => write!(
+                f,
+                "Edge {} was {} on one branch and {} on the other; keeping the first",
+                id,
+                describe(ours.is_some()),
+                describe(theirs.is_some())
+            ),
+            MergeConflict::DanglingEdge { edge, vertex } => write!(
+                f,
+                "Edge {} refers to vertex {}, which was removed; leaving it out",
+                edge, vertex
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeResult {
+    pub merged: Graph,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+// An element's id, and its state on each side, where both sides changed it differently.
+type ElementConflict<T> = (i64, Option<T>, Option<T>);
+
+// Each element's merged state, and the elements both sides changed differently, by id.
+fn merge_elements<T: Clone + Eq + Hash>(
+    base: &HashMap<i64, T>,
+    ours: &HashMap<i64, T>,
+    theirs: &HashMap<i64, T>,
+) -> (HashMap<i64, T>, Vec<ElementConflict<T>>) {
+    let ids: BTreeSet<i64> = base
+        .keys()
+        .chain(ours.keys())
+        .chain(theirs.keys())
+        .copied()
+        .collect();
+    let mut merged = HashMap::new();
+    let mut conflicts = Vec::new();
+    for id in ids {
+        let (b, o, t) = (base.get(&id), ours.get(&id), theirs.get(&id));
+        let kept = if o == t || t == b {
+            o
+        } else if o == b {
+            t
+        } else {
+            conflicts.push((id, o.cloned(), t.cloned()));
+            o
+        };
+        if let Some(element) = kept {
+            merged.insert(id, element.clone());
+        }
+    }
+    (merged, conflicts)
+}
+
+/**
+ * Merge two versions of a graph that were both derived from a common base, e.g., the documents
+ * at two branches of the history. A change made on only one side is taken. When both sides
+ * changed an element differently, ours is kept and the conflict reported. Graph properties are
+ * taken from ours.
+ */
+pub fn three_way_merge(base: &Graph, ours: &Graph, theirs: &Graph) -> MergeResult {
+    let (vertices, vertex_conflicts) =
+        merge_elements(&base.vertices, &ours.vertices, &theirs.vertices);
+    let (mut edges, edge_conflicts) = merge_elements(&base.edges, &ours.edges, &theirs.edges);
+    let mut conflicts: Vec<MergeConflict> = vertex_conflicts
+        .into_iter()
+        .map(|(id, ours, theirs)| MergeConflict::Vertex { id, ours, theirs })
+        .chain(
+            edge_conflicts
+                .into_iter()
+                .map(|(id, ours, theirs)| MergeConflict::Edge { id, ours, theirs }),
+        )
+        .collect();
+
+    let mut dangling: Vec<(i64, i64)> = edges
+        .values()
+        .filter_map(|e| {
+            [e.source, e.target]
+                .iter()
+                .find(|v| !vertices.contains_key(v))
+                .map(|v| (e.id, *v))
+        })
+        .collect();
+    dangling.sort_unstable();
+    for (edge, vertex) in dangling {
+        edges.remove(&edge);
+        conflicts.push(MergeConflict::DanglingEdge { edge, vertex });
+    }
+
+    MergeResult {
+        merged: Graph {
+            vertices,
+            edges,
+            properties: ours.properties.clone(),
+        },
+        conflicts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> Graph {
+        let mut g = Graph::new();
+        for id in 0..3 {
+            g.add_vertex(Vertex::new(id));
+        }
+        g.add_edge(Edge::new(0, 0, 1).with_attribute("weight", "1"));
+        g
+    }
+
+    #[test]
+    fn changes_on_one_side_are_taken() {
+        let mut ours = base();
+        ours.add_vertex(Vertex::new(3));
+        ours.modify_edge(Edge::new(0, 0, 1).with_attribute("weight", "2"));
+        let mut theirs = base();
+        theirs.add_edge(Edge::new(1, 1, 2));
+        theirs.remove_vertex(Vertex::new(2));
+        theirs.add_vertex(Vertex::new(4).with_attribute("label", "new"));
+
+        let result = three_way_merge(&base(), &ours, &theirs);
+        assert!(result.conflicts.is_empty());
+        let mut expected = base();
+        expected.add_vertex(Vertex::new(3));
+        expected.add_vertex(Vertex::new(4).with_attribute("label", "new"));
+        expected.remove_vertex(Vertex::new(2));
+        expected.modify_edge(Edge::new(0, 0, 1).with_attribute("weight", "2"));
+        assert_eq!(expected, result.merged);
+    }
+
+    #[test]
+    fn conflicting_changes_are_reported() {
+        let mut ours = base();
+        ours.modify_vertex(Vertex::new(1).with_attribute("label", "ours"));
+        ours.add_edge(Edge::new(1, 2, 0));
+        let mut theirs = base();
+        theirs.modify_vertex(Vertex::new(1).with_attribute("label", "theirs"));
+        theirs.remove_vertex(Vertex::new(2));
+
+        let result = three_way_merge(&base(), &ours, &theirs);
+        assert_eq!(
+            vec![
+                MergeConflict::Vertex {
+                    id: 1,
+                    ours: Some(Vertex::new(1).with_attribute("label", "ours")),
+                    theirs: Some(Vertex::new(1).with_attribute("label", "theirs")),
+                },
+                MergeConflict::DanglingEdge { edge: 1, vertex: 2 },
+            ],
+            result.conflicts
+        );
+        assert_eq!(Some("ours"), result.merged.vertices[&1].attribute("label"));
+        assert!(!result.merged.edges.contains_key(&1));
+        assert!(result.merged.check_invariants().is_ok());
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+// How serious a message is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    // The answer to a query, e.g., `:ids`, or a report of what a command did.
+    Info,
+    // Something done only in part, e.g., an import that left out edges.
+    Warning,
+    // Something that could not be done, e.g., an edge to a missing vertex.
+    Error,
+}
+
+// An error or notice for the user, which the editor's caller decides how to show.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub severity: Severity,
+    pub text: String,
+}
+
+// How a frontend shows a message.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Presentation {
+    // Briefly, e.g., as a toast that goes away by itself.
+    Transient,
+    // Until the user dismisses it, since something the user asked for was not done.
+    Persistent,
+}
+
+impl Message {
+    pub fn new(severity: Severity, text: String) -> Message {
+        Message { severity, text }
+    }
+
+    pub fn presentation(&self) -> Presentation {
+        match self.severity {
+            Severity::Info | Severity::Warning => Presentation::Transient,
+            Severity::Error => Presentation::Persistent,
+        }
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+// The most messages kept in the history, like vim's default for `:messages`.
+pub const DEFAULT_HISTORY_LIMIT: usize = 200;
+
+/**
+ * Every message reported, oldest first, up to a limit past which the oldest are dropped, like
+ * vim's message history. Unlike the messages the caller takes, the history is kept until it is
+ * cleared, e.g., for a panel the user scrolls through or for `:messages`.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageHistory {
+    messages: VecDeque<Message>,
+    limit: usize,
+}
+
+impl Default for MessageHistory {
+    fn default() -> Self {
+        MessageHistory::with_limit(DEFAULT_HISTORY_LIMIT)
+    }
+}
+
+impl MessageHistory {
+    pub fn with_limit(limit: usize) -> MessageHistory {
+        MessageHistory {
+            messages: VecDeque::new(),
+            limit,
+        }
+    }
+
+    pub fn push(&mut self, message: Message) {
+        if self.limit == 0 {
+            return;
+        }
+        while self.messages.len() >= self.limit {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(message);
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    // The message at a position in the history, counting from the oldest.
+    pub fn get(&self, index: usize) -> Option<&Message> {
+        self.messages.get(index)
+    }
+
+    // The messages, oldest first; reversed, newest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Message> + '_ {
+        self.messages.iter()
+    }
+
+    // The messages at least as serious as the severity, oldest first.
+    pub fn at_least(&self, severity: Severity) -> impl DoubleEndedIterator<Item = &Message> + '_ {
+        self.messages
+            .iter()
+            .filter(move |message| message.severity >= severity)
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    // Cap the history, dropping its oldest messages as needed.
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        while self.messages.len() > limit {
+            self.messages.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.messages.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oldest_messages_are_dropped() {
+        let mut history = MessageHistory::with_limit(3);
+        for (severity, text) in [
+            (Severity::Error, "a"),
+            (Severity::Info, "b"),
+            (Severity::Warning, "c"),
+            (Severity::Error, "d"),
+        ]
+        .iter()
+        {
+            history.push(Message::new(*severity, text.to_string()));
+        }
+        let texts: Vec<&str> = history.iter().map(|m| m.text.as_str()).collect();
+        assert_eq!(vec!["b", "c", "d"], texts);
+        let serious: Vec<&str> = history
+            .at_least(Severity::Warning)
+            .rev()
+            .map(|m| m.text.as_str())
+            .collect();
+        assert_eq!(vec!["d", "c"], serious);
+        assert_eq!(
+            Presentation::Persistent,
+            history.get(2).unwrap().presentation()
+        );
+        assert_eq!(
+            Presentation::Transient,
+            history.get(0).unwrap().presentation()
+        );
+
+        history.set_limit(1);
+        assert_eq!(Some("d"), history.get(0).map(|m| m.text.as_str()));
+        assert_eq!(1, history.len());
+    }
+}
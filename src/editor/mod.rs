@@ -1,3 +1,44 @@
-pub mod mode;
+pub mod attribute_history;
+pub mod bindings;
+pub mod command;
+pub mod completion;
+pub mod compressed_diff;
+pub mod cursor;
+pub mod error;
+pub mod event;
+pub mod export_filter;
+pub mod fold;
+pub mod form;
+pub mod history;
+pub mod hooks;
+pub mod ids;
+pub mod import;
+#[cfg(feature = "json")]
+pub mod journal;
+pub mod jumplist;
+pub mod keymap;
 pub mod keys;
+pub mod merge;
+pub mod message;
+pub mod mode;
+pub mod mouse;
+#[cfg(feature = "json")]
+pub mod op_log;
+pub mod options;
+pub mod pending_text;
+pub mod plugin;
+pub mod references;
+pub mod region;
+pub mod registers;
+pub mod replay;
+pub mod scenario;
+pub mod search;
+#[cfg(feature = "json")]
+pub mod session;
 pub mod state;
+pub mod status;
+#[cfg(feature = "json")]
+pub mod trace;
+#[cfg(feature = "json")]
+pub mod undo_file;
+pub mod view;
@@ -1,16 +1,187 @@
+use std::collections::BTreeSet;
+
+use crate::editor::form::AttributeForm;
+use crate::editor::form::FieldPrompt;
+use crate::editor::keymap::key_notation;
+use crate::editor::keymap::KeymapMode;
 use crate::editor::keys::*;
+use crate::editor::mouse::MouseEvent;
+use crate::editor::mouse::Position;
+use crate::editor::pending_text::PendingText;
+use crate::editor::plugin::PluginOperation;
+use crate::editor::references::ElementRef;
 use crate::editor::state::Input;
+use crate::editor::status::PendingCommand;
+use crate::graph::Attributes;
 
 #[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EditorMode {
     // Like vim, Command mode is the default mode with no pending operations.
     Command,
     // Insert mode commands modify the graph object-at-a-time, like insert mode in vim allows you
     // to modify character-at-a-time.
     Insert,
+    // Creating a labeled vertex requires its label, typed out in full before the vertex is created.
+    VertexLabelPending(PendingText),
     // After the user declares they want to create an edge, the state machine requires extra
     // information regarding which vertices to connect.
-    InsertEdgePending(String),
+    InsertEdgePending(PendingText),
+    // Contracting an edge requires the id of the edge to contract.
+    ContractEdgePending(PendingText),
+    // Like vim's `c`, changing an edge requires the id of the edge to change.
+    ChangeEdgePending(PendingText),
+    // With an edge chosen, its source and target, which start out as they are, are edited and
+    // Enter re-targets the edge, keeping its id and attributes.
+    EdgeEndpointsPending { edge: i64, endpoints: PendingText },
+    // Merging vertices requires the ids of the vertex to keep and the vertex to merge into it.
+    MergeVerticesPending(PendingText),
+    // Like vim's command-line mode, an ex-style command is typed out in full before it runs.
+    ExCommandPending(PendingText),
+    // Editing an edge's weight requires the id of the edge to edit.
+    WeightEdgePending(PendingText),
+    // With an edge chosen, `+` and `-` adjust its weight, optionally preceded by a count of how
+    // many steps to adjust it by.
+    WeightEditing { edge: String, count: String },
+    // Like vim's `z` commands, a fold command is a letter naming the action followed by the id of
+    // the vertex anchoring the fold.
+    FoldPending(String),
+    // Editing an element's attributes requires the element, e.g., `v3` or `e0`.
+    AttributeElementPending(PendingText),
+    // A form of the element's attributes: `j` and `k` move between fields, Enter edits the
+    // selected field, `o` adds a field, `d` deletes the selected field, and Esc closes the form,
+    // committing its changes.
+    AttributeEditing(AttributeForm),
+    // Redoing into a branch other than the most recent one requires its number among the
+    // current edit's children, counting from 1 for the oldest.
+    RedoBranchPending(String),
+    // Like vim, a count typed before a command repeats it, e.g., `3u` undoes three edits.
+    CountPending(String),
+    // A count typed in Insert mode makes the command that follows in bulk, as a single edit,
+    // e.g., `5v` creates five vertices.
+    InsertCountPending(String),
+    // Like vim's `/`, a search is typed out in full before it runs. It matches vertices by id or
+    // label, and `n` and `N` move to the next and previous matches.
+    SearchPending(PendingText),
+    // Like vim's `q`, recording a macro requires the register to record it into, a letter.
+    MacroRecordPending,
+    // Like vim's `@`, replaying a macro requires its register, or `@` for the last one replayed.
+    MacroReplayPending,
+    // Like vim's visual mode, elements are chosen first and then acted on together. An element
+    // such as `v3` or `e0` followed by Enter is added to the selection, or removed if it is
+    // already selected. With nothing typed, an operator key or `x` acts on the selection, and `a`
+    // followed by `key=value` and Enter sets an attribute of every selected element. Like vim, `"`
+    // followed by a letter names the register an operator yanks into.
+    Visual(Selection),
+    // After `"`, the letter naming a register, and then the command using it, e.g., `"ap` pastes
+    // the subgraph in register a.
+    RegisterPending(Option<char>),
+    // Like vim's operator-pending mode, an operator key (`d`, `y` or `c`) is followed by its
+    // target: the operator key again for the vertex under the cursor, `'` and a letter for a
+    // mark, `n` for the matches of the last search, or `s` for the selection.
+    OperatorPending(PendingOperator),
+    // After `g`, the letter naming a jump: `g` to the first vertex, `s` to the next source and `t`
+    // to the next sink.
+    GotoPending,
+    // Like vim's `'`, jumping to a mark requires its letter.
+    MarkJumpPending,
+    // Like Visual mode's `a`, `key=value` followed by Enter sets an attribute of the vertex under
+    // the cursor.
+    CursorAttributePending(PendingText),
+    // After `e` anchors the source of a new edge at the vertex under the cursor, motions move the
+    // cursor to its target and Enter creates the edge.
+    EdgeTargetPending(i64),
+    // Like vim's visual block mode, after Ctrl-V anchors a corner of a region at the vertex under
+    // the cursor, motions move the cursor to the opposite corner and Enter chooses the vertices
+    // inside in Visual mode.
+    RegionPending(i64),
+}
+
+// An operator acting on elements, like vim's operators.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Operator {
+    // Remove the elements, and the edges of removed vertices.
+    Delete,
+    // Make the elements the editor's selection.
+    Yank,
+    // Remove the elements, as Delete does, and enter Insert mode to replace them.
+    Change,
+}
+
+impl Operator {
+    fn from_key(key: char) -> Option<Operator> {
+        match key {
+            D_LOWER => Some(Operator::Delete),
+            Y_LOWER => Some(Operator::Yank),
+            C_LOWER => Some(Operator::Change),
+            _ => None,
+        }
+    }
+
+    pub fn key(self) -> char {
+        match self {
+            Operator::Delete => D_LOWER,
+            Operator::Yank => Y_LOWER,
+            Operator::Change => C_LOWER,
+        }
+    }
+
+    // The mode entered once the operator is carried out.
+    fn next_mode(self) -> EditorMode {
+        match self {
+            Operator::Change => Insert,
+            _ => Command,
+        }
+    }
+}
+
+// The elements an operator acts on, resolved by the editor when it is carried out.
+#[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Target {
+    // The vertex under the cursor.
+    Cursor,
+    Mark(char),
+    // The vertices the last search matches.
+    SearchMatches,
+    // The editor's selection.
+    Selection,
+    // The elements chosen in Visual mode.
+    Elements(BTreeSet<ElementRef>),
+}
+
+// An operator waiting for its target.
+#[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PendingOperator {
+    pub operator: Operator,
+    // The register the operator yanks into, if not the unnamed one.
+    pub register: Option<char>,
+    // Whether `'` was typed, so that a mark's letter is expected.
+    pub expecting_mark: bool,
+}
+
+impl PendingOperator {
+    fn new(operator: Operator, register: Option<char>) -> PendingOperator {
+        PendingOperator {
+            operator,
+            register,
+            expecting_mark: false,
+        }
+    }
+}
+
+// The elements chosen in Visual mode, and what is being typed.
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Selection {
+    pub elements: BTreeSet<ElementRef>,
+    pub typed: String,
+    // Whether the typed text is an attribute to set rather than an element.
+    pub setting_attribute: bool,
+    // The register to yank into, if not the unnamed one.
+    pub register: Option<char>,
 }
 
 /**
@@ -20,11 +191,206 @@ pub enum EditorMode {
  * state.
  */
 #[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ModalOperation {
-    CreateNewVertex,
+    // Create a vertex, with a label if one was typed.
+    CreateNewVertex { label: Option<String> },
     CreateNewEdge(String),
+    // Anchor the source of a new edge at the vertex under the cursor. The editor enters
+    // EdgeTargetPending if there is one.
+    AnchorEdgeSource,
+    // Create an edge from a vertex to the vertex under the cursor.
+    CreateEdgeToCursor(i64),
+    // Create an edge to the target of the last edge created, e.g., to fan in to a hub.
+    CreateEdgeToLastTarget(EdgeSource),
+    // Anchor a corner of a region at the vertex under the cursor. The editor enters RegionPending
+    // if there is one.
+    AnchorRegion,
+    // Choose the vertices in the region from a vertex to the vertex under the cursor. The editor
+    // enters Visual mode with them chosen.
+    SelectRegion(i64),
+    ContractEdge(String),
+    // Choose an edge to change the endpoints of. The editor enters EdgeEndpointsPending if it
+    // exists.
+    ChangeEdge(String),
+    // Give an edge new endpoints, typed as for a new edge.
+    RetargetEdge(i64, String),
+    MergeVertices(String),
+    // Replace the vertex reference being typed with its best completion.
+    AcceptCompletion,
+    // Adjust the weight of an edge by a number of steps, which may be negative.
+    AdjustWeight(String, i64),
+    Fold(FoldAction, String),
+    // Open the attribute form of an element. The editor enters AttributeEditing if it exists.
+    OpenAttributeForm(String),
+    // Replace the attributes of an element.
+    SetAttributes(String, Attributes),
+    ExCommand(String),
     Undo,
     Redo,
+    // Redo into the numbered child of the current history node.
+    RedoBranch(String),
+    // Undo every edit, back to the document the history started from, in one step.
+    UndoAll,
+    // Redo as far as the current branch goes, in one step. Like Redo, the most recent edit is
+    // followed where the branch forks.
+    RedoToTip,
+    MoveCursor(Motion),
+    // Move the cursor to the vertex a mark is on.
+    JumpToMark(char),
+    // Like vim's Ctrl-O and Ctrl-I, move the cursor to where it was before the previous jump, or
+    // back to where it was after the next one.
+    JumpBack,
+    JumpForward,
+    // Move the cursor to the first vertex matching a search.
+    Search(String),
+    // Move the cursor to the match of the last search after the cursor, or before it.
+    NextMatch,
+    PreviousMatch,
+    // Start recording the inputs that follow into a register, until `q` is pressed in Command mode.
+    StartMacro(char),
+    // Stop recording a macro if one is being recorded, or else ask for a register to record one
+    // into. The editor enters MacroRecordPending in the latter case.
+    RecordMacro,
+    // Evaluate the inputs recorded in a register.
+    ReplayMacro(char),
+    // Carry out the last operation that changed the document again, like vim's `.`.
+    RepeatLastChange,
+    // Carry out an operation a number of times. Repeated undo and redo move through the history
+    // in a single step.
+    Repeat(usize, Box<ModalOperation>),
+    // Carry out an operator on a target. Like vim, every operator puts the subgraph the target
+    // induces in a register, or the unnamed one.
+    Operate(Operator, Target, Option<char>),
+    // Add the subgraph in a register, or the unnamed one, to the document with fresh ids.
+    Paste(Option<char>),
+    // Set an attribute of every element of a target to a value.
+    SetAttribute(Target, String, String),
+    // Act on the mouse, resolving what it is on in the document.
+    Mouse(MouseEvent),
+    // Move a vertex, setting its `pos` attribute.
+    MoveVertex(i64, Position),
+    // Add the edges of pasted text, a `source,target` pair of vertices a line, creating the
+    // vertices the document does not have.
+    PasteEdges(String),
+    // An operation a plugin adds, which the plugin carries out.
+    Plugin(PluginOperation),
+}
+
+impl ModalOperation {
+    // Whether the operation is an edit of the document, which `.` can repeat.
+    pub fn is_change(&self) -> bool {
+        match self {
+            CreateNewVertex { .. }
+            | CreateNewEdge(_)
+            | CreateEdgeToCursor(_)
+            | CreateEdgeToLastTarget(_)
+            | ContractEdge(_)
+            | RetargetEdge(_, _)
+            | MergeVertices(_)
+            | AdjustWeight(_, _)
+            | SetAttributes(_, _)
+            | SetAttribute(_, _, _)
+            | MoveVertex(_, _)
+            | PasteEdges(_)
+            | Plugin(_)
+            | Paste(_) => true,
+            Operate(operator, _, _) => *operator != Operator::Yank,
+            Repeat(_, op) => op.is_change(),
+            _ => false,
+        }
+    }
+
+    // Whether the operation changes the document, including by moving through its history.
+    pub fn modifies_document(&self) -> bool {
+        match self {
+            Undo | Redo | RedoBranch(_) | UndoAll | RedoToTip => true,
+            Repeat(_, op) => op.modifies_document(),
+            op => op.is_change(),
+        }
+    }
+}
+
+// Where an edge the editor chooses the endpoints of starts.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EdgeSource {
+    // The vertex under the cursor.
+    Cursor,
+    // The vertex created last.
+    CreatedVertex,
+}
+
+// A Command mode key moving the cursor, like vim's motions.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Motion {
+    // The vertex with the next larger id.
+    NextVertex,
+    PreviousVertex,
+    // The target of an edge out of the vertex.
+    OutgoingEdge,
+    // The source of an edge into the vertex.
+    IncomingEdge,
+    // Like vim's `gg` and `G`, the vertices with the smallest and largest ids.
+    FirstVertex,
+    LastVertex,
+    // The next vertex without edges into it, or out of it, by id, wrapping around.
+    NextSource,
+    NextSink,
+}
+
+impl Motion {
+    fn from_key(key: char) -> Option<Motion> {
+        match key {
+            // Like vim, the arrow keys move as `hjkl` do.
+            J_LOWER | DOWN => Some(Motion::NextVertex),
+            K_LOWER | UP => Some(Motion::PreviousVertex),
+            L_LOWER | RIGHT => Some(Motion::OutgoingEdge),
+            H_LOWER | LEFT => Some(Motion::IncomingEdge),
+            G_UPPER => Some(Motion::LastVertex),
+            _ => None,
+        }
+    }
+
+    // Whether the motion is a jump, which the jumplist remembers.
+    pub fn is_jump(self) -> bool {
+        !matches!(
+            self,
+            Motion::NextVertex
+                | Motion::PreviousVertex
+                | Motion::OutgoingEdge
+                | Motion::IncomingEdge
+        )
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FoldAction {
+    // Open or close the fold at a vertex, creating a closed fold of its descendants if needed.
+    Toggle,
+    Open,
+    // Close the fold at a vertex, creating a fold of its descendants if needed.
+    Close,
+    Delete,
+    OpenAll,
+    CloseAll,
+}
+
+impl FoldAction {
+    // The action named by the key following `z`.
+    fn from_key(key: char) -> Option<FoldAction> {
+        match key {
+            'a' => Some(FoldAction::Toggle),
+            'o' => Some(FoldAction::Open),
+            'c' => Some(FoldAction::Close),
+            'd' => Some(FoldAction::Delete),
+            'R' => Some(FoldAction::OpenAll),
+            'M' => Some(FoldAction::CloseAll),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -43,32 +409,471 @@ use ModalOperation::*;
 use TransitionResult::*;
 
 impl EditorMode {
+    // The partially typed vertex reference in a mode that expects vertex references, if any.
+    pub fn pending_vertex_reference(&self) -> Option<&str> {
+        match self {
+            InsertEdgePending(s)
+            | MergeVerticesPending(s)
+            | EdgeEndpointsPending { endpoints: s, .. } => {
+                Some(s.as_str().rsplit(',').next().unwrap_or(""))
+            }
+            _ => None,
+        }
+    }
+
+    // Replace the vertex reference being typed, returning false if none is.
+    pub fn replace_vertex_reference(&mut self, text: &str) -> bool {
+        match self {
+            InsertEdgePending(s)
+            | MergeVerticesPending(s)
+            | EdgeEndpointsPending { endpoints: s, .. } => {
+                let start = s.as_str().rfind(',').map_or(0, |i| i + 1);
+                *s = PendingText::from(format!("{}{}", &s.as_str()[..start], text).as_str());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /**
+     * Whether the edits made in this mode are coalesced into a single history node. Like vim,
+     * everything done between entering Insert mode and leaving it is undone at once, including
+     * edits made in the modes entered from Insert mode.
+     */
+    pub fn coalesces_edits(&self) -> bool {
+        matches!(
+            self,
+            Insert
+                | InsertCountPending(_)
+                | VertexLabelPending(_)
+                | InsertEdgePending(_)
+                | ContractEdgePending(_)
+                | ChangeEdgePending(_)
+                | EdgeEndpointsPending { .. }
+                | MergeVerticesPending(_)
+                | WeightEdgePending(_)
+                | WeightEditing { .. }
+                | AttributeElementPending(_)
+                | AttributeEditing(_)
+        )
+    }
+
+    // The text typed so far in a mode that expects text, if any.
+    pub fn pending_text(&self) -> Option<&str> {
+        match self {
+            VertexLabelPending(s)
+            | InsertEdgePending(s)
+            | ContractEdgePending(s)
+            | ChangeEdgePending(s)
+            | EdgeEndpointsPending { endpoints: s, .. }
+            | MergeVerticesPending(s)
+            | ExCommandPending(s)
+            | WeightEdgePending(s)
+            | AttributeElementPending(s)
+            | SearchPending(s)
+            | CursorAttributePending(s) => Some(s.as_str()),
+            FoldPending(s) | RedoBranchPending(s) => Some(s),
+            Visual(selection) if selection.setting_attribute || !selection.typed.is_empty() => {
+                Some(&selection.typed)
+            }
+            AttributeEditing(form) => form.prompt().map(FieldPrompt::text),
+            _ => None,
+        }
+    }
+
+    // What has been typed toward the command in progress, if one is.
+    pub fn pending_command(&self) -> Option<PendingCommand> {
+        let mut command = PendingCommand {
+            count: self.count(),
+            ..PendingCommand::default()
+        };
+        let typed = |key: char, text: &str| format!("{}{}", key, text);
+        match self {
+            Command | Insert | AttributeEditing(_) => return None,
+            VertexLabelPending(s) => command.keys = typed(V_UPPER, s.as_str()),
+            InsertEdgePending(s) => command.keys = typed(E_LOWER, s.as_str()),
+            ContractEdgePending(s) => command.keys = typed(C_UPPER, s.as_str()),
+            ChangeEdgePending(s) | EdgeEndpointsPending { endpoints: s, .. } => {
+                command.keys = typed(C_LOWER, s.as_str())
+            }
+            MergeVerticesPending(s) => command.keys = typed(M_UPPER, s.as_str()),
+            ExCommandPending(s) => command.keys = typed(COLON, s.as_str()),
+            WeightEdgePending(s) => command.keys = typed(W_LOWER, s.as_str()),
+            AttributeElementPending(s) | CursorAttributePending(s) => {
+                command.keys = typed(A_LOWER, s.as_str())
+            }
+            SearchPending(s) => command.keys = typed(SLASH, s.as_str()),
+            FoldPending(s) => command.keys = typed(Z_LOWER, s),
+            RedoBranchPending(s) => command.keys = typed(B_UPPER, s),
+            WeightEditing { .. } | CountPending(_) | InsertCountPending(_) => {}
+            MacroRecordPending => command.keys = Q_LOWER.to_string(),
+            GotoPending => command.keys = G_LOWER.to_string(),
+            MarkJumpPending => command.keys = APOSTROPHE.to_string(),
+            MacroReplayPending => command.keys = AT.to_string(),
+            RegisterPending(None) => command.keys = QUOTE.to_string(),
+            EdgeTargetPending(_) => command.keys = E_LOWER.to_string(),
+            RegionPending(_) => command.keys = key_notation(&[CTRL_V]),
+            RegisterPending(Some(register)) => command.register = Some(*register),
+            OperatorPending(pending) => {
+                command.register = pending.register;
+                command.operator = Some(pending.operator);
+                if pending.expecting_mark {
+                    command.keys = APOSTROPHE.to_string();
+                }
+            }
+            Visual(selection) => {
+                command.register = selection.register;
+                command.keys = if selection.setting_attribute {
+                    typed(A_LOWER, &selection.typed)
+                } else {
+                    selection.typed.clone()
+                };
+            }
+        }
+        if command == PendingCommand::default() {
+            return None;
+        }
+        Some(command)
+    }
+
+    // The count typed before a command, if any.
+    pub fn count(&self) -> Option<usize> {
+        match self {
+            CountPending(count) | InsertCountPending(count) => {
+                Some(count.parse().unwrap_or(usize::MAX))
+            }
+            WeightEditing { count, .. } if !count.is_empty() => {
+                Some(count.parse().unwrap_or(usize::MAX))
+            }
+            _ => None,
+        }
+    }
+
+    // The mode whose keymap applies to keys typed in this mode, if keys can be mapped in it.
+    pub fn keymap_mode(&self) -> Option<KeymapMode> {
+        match self {
+            Command => Some(KeymapMode::Command),
+            Insert => Some(KeymapMode::Insert),
+            Visual(selection) if selection.typed.is_empty() && !selection.setting_attribute => {
+                Some(KeymapMode::Visual)
+            }
+            _ => None,
+        }
+    }
+
     // Transition from one mode to another, possibly the same,
     // and optionally emitting a document-modifying operation
     // or an error.
     pub fn transition(self, input: Input) -> TransitionResult {
+        match input {
+            Input::Key(key) => self.transition_on_key(key),
+            Input::Press(event) => match event.key() {
+                Some(key) => self.transition_on_key(key),
+                None => Error(format!("Unsupported key: {:?}", event), self),
+            },
+            Input::Mouse(event) => match self {
+                Command | Visual(_) => Apply(Mouse(event), self),
+                _ => self.unsupported(input),
+            },
+            Input::Paste(text) => match self {
+                Insert => Apply(PasteEdges(text), Insert),
+                _ => self.unsupported(Input::Paste(text)),
+            },
+            // Time passing only matters to keys awaiting a mapping, which are resolved before
+            // they reach the mode.
+            Input::Tick(_) => ModeChange(self),
+        }
+    }
+
+    fn transition_on_key(self, key: char) -> TransitionResult {
         match self {
-            Command => match input {
-                Input::Key(I_LOWER) => ModeChange(Insert),
-                Input::Key(U_LOWER) => Apply(Undo, Command),
-                Input::Key(U_UPPER) => Apply(Redo, Command),
-                _ => self.unknown_command(input),
+            Command => match key {
+                I_LOWER => ModeChange(Insert),
+                U_LOWER => Apply(Undo, Command),
+                U_UPPER | CTRL_R => Apply(Redo, Command),
+                digit @ '1'..='9' => ModeChange(CountPending(digit.to_string())),
+                B_UPPER => ModeChange(RedoBranchPending("".to_string())),
+                COLON => ModeChange(ExCommandPending(PendingText::new())),
+                Z_LOWER => ModeChange(FoldPending("".to_string())),
+                V_LOWER => ModeChange(Visual(Selection::default())),
+                DOT => Apply(RepeatLastChange, Command),
+                Q_LOWER => Apply(RecordMacro, Command),
+                AT => ModeChange(MacroReplayPending),
+                P_LOWER => Apply(Paste(None), Command),
+                QUOTE => ModeChange(RegisterPending(None)),
+                SLASH => ModeChange(SearchPending(PendingText::new())),
+                A_LOWER => ModeChange(CursorAttributePending(PendingText::new())),
+                E_LOWER => Apply(AnchorEdgeSource, Command),
+                E_UPPER => Apply(CreateEdgeToLastTarget(EdgeSource::Cursor), Command),
+                CTRL_V => Apply(AnchorRegion, Command),
+                G_LOWER => ModeChange(GotoPending),
+                APOSTROPHE => ModeChange(MarkJumpPending),
+                CTRL_O => Apply(JumpBack, Command),
+                // Like vim, Ctrl-I, which is the same key as Tab.
+                TAB => Apply(JumpForward, Command),
+                // Like vim's `x`, a shorthand for `dd`.
+                X_LOWER => Apply(Operate(Operator::Delete, Target::Cursor, None), Command),
+                N_LOWER => Apply(NextMatch, Command),
+                N_UPPER => Apply(PreviousMatch, Command),
+                key => match (Motion::from_key(key), Operator::from_key(key)) {
+                    (Some(motion), _) => Apply(MoveCursor(motion), Command),
+                    (None, Some(operator)) => {
+                        ModeChange(OperatorPending(PendingOperator::new(operator, None)))
+                    }
+                    (None, None) => self.unknown_command(key),
+                },
+            },
+            Insert => match key {
+                ESC => ModeChange(Command),
+                V_LOWER => Apply(CreateNewVertex { label: None }, Insert),
+                V_UPPER => ModeChange(VertexLabelPending(PendingText::new())),
+                E_LOWER => ModeChange(InsertEdgePending(PendingText::new())),
+                E_UPPER => Apply(CreateEdgeToLastTarget(EdgeSource::CreatedVertex), Insert),
+                C_UPPER => ModeChange(ContractEdgePending(PendingText::new())),
+                C_LOWER => ModeChange(ChangeEdgePending(PendingText::new())),
+                M_UPPER => ModeChange(MergeVerticesPending(PendingText::new())),
+                W_LOWER => ModeChange(WeightEdgePending(PendingText::new())),
+                A_LOWER => ModeChange(AttributeElementPending(PendingText::new())),
+                digit @ '1'..='9' => ModeChange(InsertCountPending(digit.to_string())),
+                _ => self.unknown_command(key),
+            },
+            InsertCountPending(count) => match key {
+                ESC => ModeChange(Insert),
+                BACKSPACE if count.len() == 1 => ModeChange(Insert),
+                BACKSPACE => ModeChange(InsertCountPending(count[..count.len() - 1].to_string())),
+                V_LOWER => match count.parse() {
+                    Ok(count) => Apply(
+                        Repeat(count, Box::new(CreateNewVertex { label: None })),
+                        Insert,
+                    ),
+                    Err(_) => Error(format!("Count is too large: {}", count), Insert),
+                },
+                digit if digit.is_ascii_digit() => {
+                    ModeChange(InsertCountPending(count + &digit.to_string()))
+                }
+                _ => InsertCountPending(count).unknown_command(key),
+            },
+            VertexLabelPending(s) => match key {
+                ESC => ModeChange(Insert),
+                BACKSPACE if s.is_empty() => ModeChange(Insert),
+                ENTER => {
+                    let label = Some(s.into_string()).filter(|label| !label.is_empty());
+                    Apply(CreateNewVertex { label }, Insert)
+                }
+                key => ModeChange(VertexLabelPending(s.typed(key))),
+            },
+            InsertEdgePending(s) => match key {
+                ESC => ModeChange(Insert),
+                BACKSPACE if s.is_empty() => ModeChange(Insert),
+                ENTER => Apply(CreateNewEdge(s.into_string()), Insert),
+                TAB => Apply(AcceptCompletion, InsertEdgePending(s)),
+                key => ModeChange(InsertEdgePending(s.typed(key))),
+            },
+            ChangeEdgePending(s) => match key {
+                ESC => ModeChange(Insert),
+                BACKSPACE if s.is_empty() => ModeChange(Insert),
+                ENTER => Apply(ChangeEdge(s.into_string()), Insert),
+                key => ModeChange(ChangeEdgePending(s.typed(key))),
+            },
+            EdgeEndpointsPending { edge, endpoints } => match key {
+                ESC => ModeChange(Insert),
+                BACKSPACE if endpoints.is_empty() => ModeChange(Insert),
+                ENTER => Apply(RetargetEdge(edge, endpoints.into_string()), Insert),
+                TAB => Apply(AcceptCompletion, EdgeEndpointsPending { edge, endpoints }),
+                key => ModeChange(EdgeEndpointsPending {
+                    edge,
+                    endpoints: endpoints.typed(key),
+                }),
+            },
+            ContractEdgePending(s) => match key {
+                ESC => ModeChange(Insert),
+                BACKSPACE if s.is_empty() => ModeChange(Insert),
+                ENTER => Apply(ContractEdge(s.into_string()), Insert),
+                key => ModeChange(ContractEdgePending(s.typed(key))),
+            },
+            SearchPending(s) => match key {
+                ESC => ModeChange(Command),
+                BACKSPACE if s.is_empty() => ModeChange(Command),
+                ENTER => Apply(Search(s.into_string()), Command),
+                key => ModeChange(SearchPending(s.typed(key))),
+            },
+            ExCommandPending(s) => match key {
+                ESC => ModeChange(Command),
+                BACKSPACE if s.is_empty() => ModeChange(Command),
+                ENTER => Apply(ExCommand(s.into_string()), Command),
+                key => ModeChange(ExCommandPending(s.typed(key))),
+            },
+            MergeVerticesPending(s) => match key {
+                ESC => ModeChange(Insert),
+                BACKSPACE if s.is_empty() => ModeChange(Insert),
+                ENTER => Apply(MergeVertices(s.into_string()), Insert),
+                TAB => Apply(AcceptCompletion, MergeVerticesPending(s)),
+                key => ModeChange(MergeVerticesPending(s.typed(key))),
+            },
+            WeightEdgePending(s) => match key {
+                ESC => ModeChange(Insert),
+                BACKSPACE if s.is_empty() => ModeChange(Insert),
+                ENTER => ModeChange(WeightEditing {
+                    edge: s.into_string(),
+                    count: "".to_string(),
+                }),
+                key => ModeChange(WeightEdgePending(s.typed(key))),
             },
-            Insert => match input {
-                Input::Key(ESC) => ModeChange(Command),
-                Input::Key(V_LOWER) => Apply(CreateNewVertex, Insert),
-                Input::Key(E_LOWER) => ModeChange(InsertEdgePending("".to_string())),
-                _ => self.unknown_command(input),
+            FoldPending(mut s) => match (key, s.chars().next().and_then(FoldAction::from_key)) {
+                (ESC, _) => ModeChange(Command),
+                (BACKSPACE, _) if s.is_empty() => ModeChange(Command),
+                (BACKSPACE, _) => {
+                    s.pop();
+                    ModeChange(FoldPending(s))
+                }
+                (key, None) => match FoldAction::from_key(key) {
+                    Some(action @ FoldAction::OpenAll) | Some(action @ FoldAction::CloseAll) => {
+                        Apply(Fold(action, "".to_string()), Command)
+                    }
+                    Some(_) => ModeChange(FoldPending(key.to_string())),
+                    None => Command.unknown_command(key),
+                },
+                (ENTER, Some(action)) => Apply(Fold(action, s[1..].to_string()), Command),
+                (next_key, Some(_)) => ModeChange(FoldPending(s + &next_key.to_string())),
             },
-            InsertEdgePending(s) => match input {
-                Input::Key(ESC) => ModeChange(Insert),
-                Input::Key(ENTER) => Apply(CreateNewEdge(s), Insert),
-                Input::Key(next_key) => ModeChange(InsertEdgePending(s + &next_key.to_string())),
+            AttributeElementPending(s) => match key {
+                ESC => ModeChange(Insert),
+                BACKSPACE if s.is_empty() => ModeChange(Insert),
+                ENTER => Apply(OpenAttributeForm(s.into_string()), Insert),
+                key => ModeChange(AttributeElementPending(s.typed(key))),
+            },
+            AttributeEditing(form) => form_transition(form, key),
+            Visual(selection) => visual_transition(selection, key),
+            RegisterPending(None) => match key {
+                ESC => ModeChange(Command),
+                register if register.is_ascii_lowercase() => {
+                    ModeChange(RegisterPending(Some(register)))
+                }
+                _ => RegisterPending(None).unknown_command(key),
+            },
+            RegisterPending(Some(register)) => match key {
+                ESC => ModeChange(Command),
+                P_LOWER => Apply(Paste(Some(register)), Command),
+                X_LOWER => Apply(
+                    Operate(Operator::Delete, Target::Cursor, Some(register)),
+                    Command,
+                ),
+                key => match Operator::from_key(key) {
+                    Some(operator) => ModeChange(OperatorPending(PendingOperator::new(
+                        operator,
+                        Some(register),
+                    ))),
+                    None => RegisterPending(Some(register)).unknown_command(key),
+                },
+            },
+            OperatorPending(pending) => operator_transition(pending, key),
+            CursorAttributePending(s) => match key {
+                ESC => ModeChange(Command),
+                BACKSPACE if s.is_empty() => ModeChange(Command),
+                ENTER => match parse_assignment(s.as_str()) {
+                    Ok((key, value)) => Apply(SetAttribute(Target::Cursor, key, value), Command),
+                    Err(err) => Error(err, CursorAttributePending(s)),
+                },
+                key => ModeChange(CursorAttributePending(s.typed(key))),
+            },
+            EdgeTargetPending(source) => match key {
+                ESC => ModeChange(Command),
+                ENTER => Apply(CreateEdgeToCursor(source), Command),
+                key => match Motion::from_key(key) {
+                    Some(motion) => Apply(MoveCursor(motion), EdgeTargetPending(source)),
+                    None => EdgeTargetPending(source).unknown_command(key),
+                },
+            },
+            RegionPending(corner) => match key {
+                ESC => ModeChange(Command),
+                ENTER => Apply(SelectRegion(corner), Command),
+                key => match Motion::from_key(key) {
+                    Some(motion) => Apply(MoveCursor(motion), RegionPending(corner)),
+                    None => RegionPending(corner).unknown_command(key),
+                },
+            },
+            MarkJumpPending => match key {
+                ESC => ModeChange(Command),
+                mark if mark.is_ascii_lowercase() => Apply(JumpToMark(mark), Command),
+                _ => MarkJumpPending.unknown_command(key),
+            },
+            GotoPending => match key {
+                ESC => ModeChange(Command),
+                G_LOWER => Apply(MoveCursor(Motion::FirstVertex), Command),
+                S_LOWER => Apply(MoveCursor(Motion::NextSource), Command),
+                T_LOWER => Apply(MoveCursor(Motion::NextSink), Command),
+                _ => GotoPending.unknown_command(key),
+            },
+            MacroRecordPending => match key {
+                ESC => ModeChange(Command),
+                register if register.is_ascii_lowercase() => Apply(StartMacro(register), Command),
+                _ => MacroRecordPending.unknown_command(key),
+            },
+            MacroReplayPending => match key {
+                ESC => ModeChange(Command),
+                register if register.is_ascii_lowercase() || register == AT => {
+                    Apply(ReplayMacro(register), Command)
+                }
+                _ => MacroReplayPending.unknown_command(key),
+            },
+            CountPending(count) => {
+                let repeat = |op| Repeat(count.parse().unwrap_or(usize::MAX), Box::new(op));
+                match key {
+                    ESC => ModeChange(Command),
+                    BACKSPACE if count.len() == 1 => ModeChange(Command),
+                    BACKSPACE => ModeChange(CountPending(count[..count.len() - 1].to_string())),
+                    U_LOWER => Apply(repeat(Undo), Command),
+                    U_UPPER | CTRL_R => Apply(repeat(Redo), Command),
+                    digit if digit.is_ascii_digit() => {
+                        ModeChange(CountPending(count + &digit.to_string()))
+                    }
+                    key => match Motion::from_key(key) {
+                        Some(motion) => Apply(repeat(MoveCursor(motion)), Command),
+                        None => CountPending(count).unknown_command(key),
+                    },
+                }
+            }
+            RedoBranchPending(s) => match key {
+                ESC => ModeChange(Command),
+                BACKSPACE if s.is_empty() => ModeChange(Command),
+                BACKSPACE => ModeChange(RedoBranchPending(s[..s.len() - 1].to_string())),
+                ENTER => Apply(RedoBranch(s), Command),
+                digit if digit.is_ascii_digit() => {
+                    ModeChange(RedoBranchPending(s + &digit.to_string()))
+                }
+                _ => RedoBranchPending(s).unknown_command(key),
+            },
+            WeightEditing { edge, count } => match key {
+                ESC | ENTER => ModeChange(Insert),
+                BACKSPACE => ModeChange(WeightEditing {
+                    edge,
+                    count: count[..count.len().saturating_sub(1)].to_string(),
+                }),
+                PLUS | MINUS => {
+                    let steps = count.parse::<i64>().unwrap_or(1);
+                    let steps = if key == MINUS { -steps } else { steps };
+                    Apply(
+                        AdjustWeight(edge.clone(), steps),
+                        WeightEditing {
+                            edge,
+                            count: "".to_string(),
+                        },
+                    )
+                }
+                digit if digit.is_ascii_digit() => ModeChange(WeightEditing {
+                    edge,
+                    count: count + &digit.to_string(),
+                }),
+                _ => WeightEditing { edge, count }.unknown_command(key),
             },
         }
     }
 
-    fn unknown_command(self, input: Input) -> TransitionResult {
+    fn unknown_command(self, key: char) -> TransitionResult {
+        self.unsupported(Input::Key(key))
+    }
+
+    fn unsupported(self, input: Input) -> TransitionResult {
         Error(
             format!(
                 "Input {:?} doesn't do anything in the current mode: {:#?}",
@@ -79,10 +884,144 @@ impl EditorMode {
     }
 }
 
+fn form_transition(mut form: AttributeForm, key: char) -> TransitionResult {
+    if form.prompt().is_some() {
+        match key {
+            ESC => form.cancel_prompt(),
+            ENTER => {
+                if let Err(err) = form.accept_prompt() {
+                    return Error(err, AttributeEditing(form));
+                }
+            }
+            _ => form.type_key(key),
+        }
+        return ModeChange(AttributeEditing(form));
+    }
+    match key {
+        ESC => {
+            let attributes = form.attributes();
+            return Apply(SetAttributes(form.element, attributes), Insert);
+        }
+        J_LOWER => form.select_next(),
+        K_LOWER => form.select_previous(),
+        O_LOWER => form.add_field(),
+        D_LOWER => form.delete_selected(),
+        ENTER => {
+            if !form.edit_selected() {
+                return Error("No field to edit".to_string(), AttributeEditing(form));
+            }
+        }
+        _ => return AttributeEditing(form).unknown_command(key),
+    }
+    ModeChange(AttributeEditing(form))
+}
+
+// The key and value of an attribute typed as `key=value`.
+fn parse_assignment(typed: &str) -> Result<(String, String), String> {
+    match typed.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("Expected key=value, got '{}'", typed)),
+    }
+}
+
+fn visual_transition(mut selection: Selection, key: char) -> TransitionResult {
+    let typing = selection.setting_attribute || !selection.typed.is_empty();
+    match key {
+        ESC if typing => {
+            selection.typed.clear();
+            selection.setting_attribute = false;
+        }
+        ESC => return ModeChange(Command),
+        BACKSPACE if typing => {
+            if selection.typed.pop().is_none() {
+                selection.setting_attribute = false;
+            }
+        }
+        _ if !selection.setting_attribute && selection.typed == QUOTE.to_string() => {
+            selection.typed.clear();
+            if !key.is_ascii_lowercase() {
+                return Error(format!("Not a register: {}", key), Visual(selection));
+            }
+            selection.register = Some(key);
+        }
+        ENTER if selection.setting_attribute => {
+            return match parse_assignment(&selection.typed) {
+                Ok((key, value)) => Apply(
+                    SetAttribute(Target::Elements(selection.elements), key, value),
+                    Command,
+                ),
+                Err(err) => {
+                    selection.typed.clear();
+                    Error(err, Visual(selection))
+                }
+            };
+        }
+        ENTER => {
+            let typed = std::mem::take(&mut selection.typed);
+            match ElementRef::parse(&typed) {
+                Some(element) => {
+                    if !selection.elements.remove(&element) {
+                        selection.elements.insert(element);
+                    }
+                }
+                None => return Error(format!("Not an element: {}", typed), Visual(selection)),
+            }
+        }
+        A_LOWER if !typing => selection.setting_attribute = true,
+        // Like vim's visual `x`, the same as `d`.
+        X_LOWER if !typing => return visual_transition(selection, D_LOWER),
+        _ => match Operator::from_key(key).filter(|_| !typing) {
+            Some(operator) => {
+                return Apply(
+                    Operate(
+                        operator,
+                        Target::Elements(selection.elements),
+                        selection.register,
+                    ),
+                    operator.next_mode(),
+                )
+            }
+            None => selection.typed.push(key),
+        },
+    }
+    ModeChange(Visual(selection))
+}
+
+fn operator_transition(mut pending: PendingOperator, key: char) -> TransitionResult {
+    let target = match key {
+        ESC => return ModeChange(Command),
+        mark if pending.expecting_mark && mark.is_ascii_lowercase() => Target::Mark(mark),
+        _ if pending.expecting_mark => return OperatorPending(pending).unknown_command(key),
+        APOSTROPHE => {
+            pending.expecting_mark = true;
+            return ModeChange(OperatorPending(pending));
+        }
+        N_LOWER => Target::SearchMatches,
+        S_LOWER => Target::Selection,
+        _ if key == pending.operator.key() => Target::Cursor,
+        _ => return OperatorPending(pending).unknown_command(key),
+    };
+    Apply(
+        Operate(pending.operator, target, pending.register),
+        pending.operator.next_mode(),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    // Type keys that each change the mode rather than apply an operation.
+    fn type_keys(mut mode: EditorMode, keys: &str) -> EditorMode {
+        for key in keys.chars() {
+            mode = match mode.transition(Input::Key(key)) {
+                ModeChange(next_mode) => next_mode,
+                result => panic!("Unexpected transition {:?}", result),
+            };
+        }
+        mode
+    }
+
     #[test]
     fn transition_to_insert_mode() {
         let mode = Command;
@@ -103,10 +1042,318 @@ mod tests {
     fn emit_operation_new_vertex() {
         let mode = Insert;
         let actual = mode.transition(Input::Key(V_LOWER));
-        let expected = Apply(CreateNewVertex, Insert);
+        let expected = Apply(CreateNewVertex { label: None }, Insert);
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn emit_operation_new_labeled_vertex() {
+        let mode = type_keys(Insert, "Vhub");
+        assert_eq!(VertexLabelPending("hub".into()), mode);
+        let label = Some("hub".to_string());
+        assert_eq!(
+            Apply(CreateNewVertex { label }, Insert),
+            mode.transition(Input::Key(ENTER))
+        );
+        // Without a label, the vertex is unlabeled.
+        assert_eq!(
+            Apply(CreateNewVertex { label: None }, Insert),
+            VertexLabelPending(PendingText::new()).transition(Input::Key(ENTER))
+        );
+    }
+
+    #[test]
+    fn emit_operation_new_vertices_with_count() {
+        let mode = type_keys(Insert, "12");
+        assert_eq!(
+            Apply(
+                Repeat(12, Box::new(CreateNewVertex { label: None })),
+                Insert
+            ),
+            mode.clone().transition(Input::Key(V_LOWER))
+        );
+        assert!(matches!(
+            mode.transition(Input::Key(E_LOWER)),
+            Error(_, InsertCountPending(_))
+        ));
+    }
+
+    #[test]
+    fn emit_operation_merge_vertices() {
+        let mode = type_keys(Insert, "M0,1");
+        let actual = mode.transition(Input::Key(ENTER));
+        let expected = Apply(MergeVertices("0,1".to_string()), Insert);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn echo_pending_commands() {
+        let typed = |keys: &str| {
+            let mode = type_keys(Command, keys);
+            mode.pending_command()
+        };
+        let echo = |keys: &str| typed(keys).map(|command| command.to_string());
+
+        let command = typed("\"ad'").unwrap();
+        assert_eq!(Some('a'), command.register);
+        assert_eq!(Some(Operator::Delete), command.operator);
+        assert_eq!("\"ad'", command.to_string());
+        assert_eq!(Some("12".to_string()), echo("12"));
+        assert_eq!(Some("e0,1".to_string()), echo("ie0,1"));
+        assert_eq!(Some(":w".to_string()), echo(":w"));
+        assert_eq!(None, echo("v"));
+        assert_eq!(None, echo("i"));
+    }
+
+    #[test]
+    fn edit_pending_text() {
+        // A mistyped target is corrected, and the source edited in place.
+        let mode = type_keys(Insert, "e0,2\u{e003}1\u{e012}\u{e012}\u{e012}1");
+        assert_eq!(
+            Apply(CreateNewEdge("10,1".to_string()), Insert),
+            mode.transition(Input::Key(ENTER))
+        );
+
+        // Backspace with nothing typed leaves the mode, like vim's command line.
+        assert_eq!(
+            ModeChange(Command),
+            ExCommandPending(PendingText::new()).transition(Input::Key(BACKSPACE))
+        );
+        assert_eq!(
+            ModeChange(Command),
+            CountPending("3".to_string()).transition(Input::Key(BACKSPACE))
+        );
+        let endpoints = EdgeEndpointsPending {
+            edge: 0,
+            endpoints: PendingText::new(),
+        };
+        assert_eq!(
+            ModeChange(Insert),
+            endpoints.transition(Input::Key(BACKSPACE))
+        );
+    }
+
+    #[test]
+    fn emit_operation_adjust_weight_with_count() {
+        let mode = type_keys(Insert, "w2\u{e007}10");
+        let editing = WeightEditing {
+            edge: "2".to_string(),
+            count: "".to_string(),
+        };
+        assert_eq!(
+            Apply(AdjustWeight("2".to_string(), -10), editing.clone()),
+            mode.transition(Input::Key(MINUS))
+        );
+        assert_eq!(
+            Apply(AdjustWeight("2".to_string(), 1), editing.clone()),
+            editing.transition(Input::Key(PLUS))
+        );
+    }
+
+    #[test]
+    fn emit_operation_fold() {
+        let mode = type_keys(Command, "za12");
+        assert_eq!(
+            Apply(Fold(FoldAction::Toggle, "12".to_string()), Command),
+            mode.transition(Input::Key(ENTER))
+        );
+        assert_eq!(
+            Apply(Fold(FoldAction::CloseAll, "".to_string()), Command),
+            FoldPending("".to_string()).transition(Input::Key('M'))
+        );
+    }
+
+    #[test]
+    fn emit_operation_set_attributes() {
+        let mut attributes = Attributes::new();
+        attributes.insert("label".to_string(), "a".to_string());
+        let mode = type_keys(
+            AttributeEditing(AttributeForm::new("v1", &attributes)),
+            "\u{e007}b\u{e007}ox=1\u{e007}",
+        );
+        attributes.insert("label".to_string(), "b".to_string());
+        attributes.insert("x".to_string(), "1".to_string());
+        assert_eq!(
+            Apply(SetAttributes("v1".to_string(), attributes), Insert),
+            mode.transition(Input::Key(ESC))
+        );
+    }
+
+    #[test]
+    fn emit_operation_redo_branch() {
+        let mode = type_keys(Command, "B2");
+        assert!(matches!(
+            mode.clone().transition(Input::Key('x')),
+            Error(_, _)
+        ));
+        assert_eq!(
+            Apply(RedoBranch("2".to_string()), Command),
+            mode.transition(Input::Key(ENTER))
+        );
+    }
+
+    #[test]
+    fn emit_operation_counted_undo_and_redo() {
+        let mode = type_keys(Command, "12");
+        assert_eq!(
+            Apply(Repeat(12, Box::new(Undo)), Command),
+            mode.clone().transition(Input::Key(U_LOWER))
+        );
+        assert_eq!(
+            Apply(Repeat(12, Box::new(Redo)), Command),
+            mode.transition(Input::Key(CTRL_R))
+        );
+        // As in vim, a count cannot start with 0.
+        assert!(matches!(
+            Command.transition(Input::Key(DIGIT_0)),
+            Error(_, Command)
+        ));
+    }
+
+    #[test]
+    fn emit_operation_macros() {
+        assert_eq!(
+            Apply(RecordMacro, Command),
+            Command.transition(Input::Key(Q_LOWER))
+        );
+        assert_eq!(
+            Apply(StartMacro('a'), Command),
+            MacroRecordPending.transition(Input::Key('a'))
+        );
+        assert!(matches!(
+            MacroRecordPending.transition(Input::Key(DIGIT_1)),
+            Error(_, MacroRecordPending)
+        ));
+        assert_eq!(
+            ModeChange(MacroReplayPending),
+            Command.transition(Input::Key(AT))
+        );
+        assert_eq!(
+            Apply(ReplayMacro(AT), Command),
+            MacroReplayPending.transition(Input::Key(AT))
+        );
+    }
+
+    #[test]
+    fn emit_operation_motions() {
+        assert_eq!(
+            Apply(MoveCursor(Motion::OutgoingEdge), Command),
+            Command.transition(Input::Key(L_LOWER))
+        );
+        assert_eq!(
+            Apply(Repeat(3, Box::new(MoveCursor(Motion::NextVertex))), Command),
+            CountPending("3".to_string()).transition(Input::Key(J_LOWER))
+        );
+    }
+
+    #[test]
+    fn emit_operation_operator_with_target() {
+        let transitions = |keys: &str| {
+            let last = keys.chars().last().unwrap();
+            type_keys(Command, &keys[..keys.len() - last.len_utf8()]).transition(Input::Key(last))
+        };
+        assert_eq!(
+            Apply(Operate(Operator::Delete, Target::Cursor, None), Command),
+            transitions("dd")
+        );
+        assert_eq!(
+            Apply(
+                Operate(Operator::Yank, Target::Mark('a'), Some('b')),
+                Command
+            ),
+            transitions("\"by'a")
+        );
+        assert_eq!(
+            Apply(
+                Operate(Operator::Change, Target::SearchMatches, None),
+                Insert
+            ),
+            transitions("cn")
+        );
+        assert_eq!(
+            Apply(Operate(Operator::Delete, Target::Selection, None), Command),
+            transitions("ds")
+        );
+        assert!(matches!(transitions("dy"), Error(_, OperatorPending(_))));
+    }
+
+    #[test]
+    fn emit_operation_search() {
+        let mode = type_keys(Command, "/ab");
+        assert_eq!(
+            Apply(Search("ab".to_string()), Command),
+            mode.transition(Input::Key(ENTER))
+        );
+        assert_eq!(
+            Apply(PreviousMatch, Command),
+            Command.transition(Input::Key(N_UPPER))
+        );
+    }
+
+    #[test]
+    fn emit_operation_paste() {
+        assert_eq!(
+            Apply(Paste(None), Command),
+            Command.transition(Input::Key(P_LOWER))
+        );
+        assert_eq!(
+            Apply(Paste(Some('a')), Command),
+            RegisterPending(Some('a')).transition(Input::Key(P_LOWER))
+        );
+        assert!(matches!(
+            RegisterPending(None).transition(Input::Key(DIGIT_1)),
+            Error(_, RegisterPending(None))
+        ));
+    }
+
+    #[test]
+    fn emit_operations_on_the_selection() {
+        let mut mode = type_keys(Command, "vv1\u{e007}e0\u{e007}v2\u{e007}v1\u{e007}");
+        let elements: BTreeSet<ElementRef> = [ElementRef::Edge(0), ElementRef::Vertex(2)]
+            .iter()
+            .copied()
+            .collect();
+        assert_eq!(
+            Apply(
+                Operate(Operator::Delete, Target::Elements(elements.clone()), None),
+                Command
+            ),
+            mode.clone().transition(Input::Key(D_LOWER))
+        );
+        let named = type_keys(mode.clone(), "\"b");
+        assert_eq!(
+            Apply(
+                Operate(
+                    Operator::Yank,
+                    Target::Elements(elements.clone()),
+                    Some('b')
+                ),
+                Command
+            ),
+            named.transition(Input::Key(Y_LOWER))
+        );
+        mode = type_keys(mode, "acolor=red");
+        assert_eq!(
+            Apply(
+                SetAttribute(
+                    Target::Elements(elements),
+                    "color".to_string(),
+                    "red".to_string()
+                ),
+                Command
+            ),
+            mode.transition(Input::Key(ENTER))
+        );
+        assert!(matches!(
+            Visual(Selection {
+                typed: "x1".to_string(),
+                ..Selection::default()
+            })
+            .transition(Input::Key(ENTER)),
+            Error(_, Visual(_))
+        ));
+    }
+
     #[test]
     fn transition_command_err() {
         let mode = Command;
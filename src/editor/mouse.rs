@@ -0,0 +1,62 @@
+use std::fmt;
+use std::hash::Hash;
+use std::hash::Hasher;
+
+// A point in the coordinates vertices are laid out in, as the `pos` attribute gives them.
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Position {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Position {
+    pub fn new(x: f64, y: f64) -> Position {
+        Position { x, y }
+    }
+}
+
+// Positions are compared bit for bit, so that inputs holding them can be recorded and compared.
+impl PartialEq for Position {
+    fn eq(&self, other: &Position) -> bool {
+        (self.x.to_bits(), self.y.to_bits()) == (other.x.to_bits(), other.y.to_bits())
+    }
+}
+
+impl Eq for Position {}
+
+impl Hash for Position {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.x.to_bits(), self.y.to_bits()).hash(state);
+    }
+}
+
+// The position as a `pos` attribute.
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{}", self.x, self.y)
+    }
+}
+
+/**
+ * What a frontend reports of the mouse, at a position in the document's coordinates. A press
+ * on a vertex puts the cursor on it, or in Visual mode chooses it, and dragging it from there
+ * moves it where the mouse is released, as a single edit.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MouseEvent {
+    Press(Position),
+    Drag(Position),
+    Release(Position),
+}
+
+impl MouseEvent {
+    pub fn position(self) -> Position {
+        match self {
+            MouseEvent::Press(position)
+            | MouseEvent::Drag(position)
+            | MouseEvent::Release(position) => position,
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Write;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::format::ParseError;
+use crate::graph::Diff;
+use crate::graph::Graph;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Line {
+    // Milliseconds since the Unix epoch.
+    at: u64,
+    diff: Diff,
+}
+
+// A diff applied to the document, and when it was applied.
+#[derive(Debug, Clone)]
+pub struct LoggedDiff {
+    pub at: SystemTime,
+    pub diff: Diff,
+}
+
+/**
+ * An append-only log of every diff applied to the document, including undo and redo, with a line
+ * of JSON per diff. Unlike the recovery journal, the log is never rewritten or discarded, so that
+ * it records how a document came to be, and replaying it from the start reconstructs the
+ * document.
+ */
+#[derive(Debug)]
+pub struct OpLog {
+    path: String,
+    file: File,
+}
+
+impl OpLog {
+    // Append to the log at the path, creating it if necessary.
+    pub fn open(path: &str) -> io::Result<OpLog> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(OpLog {
+            path: path.to_string(),
+            file,
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn append(&mut self, diff: &Diff, at: SystemTime) -> io::Result<()> {
+        let at = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let mut line = serde_json::to_string(&Line {
+            at: at.as_millis() as u64,
+            diff: diff.clone(),
+        })
+        .unwrap();
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        self.file.sync_data()
+    }
+}
+
+// Read the diffs in the log at the path. As with the recovery journal, a partial last line, left
+// by a crash during an append, is ignored.
+pub fn read_log(path: &str) -> Result<Vec<LoggedDiff>, ParseError> {
+    let text = std::fs::read_to_string(path).map_err(|err| ParseError::new(1, &err.to_string()))?;
+    let lines: Vec<&str> = text.lines().collect();
+    let mut logged = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        match serde_json::from_str::<Line>(line) {
+            Ok(line) => logged.push(LoggedDiff {
+                at: UNIX_EPOCH + Duration::from_millis(line.at),
+                diff: line.diff,
+            }),
+            Err(_) if i + 1 == lines.len() && !text.ends_with('\n') => break,
+            Err(err) => return Err(ParseError::new(i + 1, &err.to_string())),
+        }
+    }
+    Ok(logged)
+}
+
+// Reconstruct the document from its log, as it was after the diffs applied up to and including
+// the time given, or after all of them. A log that was damaged or edited by hand may hold a diff
+// that does not apply to the document before it, which is reported with the diff's line.
+pub fn replay(logged: &[LoggedDiff], until: Option<SystemTime>) -> Result<Graph, ParseError> {
+    let mut graph = Graph::new();
+    for (i, entry) in logged
+        .iter()
+        .enumerate()
+        .take_while(|(_, entry)| until.is_none_or(|until| entry.at <= until))
+    {
+        graph
+            .try_apply_all(entry.diff.operations.clone())
+            .map_err(|err| ParseError::new(i + 1, &err.to_string()))?;
+    }
+    Ok(graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Edge;
+    use crate::graph::GraphOperation;
+    use crate::graph::Vertex;
+
+    #[test]
+    fn replay_reconstructs_the_document() {
+        let path = std::env::temp_dir()
+            .join(format!("gri-op-log-{}.jsonl", std::process::id()))
+            .display()
+            .to_string();
+        let mut graph = Graph::new();
+        let mut log = OpLog::open(&path).unwrap();
+        for id in 0..3 {
+            let diff = graph.apply(GraphOperation::AddVertex(Vertex::new(id)));
+            log.append(&diff, UNIX_EPOCH + Duration::from_secs(id as u64))
+                .unwrap();
+        }
+        let undo = graph.apply_all(
+            Diff {
+                operations: vec![GraphOperation::AddVertex(Vertex::new(2))],
+            }
+            .invert()
+            .operations,
+        );
+        log.append(&undo, UNIX_EPOCH + Duration::from_secs(3))
+            .unwrap();
+        drop(log);
+
+        // Reopening appends rather than truncating.
+        let mut log = OpLog::open(&path).unwrap();
+        log.file
+            .write_all(b"{\"at\": 4, \"diff\": {\"oper")
+            .unwrap();
+
+        let logged = read_log(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(4, logged.len());
+        assert_eq!(UNIX_EPOCH + Duration::from_secs(1), logged[1].at);
+        assert_eq!(Ok(graph), replay(&logged, None));
+        assert_eq!(
+            3,
+            replay(&logged, Some(UNIX_EPOCH + Duration::from_secs(2)))
+                .unwrap()
+                .vertices
+                .len()
+        );
+    }
+
+    #[test]
+    fn replay_rejects_diffs_that_do_not_apply() {
+        let edge = Diff {
+            operations: vec![GraphOperation::AddEdge(Edge::new(0, 0, 1))],
+        };
+        let vertex = Diff {
+            operations: vec![GraphOperation::AddVertex(Vertex::new(0))],
+        };
+        let logged: Vec<LoggedDiff> = vec![vertex, edge]
+            .into_iter()
+            .map(|diff| LoggedDiff {
+                at: UNIX_EPOCH,
+                diff,
+            })
+            .collect();
+        assert_eq!(
+            Err(ParseError::new(2, "Unknown vertex 1")),
+            replay(&logged, None)
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use std::fmt;
+
+/**
+ * The settings that change how the editor edits, like vim's options, each of which `:set` knows by
+ * name. They are saved with the document's undo file, so that a document reopens with the
+ * options it was edited with.
+ */
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct EditorOptions {
+    // Whether the edges the editor creates are directed. Undirected edges are created with the
+    // attribute `directed=false`.
+    pub directed: bool,
+    // Whether the edits of an Insert mode session are undone at once, or one at a time.
+    pub undo_grouping: bool,
+    // Whether new vertices are given a `pos` attribute, in a row to the right of the others.
+    pub auto_layout: bool,
+    // In strict mode, document changes that would be silent no-ops are reported as errors and
+    // leave the document and history untouched.
+    pub strict: bool,
+    // The amount by which `+` and `-` change an edge's weight.
+    pub weight_step: f64,
+    // Like vim's `readonly`, reject every change to the document, including undo and redo, e.g.,
+    // when viewing a reference graph. It applies to the document as opened, so it is not saved.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub readonly: bool,
+}
+
+impl Default for EditorOptions {
+    fn default() -> Self {
+        EditorOptions {
+            directed: true,
+            undo_grouping: true,
+            auto_layout: false,
+            strict: false,
+            weight_step: 1.0,
+            readonly: false,
+        }
+    }
+}
+
+// The names of the options, in the order `:set` lists them.
+pub const NAMES: [&str; 6] = [
+    "auto-layout",
+    "directed",
+    "readonly",
+    "strict",
+    "undo-grouping",
+    "weight-step",
+];
+
+// Why a setting typed after `:set` could not be carried out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionError {
+    UnknownOption(String),
+    // The value typed, and the option it is not a value of.
+    InvalidValue(String, &'static str),
+}
+
+impl fmt::Display for OptionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OptionError::UnknownOption(name) => write!(f, "Unknown option: {}", name),
+            OptionError::InvalidValue(value, name) => {
+                write!(f, "Invalid value for {}: {}", name, value)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OptionError {}
+
+fn parse_flag(value: &str, name: &'static str) -> Result<bool, OptionError> {
+    match value {
+        "on" | "true" => Ok(true),
+        "off" | "false" => Ok(false),
+        _ => Err(OptionError::InvalidValue(value.to_string(), name)),
+    }
+}
+
+fn show_flag(flag: bool) -> String {
+    if flag { "on" } else { "off" }.to_string()
+}
+
+impl EditorOptions {
+    // The option's value, as `:set` shows it.
+    pub fn get(&self, name: &str) -> Result<String, OptionError> {
+        Ok(match name {
+            "auto-layout" => show_flag(self.auto_layout),
+            "directed" => show_flag(self.directed),
+            "readonly" => show_flag(self.readonly),
+            "strict" => show_flag(self.strict),
+            "undo-grouping" => show_flag(self.undo_grouping),
+            "weight-step" => self.weight_step.to_string(),
+            _ => return Err(OptionError::UnknownOption(name.to_string())),
+        })
+    }
+
+    // Set an option to a value typed as `:set` shows it.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), OptionError> {
+        match name {
+            "auto-layout" => self.auto_layout = parse_flag(value, "auto-layout")?,
+            "directed" => self.directed = parse_flag(value, "directed")?,
+            "readonly" => self.readonly = parse_flag(value, "readonly")?,
+            "strict" => self.strict = parse_flag(value, "strict")?,
+            "undo-grouping" => self.undo_grouping = parse_flag(value, "undo-grouping")?,
+            "weight-step" => {
+                self.weight_step = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|step| step.is_finite())
+                    .ok_or_else(|| OptionError::InvalidValue(value.to_string(), "weight-step"))?
+            }
+            _ => return Err(OptionError::UnknownOption(name.to_string())),
+        }
+        Ok(())
+    }
+
+    /**
+     * Carry out a setting typed after `:set`, like vim's: `name=value` sets an option, `name`
+     * turns a flag on or shows any other option, `noname` turns a flag off and `name?` shows the
+     * option. Returns what is shown, as `name=value`, if anything.
+     */
+    pub fn apply(&mut self, setting: &str) -> Result<Option<String>, OptionError> {
+        let show = |options: &EditorOptions, name: &str| {
+            options
+                .get(name)
+                .map(|value| Some(format!("{}={}", name, value)))
+        };
+        if let Some((name, value)) = setting.split_once('=') {
+            return self.set(name, value).map(|_| None);
+        }
+        if let Some(name) = setting.strip_suffix('?') {
+            return show(self, name);
+        }
+        let is_flag = |options: &EditorOptions, name: &str| {
+            options
+                .get(name)
+                .is_ok_and(|value| value == "on" || value == "off")
+        };
+        if is_flag(self, setting) {
+            return self.set(setting, "on").map(|_| None);
+        }
+        match setting.strip_prefix("no") {
+            Some(name) if is_flag(self, name) => self.set(name, "off").map(|_| None),
+            _ => show(self, setting),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_like_vim() {
+        let mut options = EditorOptions::default();
+        assert_eq!(Ok(None), options.apply("nodirected"));
+        assert!(!options.directed);
+        assert_eq!(Ok(None), options.apply("auto-layout"));
+        assert!(options.auto_layout);
+        assert_eq!(Ok(None), options.apply("weight-step=0.5"));
+        assert_eq!(0.5, options.weight_step);
+        assert_eq!(
+            Ok(Some("weight-step=0.5".to_string())),
+            options.apply("weight-step")
+        );
+        assert_eq!(Ok(Some("strict=off".to_string())), options.apply("strict?"));
+
+        assert_eq!(
+            Err(OptionError::InvalidValue("yes".to_string(), "strict")),
+            options.apply("strict=yes")
+        );
+        assert_eq!(
+            Err(OptionError::UnknownOption("noweight-step".to_string())),
+            options.apply("noweight-step")
+        );
+        assert!(options.apply("weight-step=inf").is_err());
+        for name in NAMES.iter() {
+            assert!(options.get(name).is_ok());
+        }
+    }
+}
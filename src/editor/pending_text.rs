@@ -0,0 +1,105 @@
+use std::fmt;
+
+use crate::editor::keys::BACKSPACE;
+use crate::editor::keys::LEFT;
+use crate::editor::keys::RIGHT;
+
+/**
+ * Text being typed into a pending mode, e.g., the vertices of a new edge, with a cursor that
+ * Left and Right move, so that a mistake can be corrected with Backspace instead of starting over.
+ * Like vim's command line, other keys are inserted at the cursor.
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PendingText {
+    text: String,
+    // The number of characters before the cursor.
+    cursor: usize,
+}
+
+impl PendingText {
+    pub fn new() -> PendingText {
+        PendingText::default()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn into_string(self) -> String {
+        self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    // The text with a key typed at the cursor.
+    pub fn typed(mut self, key: char) -> PendingText {
+        match key {
+            BACKSPACE if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.text.remove(self.byte_offset(self.cursor));
+            }
+            BACKSPACE => {}
+            LEFT => self.cursor = self.cursor.saturating_sub(1),
+            RIGHT => self.cursor = (self.cursor + 1).min(self.text.chars().count()),
+            key => {
+                self.text.insert(self.byte_offset(self.cursor), key);
+                self.cursor += 1;
+            }
+        }
+        self
+    }
+
+    fn byte_offset(&self, cursor: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(cursor)
+            .map_or(self.text.len(), |(i, _)| i)
+    }
+}
+
+// The text, with the cursor at its end.
+impl From<&str> for PendingText {
+    fn from(text: &str) -> PendingText {
+        PendingText {
+            text: text.to_string(),
+            cursor: text.chars().count(),
+        }
+    }
+}
+
+impl fmt::Display for PendingText {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_at_the_cursor() {
+        let typed = |text: PendingText, keys: &str| keys.chars().fold(text, PendingText::typed);
+        let text = typed(PendingText::new(), "0,12");
+        assert_eq!(PendingText::from("0,12"), text);
+
+        let text = typed(text, "\u{e012}\u{e003}\u{e003}3");
+        assert_eq!("032", text.as_str());
+        assert_eq!(2, text.cursor());
+
+        let text = typed(text, "\u{e014}\u{e014}\u{e014}é\u{e012}\u{e012}\u{e003}");
+        assert_eq!("02é", text.as_str());
+        assert_eq!(1, text.cursor());
+        assert_eq!(
+            "03,é",
+            typed(PendingText::new(), "\u{e003}\u{e012}03,é").as_str()
+        );
+    }
+}
@@ -0,0 +1,652 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+
+use crate::editor::hooks::Hook;
+use crate::editor::keymap::key_notation;
+use crate::editor::keymap::Keymap;
+use crate::editor::keymap::KeymapMode;
+use crate::editor::references::ElementRef;
+use crate::graph::Attributes;
+use crate::graph::Graph;
+use crate::graph::GraphOperation;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OperationKind {
+    AddVertex,
+    RemoveVertex,
+    ModifyVertex,
+    AddEdge,
+    RemoveEdge,
+    ModifyEdge,
+    ContractEdge,
+    MergeVertices,
+}
+
+impl OperationKind {
+    pub fn of(operation: &GraphOperation) -> OperationKind {
+        match operation {
+            GraphOperation::AddVertex(_) => OperationKind::AddVertex,
+            GraphOperation::RemoveVertex(_) => OperationKind::RemoveVertex,
+            GraphOperation::ModifyVertex { .. } => OperationKind::ModifyVertex,
+            GraphOperation::AddEdge(_) => OperationKind::AddEdge,
+            GraphOperation::RemoveEdge(_) => OperationKind::RemoveEdge,
+            GraphOperation::ModifyEdge { .. } => OperationKind::ModifyEdge,
+            GraphOperation::ContractEdge(_) => OperationKind::ContractEdge,
+            GraphOperation::MergeVertices { .. } => OperationKind::MergeVertices,
+        }
+    }
+}
+
+/**
+ * What a plugin is allowed to do, declared when it is registered. An attribute namespace is the
+ * part of a key before its first dot, so a plugin with the namespace `layout` may set `layout`,
+ * `layout.x` and so on, but not `label`.
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    pub operations: BTreeSet<OperationKind>,
+    pub attribute_namespaces: BTreeSet<String>,
+    // Whether the plugin may undo and redo.
+    pub history: bool,
+}
+
+// Why a plugin's request was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PermissionError {
+    UnknownPlugin(String),
+    Operation(String, OperationKind),
+    Attribute(String, String),
+    History(String),
+}
+
+impl fmt::Display for PermissionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PermissionError::UnknownPlugin(plugin) => write!(f, "Unknown plugin {}", plugin),
+            PermissionError::Operation(plugin, kind) => {
+                write!(f, "Plugin {} may not perform {:?}", plugin, kind)
+            }
+            PermissionError::Attribute(plugin, key) => {
+                write!(f, "Plugin {} may not change attribute {}", plugin, key)
+            }
+            PermissionError::History(plugin) => {
+                write!(f, "Plugin {} may not change the history", plugin)
+            }
+        }
+    }
+}
+
+impl Error for PermissionError {}
+
+// Why a plugin could not reserve a leader namespace or bind keys in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BindingError {
+    UnknownPlugin(String),
+    EmptyNamespace,
+    // The keys overlap the namespace of the given plugin.
+    NamespaceTaken(Vec<char>, String),
+    NoNamespace(String),
+    // The keys are already mapped in the mode, other than by the plugin.
+    Conflict(KeymapMode, Vec<char>),
+    // The ex command is already added by the given plugin.
+    CommandTaken(String, String),
+}
+
+impl fmt::Display for BindingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BindingError::UnknownPlugin(plugin) => write!(f, "Unknown plugin {}", plugin),
+            BindingError::EmptyNamespace => write!(f, "A leader namespace needs at least one key"),
+            BindingError::NamespaceTaken(keys, owner) => write!(
+                f,
+                "<leader>{} overlaps the namespace of plugin {}",
+                key_notation(keys),
+                owner
+            ),
+            BindingError::NoNamespace(plugin) => {
+                write!(f, "Plugin {} has not reserved a leader namespace", plugin)
+            }
+            BindingError::Conflict(mode, keys) => {
+                write!(
+                    f,
+                    "{} is already mapped in {} mode",
+                    key_notation(keys),
+                    mode
+                )
+            }
+            BindingError::CommandTaken(command, owner) => {
+                write!(
+                    f,
+                    "Command {} is already added by plugin {}",
+                    command, owner
+                )
+            }
+        }
+    }
+}
+
+impl Error for BindingError {}
+
+// What a plugin asks the editor to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PluginRequest {
+    // Apply operations to the document, as a single edit.
+    Operations(Vec<GraphOperation>),
+    Undo,
+    Redo,
+}
+
+/**
+ * An operation a plugin adds to the editor, named rather than boxed so that operations stay
+ * comparable, recordable and serializable. The editor hands it to the plugin of that name.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PluginOperation {
+    pub plugin: String,
+    pub name: String,
+    // The words typed after the ex command, if it was typed as one.
+    pub args: Vec<String>,
+}
+
+// What a plugin sees of the editor when it carries out an operation.
+#[derive(Debug, Clone, Copy)]
+pub struct PluginContext<'a> {
+    pub document: &'a Graph,
+    pub cursor: Option<i64>,
+    pub selection: &'a [ElementRef],
+}
+
+/**
+ * A domain-specific tool built on the editor, which adds operations without changing the modes.
+ * Each of the plugin's ex commands is an operation of the same name, and its bindings map keys
+ * under its leader namespace to other keys, typically to one of its commands, as vim plugins map
+ * keys to `:call ...<CR>`. What an operation does is a request checked against the capabilities,
+ * so a plugin cannot do more than it declared.
+ */
+pub trait Plugin: fmt::Debug + Send + Sync {
+    fn name(&self) -> &str;
+
+    fn capabilities(&self) -> Capabilities;
+
+    // The keys after the leader that begin the plugin's bindings.
+    fn leader_namespace(&self) -> Option<Vec<char>> {
+        None
+    }
+
+    // The keys after the namespace, by mode, and the keys they stand for.
+    fn bindings(&self) -> Vec<(KeymapMode, Vec<char>, Vec<char>)> {
+        Vec::new()
+    }
+
+    fn ex_commands(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    // The ex commands to run at hooks, e.g., one of the plugin's own to lay out added vertices.
+    fn autocommands(&self) -> Vec<(Hook, String)> {
+        Vec::new()
+    }
+
+    // Carry out one of the plugin's operations, or say why it cannot be carried out.
+    fn operate(
+        &mut self,
+        operation: &PluginOperation,
+        context: PluginContext,
+    ) -> Result<PluginRequest, String>;
+}
+
+fn namespace(key: &str) -> &str {
+    key.split('.').next().unwrap_or(key)
+}
+
+// The keys whose values differ between the attributes.
+fn changed_keys<'a>(old: &'a Attributes, new: &'a Attributes) -> impl Iterator<Item = &'a str> {
+    old.keys()
+        .chain(new.keys())
+        .filter(move |key| old.get(*key) != new.get(*key))
+        .map(String::as_str)
+}
+
+impl Capabilities {
+    /**
+     * Check that the plugin may perform the operation on the graph. Attribute changes of
+     * modifications are found by comparing against the graph, not the operation's `old`, so a
+     * plugin cannot misreport what it changes. Removals may drop attributes of any namespace.
+     */
+    pub fn check(
+        &self,
+        plugin: &str,
+        graph: &Graph,
+        operation: &GraphOperation,
+    ) -> Result<(), PermissionError> {
+        let kind = OperationKind::of(operation);
+        if !self.operations.contains(&kind) {
+            return Err(PermissionError::Operation(plugin.to_string(), kind));
+        }
+        let empty = Attributes::new();
+        let (old, new) = match operation {
+            GraphOperation::AddVertex(v) => (&empty, &v.attributes),
+            GraphOperation::AddEdge(e) => (&empty, &e.attributes),
+            GraphOperation::ModifyVertex { new, .. } => (
+                graph
+                    .vertices
+                    .get(&new.id)
+                    .map_or(&empty, |v| &v.attributes),
+                &new.attributes,
+            ),
+            GraphOperation::ModifyEdge { new, .. } => (
+                graph.edges.get(&new.id).map_or(&empty, |e| &e.attributes),
+                &new.attributes,
+            ),
+            _ => return Ok(()),
+        };
+        let forbidden = changed_keys(old, new)
+            .find(|key| !self.attribute_namespaces.contains(namespace(key)))
+            .map(str::to_string);
+        match forbidden {
+            Some(key) => Err(PermissionError::Attribute(plugin.to_string(), key)),
+            None => Ok(()),
+        }
+    }
+}
+
+/**
+ * The plugins registered with an editor, by name. Like vim plugins, which map keys under
+ * `<leader>`, each plugin may reserve the keys that follow the leader in a namespace of its own,
+ * e.g., `l` for `<leader>l...`, and bind keys only there. No namespace is the prefix of another's,
+ * so the bindings of two plugins can never collide, and none shadow the built-in bindings unless
+ * the leader is set to a key they use.
+ */
+#[derive(Debug, Default)]
+pub struct Plugins {
+    capabilities: BTreeMap<String, Capabilities>,
+    // The plugins registered as implementations of `Plugin`, and the ex commands they add.
+    installed: BTreeMap<String, Box<dyn Plugin>>,
+    commands: BTreeMap<String, String>,
+    // The keys after the leader that begin each plugin's bindings.
+    namespaces: BTreeMap<String, Vec<char>>,
+    // The plugin that made each mapping, and what it mapped the keys to, so that a mapping the
+    // user has since replaced is left alone.
+    bindings: BTreeMap<(KeymapMode, Vec<char>), (String, Vec<char>)>,
+}
+
+impl Plugins {
+    pub fn new() -> Plugins {
+        Plugins::default()
+    }
+
+    // Register a plugin, replacing the capabilities of any plugin of the same name.
+    pub fn register(&mut self, name: &str, capabilities: Capabilities) {
+        self.capabilities.insert(name.to_string(), capabilities);
+    }
+
+    // Unregister a plugin, releasing its namespace and commands. Its bindings are left to `unbind`.
+    pub fn unregister(&mut self, name: &str) -> bool {
+        self.namespaces.remove(name);
+        self.installed.remove(name);
+        self.commands.retain(|_, owner| owner != name);
+        self.capabilities.remove(name).is_some()
+    }
+
+    /**
+     * Register a plugin with its capabilities, leader namespace, bindings and ex commands, which
+     * must not collide with another plugin's. A plugin that cannot be installed entirely is not
+     * installed at all.
+     */
+    pub fn install(
+        &mut self,
+        keymap: &mut Keymap,
+        plugin: Box<dyn Plugin>,
+    ) -> Result<(), BindingError> {
+        let name = plugin.name().to_string();
+        let commands = plugin.ex_commands();
+        let taken = commands.iter().find_map(|command| {
+            self.commands
+                .get(command)
+                .filter(|owner| **owner != name)
+                .map(|owner| BindingError::CommandTaken(command.clone(), owner.clone()))
+        });
+        if let Some(err) = taken {
+            return Err(err);
+        }
+        self.unbind(keymap, &name);
+        self.unregister(&name);
+        self.register(&name, plugin.capabilities());
+        let bound = match plugin.leader_namespace() {
+            Some(keys) => self.reserve_namespace(&name, keys).and_then(|_| {
+                plugin
+                    .bindings()
+                    .into_iter()
+                    .try_for_each(|(mode, keys, to)| {
+                        self.bind(keymap, &name, mode, &keys, to).map(|_| ())
+                    })
+            }),
+            None => Ok(()),
+        };
+        if let Err(err) = bound {
+            self.unbind(keymap, &name);
+            self.unregister(&name);
+            return Err(err);
+        }
+        for command in commands {
+            self.commands.insert(command, name.clone());
+        }
+        self.installed.insert(name, plugin);
+        Ok(())
+    }
+
+    // The plugin that added an ex command.
+    pub fn command_owner(&self, command: &str) -> Option<&str> {
+        self.commands.get(command).map(String::as_str)
+    }
+
+    pub fn installed_mut(&mut self, name: &str) -> Option<&mut (dyn Plugin + 'static)> {
+        self.installed.get_mut(name).map(|plugin| plugin.as_mut())
+    }
+
+    pub fn capabilities(&self, name: &str) -> Option<&Capabilities> {
+        self.capabilities.get(name)
+    }
+
+    // Reserve the keys after the leader for a plugin, replacing any namespace it reserved before.
+    pub fn reserve_namespace(&mut self, plugin: &str, keys: Vec<char>) -> Result<(), BindingError> {
+        if !self.capabilities.contains_key(plugin) {
+            return Err(BindingError::UnknownPlugin(plugin.to_string()));
+        }
+        if keys.is_empty() {
+            return Err(BindingError::EmptyNamespace);
+        }
+        let overlapping = self.namespaces.iter().find(|(owner, reserved)| {
+            owner.as_str() != plugin && (reserved.starts_with(&keys) || keys.starts_with(reserved))
+        });
+        if let Some((owner, _)) = overlapping {
+            return Err(BindingError::NamespaceTaken(keys, owner.clone()));
+        }
+        self.namespaces.insert(plugin.to_string(), keys);
+        Ok(())
+    }
+
+    pub fn namespace(&self, plugin: &str) -> Option<&[char]> {
+        self.namespaces.get(plugin).map(Vec::as_slice)
+    }
+
+    // The plugin whose namespace the keys typed after the leader are in.
+    pub fn namespace_owner(&self, keys: &[char]) -> Option<&str> {
+        self.namespaces
+            .iter()
+            .find(|(_, reserved)| keys.starts_with(reserved))
+            .map(|(owner, _)| owner.as_str())
+    }
+
+    /**
+     * Map `<leader>`, the plugin's namespace and then the keys to the keys given, in the keymap,
+     * returning the keys mapped. A plugin may replace its own mappings, but not one the user or a
+     * keymap file made.
+     */
+    pub fn bind(
+        &mut self,
+        keymap: &mut Keymap,
+        plugin: &str,
+        mode: KeymapMode,
+        keys: &[char],
+        to: Vec<char>,
+    ) -> Result<Vec<char>, BindingError> {
+        let namespace = self
+            .namespaces
+            .get(plugin)
+            .ok_or_else(|| BindingError::NoNamespace(plugin.to_string()))?;
+        let mut mapped = vec![keymap.leader()];
+        mapped.extend(namespace);
+        mapped.extend(keys);
+
+        let key = (mode, mapped.clone());
+        let owned = self
+            .bindings
+            .get(&key)
+            .filter(|(owner, bound)| owner == plugin && keymap.get(mode, &mapped) == Some(bound));
+        if keymap.get(mode, &mapped).is_some() && owned.is_none() {
+            return Err(BindingError::Conflict(mode, mapped));
+        }
+        keymap.map(mode, mapped.clone(), to.clone());
+        self.bindings.insert(key, (plugin.to_string(), to));
+        Ok(mapped)
+    }
+
+    // Remove the plugin's mappings from the keymap, except those replaced since it made them.
+    pub fn unbind(&mut self, keymap: &mut Keymap, plugin: &str) {
+        let (unbound, kept) = std::mem::take(&mut self.bindings)
+            .into_iter()
+            .partition(|(_, (owner, _))| owner == plugin);
+        self.bindings = kept;
+        for ((mode, keys), (_, to)) in unbound {
+            if keymap.get(mode, &keys) == Some(&to[..]) {
+                keymap.unmap(mode, &keys);
+            }
+        }
+    }
+
+    // Check a whole request, so that a request is either allowed entirely or refused.
+    pub fn check(
+        &self,
+        plugin: &str,
+        graph: &Graph,
+        request: &PluginRequest,
+    ) -> Result<(), PermissionError> {
+        let capabilities = self
+            .capabilities
+            .get(plugin)
+            .ok_or_else(|| PermissionError::UnknownPlugin(plugin.to_string()))?;
+        match request {
+            PluginRequest::Operations(operations) => operations
+                .iter()
+                .try_for_each(|op| capabilities.check(plugin, graph, op)),
+            PluginRequest::Undo | PluginRequest::Redo if capabilities.history => Ok(()),
+            PluginRequest::Undo | PluginRequest::Redo => {
+                Err(PermissionError::History(plugin.to_string()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Vertex;
+
+    #[test]
+    fn operations_and_namespaces_are_enforced() {
+        let mut graph = Graph::new();
+        graph.add_vertex(Vertex::new(0).with_attribute("label", "a"));
+
+        let mut plugins = Plugins::new();
+        plugins.register(
+            "layout",
+            Capabilities {
+                operations: [OperationKind::ModifyVertex].iter().copied().collect(),
+                attribute_namespaces: ["layout".to_string()].iter().cloned().collect(),
+                history: false,
+            },
+        );
+
+        let positioned = Vertex::new(0)
+            .with_attribute("label", "a")
+            .with_attribute("layout.x", "3");
+        let modify = |new: Vertex| {
+            PluginRequest::Operations(vec![GraphOperation::ModifyVertex {
+                old: new.clone(),
+                new,
+            }])
+        };
+        assert_eq!(Ok(()), plugins.check("layout", &graph, &modify(positioned)));
+        assert_eq!(
+            Err(PermissionError::Attribute(
+                "layout".to_string(),
+                "label".to_string()
+            )),
+            plugins.check("layout", &graph, &modify(Vertex::new(0)))
+        );
+        assert_eq!(
+            Err(PermissionError::Operation(
+                "layout".to_string(),
+                OperationKind::AddVertex
+            )),
+            plugins.check(
+                "layout",
+                &graph,
+                &PluginRequest::Operations(vec![GraphOperation::AddVertex(Vertex::new(1))])
+            )
+        );
+        assert_eq!(
+            Err(PermissionError::History("layout".to_string())),
+            plugins.check("layout", &graph, &PluginRequest::Undo)
+        );
+        assert_eq!(
+            Err(PermissionError::UnknownPlugin("lint".to_string())),
+            plugins.check("lint", &graph, &PluginRequest::Redo)
+        );
+    }
+
+    #[derive(Debug)]
+    struct Commands(&'static str, Vec<char>, Vec<&'static str>);
+
+    impl Plugin for Commands {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities::default()
+        }
+
+        fn leader_namespace(&self) -> Option<Vec<char>> {
+            Some(self.1.clone())
+        }
+
+        fn bindings(&self) -> Vec<(KeymapMode, Vec<char>, Vec<char>)> {
+            vec![(KeymapMode::Command, vec!['a'], vec![':'])]
+        }
+
+        fn ex_commands(&self) -> Vec<String> {
+            self.2.iter().map(|command| command.to_string()).collect()
+        }
+
+        fn operate(
+            &mut self,
+            _: &PluginOperation,
+            _: PluginContext,
+        ) -> Result<PluginRequest, String> {
+            Ok(PluginRequest::Undo)
+        }
+    }
+
+    #[test]
+    fn plugins_are_installed_entirely_or_not_at_all() {
+        let mut plugins = Plugins::new();
+        let mut keymap = Keymap::new();
+        let install = |plugins: &mut Plugins, keymap: &mut Keymap, plugin: Commands| {
+            plugins.install(keymap, Box::new(plugin))
+        };
+        assert_eq!(
+            Ok(()),
+            install(
+                &mut plugins,
+                &mut keymap,
+                Commands("layout", vec!['l'], vec!["spread"])
+            )
+        );
+        assert_eq!(Some("layout"), plugins.command_owner("spread"));
+        assert_eq!(
+            Some(&[':'][..]),
+            keymap.get(KeymapMode::Command, &['\\', 'l', 'a'])
+        );
+
+        assert_eq!(
+            Err(BindingError::CommandTaken(
+                "spread".to_string(),
+                "layout".to_string()
+            )),
+            install(
+                &mut plugins,
+                &mut keymap,
+                Commands("lint", vec!['L'], vec!["spread"])
+            )
+        );
+        assert_eq!(
+            Err(BindingError::NamespaceTaken(
+                vec!['l'],
+                "layout".to_string()
+            )),
+            install(
+                &mut plugins,
+                &mut keymap,
+                Commands("lint", vec!['l'], vec!["check"])
+            )
+        );
+        assert_eq!(None, plugins.capabilities("lint"));
+        assert_eq!(None, plugins.command_owner("check"));
+
+        assert!(plugins.unregister("layout"));
+        assert_eq!(None, plugins.command_owner("spread"));
+        assert!(plugins.installed_mut("layout").is_none());
+    }
+
+    #[test]
+    fn plugins_bind_keys_in_their_namespaces() {
+        let mut plugins = Plugins::new();
+        plugins.register("layout", Capabilities::default());
+        plugins.register("lint", Capabilities::default());
+        let mut keymap = Keymap::new();
+
+        assert_eq!(Ok(()), plugins.reserve_namespace("layout", vec!['l']));
+        assert_eq!(
+            Err(BindingError::NamespaceTaken(
+                vec!['l', 'i'],
+                "layout".to_string()
+            )),
+            plugins.reserve_namespace("lint", vec!['l', 'i'])
+        );
+        assert_eq!(Ok(()), plugins.reserve_namespace("lint", vec!['L']));
+        assert_eq!(Some("layout"), plugins.namespace_owner(&['l', 'x']));
+        assert_eq!(None, plugins.namespace_owner(&['x']));
+        assert_eq!(
+            Err(BindingError::UnknownPlugin("color".to_string())),
+            plugins.reserve_namespace("color", vec!['c'])
+        );
+
+        let mode = KeymapMode::Command;
+        assert_eq!(
+            Ok(vec!['\\', 'l', 'a']),
+            plugins.bind(&mut keymap, "layout", mode, &['a'], vec!['v'])
+        );
+        assert_eq!(
+            Ok(vec!['\\', 'l', 'a']),
+            plugins.bind(&mut keymap, "layout", mode, &['a'], vec!['x'])
+        );
+        assert_eq!(Some(&['x'][..]), keymap.get(mode, &['\\', 'l', 'a']));
+
+        // A mapping the user made is not replaced, and outlives the plugin.
+        keymap.map(mode, vec!['\\', 'L', 'a'], vec!['u']);
+        assert_eq!(
+            Err(BindingError::Conflict(mode, vec!['\\', 'L', 'a'])),
+            plugins.bind(&mut keymap, "lint", mode, &['a'], vec!['v'])
+        );
+        plugins
+            .bind(&mut keymap, "lint", mode, &['b'], vec!['v'])
+            .unwrap();
+        keymap.map(mode, vec!['\\', 'L', 'b'], vec!['U']);
+        plugins.unbind(&mut keymap, "lint");
+        assert_eq!(Some(&['U'][..]), keymap.get(mode, &['\\', 'L', 'b']));
+
+        plugins.unbind(&mut keymap, "layout");
+        assert!(plugins.unregister("layout"));
+        assert_eq!(None, keymap.get(mode, &['\\', 'l', 'a']));
+        assert_eq!(None, plugins.namespace("layout"));
+        assert_eq!(
+            Err(BindingError::NoNamespace("layout".to_string())),
+            plugins.bind(&mut keymap, "layout", mode, &['a'], vec!['v'])
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use crate::editor::view::ViewId;
+use crate::graph::Diff;
+use crate::graph::GraphOperation;
+use crate::schema::ElementKind;
+
+// An auxiliary name for document elements, kept outside the document itself.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Reference {
+    Alias(String),
+    Mark(char),
+    Pin,
+    Cluster(String),
+    Selection,
+    Cursor,
+    // The cursor and selection of a view while another view is active.
+    ViewCursor(ViewId),
+    ViewSelection(ViewId),
+}
+
+impl Reference {
+    // Whether the reference names at most one element, so that adding an element replaces it.
+    pub fn is_single_valued(&self) -> bool {
+        matches!(
+            self,
+            Reference::Alias(_) | Reference::Mark(_) | Reference::Cursor | Reference::ViewCursor(_)
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ElementRef {
+    Vertex(i64),
+    Edge(i64),
+}
+
+impl ElementRef {
+    pub fn new(kind: ElementKind, id: i64) -> ElementRef {
+        match kind {
+            ElementKind::Vertex => ElementRef::Vertex(id),
+            ElementKind::Edge => ElementRef::Edge(id),
+        }
+    }
+
+    // Parse a reference to an element such as `v3` or `e0`.
+    pub fn parse(element: &str) -> Option<ElementRef> {
+        let kind = match element.chars().next()? {
+            'v' => ElementKind::Vertex,
+            'e' => ElementKind::Edge,
+            _ => return None,
+        };
+        Some(ElementRef::new(kind, element[1..].parse::<i64>().ok()?))
+    }
+}
+
+/**
+ * The registry of every reference to document elements. It participates in diff application:
+ * when an element is removed, the references to it are set aside, and when the element is added
+ * back, e.g., by undo, they are restored. A single-valued reference that has been given to
+ * another element in the meantime is not restored.
+ *
+ * Features that refer to elements should register their references here rather than handling
+ * removals themselves.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct References {
+    live: BTreeMap<Reference, BTreeSet<ElementRef>>,
+    // The references removed elements had, by element.
+    removed: BTreeMap<ElementRef, BTreeSet<Reference>>,
+}
+
+impl References {
+    pub fn new() -> References {
+        References::default()
+    }
+
+    pub fn add(&mut self, reference: Reference, element: ElementRef) {
+        let elements = self.live.entry(reference.clone()).or_default();
+        if reference.is_single_valued() {
+            elements.clear();
+        }
+        elements.insert(element);
+    }
+
+    pub fn remove(&mut self, reference: &Reference, element: ElementRef) -> bool {
+        let removed = match self.live.get_mut(reference) {
+            Some(elements) => elements.remove(&element),
+            None => false,
+        };
+        if self.live.get(reference).is_some_and(BTreeSet::is_empty) {
+            self.live.remove(reference);
+        }
+        removed
+    }
+
+    pub fn clear(&mut self, reference: &Reference) {
+        self.live.remove(reference);
+    }
+
+    // Clear a reference, and keep it from being restored to elements set aside.
+    pub fn forget(&mut self, reference: &Reference) {
+        self.clear(reference);
+        for references in self.removed.values_mut() {
+            references.remove(reference);
+        }
+    }
+
+    // The elements a reference names, in order.
+    pub fn elements(&self, reference: &Reference) -> impl Iterator<Item = ElementRef> + '_ {
+        self.live.get(reference).into_iter().flatten().copied()
+    }
+
+    // The element a single-valued reference names, if any.
+    pub fn element(&self, reference: &Reference) -> Option<ElementRef> {
+        self.elements(reference).next()
+    }
+
+    pub fn references_to(&self, element: ElementRef) -> Vec<Reference> {
+        self.live
+            .iter()
+            .filter(|(_, elements)| elements.contains(&element))
+            .map(|(reference, _)| reference.clone())
+            .collect()
+    }
+
+    // Give the elements one reference names to another instead, including those set aside.
+    pub fn rename(&mut self, from: &Reference, to: Reference) {
+        self.live.remove(&to);
+        if let Some(elements) = self.live.remove(from) {
+            self.live.insert(to.clone(), elements);
+        }
+        for references in self.removed.values_mut() {
+            references.remove(&to);
+            if references.remove(from) {
+                references.insert(to.clone());
+            }
+        }
+    }
+
+    // Every reference with the elements it names, and every removed element with the references
+    // set aside for it, e.g., to save them.
+    #[allow(clippy::type_complexity)]
+    pub fn entries(
+        &self,
+    ) -> (
+        Vec<(Reference, BTreeSet<ElementRef>)>,
+        Vec<(ElementRef, BTreeSet<Reference>)>,
+    ) {
+        (
+            self.live.clone().into_iter().collect(),
+            self.removed.clone().into_iter().collect(),
+        )
+    }
+
+    // The references saved by `entries`.
+    pub fn from_entries(
+        live: Vec<(Reference, BTreeSet<ElementRef>)>,
+        removed: Vec<(ElementRef, BTreeSet<Reference>)>,
+    ) -> References {
+        References {
+            live: live
+                .into_iter()
+                .filter(|(_, elements)| !elements.is_empty())
+                .collect(),
+            removed: removed.into_iter().collect(),
+        }
+    }
+
+    // Move the references to elements that were renumbered, as by `IdMapping`'s old and new ids.
+    pub fn renumber(&mut self, vertices: &BTreeMap<i64, i64>, edges: &BTreeMap<i64, i64>) {
+        let renumbered = |element: ElementRef| match element {
+            ElementRef::Vertex(id) => ElementRef::Vertex(*vertices.get(&id).unwrap_or(&id)),
+            ElementRef::Edge(id) => ElementRef::Edge(*edges.get(&id).unwrap_or(&id)),
+        };
+        for elements in self.live.values_mut() {
+            *elements = elements.iter().copied().map(renumbered).collect();
+        }
+    }
+
+    // Forget the references set aside for removed elements that can no longer be added back.
+    pub fn forget_removed<F: Fn(ElementRef) -> bool>(&mut self, can_return: F) {
+        self.removed.retain(|element, _| can_return(*element));
+    }
+
+    // Bring the references up to date with a diff that was just applied to the document.
+    pub fn apply(&mut self, diff: &Diff) {
+        for op in diff.operations.iter() {
+            match op {
+                GraphOperation::RemoveVertex(v) => self.set_aside(ElementRef::Vertex(v.id)),
+                GraphOperation::RemoveEdge(e) => self.set_aside(ElementRef::Edge(e.id)),
+                GraphOperation::AddVertex(v) => self.restore(ElementRef::Vertex(v.id)),
+                GraphOperation::AddEdge(e) => self.restore(ElementRef::Edge(e.id)),
+                _ => {}
+            }
+        }
+    }
+
+    fn set_aside(&mut self, element: ElementRef) {
+        let references = self.references_to(element);
+        for reference in references.iter() {
+            self.remove(reference, element);
+        }
+        if !references.is_empty() {
+            self.removed.entry(element).or_default().extend(references);
+        }
+    }
+
+    fn restore(&mut self, element: ElementRef) {
+        for reference in self.removed.remove(&element).into_iter().flatten() {
+            if !reference.is_single_valued() || !self.live.contains_key(&reference) {
+                self.add(reference, element);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Edge;
+    use crate::graph::Graph;
+    use crate::graph::Vertex;
+
+    #[test]
+    fn references_are_restored_when_removal_is_undone() {
+        let mut g = Graph::new();
+        for id in 0..3 {
+            g.apply(GraphOperation::AddVertex(Vertex::new(id)));
+        }
+        g.apply(GraphOperation::AddEdge(Edge::new(0, 0, 1)));
+
+        let mut references = References::new();
+        references.add(Reference::Mark('a'), ElementRef::Vertex(1));
+        references.add(Reference::Cursor, ElementRef::Vertex(1));
+        references.add(Reference::Pin, ElementRef::Vertex(1));
+        references.add(Reference::Pin, ElementRef::Vertex(2));
+        references.add(Reference::Selection, ElementRef::Edge(0));
+
+        let removal = g.apply(GraphOperation::RemoveVertex(Vertex::new(1)));
+        references.apply(&removal);
+        assert!(references.references_to(ElementRef::Vertex(1)).is_empty());
+        assert_eq!(None, references.element(&Reference::Selection));
+        assert_eq!(
+            vec![ElementRef::Vertex(2)],
+            references.elements(&Reference::Pin).collect::<Vec<_>>()
+        );
+
+        // The cursor moved on while the vertex was gone, so it stays where it is.
+        references.add(Reference::Cursor, ElementRef::Vertex(2));
+
+        let undo = g.apply_all(removal.invert().operations);
+        references.apply(&undo);
+        assert_eq!(
+            vec![Reference::Mark('a'), Reference::Pin],
+            references.references_to(ElementRef::Vertex(1))
+        );
+        assert_eq!(
+            Some(ElementRef::Edge(0)),
+            references.element(&Reference::Selection)
+        );
+        assert_eq!(
+            Some(ElementRef::Vertex(2)),
+            references.element(&Reference::Cursor)
+        );
+    }
+
+    #[test]
+    fn single_valued_references_name_one_element() {
+        let mut references = References::new();
+        references.add(Reference::Alias("hub".to_string()), ElementRef::Vertex(1));
+        references.add(Reference::Alias("hub".to_string()), ElementRef::Vertex(2));
+        references.add(Reference::Cluster("c".to_string()), ElementRef::Vertex(1));
+        references.add(Reference::Cluster("c".to_string()), ElementRef::Vertex(2));
+
+        assert_eq!(
+            vec![ElementRef::Vertex(2)],
+            references
+                .elements(&Reference::Alias("hub".to_string()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            2,
+            references
+                .elements(&Reference::Cluster("c".to_string()))
+                .count()
+        );
+        assert!(references.remove(&Reference::Cluster("c".to_string()), ElementRef::Vertex(1)));
+        assert!(!references.remove(&Reference::Pin, ElementRef::Vertex(1)));
+    }
+}
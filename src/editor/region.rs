@@ -0,0 +1,65 @@
+use std::collections::BTreeSet;
+
+use crate::editor::mouse::Position;
+use crate::format::layout::layout;
+use crate::graph::Graph;
+
+// How far from a vertex's position a point may be and still be on the vertex, about a third of
+// the spacing of the automatic layout.
+const VERTEX_RADIUS: f64 = 0.5;
+
+// The vertex a position is on, the nearest one if it is on several.
+pub fn vertex_at(graph: &Graph, position: Position) -> Option<i64> {
+    layout(graph)
+        .into_iter()
+        .map(|(id, (x, y))| (id, (x - position.x).hypot(y - position.y)))
+        .filter(|(_, distance)| *distance <= VERTEX_RADIUS)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(id, _)| id)
+}
+
+/**
+ * The vertices inside the rectangle with two vertices at opposite corners, including the corners
+ * and anything on the rectangle's sides. Vertices are positioned as the drawing formats lay them
+ * out, so a region of vertices without a `pos` attribute is the one seen in a drawing.
+ */
+pub fn vertices_between(graph: &Graph, corner: i64, opposite: i64) -> BTreeSet<i64> {
+    let positions = layout(graph);
+    let ((x1, y1), (x2, y2)) = match (positions.get(&corner), positions.get(&opposite)) {
+        (Some(corner), Some(opposite)) => (*corner, *opposite),
+        _ => return BTreeSet::new(),
+    };
+    let (left, right) = (x1.min(x2), x1.max(x2));
+    let (bottom, top) = (y1.min(y2), y1.max(y2));
+    positions
+        .into_iter()
+        .filter(|(_, (x, y))| (left..=right).contains(x) && (bottom..=top).contains(y))
+        .map(|(id, _)| id)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Vertex;
+
+    #[test]
+    fn vertices_inside_the_rectangle() {
+        let mut g = Graph::new();
+        for (id, pos) in [(0, "0,0"), (1, "2,1"), (2, "1,0.5"), (3, "3,0"), (4, "1,1")].iter() {
+            g.add_vertex(Vertex::new(*id).with_attribute("pos", pos));
+        }
+        let expected: BTreeSet<i64> = [0, 1, 2, 4].iter().copied().collect();
+        assert_eq!(expected, vertices_between(&g, 1, 0));
+        assert_eq!(expected, vertices_between(&g, 0, 1));
+        assert_eq!(
+            vec![&3],
+            vertices_between(&g, 3, 3).iter().collect::<Vec<_>>()
+        );
+        assert!(vertices_between(&g, 0, 7).is_empty());
+
+        assert_eq!(Some(2), vertex_at(&g, Position::new(1.1, 0.6)));
+        assert_eq!(Some(4), vertex_at(&g, Position::new(1.0, 0.8)));
+        assert_eq!(None, vertex_at(&g, Position::new(-1.0, 0.0)));
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+
+use crate::editor::replay::InputLog;
+use crate::graph::Graph;
+
+// The register yanks go to, and pastes come from, when no register is named.
+pub const UNNAMED: char = '"';
+
+// The contents of a register.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Register {
+    // The inputs recorded by `q`.
+    Macro(InputLog),
+    // The subgraph yanked by `y`.
+    Subgraph(Graph),
+}
+
+/**
+ * Like vim's registers, named storage shared by macros and yanked subgraphs: recording a macro
+ * into a register replaces the subgraph yanked into it, and vice versa. Registers are named by
+ * lowercase letters, and a yank into a named register also goes to the unnamed register.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Registers {
+    registers: BTreeMap<char, Register>,
+}
+
+impl Registers {
+    pub fn new() -> Registers {
+        Registers::default()
+    }
+
+    pub fn get(&self, name: char) -> Option<&Register> {
+        self.registers.get(&name)
+    }
+
+    pub fn set(&mut self, name: char, register: Register) {
+        self.registers.insert(name, register);
+    }
+
+    // The names of the registers holding something, in order.
+    pub fn names(&self) -> impl Iterator<Item = char> + '_ {
+        self.registers.keys().copied()
+    }
+
+    pub fn yank(&mut self, name: Option<char>, subgraph: Graph) {
+        if let Some(name) = name {
+            self.set(name, Register::Subgraph(subgraph.clone()));
+        }
+        self.set(UNNAMED, Register::Subgraph(subgraph));
+    }
+
+    // The subgraph in a register, or an error describing why there is none.
+    pub fn subgraph(&self, name: Option<char>) -> Result<&Graph, String> {
+        let name = name.unwrap_or(UNNAMED);
+        match self.get(name) {
+            Some(Register::Subgraph(graph)) => Ok(graph),
+            Some(Register::Macro(_)) => Err(format!("Register {} holds a macro", name)),
+            None => Err(format!("Nothing in register {}", name)),
+        }
+    }
+
+    // The macro in a register, or an error describing why there is none.
+    pub fn recorded_macro(&self, name: char) -> Result<&InputLog, String> {
+        match self.get(name) {
+            Some(Register::Macro(inputs)) => Ok(inputs),
+            Some(Register::Subgraph(_)) => Err(format!("Register {} holds a subgraph", name)),
+            None => Err(format!("No macro in register {}", name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Vertex;
+
+    #[test]
+    fn yanks_replace_macros() {
+        let mut registers = Registers::new();
+        registers.set('a', Register::Macro(InputLog::new()));
+        assert!(registers.subgraph(Some('a')).is_err());
+
+        let mut g = Graph::new();
+        g.add_vertex(Vertex::new(0));
+        registers.yank(Some('a'), g.clone());
+        assert_eq!(Ok(&g), registers.subgraph(Some('a')));
+        assert_eq!(Ok(&g), registers.subgraph(None));
+        assert!(registers.recorded_macro('a').is_err());
+        assert_eq!(vec![UNNAMED, 'a'], registers.names().collect::<Vec<_>>());
+    }
+}
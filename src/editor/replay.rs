@@ -0,0 +1,140 @@
+use crate::editor::state::EditorState;
+use crate::editor::state::Input;
+#[cfg(feature = "json")]
+use crate::format::ParseError;
+
+// The version of the layout written by `InputLog::to_json`.
+#[cfg(feature = "json")]
+pub const VERSION: u32 = 1;
+
+#[cfg(feature = "json")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Layout {
+    version: u32,
+    inputs: Vec<Input>,
+}
+
+/**
+ * The inputs an editor evaluated, in order, e.g., to attach to a bug report. Evaluation is
+ * deterministic, so replaying the inputs in a new editor reproduces the editor that recorded
+ * them, provided it was new when recording started: the same mode, document, id counters and
+ * history tree, apart from the times edits were made. Inputs that read files, e.g., `:e`, only
+ * reproduce the same state if the files are unchanged.
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputLog {
+    pub inputs: Vec<Input>,
+}
+
+impl InputLog {
+    pub fn new() -> InputLog {
+        InputLog::default()
+    }
+
+    pub fn push(&mut self, input: Input) {
+        self.inputs.push(input);
+    }
+
+    pub fn replay(&self) -> EditorState {
+        let mut state = EditorState::new();
+        self.replay_onto(&mut state);
+        state
+    }
+
+    // Evaluate the inputs in an existing editor, e.g., one configured like the recording one.
+    pub fn replay_onto(&self, state: &mut EditorState) {
+        for input in self.inputs.iter() {
+            state.evaluate(input.clone());
+        }
+    }
+
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&Layout {
+            version: VERSION,
+            inputs: self.inputs.clone(),
+        })
+        .unwrap()
+    }
+
+    #[cfg(feature = "json")]
+    pub fn from_json(text: &str) -> Result<InputLog, ParseError> {
+        let layout: Layout = serde_json::from_str(text)
+            .map_err(|err| ParseError::new(err.line(), &err.to_string()))?;
+        if layout.version > VERSION {
+            return Err(ParseError::new(
+                1,
+                &format!(
+                    "Input log version {} is newer than the supported version {}",
+                    layout.version, VERSION
+                ),
+            ));
+        }
+        Ok(InputLog {
+            inputs: layout.inputs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use super::*;
+    use crate::editor::history::HistoryNodeView;
+
+    // The history tree, without the times edits were made, which replay cannot reproduce.
+    fn shape(state: &EditorState) -> Vec<HistoryNodeView> {
+        let mut view = state.history().view();
+        for node in view.iter_mut() {
+            node.created = SystemTime::UNIX_EPOCH;
+            node.last_touched = SystemTime::UNIX_EPOCH;
+        }
+        view
+    }
+
+    #[test]
+    fn replay_reproduces_the_session() {
+        let mut state = EditorState::new();
+        state.evaluate(Input::Key('i'));
+        state.start_recording();
+        let keys = "vvve0,1\u{e007}\u{1b}uu:history message kept\u{e007}ive3,0";
+        for key in keys.chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let log = state.stop_recording().unwrap();
+        state.evaluate(Input::Key('v'));
+        assert_eq!(keys.chars().count(), log.inputs.len());
+
+        // The recording started in insert mode, so the replaying editor must too.
+        let mut replayed = EditorState::new();
+        InputLog {
+            inputs: vec![Input::Key('i')],
+        }
+        .replay_onto(&mut replayed);
+        log.replay_onto(&mut replayed);
+        replayed.evaluate(Input::Key('v'));
+
+        assert_eq!(state.mode(), replayed.mode());
+        assert_eq!(state.document(), replayed.document());
+        assert_eq!(state.next_ids(), replayed.next_ids());
+        assert_eq!(shape(&state), shape(&replayed));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_round_trip() {
+        let log = InputLog {
+            inputs: "iv\u{1b}".chars().map(Input::Key).collect(),
+        };
+        assert_eq!(log, InputLog::from_json(&log.to_json()).unwrap());
+        assert_eq!(
+            log.replay().document(),
+            InputLog::from_json(&log.to_json())
+                .unwrap()
+                .replay()
+                .document()
+        );
+        assert!(InputLog::from_json(r#"{"version": 2, "inputs": []}"#).is_err());
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+
+use crate::graph::Diff;
+use crate::graph::DiffSummary;
+use crate::graph::Graph;
+
+// How two scenarios differ, counted by kind of change needed to turn the first into the second.
+pub type ScenarioComparison = DiffSummary;
+
+/**
+ * Scenarios are named variants of a document. They share a base graph, and each is stored as the
+ * diff that turns the base into the variant, so that many variants of a large document stay
+ * cheap.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Scenarios {
+    base: Graph,
+    variants: BTreeMap<String, Diff>,
+    // The scenario most recently saved or switched to.
+    active: Option<String>,
+}
+
+impl Scenarios {
+    pub fn new() -> Scenarios {
+        Scenarios::default()
+    }
+
+    pub fn base(&self) -> &Graph {
+        &self.base
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.variants.keys().map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.variants.is_empty()
+    }
+
+    pub fn active(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+
+    pub fn set_active(&mut self, name: &str) {
+        self.active = Some(name.to_string());
+    }
+
+    // Save the graph as the named scenario. The first scenario saved also becomes the base.
+    pub fn save(&mut self, name: &str, graph: &Graph) {
+        if self.variants.is_empty() {
+            self.base = graph.clone();
+        }
+        self.variants
+            .insert(name.to_string(), self.base.difference(graph));
+        self.active = Some(name.to_string());
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        if self.active.as_deref() == Some(name) {
+            self.active = None;
+        }
+        self.variants.remove(name).is_some()
+    }
+
+    pub fn graph(&self, name: &str) -> Option<Graph> {
+        let diff = self.variants.get(name)?;
+        let mut graph = self.base.clone();
+        graph.apply_all(diff.operations.clone());
+        Some(graph)
+    }
+
+    // Share a different base graph, without changing any scenario.
+    pub fn rebase(&mut self, base: &Graph) {
+        let graphs: Vec<(String, Graph)> = self
+            .variants
+            .keys()
+            .map(|name| (name.clone(), self.graph(name).unwrap()))
+            .collect();
+        self.base = base.clone();
+        for (name, graph) in graphs {
+            self.variants.insert(name, self.base.difference(&graph));
+        }
+    }
+
+    pub fn compare(&self, from: &str, to: &str) -> Option<ScenarioComparison> {
+        Some(self.graph(from)?.difference(&self.graph(to)?).summary())
+    }
+
+    // Every pair of distinct scenarios, in order of name, compared.
+    pub fn compare_all(&self) -> Vec<(String, String, ScenarioComparison)> {
+        let graphs: Vec<(&String, Graph)> = self
+            .variants
+            .keys()
+            .map(|name| (name, self.graph(name).unwrap()))
+            .collect();
+        let mut comparisons = Vec::new();
+        for (i, (from, from_graph)) in graphs.iter().enumerate() {
+            for (to, to_graph) in graphs.iter().skip(i + 1) {
+                comparisons.push((
+                    from.to_string(),
+                    to.to_string(),
+                    from_graph.difference(to_graph).summary(),
+                ));
+            }
+        }
+        comparisons
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Edge;
+    use crate::graph::Vertex;
+
+    fn network() -> Graph {
+        let mut g = Graph::new();
+        for id in 0..3 {
+            g.add_vertex(Vertex::new(id));
+        }
+        g.add_edge(Edge::new(0, 0, 1).with_attribute("weight", "5"));
+        g
+    }
+
+    #[test]
+    fn scenarios_share_a_base() {
+        let mut scenarios = Scenarios::new();
+        scenarios.save("A", &network());
+
+        let mut b = network();
+        b.add_edge(Edge::new(1, 1, 2));
+        b.modify_edge(Edge::new(0, 0, 1).with_attribute("weight", "3"));
+        scenarios.save("B", &b);
+
+        assert_eq!(Some(network()), scenarios.graph("A"));
+        assert_eq!(Some(b.clone()), scenarios.graph("B"));
+        assert_eq!(Some("B"), scenarios.active());
+        assert_eq!(
+            Some(ScenarioComparison {
+                added_edges: 1,
+                modified_edges: 1,
+                ..ScenarioComparison::default()
+            }),
+            scenarios.compare("A", "B")
+        );
+
+        scenarios.rebase(&b);
+        assert_eq!(Some(network()), scenarios.graph("A"));
+        assert!(scenarios.compare("B", "B").unwrap().is_empty());
+        assert_eq!(None, scenarios.compare("A", "C"));
+    }
+
+    #[test]
+    fn compare_all_pairs() {
+        let mut scenarios = Scenarios::new();
+        for (name, vertices) in [("A", 1), ("B", 2), ("C", 3)].iter() {
+            let mut g = Graph::new();
+            for id in 0..*vertices {
+                g.add_vertex(Vertex::new(id));
+            }
+            scenarios.save(name, &g);
+        }
+        let pairs: Vec<(String, String, usize)> = scenarios
+            .compare_all()
+            .into_iter()
+            .map(|(from, to, c)| (from, to, c.added_vertices))
+            .collect();
+        assert_eq!(
+            vec![
+                ("A".to_string(), "B".to_string(), 1),
+                ("A".to_string(), "C".to_string(), 2),
+                ("B".to_string(), "C".to_string(), 1),
+            ],
+            pairs
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use crate::graph::Graph;
+
+// The ids of the vertices a search matches, in order: those whose id is the query, and those
+// whose label contains it.
+pub fn matching_vertices(graph: &Graph, query: &str) -> Vec<i64> {
+    let mut matches: Vec<i64> = graph
+        .vertices
+        .values()
+        .filter(|v| {
+            v.id.to_string() == query || v.attribute("label").is_some_and(|l| l.contains(query))
+        })
+        .map(|v| v.id)
+        .collect();
+    matches.sort_unstable();
+    matches
+}
+
+// The match after the given vertex, or before it if not forward, wrapping around the matches.
+pub fn next_match(matches: &[i64], from: Option<i64>, forward: bool) -> Option<i64> {
+    let from = match from {
+        Some(from) => from,
+        None => return matches.first().copied(),
+    };
+    if forward {
+        matches
+            .iter()
+            .find(|id| **id > from)
+            .or_else(|| matches.first())
+            .copied()
+    } else {
+        matches
+            .iter()
+            .rev()
+            .find(|id| **id < from)
+            .or_else(|| matches.last())
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Vertex;
+
+    #[test]
+    fn matches_ids_and_labels() {
+        let mut g = Graph::new();
+        g.add_vertex(Vertex::new(12).with_attribute("label", "alice"));
+        g.add_vertex(Vertex::new(3).with_attribute("label", "12 monkeys"));
+        g.add_vertex(Vertex::new(1));
+        g.add_vertex(Vertex::new(4).with_attribute("label", "bob"));
+        let matches = matching_vertices(&g, "12");
+        assert_eq!(vec![3, 12], matches);
+
+        assert_eq!(Some(3), next_match(&matches, None, true));
+        assert_eq!(Some(12), next_match(&matches, Some(3), true));
+        assert_eq!(Some(3), next_match(&matches, Some(12), true));
+        assert_eq!(Some(12), next_match(&matches, Some(3), false));
+        assert_eq!(Some(3), next_match(&matches, Some(4), false));
+        assert_eq!(None, next_match(&[], Some(4), false));
+    }
+}
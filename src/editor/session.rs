@@ -0,0 +1,217 @@
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+use crate::editor::history::FlatHistory;
+use crate::editor::history::History;
+use crate::editor::history::HistoryEntry;
+use crate::editor::history::HistoryLimit;
+use crate::editor::mode::ModalOperation;
+use crate::editor::options::EditorOptions;
+use crate::editor::references::ElementRef;
+use crate::editor::references::Reference;
+use crate::editor::references::References;
+use crate::editor::view::Views;
+use crate::format::ParseError;
+use crate::graph::Attributes;
+use crate::graph::Edge;
+use crate::graph::Graph;
+use crate::graph::Vertex;
+
+// The version of the session layout written by `Session::to_json`.
+pub const VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Node {
+    parent: Option<usize>,
+    entry: HistoryEntry,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Layout {
+    version: u32,
+    #[serde(default, skip_serializing_if = "Attributes::is_empty")]
+    properties: Attributes,
+    vertices: Vec<Vertex>,
+    edges: Vec<Edge>,
+    current: usize,
+    nodes: Vec<Node>,
+    // Bookmarked nodes by position in `nodes`. Sessions written before bookmarks were saved have
+    // none, and no history limit.
+    #[serde(default)]
+    bookmarks: Vec<(String, usize)>,
+    #[serde(default)]
+    history_limit: HistoryLimit,
+    next_vertex_id: i64,
+    next_edge_id: i64,
+    options: EditorOptions,
+    // References are keyed by values that are not strings, so they are kept as lists of pairs.
+    references: Vec<(Reference, BTreeSet<ElementRef>)>,
+    set_aside: Vec<(ElementRef, BTreeSet<Reference>)>,
+    views: Views,
+    last_change: Option<ModalOperation>,
+    search: Option<String>,
+}
+
+/**
+ * Everything needed to pick an editing session up where it was left off: the document, its undo
+ * tree with the position and bookmarks in it, the next ids to allocate, the options, every
+ * reference, including marks and the cursor and selection of each view, and what `.` and `n`
+ * repeat. Unlike an undo
+ * file, a session does not depend on a document saved elsewhere. A restored session starts in
+ * Command mode, without folds, registers or pending keys.
+ */
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub document: Graph,
+    pub history: History,
+    pub next_vertex_id: i64,
+    pub next_edge_id: i64,
+    pub options: EditorOptions,
+    pub references: References,
+    pub views: Views,
+    pub last_change: Option<ModalOperation>,
+    pub search: Option<String>,
+}
+
+impl Session {
+    pub fn to_json(&self) -> String {
+        let mut vertices: Vec<Vertex> = self.document.vertices.values().cloned().collect();
+        vertices.sort_by_key(|v| v.id);
+        let mut edges: Vec<Edge> = self.document.edges.values().cloned().collect();
+        edges.sort_by_key(|e| e.id);
+        let history = self.history.flatten();
+        let (references, set_aside) = self.references.entries();
+        let layout = Layout {
+            version: VERSION,
+            properties: self.document.properties.clone(),
+            vertices,
+            edges,
+            current: history.current,
+            nodes: history
+                .nodes
+                .into_iter()
+                .map(|(parent, entry)| Node { parent, entry })
+                .collect(),
+            bookmarks: history.bookmarks,
+            history_limit: history.limit,
+            next_vertex_id: self.next_vertex_id,
+            next_edge_id: self.next_edge_id,
+            options: self.options.clone(),
+            references,
+            set_aside,
+            views: self.views.clone(),
+            last_change: self.last_change.clone(),
+            search: self.search.clone(),
+        };
+        serde_json::to_string(&layout).unwrap()
+    }
+
+    pub fn from_json(text: &str) -> Result<Session, ParseError> {
+        let layout: Layout = serde_json::from_str(text)
+            .map_err(|err| ParseError::new(err.line(), &err.to_string()))?;
+        if layout.version > VERSION {
+            return Err(ParseError::new(
+                1,
+                &format!(
+                    "Session version {} is newer than the supported version {}",
+                    layout.version, VERSION
+                ),
+            ));
+        }
+        let nodes = layout
+            .nodes
+            .into_iter()
+            .map(|node| (node.parent, node.entry))
+            .collect();
+        let history = History::unflatten(FlatHistory {
+            nodes,
+            current: layout.current,
+            bookmarks: layout.bookmarks,
+            limit: layout.history_limit,
+        })
+        .ok_or_else(|| ParseError::new(1, "Session does not describe a history tree"))?;
+        if !layout.views.contains(layout.views.active()) {
+            return Err(ParseError::new(1, "Session has no active view"));
+        }
+        let mut document = Graph::new();
+        document.properties = layout.properties;
+        document.vertices = layout.vertices.into_iter().map(|v| (v.id, v)).collect();
+        document.edges = layout.edges.into_iter().map(|e| (e.id, e)).collect();
+        Ok(Session {
+            document,
+            history,
+            next_vertex_id: layout.next_vertex_id,
+            next_edge_id: layout.next_edge_id,
+            options: layout.options,
+            references: References::from_entries(layout.references, layout.set_aside),
+            views: layout.views,
+            last_change: layout.last_change,
+            search: layout.search,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Diff;
+    use crate::graph::GraphOperation;
+
+    #[test]
+    fn round_trip() {
+        let mut history = History::new();
+        history.record(Diff {
+            operations: vec![GraphOperation::AddVertex(Vertex::new(0))],
+        });
+        history.bookmark("first");
+        let limit = HistoryLimit {
+            max_nodes: Some(100),
+            max_bytes: Some(1 << 20),
+        };
+        history.set_limit(limit);
+        let mut document = Graph::new();
+        document.add_vertex(Vertex::new(0).with_attribute("label", "a"));
+        document.add_vertex(Vertex::new(1));
+        document.add_edge(Edge::new(0, 0, 1));
+        let mut references = References::new();
+        references.add(Reference::Mark('m'), ElementRef::Vertex(1));
+        references.add(Reference::Cursor, ElementRef::Vertex(0));
+        references.add(Reference::Selection, ElementRef::Edge(0));
+        let session = Session {
+            document: document.clone(),
+            history,
+            next_vertex_id: 2,
+            next_edge_id: 1,
+            options: EditorOptions {
+                strict: true,
+                ..EditorOptions::default()
+            },
+            references,
+            views: Views::new(),
+            last_change: Some(ModalOperation::CreateNewVertex { label: None }),
+            search: Some("a".to_string()),
+        };
+
+        let restored = Session::from_json(&session.to_json()).unwrap();
+        assert_eq!(document, restored.document);
+        assert_eq!(1, restored.history.len());
+        assert_eq!(
+            Some(restored.history.current()),
+            restored.history.bookmarked("first")
+        );
+        assert_eq!(limit, restored.history.limit());
+        assert_eq!((2, 1), (restored.next_vertex_id, restored.next_edge_id));
+        assert!(restored.options.strict);
+        assert_eq!(
+            Some(ElementRef::Vertex(1)),
+            restored.references.element(&Reference::Mark('m'))
+        );
+        assert_eq!(session.references.entries(), restored.references.entries());
+        assert_eq!(session.views, restored.views);
+        assert_eq!(session.last_change, restored.last_change);
+        assert_eq!(Some("a"), restored.search.as_deref());
+
+        assert!(Session::from_json("{\"version\": 2}").is_err());
+    }
+}
@@ -1,15 +1,119 @@
-use indextree::Arena;
 use indextree::NodeId;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
 
+use crate::computed::ComputedAttribute;
+use crate::computed::ComputedAttributes;
+use crate::editor::attribute_history::AttributeHistory;
+use crate::editor::bindings::bindings;
+use crate::editor::bindings::Binding;
+use crate::editor::command::Command;
+use crate::editor::completion;
+use crate::editor::completion::Completion;
+use crate::editor::completion::CompletionBudget;
+use crate::editor::cursor::motion_target;
+use crate::editor::error::EditorError;
+use crate::editor::event::EditorEvent;
+use crate::editor::event::Subscribers;
+use crate::editor::fold::FoldKind;
+use crate::editor::fold::Folds;
+use crate::editor::form::AttributeForm;
+use crate::editor::history::History;
+use crate::editor::history::HistoryEvent;
+use crate::editor::history::HistoryLimit;
+use crate::editor::hooks::element_events;
+use crate::editor::hooks::Hook;
+use crate::editor::hooks::HookAction;
+use crate::editor::hooks::HookCallback;
+use crate::editor::hooks::HookEvent;
+use crate::editor::hooks::HookId;
+use crate::editor::hooks::Hooks;
+use crate::editor::ids::compact;
+use crate::editor::ids::IdAllocator;
+use crate::editor::ids::Sequential;
+use crate::editor::import::renumber;
+use crate::editor::import::IdMapping;
+#[cfg(feature = "json")]
+use crate::editor::journal::Autosave;
+#[cfg(feature = "json")]
+use crate::editor::journal::RecoveryJournal;
+use crate::editor::jumplist::Jumplist;
+use crate::editor::keymap::Keymap;
+use crate::editor::keymap::KeymapMode;
+use crate::editor::keymap::Resolution;
+use crate::editor::keys::KeyEvent;
+use crate::editor::keys::AT;
+use crate::editor::merge::three_way_merge;
+use crate::editor::merge::MergeConflict;
+use crate::editor::message::Message;
+use crate::editor::message::MessageHistory;
+use crate::editor::message::Severity;
+use crate::editor::mode::EdgeSource;
 use crate::editor::mode::EditorMode;
+use crate::editor::mode::FoldAction;
 use crate::editor::mode::ModalOperation;
+use crate::editor::mode::Operator;
+use crate::editor::mode::Selection;
+use crate::editor::mode::Target;
 use crate::editor::mode::TransitionResult;
+use crate::editor::mouse::MouseEvent;
+use crate::editor::mouse::Position;
+#[cfg(feature = "json")]
+use crate::editor::op_log::OpLog;
+use crate::editor::options::EditorOptions;
+use crate::editor::pending_text::PendingText;
+use crate::editor::plugin::BindingError;
+use crate::editor::plugin::Capabilities;
+use crate::editor::plugin::PermissionError;
+use crate::editor::plugin::Plugin;
+use crate::editor::plugin::PluginContext;
+use crate::editor::plugin::PluginOperation;
+use crate::editor::plugin::PluginRequest;
+use crate::editor::plugin::Plugins;
+use crate::editor::references::ElementRef;
+use crate::editor::references::Reference;
+use crate::editor::references::References;
+use crate::editor::region::vertex_at;
+use crate::editor::region::vertices_between;
+use crate::editor::registers::Register;
+use crate::editor::registers::Registers;
+use crate::editor::replay::InputLog;
+use crate::editor::scenario::Scenarios;
+use crate::editor::search::matching_vertices;
+use crate::editor::search::next_match;
+use crate::editor::status::PendingCommand;
+use crate::editor::status::Status;
+#[cfg(feature = "json")]
+use crate::editor::trace::TraceRecorder;
+use crate::editor::view::ViewId;
+use crate::editor::view::Viewport;
+use crate::editor::view::Views;
+use crate::format::clipboard::from_clipboard;
+use crate::format::clipboard::to_clipboard;
+use crate::format::layout::positions_after;
+use crate::format::ParseError;
+use crate::graph::Attributes;
 use crate::graph::Diff;
 use crate::graph::Edge;
 use crate::graph::Graph;
 use crate::graph::GraphOperation;
 use crate::graph::Vertex;
+use crate::schema::ElementKind;
+use crate::schema::Schema;
 
+mod ex;
+mod persistence;
+
+/**
+ * An editing session: the document, its history, and the modes keys move through. An EditorState
+ * is Send and Sync, so a frontend may handle input and render on different threads, e.g., by
+ * sharing it as an `Arc<Mutex<EditorState>>`, and the events of `subscribe` may be received on
+ * any thread.
+ */
 #[derive(Debug)]
 pub struct EditorState {
     mode: EditorMode,
@@ -18,23 +122,143 @@ pub struct EditorState {
     // position in the history tree.
     document: Graph,
 
-    // The "undo tree" of this editing session
-    // Children of a given node are appended in time order.
-    history_tree: Arena<Diff>,
+    // The "undo tree" of this editing session, positioned at the node corresponding to the last
+    // edit of the document.
+    history: History,
+
+    vertex_ids: Box<dyn IdAllocator>,
+    edge_ids: Box<dyn IdAllocator>,
+    // The ids handed out for the edit being interpreted, which are released if it is rejected.
+    allocated: Vec<ElementRef>,
+
+    options: EditorOptions,
+
+    // When set, the document's invariants are verified after every N document changes, and a
+    // violation aborts with the full report. Meant for debugging the editor itself.
+    invariant_check_interval: Option<usize>,
+    changes_since_invariant_check: usize,
+
+    // When set, new edits must conform to the schema. Undo and redo are not validated, since
+    // they only restore earlier states of the document.
+    schema: Option<Schema>,
+
+    // Attributes derived from the document, which are readable alongside stored attributes but
+    // never become part of the document or its history.
+    computed: ComputedAttributes,
+
+    // Vertices recently created or referenced, from least to most recent, used to rank
+    // completions.
+    recent_vertices: Vec<i64>,
+    completion_budget: CompletionBudget,
+    completions: Vec<Completion>,
+
+    subscribers: Subscribers,
 
-    // The id of the node in the history tree corresponding
-    // to the last edit of the document.
-    last_edit: Option<NodeId>,
+    folds: Folds,
 
-    next_vertex_id: i64,
-    next_edge_id: i64,
+    scenarios: Scenarios,
+
+    // The capabilities of each registered plugin, enforced whenever a plugin makes a request.
+    plugins: Plugins,
+    // The callbacks and autocommands run at hooks, and whether they are running, in which case
+    // the hooks their changes would run are not.
+    hooks: Hooks,
+    running_hooks: bool,
+
+    // Aliases, marks, pins and other names for elements, kept consistent with the document.
+    references: References,
+    // The views open on the document. The cursor and selection of the active view are
+    // `Reference::Cursor` and `Reference::Selection`; those of the others are set aside as
+    // `Reference::ViewCursor` and `Reference::ViewSelection`.
+    views: Views,
+
+    // The previous values of attributes, if they are being recorded.
+    attribute_history: Option<AttributeHistory>,
+
+    // The journal of changes made since the document was last saved, for crash recovery.
+    #[cfg(feature = "json")]
+    autosave: Autosave,
+    // A journal found when the document was opened, until it is recovered or discarded.
+    #[cfg(feature = "json")]
+    recovery: Option<RecoveryJournal>,
+    // The log every change to the document is appended to, if one is being kept.
+    #[cfg(feature = "json")]
+    op_log: Option<OpLog>,
+    // The file every input is traced to, if the editor is being traced.
+    #[cfg(feature = "json")]
+    trace: Option<TraceRecorder>,
+
+    // The history node that coalescing edits are folded into, while the mode coalesces edits or
+    // an undo group is open.
+    coalescing: Option<NodeId>,
+    // The number of undo groups opened with `begin_undo_group` and not yet ended.
+    undo_groups: usize,
+
+    // The inputs evaluated since recording started, if it has.
+    recording: Option<InputLog>,
+    // The last operation that changed the document, which `.` repeats.
+    last_change: Option<ModalOperation>,
+    // The last search, which `n` and `N` repeat.
+    search: Option<String>,
+    // Where the cursor jumped from, which Ctrl-O and Ctrl-I retrace.
+    jumps: Jumplist,
+    // The vertex created last and the target of the edge created last, which `E` connects.
+    created_vertex: Option<i64>,
+    edge_target: Option<i64>,
+    // The vertex the mouse was pressed on, and whether it has been dragged since.
+    drag: Option<(i64, bool)>,
+    // Macros recorded with `q` and subgraphs yanked with `y`.
+    registers: Registers,
+    keymap: Keymap,
+    pending_keys: Vec<char>,
+    // How long to wait for the rest of a mapping before resolving the keys typed so far, if at
+    // all, and how long has passed since the last key.
+    key_timeout: Option<Duration>,
+    waited: Duration,
+    // The errors and notices not yet taken by the caller, oldest first.
+    messages: Vec<Message>,
+    // Every error and notice reported, until cleared, which `:messages` lists.
+    message_history: MessageHistory,
+    // The last error or notice, cleared when the next key is typed.
+    message: Option<Message>,
+    // The macro being recorded, if any, and its register.
+    macro_recording: Option<(char, InputLog)>,
+    // The registers of the macros being replayed, innermost last.
+    replaying: Vec<char>,
+    last_macro: Option<char>,
 }
 
+// Keep the editor shareable between threads: a field that is not Send or Sync, e.g., an `Rc`, is
+// a compile error here rather than in a frontend.
+const _: fn() = || {
+    fn shareable<T: Send + Sync>() {}
+    shareable::<EditorState>();
+    shareable::<EditorEvent>();
+    shareable::<Graph>();
+    shareable::<History>();
+};
+
+const MAX_RECENT_VERTICES: usize = 32;
+
+// The most messages kept for a caller that does not take them; older ones are dropped.
+const MAX_MESSAGES: usize = 100;
+
+// How long to wait for the rest of a mapping, like vim's default `timeoutlen`.
+pub const DEFAULT_KEY_TIMEOUT: Duration = Duration::from_millis(1000);
+
+// The weight of an edge without a weight attribute, as in most weighted graph algorithms.
+const DEFAULT_WEIGHT: f64 = 1.0;
+
 #[derive(Debug)]
 pub struct OpInterpretation {
     document_changes: Diff,
     new_history_node: bool,
     set_last_edit: Option<NodeId>,
+    // Whether the changes may be folded into the previous history node, if it was also made by
+    // coalescing edits, e.g., in the same Insert mode session.
+    coalesce: bool,
+    // Whether the history move is an undo, after which the PostUndo hooks run.
+    undo: bool,
 }
 
 impl Default for OpInterpretation {
@@ -45,6 +269,8 @@ impl Default for OpInterpretation {
             },
             new_history_node: false,
             set_last_edit: None,
+            coalesce: false,
+            undo: false,
         }
     }
 }
@@ -55,8 +281,25 @@ impl OpInterpretation {
             document_changes: Diff { operations: ops },
             new_history_node: true,
             set_last_edit: None,
+            coalesce: false,
+            undo: false,
+        }
+    }
+
+    // Move to another node of the history tree, changing the document accordingly.
+    pub fn history_move(diff: Diff, node: NodeId) -> Self {
+        OpInterpretation {
+            document_changes: diff,
+            new_history_node: false,
+            set_last_edit: Some(node),
+            coalesce: false,
+            undo: false,
         }
     }
+
+    fn undo(self) -> Self {
+        OpInterpretation { undo: true, ..self }
+    }
 }
 
 impl Default for EditorState {
@@ -70,194 +313,4179 @@ impl EditorState {
         EditorState {
             mode: EditorMode::Command,
             document: Graph::new(),
-            history_tree: Arena::new(),
-            last_edit: None,
-            next_vertex_id: 0,
-            next_edge_id: 0,
+            history: History::new(),
+            vertex_ids: Box::new(Sequential::default()),
+            edge_ids: Box::new(Sequential::default()),
+            allocated: Vec::new(),
+            options: EditorOptions::default(),
+            invariant_check_interval: None,
+            changes_since_invariant_check: 0,
+            schema: None,
+            computed: ComputedAttributes::new(),
+            recent_vertices: Vec::new(),
+            completion_budget: CompletionBudget::default(),
+            completions: Vec::new(),
+            subscribers: Subscribers::default(),
+            folds: Folds::new(),
+            scenarios: Scenarios::new(),
+            plugins: Plugins::new(),
+            hooks: Hooks::new(),
+            running_hooks: false,
+            references: References::new(),
+            views: Views::new(),
+            attribute_history: None,
+            #[cfg(feature = "json")]
+            autosave: Autosave::default(),
+            #[cfg(feature = "json")]
+            recovery: None,
+            #[cfg(feature = "json")]
+            op_log: None,
+            #[cfg(feature = "json")]
+            trace: None,
+            coalescing: None,
+            undo_groups: 0,
+            recording: None,
+            last_change: None,
+            search: None,
+            jumps: Jumplist::new(),
+            created_vertex: None,
+            edge_target: None,
+            drag: None,
+            registers: Registers::new(),
+            keymap: Keymap::new(),
+            pending_keys: Vec::new(),
+            messages: Vec::new(),
+            message_history: MessageHistory::default(),
+            message: None,
+            key_timeout: Some(DEFAULT_KEY_TIMEOUT),
+            waited: Duration::ZERO,
+            macro_recording: None,
+            replaying: Vec::new(),
+            last_macro: None,
         }
     }
 
-    pub fn evaluate(&mut self, input: Input) {
-        let transition_result = self.mode.clone().transition(input);
-        match transition_result {
-            TransitionResult::ModeChange(next_mode) => {
-                self.mode = next_mode;
-            }
-            TransitionResult::Apply(op, next_mode) => {
-                self.mode = next_mode;
-                let interpreted_op = self.interpret_modal_operation(op);
-                let diff = self
-                    .document
-                    .apply_all(interpreted_op.document_changes.operations);
+    // An editor for an existing graph, e.g., one built by an importer, with a fresh history.
+    pub fn from_graph(graph: Graph) -> EditorState {
+        EditorState::from_document_with_history(graph, History::new())
+    }
 
-                if interpreted_op.new_history_node {
-                    let new_node_id = self.history_tree.new_node(diff);
-                    if let Some(node_id) = self.last_edit {
-                        node_id.append(new_node_id, &mut self.history_tree);
-                    }
-                    self.last_edit = Some(new_node_id);
-                }
+    /**
+     * An editor for a document with the history it was edited with, positioned at the edit that
+     * made the document as given. New elements are given ids above those of the document and of
+     * everything in the history, so that undo and redo cannot collide with them.
+     */
+    pub fn from_document_with_history(document: Graph, history: History) -> EditorState {
+        let mut state = EditorState::new();
+        state
+            .vertex_ids
+            .reset(&document.vertices.keys().copied().collect());
+        state
+            .edge_ids
+            .reset(&document.edges.keys().copied().collect());
+        state.document = document;
+        state.history = history;
+        state.observe_history_ids();
+        state
+    }
 
-                if let Some(node_id) = interpreted_op.set_last_edit {
-                    self.last_edit = Some(node_id);
-                }
-            }
-            TransitionResult::Error(msg, next_mode) => {
-                println!("{}", msg);
-                self.mode = next_mode;
-            }
+    pub fn document(&self) -> &Graph {
+        &self.document
+    }
+
+    pub fn mode(&self) -> &EditorMode {
+        &self.mode
+    }
+
+    pub fn status(&self) -> Status<'_> {
+        Status {
+            mode: &self.mode,
+            pending_text: self.mode.pending_text(),
+            count: self.mode.count(),
+            pending_keys: &self.pending_keys,
+            message: self.message.as_ref(),
         }
     }
 
-    fn interpret_modal_operation(&mut self, op: ModalOperation) -> OpInterpretation {
-        match op {
-            ModalOperation::CreateNewVertex => {
-                let v = Vertex {
-                    id: self.next_vertex_id,
-                };
-                self.next_vertex_id += 1;
-                OpInterpretation::standard_op(vec![GraphOperation::AddVertex(v)])
-            }
-            ModalOperation::CreateNewEdge(chosen_vertices) => {
-                let maybe_edge =
-                    chosen_vertices
-                        .rsplit_once(',')
-                        .map(|(source_id, target_id)| {
-                            let source = self.document.resolve_vertex(source_id).expect(
-                                format!("Could not find source vertex {}", source_id).as_str(),
-                            );
-                            let target = self.document.resolve_vertex(target_id).expect(
-                                format!("Could not find target vertex {}", target_id).as_str(),
-                            );
-                            Edge {
-                                id: self.next_edge_id,
-                                source: source,
-                                target: target,
-                            }
-                        });
+    // What has been typed toward the command in progress, including keys awaiting a mapping.
+    pub fn pending_command(&self) -> Option<PendingCommand> {
+        match self.mode.pending_command() {
+            None if self.pending_keys.is_empty() => None,
+            command => Some(PendingCommand {
+                pending_keys: self.pending_keys.clone(),
+                ..command.unwrap_or_default()
+            }),
+        }
+    }
 
-                match maybe_edge {
-                    Some(e) => {
-                        self.next_edge_id += 1;
-                        OpInterpretation::standard_op(vec![GraphOperation::AddEdge(e)])
-                    }
-                    // Replace this with an error message reported to the user.
-                    None => panic!(
-                        "Unable to parse '{}' as a list of two vertex ids.",
-                        chosen_vertices
-                    ),
+    // Take the errors and notices reported since they were last taken, oldest first. They are
+    // also sent to subscribers as they are reported.
+    pub fn take_messages(&mut self) -> Vec<Message> {
+        std::mem::take(&mut self.messages)
+    }
+
+    // The messages reported so far, including those already taken, e.g., for a message panel.
+    pub fn message_history(&self) -> &MessageHistory {
+        &self.message_history
+    }
+
+    pub fn clear_message_history(&mut self) {
+        self.message_history.clear();
+    }
+
+    // Keep at most the number of messages in the history, or none if 0.
+    pub fn set_message_history_limit(&mut self, limit: usize) {
+        self.message_history.set_limit(limit);
+    }
+
+    fn report(&mut self, severity: Severity, text: String) {
+        let message = Message::new(severity, text);
+        self.message_history.push(message.clone());
+        self.show(message);
+    }
+
+    // Give the caller a message, without recording it in the history, e.g., one `:messages` lists.
+    fn show(&mut self, message: Message) {
+        if self.messages.len() == MAX_MESSAGES {
+            self.messages.remove(0);
+        }
+        self.messages.push(message.clone());
+        self.message = Some(message.clone());
+        self.subscribers.emit(EditorEvent::Message(message));
+    }
+
+    fn info(&mut self, text: String) {
+        self.report(Severity::Info, text);
+    }
+
+    fn warn(&mut self, text: String) {
+        self.report(Severity::Warning, text);
+    }
+
+    fn error(&mut self, text: String) {
+        self.report(Severity::Error, text);
+    }
+
+    // The ids the next new vertex and edge will be given.
+    pub fn next_ids(&self) -> (i64, i64) {
+        (self.vertex_ids.peek(), self.edge_ids.peek())
+    }
+
+    pub fn folds(&self) -> &Folds {
+        &self.folds
+    }
+
+    pub fn scenarios(&self) -> &Scenarios {
+        &self.scenarios
+    }
+
+    pub fn references(&self) -> &References {
+        &self.references
+    }
+
+    // Name an element of the document, returning false if there is no such element.
+    pub fn add_reference(&mut self, reference: Reference, element: ElementRef) -> bool {
+        let exists = match element {
+            ElementRef::Vertex(id) => self.document.vertices.contains_key(&id),
+            ElementRef::Edge(id) => self.document.edges.contains_key(&id),
+        };
+        if exists {
+            self.references.add(reference, element);
+        }
+        exists
+    }
+
+    pub fn remove_reference(&mut self, reference: &Reference, element: ElementRef) -> bool {
+        self.references.remove(reference, element)
+    }
+
+    pub fn register_plugin(&mut self, name: &str, capabilities: Capabilities) {
+        self.plugins.register(name, capabilities);
+    }
+
+    // Unregister a plugin, removing the keys it bound and releasing its leader namespace.
+    pub fn unregister_plugin(&mut self, name: &str) -> bool {
+        self.plugins.unbind(&mut self.keymap, name);
+        self.hooks.remove_owned_by(name);
+        self.plugins.unregister(name)
+    }
+
+    // Register a plugin along with its bindings, ex commands and autocommands.
+    pub fn install_plugin(&mut self, plugin: Box<dyn Plugin>) -> Result<(), BindingError> {
+        let name = plugin.name().to_string();
+        let autocommands = plugin.autocommands();
+        self.plugins.install(&mut self.keymap, plugin)?;
+        for (hook, command) in autocommands {
+            self.hooks
+                .add(hook, HookAction::Autocommand(command), Some(name.clone()));
+        }
+        Ok(())
+    }
+
+    /**
+     * Run a callback at a hook from now on, after the callbacks added before it, e.g., to lay out
+     * added vertices or to validate the document before it is saved. Returns an id to remove it
+     * with. Changes a callback makes to the document after an edit are part of that edit, so that
+     * undo undoes them together.
+     */
+    pub fn add_hook(&mut self, hook: Hook, callback: HookCallback) -> HookId {
+        self.hooks.add(
+            hook,
+            HookAction::Callback(Arc::new(Mutex::new(callback))),
+            None,
+        )
+    }
+
+    // Run an ex command at a hook, like `:autocmd`.
+    pub fn add_autocommand(&mut self, hook: Hook, command: &str) -> HookId {
+        self.hooks
+            .add(hook, HookAction::Autocommand(command.to_string()), None)
+    }
+
+    pub fn remove_hook(&mut self, id: HookId) -> bool {
+        self.hooks.remove(id)
+    }
+
+    // Run what is added at the event's hook, in order, until something fails.
+    fn run_hooks(&mut self, event: &HookEvent) -> Result<(), String> {
+        if self.running_hooks {
+            return Ok(());
+        }
+        let actions = self.hooks.actions(event.hook());
+        if actions.is_empty() {
+            return Ok(());
+        }
+        self.running_hooks = true;
+        let mut result = Ok(());
+        for action in actions {
+            result = match action {
+                HookAction::Callback(callback) => {
+                    let mut callback = callback
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner());
+                    callback(self, event)
                 }
+                HookAction::Autocommand(command) => self
+                    .execute(Command::Ex(event.expand(&command)))
+                    .map_err(|err| err.to_string()),
+            };
+            if result.is_err() {
+                break;
             }
-            ModalOperation::Undo => match self.last_edit {
-                None => OpInterpretation::default(),
-                Some(last_edit_id) => {
-                    let last_edit = (*(self.history_tree.get(last_edit_id).unwrap())).clone();
-                    let diff = Diff {
-                        operations: last_edit
-                            .get()
-                            .operations
-                            .iter()
-                            .map(|op| op.invert())
-                            .collect(),
-                    };
-                    OpInterpretation {
-                        document_changes: diff,
-                        new_history_node: false,
-                        set_last_edit: last_edit.parent(),
-                    }
-                }
-            },
-            ModalOperation::Redo => match self.last_edit {
-                None => OpInterpretation::default(),
-                Some(last_edit_id) => match (*self.history_tree.get(last_edit_id).unwrap())
-                    .last_child()
-                {
-                    None => OpInterpretation::default(),
-                    Some(next_state_id) => OpInterpretation {
-                        document_changes: (*(*self.history_tree.get(next_state_id).unwrap()).get())
-                            .clone(),
-                        new_history_node: false,
-                        set_last_edit: Some(next_state_id),
-                    },
-                },
-            },
         }
+        self.running_hooks = false;
+        result
     }
-}
 
-#[derive(Debug, Eq, PartialEq)]
-pub enum Input {
-    Key(char),
-}
+    fn run_hooks_or_report(&mut self, event: &HookEvent) {
+        if let Err(err) = self.run_hooks(event) {
+            self.error(format!("{} hook failed: {}", event.hook(), err));
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::editor::keys::*;
-    use crate::graph::Edge;
-    use crate::graph::Graph;
-    use crate::graph::Vertex;
+    // Run the hooks of the elements an edit added and removed, folding any changes they make
+    // into the edit, if it made a new history node.
+    fn run_element_hooks(&mut self, events: Vec<HookEvent>, new_history_node: bool) {
+        if events.is_empty() {
+            return;
+        }
+        let coalescing = self.coalescing;
+        if new_history_node {
+            self.coalescing = Some(self.history.current());
+            self.undo_groups += 1;
+        }
+        for event in events.iter() {
+            self.run_hooks_or_report(event);
+        }
+        if new_history_node {
+            self.undo_groups -= 1;
+            self.coalescing = coalescing;
+        }
+    }
 
-    fn single_edge_graph() -> Graph {
-        let mut single_edge = Graph::new();
-        let v0 = Vertex { id: 0 };
-        let v1 = Vertex { id: 1 };
-        let e0 = Edge {
-            id: 0,
-            source: v0.id,
-            target: v1.id,
+    // Reserve the keys after `<leader>` for a plugin's bindings.
+    pub fn reserve_leader_namespace(
+        &mut self,
+        plugin: &str,
+        keys: Vec<char>,
+    ) -> Result<(), BindingError> {
+        self.plugins.reserve_namespace(plugin, keys)
+    }
+
+    // Map `<leader>`, the plugin's namespace and the keys to other keys, returning the keys mapped.
+    pub fn bind_leader_keys(
+        &mut self,
+        plugin: &str,
+        mode: KeymapMode,
+        keys: &[char],
+        to: Vec<char>,
+    ) -> Result<Vec<char>, BindingError> {
+        self.plugins.bind(&mut self.keymap, plugin, mode, keys, to)
+    }
+
+    // Carry out a plugin's request, if its capabilities allow all of it.
+    pub fn dispatch(
+        &mut self,
+        plugin: &str,
+        request: PluginRequest,
+    ) -> Result<(), PermissionError> {
+        self.plugins.check(plugin, &self.document, &request)?;
+        let interpretation = match request {
+            PluginRequest::Operations(operations) => OpInterpretation::standard_op(operations),
+            PluginRequest::Undo => self.interpret_or_report(ModalOperation::Undo),
+            PluginRequest::Redo => self.interpret_or_report(ModalOperation::Redo),
         };
-        single_edge.add_vertex(v0);
-        single_edge.add_vertex(v1);
-        single_edge.add_edge(e0);
-        return single_edge.clone();
+        self.apply_or_report(interpretation);
+        Ok(())
     }
 
-    #[test]
-    fn insert_two_vertices_and_edge() {
-        let mut state = EditorState::new();
-        state.evaluate(Input::Key(I_LOWER));
-        state.evaluate(Input::Key(V_LOWER));
-        state.evaluate(Input::Key(V_LOWER));
-        state.evaluate(Input::Key(E_LOWER));
-        state.evaluate(Input::Key(DIGIT_0));
-        state.evaluate(Input::Key(COMMA));
-        state.evaluate(Input::Key(DIGIT_1));
-        state.evaluate(Input::Key(ENTER));
+    // Hand an operation to the plugin that added it, and carry out what the plugin requests.
+    fn interpret_plugin_operation(
+        &mut self,
+        operation: PluginOperation,
+    ) -> Result<OpInterpretation, EditorError> {
+        let cursor = self.cursor();
+        let selection: Vec<ElementRef> = self.references.elements(&Reference::Selection).collect();
+        let context = PluginContext {
+            document: &self.document,
+            cursor,
+            selection: &selection,
+        };
+        let request = self
+            .plugins
+            .installed_mut(&operation.plugin)
+            .ok_or_else(|| EditorError::Plugin(format!("Unknown plugin {}", operation.plugin)))?
+            .operate(&operation, context)
+            .map_err(EditorError::Plugin)?;
+        self.plugins
+            .check(&operation.plugin, &self.document, &request)
+            .map_err(|err| EditorError::Plugin(err.to_string()))?;
+        match request {
+            PluginRequest::Operations(operations) => Ok(OpInterpretation::standard_op(operations)),
+            PluginRequest::Undo => self.interpret_modal_operation(ModalOperation::Undo),
+            PluginRequest::Redo => self.interpret_modal_operation(ModalOperation::Redo),
+        }
+    }
 
-        assert_eq!(EditorMode::Insert, state.mode);
+    /**
+     * Carry out a command as the keys for it would, without typing them. The mode is left as it
+     * is, and an edit is a history node of its own, even in Insert mode. An edit that the document
+     * rejects, e.g., in strict mode or by its schema, is returned as an error.
+     */
+    pub fn execute(&mut self, command: Command) -> Result<(), EditorError> {
+        if self.undo_groups == 0 {
+            self.coalescing = None;
+        }
+        let result = self.apply_modal_operation(command.into());
+        if self.undo_groups == 0 {
+            self.coalescing = None;
+        }
+        self.update_completions();
+        result
+    }
 
-        let expected = single_edge_graph();
-        assert_eq!(expected, state.document);
+    // Start or stop recording the previous values of attributes as they change.
+    pub fn set_attribute_history(&mut self, enabled: bool) {
+        match (enabled, &self.attribute_history) {
+            (true, None) => self.attribute_history = Some(AttributeHistory::new()),
+            (false, _) => self.attribute_history = None,
+            _ => {}
+        }
     }
 
-    #[test]
-    fn undo_redo() {
-        let mut state = EditorState::new();
-        state.evaluate(Input::Key(I_LOWER));
-        state.evaluate(Input::Key(V_LOWER));
-        state.evaluate(Input::Key(V_LOWER));
-        state.evaluate(Input::Key(E_LOWER));
-        state.evaluate(Input::Key(DIGIT_0));
-        state.evaluate(Input::Key(COMMA));
-        state.evaluate(Input::Key(DIGIT_1));
-        state.evaluate(Input::Key(ENTER));
-        state.evaluate(Input::Key(ESC));
+    pub fn attribute_history(&self) -> Option<&AttributeHistory> {
+        self.attribute_history.as_ref()
+    }
 
-        let single_edge = single_edge_graph();
-        assert_eq!(EditorMode::Command, state.mode);
-        assert_eq!(single_edge, state.document);
+    pub fn set_weight_step(&mut self, step: f64) {
+        self.options.weight_step = step;
+    }
 
-        let mut undid = single_edge_graph();
-        undid.remove_edge(*undid.edges.values().next().unwrap());
-        state.evaluate(Input::Key(U_LOWER));
+    pub fn options(&self) -> &EditorOptions {
+        &self.options
+    }
 
-        assert_eq!(EditorMode::Command, state.mode);
-        assert_eq!(undid, state.document);
+    pub fn set_options(&mut self, options: EditorOptions) {
+        self.options = options;
+    }
 
-        state.evaluate(Input::Key(U_UPPER));
+    pub fn history(&self) -> &History {
+        &self.history
+    }
 
-        assert_eq!(EditorMode::Command, state.mode);
-        assert_eq!(single_edge, state.document);
+    // Cap the size of the history, collapsing its oldest edits as needed.
+    pub fn set_history_limit(&mut self, limit: HistoryLimit) {
+        self.history.set_limit(limit);
+        self.emit_history_events();
+    }
+
+    // Move the document to the state at any node of the history, e.g., one clicked in a history
+    // panel, as a single change. Returns false if there is no such node.
+    pub fn goto_history_node(&mut self, node: NodeId) -> bool {
+        if self.history.entry(node).is_none() {
+            return false;
+        }
+        let interpretation = self.history_jump(node);
+        self.apply_or_report(interpretation);
+        true
+    }
+
+    // The document as it was at a node of the history.
+    pub fn document_at(&self, node: NodeId) -> Option<Graph> {
+        let mut graph = self.document.clone();
+        graph.apply_all(
+            self.history
+                .path_diff(self.history.current(), node)?
+                .operations,
+        );
+        Some(graph)
+    }
+
+    /**
+     * The net changes between the documents at two nodes of the history, e.g., everything done
+     * since the document was saved. Unlike the edits along the path between the nodes, changes
+     * that cancel out are left out, and each element changes at most once.
+     */
+    pub fn history_diff(&self, from: NodeId, to: NodeId) -> Option<Diff> {
+        Some(self.document_at(from)?.difference(&self.document_at(to)?))
+    }
+
+    /**
+     * Merge the documents at two nodes of the history, e.g., the tips of two branches explored
+     * from the same edit, into a new edit after the current one. Each side's changes since the
+     * nodes' common ancestor are combined, keeping the first node's side of any conflict. Returns
+     * the conflicts, or None if either node does not exist.
+     */
+    pub fn merge_history(&mut self, ours: NodeId, theirs: NodeId) -> Option<Vec<MergeConflict>> {
+        let (interpretation, conflicts) = self.history_merge(ours, theirs)?;
+        self.apply_or_report(interpretation);
+        Some(conflicts)
+    }
+
+    fn history_merge(
+        &self,
+        ours: NodeId,
+        theirs: NodeId,
+    ) -> Option<(OpInterpretation, Vec<MergeConflict>)> {
+        let base = self.document_at(self.history.common_ancestor(ours, theirs)?)?;
+        let result = three_way_merge(&base, &self.document_at(ours)?, &self.document_at(theirs)?);
+        let diff = self.document.difference(&result.merged);
+        Some((
+            OpInterpretation::standard_op(diff.operations),
+            result.conflicts,
+        ))
+    }
+
+    // The edits that can be redone from the current one, oldest first. Redo follows the last.
+    pub fn history_children(&self) -> Vec<NodeId> {
+        self.history.children(self.history.current())
+    }
+
+    /**
+     * Make the edits from now until the matching `end_undo_group` a single history node, undone
+     * and redone at once, e.g., for a script or plugin that makes several edits. Groups nest;
+     * only ending the outermost one ends the history node.
+     */
+    pub fn begin_undo_group(&mut self) {
+        if self.undo_groups == 0 && !self.mode.coalesces_edits() {
+            self.coalescing = None;
+        }
+        self.undo_groups += 1;
+    }
+
+    pub fn end_undo_group(&mut self) {
+        self.undo_groups = self.undo_groups.saturating_sub(1);
+        if self.undo_groups == 0 && !self.mode.coalesces_edits() {
+            self.coalescing = None;
+        }
+    }
+
+    /**
+     * Whether new elements may be given the ids of elements that appear nowhere anymore, neither
+     * in the document nor in its history, e.g., after the branches they were made on are deleted.
+     * Off by default, so that ids are never reused. Returns whether the allocators can recycle ids.
+     */
+    pub fn set_id_recycling(&mut self, recycling: bool) -> bool {
+        let recycles = self.vertex_ids.set_recycling(recycling);
+        let recycles = self.edge_ids.set_recycling(recycling) && recycles;
+        self.recycle_ids();
+        recycles
+    }
+
+    /**
+     * Give new vertices and edges ids from the allocators, instead of numbering them from 0, e.g.,
+     * to match the keys of an external database. The allocators are told of every id in the
+     * document and its history, so that they never hand one out again.
+     */
+    pub fn set_id_allocators(
+        &mut self,
+        mut vertex_ids: Box<dyn IdAllocator>,
+        mut edge_ids: Box<dyn IdAllocator>,
+    ) {
+        let (vertices, edges) = self.history.element_ids();
+        for id in vertices.iter().chain(self.document.vertices.keys()) {
+            vertex_ids.observe(*id);
+        }
+        for id in edges.iter().chain(self.document.edges.keys()) {
+            edge_ids.observe(*id);
+        }
+        self.vertex_ids = vertex_ids;
+        self.edge_ids = edge_ids;
+    }
+
+    /**
+     * Renumber the document's vertices and edges from 0 without gaps, in order of id, as a single
+     * edit. References to elements follow them to their new ids, but undoing the edit does not
+     * move them back. Returns the new id of each element, by its old id, or no ids if the edit is
+     * rejected, e.g., because the document is read-only.
+     */
+    pub fn compact_ids(&mut self) -> IdMapping {
+        self.try_compact_ids().unwrap_or_else(|err| {
+            self.error(err.to_string());
+            IdMapping::default()
+        })
+    }
+
+    fn try_compact_ids(&mut self) -> Result<IdMapping, EditorError> {
+        let (compacted, mapping) = compact(&self.document);
+        let diff = self.document.difference(&compacted);
+        let references = self.references.clone();
+        // Renumbered first, so that removing an element's old id does not set its references aside.
+        self.references.renumber(&mapping.vertices, &mapping.edges);
+        if let Err(err) = self.apply_interpretation(OpInterpretation::standard_op(diff.operations))
+        {
+            self.references = references;
+            return Err(err);
+        }
+        Ok(mapping)
+    }
+
+    // Record the inputs evaluated from now on, e.g., to reproduce a bug with `InputLog::replay`.
+    pub fn start_recording(&mut self) {
+        self.recording = Some(InputLog::new());
+    }
+
+    // Stop recording, returning the inputs recorded, if recording had started.
+    pub fn stop_recording(&mut self) -> Option<InputLog> {
+        self.recording.take()
+    }
+
+    // The vertices the last search matches in the document, in order, e.g., to highlight them.
+    pub fn search_matches(&self) -> Vec<i64> {
+        match &self.search {
+            Some(query) => matching_vertices(&self.document, query),
+            None => Vec::new(),
+        }
+    }
+
+    fn move_to_match(&mut self, from: Option<i64>, forward: bool) {
+        let query = match &self.search {
+            Some(query) => query,
+            None => return self.error("No previous search".to_string()),
+        };
+        match next_match(&self.search_matches(), from, forward) {
+            Some(id) => {
+                self.jump_to(id);
+            }
+            None => self.error(format!("Pattern not found: {}", query)),
+        }
+    }
+
+    // The vertex under the cursor, if any. Like other references, the cursor is set aside while
+    // its vertex is removed.
+    pub fn cursor(&self) -> Option<i64> {
+        match self.references.element(&Reference::Cursor) {
+            Some(ElementRef::Vertex(id)) => Some(id),
+            _ => None,
+        }
+    }
+
+    // Put the cursor on a vertex, returning whether the vertex exists.
+    pub fn set_cursor(&mut self, id: i64) -> bool {
+        self.add_reference(Reference::Cursor, ElementRef::Vertex(id))
+    }
+
+    pub fn views(&self) -> &Views {
+        &self.views
+    }
+
+    // Open a view with the cursor, selection and viewport of the active view, and make it active,
+    // like vim's `:split`.
+    pub fn split_view(&mut self) -> ViewId {
+        let id = self.views.open();
+        let cursor: Vec<ElementRef> = self.references.elements(&Reference::Cursor).collect();
+        let selection: Vec<ElementRef> = self.references.elements(&Reference::Selection).collect();
+        for element in cursor {
+            self.references.add(Reference::ViewCursor(id), element);
+        }
+        for element in selection {
+            self.references.add(Reference::ViewSelection(id), element);
+        }
+        self.focus_view(id);
+        id
+    }
+
+    // Make a view active, returning whether it is open. Like changing windows in vim, this leaves
+    // Visual mode.
+    pub fn focus_view(&mut self, id: ViewId) -> bool {
+        let active = self.views.active();
+        if !self.views.activate(id) {
+            return false;
+        }
+        if id != active {
+            self.set_aside_view(active);
+            self.restore_view(id);
+            self.leave_visual_mode();
+        }
+        true
+    }
+
+    // Close a view, unless it is the only one. Closing the active view activates another.
+    pub fn close_view(&mut self, id: ViewId) -> bool {
+        let was_active = id == self.views.active();
+        if !self.views.close(id) {
+            return false;
+        }
+        if was_active {
+            self.references.forget(&Reference::Cursor);
+            self.references.forget(&Reference::Selection);
+            self.restore_view(self.views.active());
+            self.leave_visual_mode();
+        } else {
+            self.references.forget(&Reference::ViewCursor(id));
+            self.references.forget(&Reference::ViewSelection(id));
+        }
+        true
+    }
+
+    fn leave_visual_mode(&mut self) {
+        if let EditorMode::Visual(_) = self.mode {
+            let before = std::mem::replace(&mut self.mode, EditorMode::Command);
+            self.emit_mode_change(before);
+        }
+    }
+
+    fn set_aside_view(&mut self, id: ViewId) {
+        self.references
+            .rename(&Reference::Cursor, Reference::ViewCursor(id));
+        self.references
+            .rename(&Reference::Selection, Reference::ViewSelection(id));
+    }
+
+    fn restore_view(&mut self, id: ViewId) {
+        self.references
+            .rename(&Reference::ViewCursor(id), Reference::Cursor);
+        self.references
+            .rename(&Reference::ViewSelection(id), Reference::Selection);
+    }
+
+    // The vertex under the cursor of a view, which need not be the active one.
+    pub fn view_cursor(&self, id: ViewId) -> Option<i64> {
+        if id == self.views.active() {
+            return self.cursor();
+        }
+        match self.references.element(&Reference::ViewCursor(id)) {
+            Some(ElementRef::Vertex(id)) => Some(id),
+            _ => None,
+        }
+    }
+
+    // The elements selected in a view, in order.
+    pub fn view_selection(&self, id: ViewId) -> Vec<ElementRef> {
+        let reference = if id == self.views.active() {
+            Reference::Selection
+        } else {
+            Reference::ViewSelection(id)
+        };
+        self.references.elements(&reference).collect()
+    }
+
+    pub fn viewport(&self, id: ViewId) -> Option<Viewport> {
+        self.views.viewport(id)
+    }
+
+    // Set the part of the layout a view shows, returning whether the view is open.
+    pub fn set_viewport(&mut self, id: ViewId, viewport: Viewport) -> bool {
+        self.views.set_viewport(id, viewport)
+    }
+
+    // Put the cursor on a vertex, remembering where it jumped from in the jumplist.
+    fn jump_to(&mut self, id: i64) -> bool {
+        if !self.document.vertices.contains_key(&id) {
+            return false;
+        }
+        if let Some(from) = self.cursor().filter(|from| *from != id) {
+            self.jumps.record(from);
+        }
+        self.set_cursor(id)
+    }
+
+    // Retrace the jumplist, skipping the vertices that have since been removed.
+    fn retrace_jump(&mut self, back: bool) {
+        loop {
+            let jump = if back {
+                self.jumps.back(self.cursor())
+            } else {
+                self.jumps.forward()
+            };
+            match jump {
+                Some(id) if self.set_cursor(id) => return,
+                Some(_) => {}
+                None if back => return self.error("At the oldest jump".to_string()),
+                None => return self.error("At the newest jump".to_string()),
+            }
+        }
+    }
+
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    // Add the mappings of a keymap file to the keymap.
+    pub fn load_keymap(&mut self, path: &str) -> Result<(), ParseError> {
+        let text =
+            std::fs::read_to_string(path).map_err(|err| ParseError::new(1, &err.to_string()))?;
+        self.keymap.extend_from_toml(&text)
+    }
+
+    // The bindings in effect in a mode, including the keymap's, for help and cheat sheets.
+    pub fn bindings(&self, mode: KeymapMode) -> Vec<Binding> {
+        bindings(mode, &self.keymap)
+    }
+
+    pub fn registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    // The macro recorded in a register, if any.
+    pub fn recorded_macro(&self, register: char) -> Option<&InputLog> {
+        self.registers.recorded_macro(register).ok()
+    }
+
+    pub fn set_macro(&mut self, register: char, inputs: InputLog) {
+        self.registers.set(register, Register::Macro(inputs));
+    }
+
+    fn replay_macro(&mut self, register: char) {
+        let register = match (register, self.last_macro) {
+            (AT, Some(last)) => last,
+            (AT, None) => return self.error("No macro has been replayed".to_string()),
+            _ => register,
+        };
+        if self.replaying.contains(&register) {
+            return self.error(format!("Macro {} cannot replay itself", register));
+        }
+        let inputs = match self.registers.recorded_macro(register) {
+            Ok(inputs) => inputs.clone(),
+            Err(err) => return self.error(err),
+        };
+        self.last_macro = Some(register);
+        self.replaying.push(register);
+        inputs.replay_onto(self);
+        self.replaying.pop();
+    }
+
+    // Receive the events emitted from now on.
+    pub fn subscribe(&mut self) -> Receiver<EditorEvent> {
+        self.subscribers.subscribe()
+    }
+
+    // Candidates for the vertex reference currently being typed, best first. Tab accepts the first.
+    pub fn completions(&self) -> &[Completion] {
+        &self.completions
+    }
+
+    pub fn set_completion_budget(&mut self, budget: CompletionBudget) {
+        self.completion_budget = budget;
+    }
+
+    fn update_completions(&mut self) {
+        let completions = match self.mode.pending_vertex_reference() {
+            Some(prefix) => completion::complete(
+                &self.document,
+                &self.recent_vertices,
+                prefix,
+                self.completion_budget,
+            ),
+            None => Vec::new(),
+        };
+        if completions != self.completions {
+            self.completions = completions.clone();
+            self.subscribers.emit(EditorEvent::Completions(completions));
+        }
+    }
+
+    fn touch_vertices(&mut self, diff: &Diff) {
+        for op in diff.operations.iter() {
+            let touched = match op {
+                GraphOperation::AddVertex(v) => vec![v.id],
+                GraphOperation::AddEdge(e) => vec![e.source, e.target],
+                _ => Vec::new(),
+            };
+            for id in touched {
+                self.recent_vertices.retain(|r| *r != id);
+                self.recent_vertices.push(id);
+            }
+        }
+        if self.recent_vertices.len() > MAX_RECENT_VERTICES {
+            let excess = self.recent_vertices.len() - MAX_RECENT_VERTICES;
+            self.recent_vertices.drain(..excess);
+        }
+    }
+
+    pub fn declare_computed_attribute(&mut self, name: &str, attribute: ComputedAttribute) {
+        self.computed.declare(name, attribute);
+    }
+
+    pub fn remove_computed_attribute(&mut self, name: &str) -> Option<ComputedAttribute> {
+        self.computed.remove(name)
+    }
+
+    // The stored and computed attributes of a vertex.
+    pub fn vertex_attributes(&self, vertex_id: i64) -> Option<Attributes> {
+        self.document.vertices.get(&vertex_id).map(|v| {
+            let mut attributes = v.attributes.clone();
+            attributes.extend(self.computed.evaluate(&self.document, vertex_id));
+            attributes
+        })
+    }
+
+    pub fn schema(&self) -> Option<&Schema> {
+        self.schema.as_ref()
+    }
+
+    pub fn set_schema(&mut self, schema: Option<Schema>) {
+        self.schema = schema;
+    }
+
+    pub fn set_strict(&mut self, strict: bool) {
+        self.options.strict = strict;
+    }
+
+    pub fn set_invariant_check_interval(&mut self, interval: Option<usize>) {
+        self.invariant_check_interval = interval;
+        self.changes_since_invariant_check = 0;
+    }
+
+    fn maybe_check_invariants(&mut self) {
+        if let Some(interval) = self.invariant_check_interval {
+            self.changes_since_invariant_check += 1;
+            if self.changes_since_invariant_check >= interval {
+                self.changes_since_invariant_check = 0;
+                let report = self.document.check_invariants();
+                if !report.is_ok() {
+                    panic!("{}", report);
+                }
+            }
+        }
+    }
+
+    /**
+     * Evaluate the keys of a script written in the notation of mappings, e.g.,
+     * `ivve0,1<CR><Esc>u`, as if they were typed. Nothing is evaluated if the script names a key
+     * that does not exist. Keys at the end that may begin a mapping wait for the rest of it, as
+     * typed keys do.
+     */
+    pub fn evaluate_script(&mut self, script: &str) -> Result<(), String> {
+        for key in self.keymap.parse_keys(script)? {
+            self.evaluate(Input::Key(key));
+        }
+        Ok(())
+    }
+
+    pub fn evaluate(&mut self, input: Input) {
+        // Keys are recorded, mapped and replayed as the editor encodes them.
+        let input = match input {
+            Input::Press(event) => match event.key() {
+                Some(key) => Input::Key(key),
+                None => return self.error(format!("Unsupported key: {:?}", event)),
+            },
+            input => input,
+        };
+        // The inputs a macro replays are not recorded again, since replaying is recorded.
+        if self.replaying.is_empty() {
+            if let Some(recording) = &mut self.recording {
+                recording.push(input.clone());
+            }
+            #[cfg(feature = "json")]
+            self.trace_input(&input);
+            if let Some((_, recording)) = &mut self.macro_recording {
+                recording.push(input.clone());
+            }
+        }
+        match input {
+            Input::Key(key) => {
+                self.message = None;
+                self.waited = Duration::ZERO;
+                self.pending_keys.push(key);
+                self.resolve_pending_keys(false);
+            }
+            Input::Tick(elapsed) if !self.pending_keys.is_empty() => {
+                self.waited += elapsed;
+                if self
+                    .key_timeout
+                    .is_some_and(|timeout| self.waited >= timeout)
+                {
+                    self.waited = Duration::ZERO;
+                    self.resolve_pending_keys(true);
+                }
+            }
+            Input::Mouse(_) | Input::Paste(_) => {
+                self.message = None;
+                self.transition(input);
+            }
+            Input::Tick(_) | Input::Press(_) => {}
+        }
+    }
+
+    // Evaluate the typed keys whose meaning is decided, keeping those that may begin a mapping
+    // unless the time to type the rest of it is up.
+    fn resolve_pending_keys(&mut self, timed_out: bool) {
+        while !self.pending_keys.is_empty() {
+            let resolution = match self.mode.keymap_mode() {
+                Some(mode) if timed_out => self.keymap.resolve_now(mode, &self.pending_keys),
+                Some(mode) => self.keymap.resolve(mode, &self.pending_keys),
+                None => Resolution::Unmapped,
+            };
+            match resolution {
+                Resolution::Pending => return,
+                Resolution::Mapped(len, keys) => {
+                    self.pending_keys.drain(..len);
+                    for key in keys {
+                        self.transition(Input::Key(key));
+                    }
+                }
+                Resolution::Unmapped => {
+                    let key = self.pending_keys.remove(0);
+                    self.transition(Input::Key(key));
+                }
+            }
+        }
+    }
+
+    // The keys typed so far that begin a mapping, and so are not evaluated yet.
+    pub fn pending_keys(&self) -> &[char] {
+        &self.pending_keys
+    }
+
+    pub fn key_timeout(&self) -> Option<Duration> {
+        self.key_timeout
+    }
+
+    // Set how long keys that begin a mapping wait for the rest of it, as measured by the
+    // `Input::Tick`s evaluated since the last key, or `None` to wait until it is decided.
+    pub fn set_key_timeout(&mut self, timeout: Option<Duration>) {
+        self.key_timeout = timeout;
+    }
+
+    // Evaluate an input without recording or mapping it.
+    fn transition(&mut self, input: Input) {
+        let before = self.mode.clone();
+        let transition_result = self.mode.clone().transition(input);
+        match transition_result {
+            TransitionResult::ModeChange(next_mode) => {
+                self.mode = next_mode;
+            }
+            TransitionResult::Apply(op, next_mode) => {
+                let typed_in = std::mem::replace(&mut self.mode, next_mode);
+                if let Err(err) = self.apply_modal_operation(op) {
+                    self.error(err.to_string());
+                    // Like vim's command line, what was typed is kept to be corrected.
+                    self.mode = typed_in;
+                }
+            }
+            TransitionResult::Error(msg, next_mode) => {
+                self.error(msg);
+                self.mode = next_mode;
+            }
+        }
+        if self.undo_groups == 0 && !self.mode.coalesces_edits() {
+            self.coalescing = None;
+        }
+        self.emit_mode_change(before);
+        self.update_completions();
+    }
+
+    fn emit_mode_change(&mut self, before: EditorMode) {
+        if self.mode != before {
+            self.subscribers.emit(EditorEvent::Mode(self.mode.clone()));
+            let to = self.mode.clone();
+            self.run_hooks_or_report(&HookEvent::ModeChanged { from: before, to });
+        }
+    }
+
+    fn apply_modal_operation(&mut self, op: ModalOperation) -> Result<(), EditorError> {
+        match op {
+            ModalOperation::Repeat(count, op)
+                if !matches!(
+                    *op,
+                    ModalOperation::Undo
+                        | ModalOperation::Redo
+                        | ModalOperation::CreateNewVertex { .. }
+                ) =>
+            {
+                for _ in 0..count {
+                    self.apply_modal_operation((*op).clone())?;
+                }
+            }
+            ModalOperation::ReplayMacro(register) => self.replay_macro(register),
+            ModalOperation::RepeatLastChange => match self.last_change.clone() {
+                Some(op) => self.apply_modal_operation(op)?,
+                None => self.error("No change to repeat".to_string()),
+            },
+            op if self.options.readonly && op.modifies_document() => {
+                return Err(EditorError::ReadOnly);
+            }
+            op => {
+                let change = if op.is_change() {
+                    Some(op.clone())
+                } else {
+                    None
+                };
+                let mut interpreted_op = self.interpret_modal_operation(op)?;
+                if !interpreted_op.document_changes.operations.is_empty() && change.is_some() {
+                    self.last_change = change;
+                }
+                interpreted_op.coalesce = self.mode.coalesces_edits() && self.options.undo_grouping;
+                self.apply_interpretation(interpreted_op)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Interpret an operation, reporting why it cannot be carried out if it cannot.
+    fn interpret_or_report(&mut self, op: ModalOperation) -> OpInterpretation {
+        self.interpret_modal_operation(op).unwrap_or_else(|err| {
+            self.error(err.to_string());
+            OpInterpretation::default()
+        })
+    }
+
+    // Apply an interpretation, or report why it could not be applied.
+    fn apply_or_report(&mut self, interpreted_op: OpInterpretation) {
+        if let Err(err) = self.apply_interpretation(interpreted_op) {
+            self.error(err.to_string());
+        }
+    }
+
+    fn apply_interpretation(
+        &mut self,
+        interpreted_op: OpInterpretation,
+    ) -> Result<(), EditorError> {
+        let from = self.history.current();
+        let allocated = std::mem::take(&mut self.allocated);
+        // Operations that do not look like changes may still make some, e.g., ex commands.
+        let modifies = !interpreted_op.document_changes.operations.is_empty()
+            || interpreted_op.set_last_edit.is_some();
+        if self.options.readonly && modifies {
+            self.release_ids(allocated);
+            return Err(EditorError::ReadOnly);
+        }
+        let changes = interpreted_op.document_changes.operations;
+        if let (true, Some(schema)) = (interpreted_op.new_history_node, &self.schema) {
+            if let Some(err) = changes.iter().find_map(|op| schema.validate(op).err()) {
+                self.release_ids(allocated);
+                return Err(EditorError::Schema(err));
+            }
+        }
+        let diff = if self.options.strict {
+            match self.document.try_apply_all(changes) {
+                Ok(diff) => diff,
+                Err(err) => {
+                    self.release_ids(allocated);
+                    return Err(EditorError::Graph(err));
+                }
+            }
+        } else {
+            self.document.apply_all(changes)
+        };
+        let hook_events = if self.hooks.is_empty() || self.running_hooks {
+            Vec::new()
+        } else {
+            element_events(&diff)
+        };
+        if !diff.operations.is_empty() {
+            // Elements may be added with ids that were not allocated, e.g., by switching to a
+            // scenario, and those ids must not be allocated again.
+            for op in diff.operations.iter() {
+                match op {
+                    GraphOperation::AddVertex(v) => self.vertex_ids.observe(v.id),
+                    GraphOperation::AddEdge(e) => self.edge_ids.observe(e.id),
+                    _ => {}
+                }
+            }
+            self.maybe_check_invariants();
+            self.folds.update(&self.document, &diff);
+            self.references.apply(&diff);
+            #[cfg(feature = "json")]
+            if let Err(err) = self.autosave.record(&diff) {
+                self.error(format!("Could not autosave: {}", err));
+            }
+            #[cfg(feature = "json")]
+            self.log_operations(&diff);
+            if let Some(attribute_history) = &mut self.attribute_history {
+                attribute_history.record(&diff);
+            }
+            self.subscribers.emit(EditorEvent::Document(diff.clone()));
+        }
+
+        if interpreted_op.new_history_node {
+            self.touch_vertices(&diff);
+            let coalesce = interpreted_op.coalesce || self.undo_groups > 0;
+            if coalesce && self.coalescing == Some(self.history.current()) {
+                self.history.amend(diff);
+            } else {
+                self.history.record(diff);
+            }
+            self.coalescing = if coalesce {
+                Some(self.history.current())
+            } else {
+                None
+            };
+        }
+
+        if let Some(node_id) = interpreted_op.set_last_edit {
+            self.history.move_to(node_id);
+        }
+        self.emit_history_events();
+
+        self.run_element_hooks(hook_events, interpreted_op.new_history_node);
+        if interpreted_op.undo && self.history.current() != from {
+            self.run_hooks_or_report(&HookEvent::PostUndo);
+        }
+        Ok(())
+    }
+
+    fn emit_history_events(&mut self) {
+        let mut shrunk = false;
+        for event in self.history.take_events() {
+            shrunk |= matches!(
+                event,
+                HistoryEvent::NodesRemoved(_) | HistoryEvent::NodeMerged { .. }
+            );
+            self.subscribers.emit(EditorEvent::History(event));
+        }
+        // Deleting or collapsing edits may leave ids that appear nowhere.
+        if shrunk {
+            self.recycle_ids();
+        }
+    }
+
+    fn recycle_ids(&mut self) {
+        if !self.vertex_ids.is_recycling() {
+            return;
+        }
+        let (mut vertices, mut edges) = self.history.element_ids();
+        vertices.extend(self.document.vertices.keys().copied());
+        edges.extend(self.document.edges.keys().copied());
+        self.vertex_ids.recycle(&vertices);
+        self.edge_ids.recycle(&edges);
+        // An element whose id is recycled can never be added back by undo or redo.
+        self.references.forget_removed(|element| match element {
+            ElementRef::Vertex(id) => vertices.contains(&id),
+            ElementRef::Edge(id) => edges.contains(&id),
+        });
+    }
+
+    fn interpret_modal_operation(
+        &mut self,
+        op: ModalOperation,
+    ) -> Result<OpInterpretation, EditorError> {
+        Ok(match op {
+            ModalOperation::CreateNewVertex { label } => {
+                let mut vertices = vec![self.new_vertex(label.as_deref())];
+                self.place_new_vertices(&mut vertices);
+                self.created_vertex = Some(vertices[0].id);
+                OpInterpretation::standard_op(
+                    vertices
+                        .into_iter()
+                        .map(GraphOperation::AddVertex)
+                        .collect(),
+                )
+            }
+            ModalOperation::CreateNewEdge(chosen_vertices) => {
+                let (source, target) = self.resolve_endpoints(&chosen_vertices)?;
+                self.edge_target = Some(target);
+                let e = self.new_edge(source, target);
+                OpInterpretation::standard_op(vec![GraphOperation::AddEdge(e)])
+            }
+            ModalOperation::AnchorEdgeSource => {
+                let source = self.cursor().ok_or(EditorError::NoCursor)?;
+                // Like the attribute form, the mode depends on the document.
+                self.mode = EditorMode::EdgeTargetPending(source);
+                OpInterpretation::default()
+            }
+            ModalOperation::CreateEdgeToCursor(source) => {
+                let target = self.cursor().ok_or(EditorError::NoCursor)?;
+                self.interpret_modal_operation(ModalOperation::CreateNewEdge(format!(
+                    "{},{}",
+                    source, target
+                )))?
+            }
+            ModalOperation::AnchorRegion => {
+                let corner = self.cursor().ok_or(EditorError::NoCursor)?;
+                self.mode = EditorMode::RegionPending(corner);
+                OpInterpretation::default()
+            }
+            ModalOperation::SelectRegion(corner) => {
+                let opposite = self.cursor().ok_or(EditorError::NoCursor)?;
+                let elements = vertices_between(&self.document, corner, opposite)
+                    .into_iter()
+                    .map(ElementRef::Vertex)
+                    .collect();
+                self.mode = EditorMode::Visual(Selection {
+                    elements,
+                    ..Selection::default()
+                });
+                OpInterpretation::default()
+            }
+            ModalOperation::Mouse(event) => self.interpret_mouse(event)?,
+            ModalOperation::PasteEdges(text) => self.interpret_edge_list(&text)?,
+            ModalOperation::Plugin(operation) => self.interpret_plugin_operation(operation)?,
+            ModalOperation::MoveVertex(id, position) => {
+                let old = self
+                    .document
+                    .vertices
+                    .get(&id)
+                    .cloned()
+                    .ok_or_else(|| EditorError::UnknownVertex(id.to_string()))?;
+                let new = old.clone().with_attribute("pos", &position.to_string());
+                OpInterpretation::standard_op(vec![GraphOperation::ModifyVertex { old, new }])
+            }
+            ModalOperation::CreateEdgeToLastTarget(from) => {
+                let source = match from {
+                    EdgeSource::Cursor => self.cursor().ok_or(EditorError::NoCursor)?,
+                    EdgeSource::CreatedVertex => self
+                        .created_vertex
+                        .filter(|id| self.document.vertices.contains_key(id))
+                        .ok_or(EditorError::NoCreatedVertex)?,
+                };
+                let target = self.edge_target.ok_or(EditorError::NoEdgeTarget)?;
+                self.interpret_modal_operation(ModalOperation::CreateNewEdge(format!(
+                    "{},{}",
+                    source, target
+                )))?
+            }
+            ModalOperation::ChangeEdge(chosen_edge) => {
+                let edge = self
+                    .document
+                    .resolve_edge(&chosen_edge)
+                    .ok_or(EditorError::UnknownEdge(chosen_edge))?;
+                let e = &self.document.edges[&edge];
+                let endpoints = PendingText::from(format!("{},{}", e.source, e.target).as_str());
+                self.mode = EditorMode::EdgeEndpointsPending { edge, endpoints };
+                OpInterpretation::default()
+            }
+            ModalOperation::RetargetEdge(edge, endpoints) => {
+                let (source, target) = self.resolve_endpoints(&endpoints)?;
+                let old = self
+                    .document
+                    .edges
+                    .get(&edge)
+                    .cloned()
+                    .ok_or_else(|| EditorError::UnknownEdge(edge.to_string()))?;
+                let mut new = old.clone();
+                new.source = source;
+                new.target = target;
+                OpInterpretation::standard_op(vec![GraphOperation::ModifyEdge { old, new }])
+            }
+            ModalOperation::ContractEdge(chosen_edge) => {
+                let id = self
+                    .document
+                    .resolve_edge(&chosen_edge)
+                    .ok_or(EditorError::UnknownEdge(chosen_edge))?;
+                OpInterpretation::standard_op(vec![GraphOperation::ContractEdge(
+                    self.document.edges[&id].clone(),
+                )])
+            }
+            ModalOperation::MergeVertices(chosen_vertices) => {
+                let (keep, remove) =
+                    chosen_vertices
+                        .rsplit_once(',')
+                        .ok_or_else(|| EditorError::Malformed {
+                            typed: chosen_vertices.clone(),
+                            expected: "a vertex to keep and a vertex to merge into it",
+                        })?;
+                let (keep, remove) = (self.resolve_vertex(keep)?, self.resolve_vertex(remove)?);
+                OpInterpretation::standard_op(vec![GraphOperation::MergeVertices {
+                    keep: self.document.vertices[&keep].clone(),
+                    remove: self.document.vertices[&remove].clone(),
+                }])
+            }
+            ModalOperation::AdjustWeight(edge, steps) => self.adjust_weight(&edge, steps)?,
+            ModalOperation::Fold(action, vertex) => {
+                self.interpret_fold(action, &vertex);
+                OpInterpretation::default()
+            }
+            ModalOperation::OpenAttributeForm(element) => {
+                // Entering the form depends on the document, so the editor rather than the mode
+                // decides it.
+                let attributes = self
+                    .element_attributes(&element)
+                    .ok_or_else(|| EditorError::UnknownElement(element.clone()))?;
+                self.mode = EditorMode::AttributeEditing(AttributeForm::new(&element, attributes));
+                OpInterpretation::default()
+            }
+            ModalOperation::SetAttributes(element, attributes) => {
+                self.set_attributes(&element, attributes)?
+            }
+            ModalOperation::Operate(operator, target, register) => {
+                let elements = self.target_elements(target);
+                if elements.is_empty() {
+                    self.error("No elements to operate on".to_string());
+                    return Ok(OpInterpretation::default());
+                }
+                let subgraph = self.subgraph_of(elements.iter().copied());
+                self.registers.yank(register, subgraph);
+                match operator {
+                    Operator::Yank => {
+                        self.references.clear(&Reference::Selection);
+                        for element in elements {
+                            if !self.add_reference(Reference::Selection, element) {
+                                self.error(format!("Could not find {:?}", element));
+                            }
+                        }
+                        OpInterpretation::default()
+                    }
+                    Operator::Delete | Operator::Change => self.delete_elements(&elements),
+                }
+            }
+            ModalOperation::Paste(register) => match self.registers.subgraph(register) {
+                Ok(subgraph) => {
+                    let subgraph = subgraph.clone();
+                    self.import(&subgraph).0
+                }
+                Err(err) => {
+                    self.error(err);
+                    OpInterpretation::default()
+                }
+            },
+            ModalOperation::SetAttribute(target, key, value) => {
+                let elements = self.target_elements(target);
+                if elements.is_empty() {
+                    self.error("No elements to set the attribute of".to_string());
+                    return Ok(OpInterpretation::default());
+                }
+                self.set_attribute_of(&elements, &key, &value)
+            }
+            ModalOperation::ExCommand(command) => self.interpret_ex_command(&command),
+            ModalOperation::Undo => match self.history.undo_target() {
+                None => OpInterpretation::default(),
+                Some((diff, parent)) => OpInterpretation::history_move(diff, parent).undo(),
+            },
+            ModalOperation::Redo => match self.history.redo_target() {
+                None => OpInterpretation::default(),
+                Some((diff, child)) => OpInterpretation::history_move(diff, child),
+            },
+            ModalOperation::Repeat(count, op)
+                if matches!(*op, ModalOperation::CreateNewVertex { .. }) =>
+            {
+                let label = match *op {
+                    ModalOperation::CreateNewVertex { label } => label,
+                    _ => None,
+                };
+                let mut vertices: Vec<Vertex> = (0..count)
+                    .map(|_| self.new_vertex(label.as_deref()))
+                    .collect();
+                self.place_new_vertices(&mut vertices);
+                self.created_vertex = vertices.last().map(|v| v.id).or(self.created_vertex);
+                OpInterpretation::standard_op(
+                    vertices
+                        .into_iter()
+                        .map(GraphOperation::AddVertex)
+                        .collect(),
+                )
+            }
+            ModalOperation::Repeat(count, op) => {
+                // Walk the history as far as it goes, then move there in one step.
+                let mut target = self.history.current();
+                for _ in 0..count {
+                    let next = match *op {
+                        ModalOperation::Undo => self.history.parent(target),
+                        ModalOperation::Redo => self.history.children(target).last().copied(),
+                        _ => None,
+                    };
+                    match next {
+                        Some(next) => target = next,
+                        None => break,
+                    }
+                }
+                match *op {
+                    ModalOperation::Undo => self.history_jump(target).undo(),
+                    _ => self.history_jump(target),
+                }
+            }
+            ModalOperation::Search(query) => {
+                self.search = Some(query);
+                self.move_to_match(None, true);
+                OpInterpretation::default()
+            }
+            ModalOperation::AcceptCompletion => {
+                match self.completions.first().map(|c| c.text.clone()) {
+                    Some(text) => {
+                        self.mode.replace_vertex_reference(&text);
+                    }
+                    None => self.error("No vertex completes the reference".to_string()),
+                }
+                OpInterpretation::default()
+            }
+            ModalOperation::NextMatch | ModalOperation::PreviousMatch => {
+                self.move_to_match(self.cursor(), op == ModalOperation::NextMatch);
+                OpInterpretation::default()
+            }
+            ModalOperation::MoveCursor(motion) => {
+                if let Some(id) = motion_target(&self.document, self.cursor(), motion) {
+                    if motion.is_jump() {
+                        self.jump_to(id);
+                    } else {
+                        self.set_cursor(id);
+                    }
+                }
+                OpInterpretation::default()
+            }
+            ModalOperation::JumpToMark(mark) => {
+                match self.references.element(&Reference::Mark(mark)) {
+                    Some(ElementRef::Vertex(id)) => {
+                        self.jump_to(id);
+                    }
+                    Some(_) => self.error(format!("Mark {} is not on a vertex", mark)),
+                    None => self.error(format!("Mark not set: {}", mark)),
+                }
+                OpInterpretation::default()
+            }
+            ModalOperation::JumpBack | ModalOperation::JumpForward => {
+                self.retrace_jump(op == ModalOperation::JumpBack);
+                OpInterpretation::default()
+            }
+            ModalOperation::StartMacro(register) => {
+                self.macro_recording = Some((register, InputLog::new()));
+                OpInterpretation::default()
+            }
+            ModalOperation::RecordMacro => {
+                match self.macro_recording.take() {
+                    Some((register, mut recording)) => {
+                        // The `q` that stopped recording.
+                        recording.inputs.pop();
+                        self.registers.set(register, Register::Macro(recording));
+                    }
+                    None => self.mode = EditorMode::MacroRecordPending,
+                }
+                OpInterpretation::default()
+            }
+            // Handled by apply_modal_operation, since they evaluate or reinterpret operations.
+            ModalOperation::ReplayMacro(_) | ModalOperation::RepeatLastChange => {
+                OpInterpretation::default()
+            }
+            ModalOperation::UndoAll => self.history_jump(self.history.root()).undo(),
+            ModalOperation::RedoToTip => self.interpret_modal_operation(ModalOperation::Repeat(
+                usize::MAX,
+                Box::new(ModalOperation::Redo),
+            ))?,
+            ModalOperation::RedoBranch(number) => {
+                let children = self.history_children();
+                let child = match number.parse::<usize>() {
+                    Ok(n) if n >= 1 && n <= children.len() => children[n - 1],
+                    _ => {
+                        self.error(format!(
+                            "No branch {}: the current edit has {} branches",
+                            number,
+                            children.len()
+                        ));
+                        return Ok(OpInterpretation::default());
+                    }
+                };
+                match self.history.redo_target_into(child) {
+                    None => OpInterpretation::default(),
+                    Some((diff, child)) => OpInterpretation::history_move(diff, child),
+                }
+            }
+        })
+    }
+
+    // A press on a vertex puts the cursor on it, or toggles whether it is chosen in Visual mode,
+    // and releasing it after dragging it moves it there.
+    fn interpret_mouse(&mut self, event: MouseEvent) -> Result<OpInterpretation, EditorError> {
+        match event {
+            MouseEvent::Press(position) => {
+                self.drag = vertex_at(&self.document, position).map(|id| (id, false));
+                match (self.drag, &mut self.mode) {
+                    (Some((id, _)), EditorMode::Visual(selection)) => {
+                        if !selection.elements.remove(&ElementRef::Vertex(id)) {
+                            selection.elements.insert(ElementRef::Vertex(id));
+                        }
+                    }
+                    (Some((id, _)), _) => {
+                        self.set_cursor(id);
+                    }
+                    (None, _) => {}
+                }
+            }
+            MouseEvent::Drag(_) => {
+                if let Some((_, dragged)) = &mut self.drag {
+                    *dragged = true;
+                }
+            }
+            MouseEvent::Release(position) => {
+                if let Some((id, true)) = self.drag.take() {
+                    return self
+                        .interpret_modal_operation(ModalOperation::MoveVertex(id, position));
+                }
+            }
+        }
+        Ok(OpInterpretation::default())
+    }
+
+    // Add the edges of an edge list as one edit. A vertex the document does not have is created,
+    // labeled with the reference, and later lines referring to it get the same vertex.
+    fn interpret_edge_list(&mut self, text: &str) -> Result<OpInterpretation, EditorError> {
+        let mut pairs = Vec::new();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            match line.split_once(',') {
+                Some((source, target))
+                    if !source.trim().is_empty() && !target.trim().is_empty() =>
+                {
+                    pairs.push((source.trim(), target.trim()))
+                }
+                _ => {
+                    return Err(EditorError::Malformed {
+                        typed: line.to_string(),
+                        expected: "a source and a target vertex",
+                    })
+                }
+            }
+        }
+
+        let mut created: BTreeMap<&str, i64> = BTreeMap::new();
+        let mut operations = Vec::new();
+        for (source, target) in pairs.iter() {
+            let mut endpoints = [0; 2];
+            for (endpoint, reference) in endpoints.iter_mut().zip([*source, *target].iter()) {
+                let existing = match self.resolve_vertex(reference) {
+                    Ok(id) => Some(id),
+                    Err(EditorError::UnknownVertex(_)) => None,
+                    Err(err) => return Err(err),
+                };
+                *endpoint = match existing.or_else(|| created.get(reference).copied()) {
+                    Some(id) => id,
+                    None => {
+                        let v = self.new_vertex(Some(reference));
+                        created.insert(reference, v.id);
+                        let id = v.id;
+                        operations.push(GraphOperation::AddVertex(v));
+                        id
+                    }
+                };
+            }
+            let e = self.new_edge(endpoints[0], endpoints[1]);
+            operations.push(GraphOperation::AddEdge(e));
+        }
+        self.info(format!(
+            "Pasted {} edges and {} new vertices",
+            pairs.len(),
+            created.len()
+        ));
+        Ok(OpInterpretation::standard_op(operations))
+    }
+
+    // The source and target of an edge, typed as two vertex references separated by a comma.
+    fn resolve_endpoints(&self, typed: &str) -> Result<(i64, i64), EditorError> {
+        let (source, target) = typed
+            .rsplit_once(',')
+            .ok_or_else(|| EditorError::Malformed {
+                typed: typed.to_string(),
+                expected: "a source and a target vertex",
+            })?;
+        Ok((self.resolve_vertex(source)?, self.resolve_vertex(target)?))
+    }
+
+    /**
+     * The vertex a reference typed in the editor names: the vertex with that id, or else the one
+     * vertex with that label, so that `alice,bob` can be typed for an edge instead of `3,17`.
+     */
+    fn resolve_vertex(&self, vertex: &str) -> Result<i64, EditorError> {
+        if let Some(id) = self.document.resolve_vertex(vertex) {
+            return Ok(id);
+        }
+        match self.document.vertices_labeled(vertex.trim()).as_slice() {
+            [] => Err(EditorError::UnknownVertex(vertex.to_string())),
+            [id] => Ok(*id),
+            ids => Err(EditorError::AmbiguousVertex(
+                vertex.trim().to_string(),
+                ids.to_vec(),
+            )),
+        }
+    }
+
+    // Hand back the ids of a rejected edit, none of which came into use.
+    fn release_ids(&mut self, allocated: Vec<ElementRef>) {
+        for element in allocated.into_iter().rev() {
+            match element {
+                ElementRef::Vertex(id) => self.vertex_ids.release(id),
+                ElementRef::Edge(id) => self.edge_ids.release(id),
+            }
+        }
+    }
+
+    // An edge with a fresh id, undirected if the `directed` option is off.
+    fn new_edge(&mut self, source: i64, target: i64) -> Edge {
+        let e = Edge::new(self.edge_ids.allocate(), source, target);
+        self.allocated.push(ElementRef::Edge(e.id));
+        if self.options.directed {
+            e
+        } else {
+            e.with_attribute("directed", "false")
+        }
+    }
+
+    // Position new vertices, if the `auto-layout` option is on.
+    fn place_new_vertices(&self, vertices: &mut [Vertex]) {
+        if !self.options.auto_layout {
+            return;
+        }
+        let positions = positions_after(&self.document, vertices.len());
+        for (v, (x, y)) in vertices.iter_mut().zip(positions) {
+            v.attributes
+                .insert("pos".to_string(), Position::new(x, y).to_string());
+        }
+    }
+
+    // A vertex with a fresh id, labeled if a label is given.
+    fn new_vertex(&mut self, label: Option<&str>) -> Vertex {
+        let v = Vertex::new(self.vertex_ids.allocate());
+        self.allocated.push(ElementRef::Vertex(v.id));
+        match label {
+            Some(label) => v.with_attribute("label", label),
+            None => v,
+        }
+    }
+
+    fn element_attributes(&self, element: &str) -> Option<&Attributes> {
+        match parse_element(element)? {
+            (ElementKind::Vertex, id) => self.document.vertices.get(&id).map(|v| &v.attributes),
+            (ElementKind::Edge, id) => self.document.edges.get(&id).map(|e| &e.attributes),
+        }
+    }
+
+    fn set_attributes(
+        &mut self,
+        element: &str,
+        attributes: Attributes,
+    ) -> Result<OpInterpretation, EditorError> {
+        if self.element_attributes(element) == Some(&attributes) {
+            return Ok(OpInterpretation::default());
+        }
+        let op = match parse_element(element) {
+            Some((ElementKind::Vertex, id)) if self.document.vertices.contains_key(&id) => {
+                let old = self.document.vertices[&id].clone();
+                let new = Vertex {
+                    attributes,
+                    ..old.clone()
+                };
+                GraphOperation::ModifyVertex { old, new }
+            }
+            Some((ElementKind::Edge, id)) if self.document.edges.contains_key(&id) => {
+                let old = self.document.edges[&id].clone();
+                let new = Edge {
+                    attributes,
+                    ..old.clone()
+                };
+                GraphOperation::ModifyEdge { old, new }
+            }
+            _ => return Err(EditorError::UnknownElement(element.to_string())),
+        };
+        Ok(OpInterpretation::standard_op(vec![op]))
+    }
+
+    fn target_elements(&self, target: Target) -> BTreeSet<ElementRef> {
+        match target {
+            Target::Cursor => self.cursor().map(ElementRef::Vertex).into_iter().collect(),
+            Target::Mark(mark) => self
+                .references
+                .element(&Reference::Mark(mark))
+                .into_iter()
+                .collect(),
+            Target::SearchMatches => self
+                .search_matches()
+                .into_iter()
+                .map(ElementRef::Vertex)
+                .collect(),
+            Target::Selection => self.references.elements(&Reference::Selection).collect(),
+            Target::Elements(elements) => elements,
+        }
+    }
+
+    // Remove the elements in one edit: the edges first, so that the vertices remove only the
+    // edges that remain.
+    fn delete_elements(&mut self, elements: &BTreeSet<ElementRef>) -> OpInterpretation {
+        let mut edges = Vec::new();
+        let mut vertices = Vec::new();
+        for element in elements.iter() {
+            match *element {
+                ElementRef::Edge(id) => match self.document.edges.get(&id) {
+                    Some(e) => edges.push(GraphOperation::RemoveEdge(e.clone())),
+                    None => self.error(format!("Could not find edge {}", id)),
+                },
+                ElementRef::Vertex(id) => match self.document.vertices.get(&id) {
+                    Some(v) => vertices.push(GraphOperation::RemoveVertex(v.clone())),
+                    None => self.error(format!("Could not find vertex {}", id)),
+                },
+            }
+        }
+        edges.extend(vertices);
+        if edges.is_empty() {
+            return OpInterpretation::default();
+        }
+        OpInterpretation::standard_op(edges)
+    }
+
+    fn set_attribute_of(
+        &mut self,
+        elements: &BTreeSet<ElementRef>,
+        key: &str,
+        value: &str,
+    ) -> OpInterpretation {
+        let mut operations = Vec::new();
+        for element in elements.iter() {
+            match *element {
+                ElementRef::Vertex(id) => match self.document.vertices.get(&id) {
+                    Some(old) if old.attribute(key) != Some(value) => {
+                        let new = old.clone().with_attribute(key, value);
+                        operations.push(GraphOperation::ModifyVertex {
+                            old: old.clone(),
+                            new,
+                        });
+                    }
+                    Some(_) => {}
+                    None => self.error(format!("Could not find vertex {}", id)),
+                },
+                ElementRef::Edge(id) => match self.document.edges.get(&id) {
+                    Some(old) if old.attribute(key) != Some(value) => {
+                        let new = old.clone().with_attribute(key, value);
+                        operations.push(GraphOperation::ModifyEdge {
+                            old: old.clone(),
+                            new,
+                        });
+                    }
+                    Some(_) => {}
+                    None => self.error(format!("Could not find edge {}", id)),
+                },
+            }
+        }
+        if operations.is_empty() {
+            return OpInterpretation::default();
+        }
+        OpInterpretation::standard_op(operations)
+    }
+
+    fn adjust_weight(&mut self, edge: &str, steps: i64) -> Result<OpInterpretation, EditorError> {
+        let e = match self.document.resolve_edge(edge) {
+            Some(id) => self.document.edges[&id].clone(),
+            None => return Err(EditorError::UnknownEdge(edge.to_string())),
+        };
+        let weight = match e.attribute("weight").map(str::parse::<f64>) {
+            None => DEFAULT_WEIGHT,
+            Some(Ok(weight)) => weight,
+            Some(Err(_)) => {
+                self.error(format!(
+                    "Weight of edge {} is not a number: {}",
+                    e.id,
+                    e.attribute("weight").unwrap()
+                ));
+                return Ok(OpInterpretation::default());
+            }
+        };
+        // Round away the error accumulated from repeatedly adding fractional steps.
+        let adjusted = ((weight + steps as f64 * self.options.weight_step) * 1e9).round() / 1e9;
+        let new = e.clone().with_attribute("weight", &adjusted.to_string());
+        Ok(OpInterpretation::standard_op(vec![
+            GraphOperation::ModifyEdge { old: e, new },
+        ]))
+    }
+
+    fn interpret_fold(&mut self, action: FoldAction, vertex: &str) {
+        let anchor = match action {
+            FoldAction::OpenAll => return self.folds.set_all_open(true),
+            FoldAction::CloseAll => return self.folds.set_all_open(false),
+            _ => match self.resolve_vertex(vertex) {
+                Ok(anchor) => anchor,
+                Err(err) => return self.error(err.to_string()),
+            },
+        };
+        if !self.folds.is_fold(anchor)
+            && (action == FoldAction::Toggle || action == FoldAction::Close)
+        {
+            self.folds
+                .create(&self.document, anchor, FoldKind::Descendants);
+            return;
+        }
+        let found = match action {
+            FoldAction::Toggle => self.folds.toggle(anchor),
+            FoldAction::Open => self.folds.set_open(anchor, true),
+            FoldAction::Close => self.folds.set_open(anchor, false),
+            _ => self.folds.delete(anchor),
+        };
+        if !found {
+            self.error(format!("No fold at vertex {}", anchor));
+        }
+    }
+
+    // Move the document to the state at any node of the history, as a single change.
+    fn history_jump(&self, node: NodeId) -> OpInterpretation {
+        match self.history.path_diff(self.history.current(), node) {
+            Some(diff) => OpInterpretation::history_move(diff, node),
+            None => OpInterpretation::default(),
+        }
+    }
+
+    /**
+     * Add a graph to the document as a single edit, giving its vertices and edges fresh ids so
+     * that they cannot collide with the document's. Returns the ids they were given, e.g., so
+     * that the caller can refer to imported elements.
+     */
+    pub fn import_graph(&mut self, graph: &Graph) -> IdMapping {
+        let (interpretation, mapping) = self.import(graph);
+        self.apply_or_report(interpretation);
+        mapping
+    }
+
+    /**
+     * The selection as clipboard text: the selected vertices, the endpoints of selected edges,
+     * and every edge among them. Ids are not kept, so the text can be pasted into any document.
+     */
+    pub fn copy_selection(&self) -> String {
+        to_clipboard(&self.subgraph_of(self.references.elements(&Reference::Selection)))
+    }
+
+    // The elements' vertices, the endpoints of their edges, and every edge among them.
+    fn subgraph_of<I: Iterator<Item = ElementRef>>(&self, elements: I) -> Graph {
+        let mut vertex_ids = Vec::new();
+        for element in elements {
+            match element {
+                ElementRef::Vertex(id) => vertex_ids.push(id),
+                ElementRef::Edge(id) => {
+                    if let Some(e) = self.document.edges.get(&id) {
+                        vertex_ids.extend([e.source, e.target].iter().copied());
+                    }
+                }
+            }
+        }
+        self.document.induced_subgraph(vertex_ids)
+    }
+
+    // Add the subgraph in clipboard text to the document as a single edit, with fresh ids, and
+    // select what was pasted.
+    pub fn paste(&mut self, text: &str) -> Result<IdMapping, ParseError> {
+        let mapping = self.import_graph(&from_clipboard(text)?);
+        self.references.clear(&Reference::Selection);
+        for id in mapping.vertices.values() {
+            self.references
+                .add(Reference::Selection, ElementRef::Vertex(*id));
+        }
+        Ok(mapping)
+    }
+
+    fn import(&mut self, graph: &Graph) -> (OpInterpretation, IdMapping) {
+        let (operations, mapping) =
+            renumber(graph, self.vertex_ids.as_mut(), self.edge_ids.as_mut());
+        self.allocated
+            .extend(operations.iter().filter_map(|op| match op {
+                GraphOperation::AddVertex(v) => Some(ElementRef::Vertex(v.id)),
+                GraphOperation::AddEdge(e) => Some(ElementRef::Edge(e.id)),
+                _ => None,
+            }));
+        if !mapping.dangling_edges.is_empty() {
+            self.warn(format!(
+                "Left out {} edges with missing endpoints",
+                mapping.dangling_edges.len()
+            ));
+        }
+        (OpInterpretation::standard_op(operations), mapping)
+    }
+}
+
+// Parse a reference to an element such as `v3` or `e0`.
+fn parse_element(element: &str) -> Option<(ElementKind, i64)> {
+    match ElementRef::parse(element)? {
+        ElementRef::Vertex(id) => Some((ElementKind::Vertex, id)),
+        ElementRef::Edge(id) => Some((ElementKind::Edge, id)),
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Input {
+    // A key as the editor encodes it, e.g., `CTRL_R` for Ctrl-R; see `KeyEvent::key`.
+    Key(char),
+    // A key with modifiers, as a frontend reports it.
+    Press(KeyEvent),
+    Mouse(MouseEvent),
+    // Text pasted all at once, e.g., with a terminal's bracketed paste, rather than typed.
+    Paste(String),
+    // Time passing without input, e.g., for keys that begin a mapping to time out.
+    Tick(Duration),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::editor::hooks::Hook;
+    use crate::editor::hooks::HookEvent;
+    use crate::editor::ids::Prefixed;
+    use crate::editor::ids::Random;
+    use crate::editor::keys::*;
+    use crate::editor::message::Presentation;
+    use crate::editor::mouse::Position;
+    use crate::graph::Edge;
+    use crate::graph::Graph;
+    use crate::graph::GraphError;
+    use crate::graph::Vertex;
+    use crate::schema::AttributeType;
+
+    pub(super) fn single_edge_graph() -> Graph {
+        let mut single_edge = Graph::new();
+        let v0 = Vertex::new(0);
+        let v1 = Vertex::new(1);
+        let e0 = Edge::new(0, v0.id, v1.id);
+        single_edge.add_vertex(v0.clone());
+        single_edge.add_vertex(v1.clone());
+        single_edge.add_edge(e0.clone());
+        single_edge
+    }
+
+    #[test]
+    fn insert_two_vertices_and_edge() {
+        let mut state = EditorState::new();
+        state.evaluate(Input::Key(I_LOWER));
+        state.evaluate(Input::Key(V_LOWER));
+        state.evaluate(Input::Key(V_LOWER));
+        state.evaluate(Input::Key(E_LOWER));
+        state.evaluate(Input::Key(DIGIT_0));
+        state.evaluate(Input::Key(COMMA));
+        state.evaluate(Input::Key(DIGIT_1));
+        state.evaluate(Input::Key(ENTER));
+
+        assert_eq!(EditorMode::Insert, state.mode);
+
+        let expected = single_edge_graph();
+        assert_eq!(expected, state.document);
+    }
+
+    #[test]
+    fn invariant_checks_pass_during_normal_editing() {
+        let mut state = EditorState::new();
+        state.set_invariant_check_interval(Some(1));
+        state.evaluate(Input::Key(I_LOWER));
+        state.evaluate(Input::Key(V_LOWER));
+        state.evaluate(Input::Key(V_LOWER));
+        state.evaluate(Input::Key(E_LOWER));
+        state.evaluate(Input::Key(DIGIT_0));
+        state.evaluate(Input::Key(COMMA));
+        state.evaluate(Input::Key(DIGIT_1));
+        state.evaluate(Input::Key(ENTER));
+        state.evaluate(Input::Key(ESC));
+        state.evaluate(Input::Key(U_LOWER));
+        state.evaluate(Input::Key(U_UPPER));
+
+        assert_eq!(single_edge_graph(), state.document);
+    }
+
+    #[test]
+    fn merge_vertices_and_undo() {
+        let mut state = EditorState::new();
+        state.evaluate(Input::Key(I_LOWER));
+        state.evaluate(Input::Key(V_LOWER));
+        state.evaluate(Input::Key(V_LOWER));
+        state.evaluate(Input::Key(E_LOWER));
+        state.evaluate(Input::Key(DIGIT_0));
+        state.evaluate(Input::Key(COMMA));
+        state.evaluate(Input::Key(DIGIT_1));
+        state.evaluate(Input::Key(ENTER));
+        state.evaluate(Input::Key(ESC));
+        state.evaluate(Input::Key(I_LOWER));
+        state.evaluate(Input::Key(M_UPPER));
+        state.evaluate(Input::Key(DIGIT_0));
+        state.evaluate(Input::Key(COMMA));
+        state.evaluate(Input::Key(DIGIT_1));
+        state.evaluate(Input::Key(ENTER));
+
+        assert_eq!(EditorMode::Insert, state.mode);
+        assert_eq!(
+            vec![0],
+            state.document.vertices.keys().copied().collect::<Vec<_>>()
+        );
+        assert_eq!(Edge::new(0, 0, 0), state.document.edges[&0]);
+
+        state.evaluate(Input::Key(ESC));
+        state.evaluate(Input::Key(U_LOWER));
+        assert_eq!(single_edge_graph(), state.document);
+    }
+
+    #[test]
+    fn infer_schema_command() {
+        let mut state = EditorState::new();
+        state
+            .document
+            .add_vertex(Vertex::new(0).with_attribute("weight", "2"));
+        for key in ":infer-schema".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        state.evaluate(Input::Key(ENTER));
+
+        assert_eq!(EditorMode::Command, state.mode);
+        assert_eq!(Some(&Schema::infer(&state.document)), state.schema(),);
+    }
+
+    #[test]
+    fn rejected_edits_do_not_use_up_ids() {
+        let mut state = EditorState::new();
+        state.document = single_edge_graph();
+        state.vertex_ids = Box::new(Sequential::starting_at(2));
+        state.edge_ids = Box::new(Sequential::starting_at(1));
+        let mut schema = Schema::default();
+        for attributes in [&mut schema.vertex_attributes, &mut schema.edge_attributes].iter_mut() {
+            attributes.insert("label".to_string(), AttributeType::Integer);
+            attributes.insert("directed".to_string(), AttributeType::Integer);
+        }
+        state.set_schema(Some(schema));
+        for key in ":set nodirected\u{e007}ie0,1\u{e007}\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(
+            "Attribute label of vertex 2 must be integer, but was 'two'",
+            state
+                .execute(Command::AddVertex {
+                    label: Some("two".to_string()),
+                })
+                .unwrap_err()
+                .to_string()
+        );
+        let rejections: Vec<String> = state
+            .take_messages()
+            .into_iter()
+            .map(|message| message.text)
+            .collect();
+        assert_eq!(
+            vec!["Attribute directed of edge 1 must be integer, but was 'false'"],
+            rejections
+        );
+        assert_eq!(single_edge_graph(), state.document);
+        assert_eq!((2, 1), state.next_ids());
+
+        state
+            .execute(Command::AddVertex {
+                label: Some("3".to_string()),
+            })
+            .unwrap();
+        assert!(state.document.vertices.contains_key(&2));
+    }
+
+    #[test]
+    fn imported_ids_are_remapped_and_returned() {
+        let mut state = EditorState::new();
+        for key in "ivve0,1\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let before = state.document.clone();
+
+        let mapping = state.import_graph(&single_edge_graph());
+        assert_eq!(Some(2), mapping.vertex(0));
+        assert_eq!(Some(3), mapping.vertex(1));
+        assert_eq!(Some(1), mapping.edge(0));
+        assert_eq!(Edge::new(1, 2, 3), state.document.edges[&1]);
+        assert_eq!(2, state.history().len());
+
+        state.evaluate(Input::Key(ESC));
+        state.evaluate(Input::Key(U_LOWER));
+        assert_eq!(before, state.document);
+    }
+
+    #[test]
+    fn selection_is_pasted_into_another_editor() {
+        let mut source = EditorState::new();
+        for key in "ivvve0,1\u{e007}e1,2\u{e007}".chars() {
+            source.evaluate(Input::Key(key));
+        }
+        source.add_reference(Reference::Selection, ElementRef::Edge(0));
+        let text = source.copy_selection();
+
+        let mut target = EditorState::new();
+        for key in "iv".chars() {
+            target.evaluate(Input::Key(key));
+        }
+        let mapping = target.paste(&text).unwrap();
+        assert_eq!(Some(1), mapping.vertex(0));
+        assert_eq!(Edge::new(0, 1, 2), target.document.edges[&0]);
+        assert_eq!(3, target.document.vertices.len());
+        assert_eq!(
+            vec![ElementRef::Vertex(1), ElementRef::Vertex(2)],
+            target
+                .references()
+                .elements(&Reference::Selection)
+                .collect::<Vec<_>>()
+        );
+        assert!(target.paste("not a graph").is_err());
+    }
+
+    #[test]
+    fn readonly_documents_reject_changes() {
+        let mut state = EditorState::new();
+        for key in "iv\u{1b}:set readonly\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        for key in "iv".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(EditorMode::Insert, *state.mode());
+        for key in "\u{1b}u:undo-all\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let messages: Vec<String> = state
+            .take_messages()
+            .into_iter()
+            .map(|message| message.text)
+            .collect();
+        assert_eq!(vec!["The document is read-only"; 3], messages);
+        assert_eq!(1, state.document.vertices.len());
+        assert_eq!(
+            Err(EditorError::ReadOnly),
+            state.execute(Command::AddVertex { label: None })
+        );
+
+        // Moving and yanking are not changes.
+        for key in "\u{1b}jyy:set noreadonly\u{e007}u".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert!(state.take_messages().is_empty());
+        assert!(state.document.vertices.is_empty());
+    }
+
+    #[test]
+    fn execute_returns_rejected_edits() {
+        let mut state = EditorState::new();
+        state.document = single_edge_graph();
+        state.set_strict(true);
+        assert_eq!(
+            Err(EditorError::Graph(GraphError::SelfMerge(0))),
+            state.execute(Command::MergeVertices("0".to_string(), "0".to_string()))
+        );
+        assert_eq!(0, state.history().len());
+        assert!(state.take_messages().is_empty());
+    }
+
+    #[test]
+    fn compute_command_declares_builtin_attribute() {
+        let mut state = EditorState::new();
+        state.document = single_edge_graph();
+        for key in ":compute out_degree".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        state.evaluate(Input::Key(ENTER));
+
+        let attributes = state.vertex_attributes(0).unwrap();
+        assert_eq!(Some("1"), attributes.get("out_degree").map(String::as_str));
+        assert_eq!(single_edge_graph(), state.document);
+    }
+
+    #[test]
+    fn completions_are_emitted_while_typing_vertex_references() {
+        let mut state = EditorState::new();
+        let events = state.subscribe();
+        for key in "ivvvvvvvvvvv".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        state.evaluate(Input::Key(E_LOWER));
+        state.evaluate(Input::Key(DIGIT_1));
+
+        // An exact id match ranks ahead of a longer id with the same prefix.
+        assert_eq!(
+            vec![1, 10],
+            state
+                .completions()
+                .iter()
+                .map(|c| c.vertex)
+                .collect::<Vec<_>>()
+        );
+
+        state.evaluate(Input::Key(ESC));
+        assert!(state.completions().is_empty());
+
+        let emitted: Vec<EditorEvent> = events
+            .try_iter()
+            .filter(|event| matches!(event, EditorEvent::Completions(_)))
+            .collect();
+        assert_eq!(3, emitted.len());
+        assert_eq!(EditorEvent::Completions(Vec::new()), emitted[2]);
+    }
+
+    #[test]
+    fn status_shows_what_is_typed() {
+        let mut state = EditorState::new();
+        for key in "12".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(Some(12), state.status().count);
+        assert_eq!(None, state.status().pending_text);
+
+        for key in "\u{1b}fie0,".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let status = state.status();
+        assert_eq!(&EditorMode::InsertEdgePending("0,".into()), status.mode);
+        assert_eq!(Some("0,"), status.pending_text);
+        assert_eq!(None, status.count);
+        // The message lasts until the next key.
+        assert_eq!(None, status.message);
+
+        state.evaluate(Input::Key(ESC));
+        state.evaluate(Input::Key('f'));
+        assert!(state
+            .status()
+            .message
+            .unwrap()
+            .text
+            .starts_with("Input Key('f')"));
+    }
+
+    #[test]
+    fn errors_and_notices_are_reported() {
+        let mut state = EditorState::new();
+        let events = state.subscribe();
+        for key in "f:ids\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let messages = state.take_messages();
+        assert_eq!(
+            vec![Severity::Error, Severity::Info],
+            messages.iter().map(|m| m.severity).collect::<Vec<_>>()
+        );
+        assert_eq!("Next vertex id 0, next edge id 0", messages[1].text);
+        assert!(state.take_messages().is_empty());
+        let emitted: Vec<EditorEvent> = events
+            .try_iter()
+            .filter(|event| matches!(event, EditorEvent::Message(_)))
+            .collect();
+        assert_eq!(EditorEvent::Message(messages[1].clone()), emitted[1]);
+    }
+
+    #[test]
+    fn messages_lists_the_message_history() {
+        let mut state = EditorState::new();
+        for key in "f:ids\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let reported = state.take_messages();
+        assert_eq!(
+            Presentation::Persistent,
+            state.message_history().get(0).unwrap().presentation()
+        );
+
+        for key in ":messages\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        // Listing the messages does not add them to the history again.
+        assert_eq!(reported, state.take_messages());
+        assert_eq!(
+            reported,
+            state.message_history().iter().cloned().collect::<Vec<_>>()
+        );
+
+        for key in ":messages clear\u{e007}:messages\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert!(state.take_messages().is_empty());
+        assert!(state.message_history().is_empty());
+    }
+
+    #[test]
+    fn tab_accepts_the_best_completion() {
+        let mut state = EditorState::new();
+        for key in "ivvv".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        state
+            .document
+            .vertices
+            .insert(2, Vertex::new(2).with_attribute("label", "Depot"));
+        for key in "e0,de\t".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(EditorMode::InsertEdgePending("0,2".into()), state.mode);
+
+        // Without a completion, the reference is left as typed.
+        for key in "\u{e007}M1,x\t".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(Some(&Edge::new(0, 0, 2)), state.document.edges.get(&0));
+        assert_eq!(EditorMode::MergeVerticesPending("1,x".into()), state.mode);
+    }
+
+    #[test]
+    fn describe_history_nodes() {
+        let mut state = EditorState::new();
+        for key in "iv\u{1b}:history message add the hub\u{e007}iv\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let described = state.history.node(2).unwrap();
+        assert_eq!(
+            Some("add the hub"),
+            state.history.entry(described).unwrap().message.as_deref()
+        );
+        let current = state.history.current();
+        assert_eq!(None, state.history.entry(current).unwrap().message);
+
+        state.evaluate(Input::Key(U_LOWER));
+        for key in ":history delete-message\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(None, state.history.entry(described).unwrap().message);
+    }
+
+    #[test]
+    fn history_changes_are_emitted() {
+        let mut state = EditorState::new();
+        let events = state.subscribe();
+        for key in "iv\u{1b}iv\u{1b}uiv\u{1b}:history delete-branch 3\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let emitted: Vec<HistoryEvent> = events
+            .try_iter()
+            .filter_map(|event| match event {
+                EditorEvent::History(event) => Some(event),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                HistoryEvent::NodeCreated { node: 2, parent: 1 },
+                HistoryEvent::CurrentMoved { from: 1, to: 2 },
+                HistoryEvent::NodeCreated { node: 3, parent: 2 },
+                HistoryEvent::CurrentMoved { from: 2, to: 3 },
+                HistoryEvent::CurrentMoved { from: 3, to: 2 },
+                HistoryEvent::NodeCreated { node: 4, parent: 2 },
+                HistoryEvent::BranchCreated { node: 4, parent: 2 },
+                HistoryEvent::CurrentMoved { from: 2, to: 4 },
+                HistoryEvent::NodesRemoved(vec![3]),
+            ],
+            emitted
+        );
+    }
+
+    #[test]
+    fn input_and_rendering_on_different_threads() {
+        use std::sync::Arc;
+        use std::sync::Mutex;
+
+        let state = Arc::new(Mutex::new(EditorState::new()));
+        let events = state.lock().unwrap().subscribe();
+        let input = {
+            let state = Arc::clone(&state);
+            std::thread::spawn(move || {
+                for key in "ivve0,1\u{e007}\u{1b}".chars() {
+                    state.lock().unwrap().evaluate(Input::Key(key));
+                }
+            })
+        };
+        let renderer = std::thread::spawn(move || {
+            events
+                .iter()
+                .filter(|event| matches!(event, EditorEvent::Document(_)))
+                .take(3)
+                .count()
+        });
+        input.join().unwrap();
+        assert_eq!(3, renderer.join().unwrap());
+        assert_eq!(1, state.lock().unwrap().document().edges.len());
+    }
+
+    #[test]
+    fn set_options_like_vim() {
+        let mut state = EditorState::new();
+        for key in ":set nodirected auto-layout\u{e007}ivve0,1\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(
+            Some("false"),
+            state.document.edges[&0].attribute("directed")
+        );
+        assert_eq!(Some("0,0"), state.document.vertices[&0].attribute("pos"));
+        assert_eq!(Some("1.5,0"), state.document.vertices[&1].attribute("pos"));
+
+        for key in "\u{1b}:set noundo-grouping\u{e007}ivv\u{1b}u".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(3, state.document.vertices.len());
+
+        for key in ":set strict?\u{e007}:set wrap\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let messages = state.take_messages();
+        assert_eq!("strict=off", messages[0].text);
+        assert_eq!("Unknown option: wrap", messages[1].text);
+    }
+
+    #[test]
+    fn execute_commands_without_keys() {
+        let mut state = EditorState::new();
+        let label = |label: &str| Command::AddVertex {
+            label: Some(label.to_string()),
+        };
+        state.execute(label("alice")).unwrap();
+        state.execute(label("bob")).unwrap();
+        let edge = Command::AddEdgeBetween("alice".to_string(), "bob".to_string());
+        assert_eq!(Ok(()), state.execute(edge));
+        assert_eq!(Edge::new(0, 0, 1), state.document.edges[&0]);
+        assert_eq!(
+            Err(EditorError::UnknownVertex("carol".to_string())),
+            state.execute(Command::AddEdgeBetween(
+                "carol".to_string(),
+                "bob".to_string()
+            ))
+        );
+
+        // A command in Insert mode is not folded into the edits typed there.
+        state.evaluate(Input::Key('i'));
+        state.execute(Command::AddVertex { label: None }).unwrap();
+        for key in "v\u{1b}u".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(3, state.document.vertices.len());
+        state.execute(Command::Undo).unwrap();
+        let size =
+            |state: &EditorState| (state.document.vertices.len(), state.document.edges.len());
+        assert_eq!((2, 1), size(&state));
+        assert_eq!(&EditorMode::Command, state.mode());
+    }
+
+    #[test]
+    fn evaluate_a_script_of_keys() {
+        let mut state = EditorState::new();
+        assert_eq!(Ok(()), state.evaluate_script("ivve0,1<CR><Esc>"));
+        assert_eq!(&EditorMode::Command, state.mode());
+        assert_eq!(Edge::new(0, 0, 1), state.document.edges[&0]);
+
+        assert_eq!(
+            Err("Unknown key: <F1>".to_string()),
+            state.evaluate_script("u<F1>")
+        );
+        assert_eq!(2, state.document.vertices.len());
+        state.evaluate_script("u").unwrap();
+        assert!(state.document.vertices.is_empty());
+    }
+
+    #[test]
+    fn document_changes_and_mode_changes_are_emitted() {
+        let mut state = EditorState::new();
+        let events = state.subscribe();
+        for key in "iv\u{1b}u".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let diff = |operation| Diff {
+            operations: vec![operation],
+        };
+        let emitted: Vec<EditorEvent> = events
+            .try_iter()
+            .filter(|event| matches!(event, EditorEvent::Document(_) | EditorEvent::Mode(_)))
+            .collect();
+        assert_eq!(
+            vec![
+                EditorEvent::Mode(EditorMode::Insert),
+                EditorEvent::Document(diff(GraphOperation::AddVertex(Vertex::new(0)))),
+                EditorEvent::Mode(EditorMode::Command),
+                EditorEvent::Document(diff(GraphOperation::RemoveVertex(Vertex::new(0)))),
+            ],
+            emitted
+        );
+    }
+
+    #[test]
+    fn counted_vertices_are_created_in_one_edit() {
+        let mut state = EditorState::new();
+        let events = state.subscribe();
+        for key in "i3v\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let emitted: Vec<HistoryEvent> = events
+            .try_iter()
+            .filter_map(|event| match event {
+                EditorEvent::History(event) => Some(event),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                HistoryEvent::NodeCreated { node: 2, parent: 1 },
+                HistoryEvent::CurrentMoved { from: 1, to: 2 },
+            ],
+            emitted
+        );
+        let mut ids: Vec<i64> = state.document.vertices.keys().copied().collect();
+        ids.sort_unstable();
+        assert_eq!(vec![0, 1, 2], ids);
+    }
+
+    #[test]
+    fn history_delete_branch_command() {
+        let mut state = EditorState::new();
+        for key in "iv\u{1b}iv\u{1b}uuiv".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let branch = state.history().branches()[0].clone();
+        assert_eq!(2, branch.node_count);
+
+        for key in format!("\u{1b}:history delete-branch {}", branch.id).chars() {
+            state.evaluate(Input::Key(key));
+        }
+        state.evaluate(Input::Key(ENTER));
+
+        assert!(state.history().branches().is_empty());
+        assert_eq!(1, state.history().len());
+    }
+
+    #[test]
+    fn weight_adjustments_are_coalesced() {
+        let mut state = EditorState::new();
+        state.document = single_edge_graph();
+        state.set_weight_step(0.5);
+        for key in "iw0\u{e007}+3+-".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(Some("2.5"), state.document.edges[&0].attribute("weight"));
+        assert_eq!(1, state.history().len());
+
+        // Leaving Insert mode ends the coalesced edit.
+        for key in "\u{1b}\u{1b}iw0\u{e007}+\u{1b}\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(Some("3"), state.document.edges[&0].attribute("weight"));
+        assert_eq!(2, state.history().len());
+
+        state.evaluate(Input::Key(U_LOWER));
+        assert_eq!(Some("2.5"), state.document.edges[&0].attribute("weight"));
+        state.evaluate(Input::Key(U_LOWER));
+        assert_eq!(single_edge_graph(), state.document);
+    }
+
+    #[test]
+    fn folds_survive_merge_and_undo() {
+        let mut state = EditorState::new();
+        for key in "ivvve0,1\u{e007}e1,2\u{e007}\u{1b}za1\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert!(state.folds().is_hidden(2));
+
+        for key in "iM0,1\u{e007}\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert!(!state.folds().is_hidden(2));
+
+        state.evaluate(Input::Key(U_LOWER));
+        assert!(state.folds().is_hidden(2));
+
+        for key in "zR:fold-cluster 0 2\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(
+            vec![2],
+            state.folds().hidden().into_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn attribute_history_records_weight_changes() {
+        let mut state = EditorState::new();
+        state.document = single_edge_graph();
+        state.set_attribute_history(true);
+        for key in "iw0\u{e007}++\u{1b}\u{1b}u".chars() {
+            state.evaluate(Input::Key(key));
+        }
+
+        let weights: Vec<Option<&str>> = state
+            .attribute_history()
+            .unwrap()
+            .changes(ElementKind::Edge, 0, "weight")
+            .iter()
+            .map(|c| c.new.as_deref())
+            .collect();
+        assert_eq!(vec![Some("2"), Some("3"), None], weights);
+        assert_eq!(Some((ElementKind::Vertex, 3)), parse_element("v3"));
+        assert_eq!(None, parse_element("x3"));
+    }
+
+    #[test]
+    fn references_are_restored_when_merge_is_undone() {
+        let mut state = EditorState::new();
+        for key in "ivve0,1\u{e007}\u{1b}:pin v0 v1 e0\u{e007}:mark a v1\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let pins: Vec<ElementRef> = state.references().elements(&Reference::Pin).collect();
+        assert_eq!(3, pins.len());
+
+        for key in "iM0,1\u{e007}\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert!(state.references().elements(&Reference::Pin).count() < 3);
+
+        state.evaluate(Input::Key(U_LOWER));
+        assert_eq!(
+            pins,
+            state
+                .references()
+                .elements(&Reference::Pin)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            Some(ElementRef::Vertex(1)),
+            state.references().element(&Reference::Mark('a'))
+        );
+        assert!(!state.add_reference(Reference::Cursor, ElementRef::Vertex(7)));
+    }
+
+    #[test]
+    fn attribute_form_commits_one_modification() {
+        let mut state = EditorState::new();
+        state.document = single_edge_graph();
+        state.edge_ids = Box::new(Sequential::starting_at(1));
+        for key in "iae0\u{e007}ow=2\u{e007}olabel=x\u{e007}\u{e007}y\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(single_edge_graph(), state.document);
+
+        state.evaluate(Input::Key(ESC));
+        assert_eq!(EditorMode::Insert, state.mode);
+        assert_eq!(Some("y"), state.document.edges[&0].attribute("label"));
+        assert_eq!(Some("2"), state.document.edges[&0].attribute("w"));
+
+        for key in "\u{1b}u".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(single_edge_graph(), state.document);
+    }
+
+    #[test]
+    fn plugins_are_limited_to_their_capabilities() {
+        use crate::editor::plugin::OperationKind;
+
+        let mut state = EditorState::new();
+        state.register_plugin(
+            "generator",
+            Capabilities {
+                operations: [OperationKind::AddVertex].iter().copied().collect(),
+                attribute_namespaces: ["gen".to_string()].iter().cloned().collect(),
+                history: false,
+            },
+        );
+        let add = |v: Vertex| PluginRequest::Operations(vec![GraphOperation::AddVertex(v)]);
+
+        assert_eq!(
+            Ok(()),
+            state.dispatch(
+                "generator",
+                add(Vertex::new(5).with_attribute("gen.seed", "1"))
+            )
+        );
+        assert!(state
+            .dispatch(
+                "generator",
+                add(Vertex::new(6).with_attribute("label", "x"))
+            )
+            .is_err());
+        assert!(state.dispatch("generator", PluginRequest::Undo).is_err());
+        assert_eq!(vec![&5], state.document.vertices.keys().collect::<Vec<_>>());
+        assert_eq!(1, state.history().len());
+    }
+
+    #[test]
+    fn plugins_add_operations_commands_and_bindings() {
+        use crate::editor::plugin::OperationKind;
+        use crate::editor::plugin::Plugin;
+        use crate::editor::plugin::PluginContext;
+        use crate::editor::plugin::PluginOperation;
+
+        // Adds a row of vertices after the cursor, or after the largest id.
+        #[derive(Debug)]
+        struct Row;
+
+        impl Plugin for Row {
+            fn name(&self) -> &str {
+                "row"
+            }
+
+            fn capabilities(&self) -> Capabilities {
+                Capabilities {
+                    operations: [OperationKind::AddVertex].iter().copied().collect(),
+                    ..Capabilities::default()
+                }
+            }
+
+            fn leader_namespace(&self) -> Option<Vec<char>> {
+                Some(vec!['r'])
+            }
+
+            fn bindings(&self) -> Vec<(KeymapMode, Vec<char>, Vec<char>)> {
+                vec![(
+                    KeymapMode::Command,
+                    vec!['r'],
+                    ":row 2\u{e007}".chars().collect(),
+                )]
+            }
+
+            fn ex_commands(&self) -> Vec<String> {
+                vec!["row".to_string()]
+            }
+
+            fn operate(
+                &mut self,
+                operation: &PluginOperation,
+                context: PluginContext,
+            ) -> Result<PluginRequest, String> {
+                let count = match operation.args.first().map(|n| n.parse::<i64>()) {
+                    Some(Ok(count)) => count,
+                    _ => return Err("Usage: row <count>".to_string()),
+                };
+                let first = context
+                    .document
+                    .vertices
+                    .keys()
+                    .max()
+                    .map_or(0, |id| id + 1);
+                Ok(PluginRequest::Operations(
+                    (first..first + count)
+                        .map(|id| GraphOperation::AddVertex(Vertex::new(id)))
+                        .collect(),
+                ))
+            }
+        }
+
+        let mut state = EditorState::new();
+        state.install_plugin(Box::new(Row)).unwrap();
+        for key in ":row 3\u{e007}\\rr".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(5, state.document.vertices.len());
+        assert_eq!(2, state.history().len());
+
+        // Each operation is a single edit, and a plugin's errors are reported as the editor's are.
+        state.evaluate(Input::Key('u'));
+        assert_eq!(3, state.document.vertices.len());
+        for key in ":row\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!("Usage: row <count>", state.take_messages()[0].text);
+
+        assert!(state.unregister_plugin("row"));
+        for key in ":row 1\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!("Unknown command: row", state.take_messages()[0].text);
+    }
+
+    #[test]
+    fn hooks_run_at_edits_modes_undo_and_saves() {
+        use std::sync::Arc;
+        use std::sync::Mutex;
+
+        let mut state = EditorState::new();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let record = seen.clone();
+        let layered = state.add_hook(
+            Hook::VertexAdded,
+            Box::new(move |state, event| {
+                record.lock().unwrap().push(event.clone());
+                if let HookEvent::VertexAdded(id) = event {
+                    let vertex = [ElementRef::Vertex(*id)].iter().copied().collect();
+                    let layer = Command::SetAttribute(vertex, "layer".to_string(), "1".to_string());
+                    state.execute(layer).map_err(|err| err.to_string())?;
+                }
+                Ok(())
+            }),
+        );
+        for hook in [Hook::VertexRemoved, Hook::ModeChanged, Hook::PostUndo].iter() {
+            let record = seen.clone();
+            state.add_hook(
+                *hook,
+                Box::new(move |_, event| {
+                    record.lock().unwrap().push(event.clone());
+                    Ok(())
+                }),
+            );
+        }
+        let vetoed = state.add_hook(
+            Hook::PreSave,
+            Box::new(|state, _| match state.document().vertices.len() {
+                0 | 1 => Ok(()),
+                _ => Err("Too many vertices".to_string()),
+            }),
+        );
+
+        let path = std::env::temp_dir().join(format!("gri-hooks-{}.dot", std::process::id()));
+        let path = path.display().to_string();
+        for key in format!("ivv\u{1b}:w {}\u{e007}u", path).chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(
+            format!("Did not write {}: Too many vertices", path),
+            state.take_messages()[0].text
+        );
+        let written = std::path::Path::new(&path).exists();
+        let _ = std::fs::remove_file(&path);
+        assert!(!written);
+        // What the hook changed is part of the Insert session's edit, and undone with it.
+        assert!(state.document.vertices.is_empty());
+        assert_eq!(1, state.history().len());
+
+        let seen = seen.lock().unwrap().clone();
+        let element_events: Vec<&HookEvent> = seen
+            .iter()
+            .filter(|event| !matches!(event, HookEvent::ModeChanged { .. }))
+            .collect();
+        assert_eq!(
+            vec![
+                &HookEvent::VertexAdded(0),
+                &HookEvent::VertexAdded(1),
+                &HookEvent::VertexRemoved(1),
+                &HookEvent::VertexRemoved(0),
+                &HookEvent::PostUndo,
+            ],
+            element_events
+        );
+        assert_eq!(
+            HookEvent::ModeChanged {
+                from: EditorMode::Command,
+                to: EditorMode::Insert
+            },
+            seen[0]
+        );
+        assert!(state.remove_hook(layered));
+        state.execute(Command::Redo).unwrap();
+        assert_eq!(Some("1"), state.document.vertices[&1].attribute("layer"));
+        assert!(state.remove_hook(vetoed));
+        assert!(!state.remove_hook(vetoed));
+
+        // Only undoing runs the PostUndo hooks, not other moves back through the history.
+        let undos = Arc::new(Mutex::new(0));
+        let count = undos.clone();
+        state.add_hook(
+            Hook::PostUndo,
+            Box::new(move |_, _| {
+                *count.lock().unwrap() += 1;
+                Ok(())
+            }),
+        );
+        assert!(state.goto_history_node(state.history().root()));
+        assert!(state.document.vertices.is_empty());
+        assert_eq!(0, *undos.lock().unwrap());
+        for key in "U2u".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert!(state.document.vertices.is_empty());
+        assert_eq!(1, *undos.lock().unwrap());
+    }
+
+    #[test]
+    fn autocommands_run_ex_commands_at_hooks() {
+        use crate::editor::plugin::Plugin;
+        use crate::editor::plugin::PluginContext;
+        use crate::editor::plugin::PluginOperation;
+
+        // Marks every edge added.
+        #[derive(Debug)]
+        struct Marker;
+
+        impl Plugin for Marker {
+            fn name(&self) -> &str {
+                "marker"
+            }
+
+            fn capabilities(&self) -> Capabilities {
+                Capabilities::default()
+            }
+
+            fn autocommands(&self) -> Vec<(Hook, String)> {
+                vec![(Hook::EdgeAdded, "mark e e<id>".to_string())]
+            }
+
+            fn operate(
+                &mut self,
+                _: &PluginOperation,
+                _: PluginContext,
+            ) -> Result<PluginRequest, String> {
+                Err("Marker has no operations".to_string())
+            }
+        }
+
+        let mut state = EditorState::new();
+        state.install_plugin(Box::new(Marker)).unwrap();
+        for key in
+            ":autocmd VertexAdded pin v<id>\u{e007}ivve0,1\u{e007}\u{1b}:autocmd\u{e007}".chars()
+        {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(
+            vec![ElementRef::Vertex(0), ElementRef::Vertex(1)],
+            state
+                .references()
+                .elements(&Reference::Pin)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            Some(ElementRef::Edge(0)),
+            state.references().element(&Reference::Mark('e'))
+        );
+        let listed: Vec<String> = state
+            .take_messages()
+            .into_iter()
+            .map(|message| message.text)
+            .collect();
+        assert_eq!(
+            vec![
+                "VertexAdded pin v<id>",
+                "EdgeAdded mark e e<id> (plugin marker)"
+            ],
+            listed
+        );
+
+        // Clearing autocommands leaves those of plugins, until the plugin is unregistered.
+        for key in ":autocmd!\u{e007}:autocmd Saved\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!("Unknown hook: Saved", state.take_messages()[0].text);
+        assert!(state.unregister_plugin("marker"));
+        for key in "ive1,2\u{e007}\u{1b}:autocmd\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(2, state.references().elements(&Reference::Pin).count());
+        assert_eq!(
+            Some(ElementRef::Edge(0)),
+            state.references().element(&Reference::Mark('e'))
+        );
+        assert!(state.take_messages().is_empty());
+    }
+
+    #[test]
+    fn plugins_bind_keys_under_the_leader() {
+        let mut state = EditorState::new();
+        state.register_plugin("generator", Capabilities::default());
+        assert!(state
+            .bind_leader_keys("generator", KeymapMode::Insert, &['v'], vec!['v'])
+            .is_err());
+        state
+            .reserve_leader_namespace("generator", vec!['g'])
+            .unwrap();
+        assert_eq!(
+            Ok(vec!['\\', 'g', 'v']),
+            state.bind_leader_keys("generator", KeymapMode::Insert, &['v'], vec!['v', 'v'])
+        );
+
+        for key in "i\\gv".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(2, state.document.vertices.len());
+
+        // The user may still map keys in a plugin's namespace, with a warning.
+        for key in "\u{1b}:map command \\gx u\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(
+            "\\gx is in the leader namespace of plugin generator",
+            state.take_messages()[0].text
+        );
+        assert!(state.unregister_plugin("generator"));
+        assert_eq!(
+            None,
+            state.keymap().get(KeymapMode::Insert, &['\\', 'g', 'v'])
+        );
+        assert_eq!(
+            Some(&['u'][..]),
+            state.keymap().get(KeymapMode::Command, &['\\', 'g', 'x'])
+        );
+    }
+
+    #[test]
+    fn switching_scenarios_is_undoable() {
+        let mut state = EditorState::new();
+        for key in
+            "ivv\u{1b}:scenario save A\u{e007}ive0,1\u{e007}\u{1b}:scenario save B\u{e007}".chars()
+        {
+            state.evaluate(Input::Key(key));
+        }
+        let b = state.document.clone();
+
+        for key in ":scenario switch A\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(state.scenarios().graph("A").unwrap(), state.document);
+        assert_eq!(Some("A"), state.scenarios().active());
+
+        state.evaluate(Input::Key(U_LOWER));
+        assert_eq!(b, state.document);
+
+        for key in
+            ":scenario switch B\u{e007}:set readonly\u{e007}:scenario switch A\u{e007}".chars()
+        {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(
+            "The document is read-only",
+            state.take_messages().pop().unwrap().text
+        );
+        assert_eq!(b, state.document);
+        assert_eq!(Some("B"), state.scenarios().active());
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut state = EditorState::new();
+        state.evaluate(Input::Key(I_LOWER));
+        state.evaluate(Input::Key(V_LOWER));
+        state.evaluate(Input::Key(V_LOWER));
+        state.evaluate(Input::Key(ESC));
+        state.evaluate(Input::Key(I_LOWER));
+        state.evaluate(Input::Key(E_LOWER));
+        state.evaluate(Input::Key(DIGIT_0));
+        state.evaluate(Input::Key(COMMA));
+        state.evaluate(Input::Key(DIGIT_1));
+        state.evaluate(Input::Key(ENTER));
+        state.evaluate(Input::Key(ESC));
+
+        let single_edge = single_edge_graph();
+        assert_eq!(EditorMode::Command, state.mode);
+        assert_eq!(single_edge, state.document);
+
+        let mut undid = single_edge_graph();
+        undid.remove_edge(undid.edges.values().next().unwrap().clone());
+        state.evaluate(Input::Key(U_LOWER));
+
+        assert_eq!(EditorMode::Command, state.mode);
+        assert_eq!(undid, state.document);
+
+        state.evaluate(Input::Key(U_UPPER));
+
+        assert_eq!(EditorMode::Command, state.mode);
+        assert_eq!(single_edge, state.document);
+    }
+
+    #[test]
+    fn visual_mode_acts_on_the_selection_in_one_edit() {
+        let mut state = EditorState::new();
+        for key in "ivvve0,1\u{e007}e1,2\u{e007}\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let before = state.document.clone();
+
+        for key in "vv0\u{e007}v2\u{e007}acolor=red\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(EditorMode::Command, state.mode);
+        assert_eq!(Some("red"), state.document.vertices[&0].attribute("color"));
+        assert_eq!(Some("red"), state.document.vertices[&2].attribute("color"));
+        assert_eq!(None, state.document.vertices[&1].attribute("color"));
+        assert_eq!(2, state.history().len());
+
+        for key in "vv1\u{e007}e0\u{e007}y".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let selected: Vec<ElementRef> =
+            state.references().elements(&Reference::Selection).collect();
+        assert_eq!(vec![ElementRef::Vertex(1), ElementRef::Edge(0)], selected);
+
+        for key in "vv1\u{e007}d".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(vec![0, 2], {
+            let mut ids: Vec<i64> = state.document.vertices.keys().copied().collect();
+            ids.sort_unstable();
+            ids
+        });
+        assert!(state.document.edges.is_empty());
+        assert_eq!(3, state.history().len());
+
+        state.evaluate(Input::Key(U_LOWER));
+        state.evaluate(Input::Key(U_LOWER));
+        assert_eq!(before, state.document);
+    }
+
+    #[test]
+    fn macros_are_recorded_and_replayed() {
+        let mut state = EditorState::new();
+        for key in "qaiv\u{1b}q@a@@".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(3, state.document.vertices.len());
+        assert_eq!(3, state.history().len());
+        assert_eq!(
+            "iv\u{1b}".chars().map(Input::Key).collect::<Vec<_>>(),
+            state.recorded_macro('a').unwrap().inputs
+        );
+
+        // A macro replaying itself stops rather than recursing forever.
+        for key in "qbiv\u{1b}@bq@b".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(5, state.document.vertices.len());
+        assert_eq!(EditorMode::Command, state.mode);
+    }
+
+    #[test]
+    fn yanked_subgraphs_are_pasted_with_fresh_ids() {
+        let mut state = EditorState::new();
+        for key in "ivvve0,1\u{e007}e1,2\u{e007}\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        for key in "v\"av0\u{e007}v1\u{e007}yp\"ap".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(EditorMode::Command, state.mode);
+        assert_eq!(7, state.document.vertices.len());
+        assert_eq!(4, state.document.edges.len());
+        assert_eq!(Some(&Edge::new(2, 3, 4)), state.document.edges.get(&2));
+        assert_eq!(3, state.history().len());
+
+        state.evaluate(Input::Key(U_LOWER));
+        assert_eq!(5, state.document.vertices.len());
+
+        for key in "qaq\"ap".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(5, state.document.vertices.len());
+    }
+
+    #[test]
+    fn mapped_keys_stand_for_their_keys() {
+        let mut state = EditorState::new();
+        let path = std::env::temp_dir().join("gri_keymap_test.toml");
+        std::fs::write(&path, "[insert]\nn = \"v\"\n[command]\nV = \"ivv<Esc>\"\n").unwrap();
+        let keymap = format!(":keymap {}\u{e007}", path.display());
+        for key in keymap
+            .chars()
+            .chain("V:map insert T ve0,1<Enter>\u{e007}iT".chars())
+        {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(3, state.document.vertices.len());
+        assert_eq!(1, state.document.edges.len());
+        assert_eq!(EditorMode::Insert, state.mode);
+
+        // The keys a mapping stands for are not mapped again.
+        for key in "\u{1b}:map insert v vv\u{e007}ivn\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(6, state.document.vertices.len());
+
+        for key in ":unmap insert n\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(None, state.keymap().get(KeymapMode::Insert, &['n']));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn multi_key_mappings_wait_for_their_keys() {
+        let mut state = EditorState::new();
+        for key in ":leader <Space>\u{e007}:map insert <leader>vv vvv\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        for key in ":map insert <leader>v v\u{e007}i  v".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        // A space alone is not mapped, so it is evaluated, and the second begins a mapping.
+        assert_eq!(&[' ', 'v'], state.pending_keys());
+        assert_eq!("<Space>v", state.pending_command().unwrap().to_string());
+        assert!(state.document.vertices.is_empty());
+
+        state.evaluate(Input::Key(V_LOWER));
+        assert_eq!(3, state.document.vertices.len());
+        assert!(state.pending_keys().is_empty());
+
+        // Keys that cannot continue a longer mapping resolve to the longest one they begin with.
+        for key in " ve".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(4, state.document.vertices.len());
+        assert_eq!(
+            EditorMode::InsertEdgePending(PendingText::new()),
+            state.mode
+        );
+    }
+
+    #[test]
+    fn pending_keys_time_out() {
+        let mut state = EditorState::new();
+        for key in
+            ":map insert g vv\u{e007}:map insert gv vvv\u{e007}:timeout 500\u{e007}ig".chars()
+        {
+            state.evaluate(Input::Key(key));
+        }
+        state.evaluate(Input::Tick(Duration::from_millis(300)));
+        assert_eq!(&['g'], state.pending_keys());
+        state.evaluate(Input::Tick(Duration::from_millis(200)));
+        assert!(state.pending_keys().is_empty());
+        assert_eq!(2, state.document.vertices.len());
+
+        // Each key restarts the wait.
+        state.evaluate(Input::Key('g'));
+        state.evaluate(Input::Tick(Duration::from_millis(300)));
+        state.evaluate(Input::Key('g'));
+        state.evaluate(Input::Tick(Duration::from_millis(300)));
+        assert_eq!(4, state.document.vertices.len());
+        assert_eq!(&['g'], state.pending_keys());
+
+        state.set_key_timeout(None);
+        state.evaluate(Input::Tick(Duration::from_secs(60)));
+        assert_eq!(&['g'], state.pending_keys());
+        state.evaluate(Input::Key(V_LOWER));
+        assert_eq!(7, state.document.vertices.len());
+    }
+
+    #[test]
+    fn select_the_vertices_of_a_region() {
+        let mut state = EditorState::new();
+        for (id, pos) in [(0, "0,0"), (1, "5,0"), (2, "2,2"), (3, "1,1")].iter() {
+            state
+                .document
+                .vertices
+                .insert(*id, Vertex::new(*id).with_attribute("pos", pos));
+        }
+        for key in "j\u{16}jj".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(EditorMode::RegionPending(0), state.mode);
+        state.evaluate(Input::Key(ENTER));
+        let chosen = [0, 2, 3].iter().copied().map(ElementRef::Vertex).collect();
+        assert_eq!(
+            EditorMode::Visual(Selection {
+                elements: chosen,
+                ..Selection::default()
+            }),
+            state.mode
+        );
+
+        state.evaluate(Input::Key(D_LOWER));
+        assert_eq!(vec![&1], state.document.vertices.keys().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn x_deletes_what_is_under_the_cursor() {
+        let mut state = EditorState::new();
+        for key in "i3ve0,1\u{e007}e1,2\u{e007}\u{1b}jjx".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert!(!state.document.vertices.contains_key(&1));
+        assert!(state.document.edges.is_empty());
+        // The vertex and its edges are removed in one edit.
+        state.evaluate(Input::Key(U_LOWER));
+        assert_eq!(3, state.document.vertices.len());
+        assert_eq!(2, state.document.edges.len());
+
+        for key in "ve1\u{e007}x".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(vec![&0], state.document.edges.keys().collect::<Vec<_>>());
+        assert_eq!(EditorMode::Command, state.mode);
+
+        // Deleting only elements that are not in the document is not an edit.
+        let edits = state.history().len();
+        let missing = [ElementRef::Vertex(7)].iter().copied().collect();
+        state.execute(Command::Delete(missing)).unwrap();
+        assert_eq!(edits, state.history().len());
+        assert_eq!(
+            "Could not find vertex 7",
+            state.take_messages().pop().unwrap().text
+        );
+    }
+
+    #[test]
+    fn operators_act_on_their_targets() {
+        let mut state = EditorState::new();
+        for key in "i4ve0,1\u{e007}e1,2\u{e007}\u{1b}:mark a v3\u{e007}jjdd".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let remaining = |state: &EditorState| {
+            let mut ids: Vec<i64> = state.document.vertices.keys().copied().collect();
+            ids.sort_unstable();
+            ids
+        };
+        assert_eq!(vec![0, 2, 3], remaining(&state));
+        assert!(state.document.edges.is_empty());
+
+        // Deleting yanks, so the vertex can be put back with a fresh id.
+        for key in "py'a".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(vec![0, 2, 3, 4], remaining(&state));
+        assert_eq!(
+            Some(ElementRef::Vertex(3)),
+            state.references().element(&Reference::Selection)
+        );
+
+        for key in "cs".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(EditorMode::Insert, state.mode);
+        assert!(!state.document.vertices.contains_key(&3));
+    }
+
+    #[test]
+    fn motions_move_the_cursor() {
+        let mut state = EditorState::new();
+        for key in "i4ve0,2\u{e007}e2,3\u{e007}\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(None, state.cursor());
+        state.evaluate(Input::Key(J_LOWER));
+        assert_eq!(Some(0), state.cursor());
+        for (key, expected) in [(L_LOWER, 2), (L_LOWER, 3), (K_LOWER, 2), (H_LOWER, 0)].iter() {
+            state.evaluate(Input::Key(*key));
+            assert_eq!(Some(*expected), state.cursor());
+        }
+        for key in "2j".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(Some(2), state.cursor());
+        assert!(!state.set_cursor(7));
+
+        for (keys, expected) in [("G", 3), ("gg", 0), ("gt", 1), ("gt", 3), ("gs", 0)].iter() {
+            for key in keys.chars() {
+                state.evaluate(Input::Key(key));
+            }
+            assert_eq!(Some(*expected), state.cursor());
+        }
+    }
+
+    #[test]
+    fn set_an_attribute_of_the_cursor() {
+        let mut state = EditorState::new();
+        for key in "i3v\u{1b}alabel=hub\u{e007}jjalabel=hub\u{e007}j.".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let label = |state: &EditorState, id: i64| {
+            state.document.vertices[&id]
+                .attribute("label")
+                .map(str::to_string)
+        };
+        assert_eq!(None, label(&state, 0));
+        assert_eq!(Some("hub".to_string()), label(&state, 1));
+        assert_eq!(Some("hub".to_string()), label(&state, 2));
+        // Without a cursor, there is nothing to set.
+        assert_eq!(
+            "No elements to set the attribute of",
+            state.take_messages()[0].text
+        );
+
+        // The typed text is kept to be corrected.
+        for key in "alabel\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(
+            EditorMode::CursorAttributePending("label".into()),
+            state.mode
+        );
+
+        // Setting nothing, or an attribute to the value it has, is not an edit.
+        let edits = state.history().len();
+        let hub = Command::SetAttribute(
+            [ElementRef::Vertex(1)].iter().copied().collect(),
+            "label".to_string(),
+            "hub".to_string(),
+        );
+        let nothing =
+            Command::SetAttribute(BTreeSet::new(), "label".to_string(), "hub".to_string());
+        for command in [hub, nothing] {
+            state.execute(command).unwrap();
+        }
+        assert_eq!(edits, state.history().len());
+    }
+
+    #[test]
+    fn label_a_vertex_as_it_is_created() {
+        let mut state = EditorState::new();
+        for key in "iVdepot\u{e007}Vhub\u{e003}\u{e003}\u{e003}\u{e007}\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let label = |id: i64| state.document.vertices[&id].attribute("label");
+        assert_eq!((Some("depot"), None), (label(0), label(1)));
+    }
+
+    #[test]
+    fn change_the_endpoints_of_an_edge() {
+        let mut state = EditorState::new();
+        for key in "i3ve0,1\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        state
+            .document
+            .edges
+            .insert(0, Edge::new(0, 0, 1).with_attribute("weight", "3"));
+        for key in "c0\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(
+            EditorMode::EdgeEndpointsPending {
+                edge: 0,
+                endpoints: "0,1".into()
+            },
+            state.mode
+        );
+        for key in "\u{e003}9\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!("Could not find vertex 9", state.take_messages()[0].text);
+        for key in "\u{e003}2\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(
+            Some(&Edge::new(0, 0, 2).with_attribute("weight", "3")),
+            state.document.edges.get(&0)
+        );
+        assert_eq!(1, state.document.edges.len());
+        assert_eq!(EditorMode::Insert, state.mode);
+    }
+
+    #[test]
+    fn fan_in_to_the_last_edge_target() {
+        let mut state = EditorState::new();
+        for key in "iE".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!("No vertex has been created", state.take_messages()[0].text);
+        for key in "3vE".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(
+            "No edge has been created to take the target of",
+            state.take_messages()[0].text
+        );
+
+        for key in "e1,0\u{e007}vEvE\u{1b}jjjE".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let mut edges: Vec<(i64, i64)> = state
+            .document
+            .edges
+            .values()
+            .map(|e| (e.source, e.target))
+            .collect();
+        edges.sort_unstable();
+        assert_eq!(vec![(1, 0), (2, 0), (3, 0), (4, 0)], edges);
+    }
+
+    #[test]
+    fn create_an_edge_between_cursor_positions() {
+        let mut state = EditorState::new();
+        // Without a cursor, there is no source to anchor.
+        for key in "i3v\u{1b}e".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!("No vertex under the cursor", state.take_messages()[0].text);
+        assert_eq!(EditorMode::Command, state.mode);
+
+        for key in "jjejj".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(EditorMode::EdgeTargetPending(1), state.mode);
+        assert_eq!(Some(2), state.cursor());
+        for key in "\u{e007}kekk\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(Some(&Edge::new(0, 1, 2)), state.document.edges.get(&0));
+        assert_eq!(1, state.document.edges.len());
+        assert_eq!(EditorMode::Command, state.mode);
+    }
+
+    #[test]
+    fn edge_creation_failures_are_reported() {
+        let mut state = EditorState::new();
+        for key in "ivv\u{1b}ie0,9\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert!(state.document.edges.is_empty());
+        assert_eq!(
+            vec![Message::new(
+                Severity::Error,
+                "Could not find vertex 9".to_string()
+            )],
+            state.take_messages()
+        );
+        // What was typed is kept to be corrected.
+        assert_eq!(EditorMode::InsertEdgePending("0,9".into()), state.mode);
+
+        for key in "\u{e003}\u{e003}\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(
+            "Unable to parse '0' as a source and a target vertex",
+            state.take_messages()[0].text
+        );
+        for key in ",1\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(Some(&Edge::new(0, 0, 1)), state.document.edges.get(&0));
+        assert_eq!(EditorMode::Insert, state.mode);
+    }
+
+    #[test]
+    fn key_events_with_modifiers() {
+        let mut state = EditorState::new();
+        for key in "ivv\u{1b}u".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert!(state.document.vertices.is_empty());
+        let r = KeyEvent::new(KeyCode::Char('r'));
+        state.evaluate(Input::Press(r.with_ctrl()));
+        assert_eq!(2, state.document.vertices.len());
+
+        for code in [KeyCode::Down, KeyCode::Down].iter() {
+            state.evaluate(Input::Press(KeyEvent::new(*code)));
+        }
+        assert_eq!(Some(1), state.cursor());
+        state.evaluate(Input::Press(r.with_alt()));
+        assert_eq!(
+            "Unsupported key: KeyEvent { code: Char('r'), modifiers: Modifiers { ctrl: false, \
+             alt: true, shift: false } }",
+            state.take_messages()[0].text
+        );
+        assert_eq!(EditorMode::Command, state.mode);
+    }
+
+    #[test]
+    fn paste_an_edge_list() {
+        let mut state = EditorState::new();
+        for key in "i3v\u{1b}i".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        state.evaluate(Input::Paste(
+            "0,1\n1,hub\nhub,spoke\n\n 2 , hub\n".to_string(),
+        ));
+        let hub = state.document.vertices[&3].clone();
+        assert_eq!(Some("hub"), hub.attribute("label"));
+        assert_eq!(
+            Some("spoke"),
+            state.document.vertices[&4].attribute("label")
+        );
+        let mut edges: Vec<(i64, i64)> = state
+            .document
+            .edges
+            .values()
+            .map(|e| (e.source, e.target))
+            .collect();
+        edges.sort_unstable();
+        assert_eq!(vec![(0, 1), (1, 3), (2, 3), (3, 4)], edges);
+        assert_eq!(
+            "Pasted 4 edges and 2 new vertices",
+            state.take_messages()[0].text
+        );
+
+        // A malformed line leaves the document as it was.
+        state.evaluate(Input::Paste("0,2\nhub".to_string()));
+        assert_eq!(
+            "Unable to parse 'hub' as a source and a target vertex",
+            state.take_messages()[0].text
+        );
+        assert_eq!(4, state.document.edges.len());
+
+        // The paste is a single edit.
+        for key in "\u{1b}u".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(3, state.document.vertices.len());
+        assert!(state.document.edges.is_empty());
+    }
+
+    #[test]
+    fn views_have_their_own_cursors_and_selections() {
+        use crate::editor::view::Viewport;
+
+        let mut state = EditorState::new();
+        for key in "ivvv\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        state.set_cursor(0);
+        state.add_reference(Reference::Selection, ElementRef::Vertex(1));
+        let split = state.split_view();
+        assert_eq!(split, state.views().active());
+        assert_eq!(Some(0), state.cursor());
+        assert_eq!(vec![ElementRef::Vertex(1)], state.view_selection(split));
+
+        state.set_cursor(2);
+        state.set_viewport(
+            split,
+            Viewport {
+                zoom: 2.0,
+                ..Viewport::default()
+            },
+        );
+        assert!(state.focus_view(0));
+        assert_eq!(Some(0), state.cursor());
+        assert_eq!(Some(2), state.view_cursor(split));
+        assert_eq!(Some(Viewport::default()), state.viewport(0));
+
+        // The cursors of other views follow the document, as the active one does.
+        state.set_cursor(2);
+        for key in "xu".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(Some(2), state.view_cursor(split));
+        state.set_cursor(1);
+        state.evaluate(Input::Key('x'));
+        assert_eq!(Vec::<ElementRef>::new(), state.view_selection(split));
+
+        assert!(state.close_view(0));
+        assert_eq!(split, state.views().active());
+        assert_eq!(Some(2), state.cursor());
+        assert!(!state.close_view(split));
+        assert!(!state.focus_view(0));
+    }
+
+    #[test]
+    fn refer_to_vertices_by_label() {
+        let mut state = EditorState::new();
+        for key in "iValice\u{e007}Vbob\u{e007}ealice, bob\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let edge = |state: &EditorState| {
+            let e = state.document.edges.values().next().unwrap();
+            (e.source, e.target)
+        };
+        assert_eq!((0, 1), edge(&state));
+
+        // A label shared by several vertices does not choose one of them.
+        for key in "Valice\u{e007}ebob,alice\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!("alice labels vertices 0, 2", state.take_messages()[0].text);
+        assert_eq!(1, state.document.edges.len());
+
+        // An id takes precedence over a label.
+        for key in "\u{1b}iV1\u{e007}e1,0\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert!(state
+            .document
+            .edges
+            .values()
+            .any(|e| (e.source, e.target) == (1, 0)));
+    }
+
+    #[test]
+    fn click_and_drag_vertices() {
+        let mut state = EditorState::new();
+        for (id, pos) in [(0, "0,0"), (1, "2,0")].iter() {
+            state
+                .document
+                .vertices
+                .insert(*id, Vertex::new(*id).with_attribute("pos", pos));
+        }
+        let mouse = |state: &mut EditorState, event: fn(Position) -> MouseEvent, x, y| {
+            state.evaluate(Input::Mouse(event(Position::new(x, y))));
+        };
+        mouse(&mut state, MouseEvent::Press, 2.1, 0.2);
+        mouse(&mut state, MouseEvent::Release, 2.1, 0.2);
+        assert_eq!(Some(1), state.cursor());
+
+        mouse(&mut state, MouseEvent::Press, 0.0, 0.1);
+        mouse(&mut state, MouseEvent::Drag, 1.0, 1.0);
+        mouse(&mut state, MouseEvent::Drag, 0.5, 3.0);
+        mouse(&mut state, MouseEvent::Release, 0.5, 3.0);
+        let pos = |state: &EditorState| {
+            state.document.vertices[&0]
+                .attribute("pos")
+                .map(str::to_string)
+        };
+        assert_eq!(Some("0.5,3".to_string()), pos(&state));
+        assert_eq!(Some(0), state.cursor());
+        state.evaluate(Input::Key(U_LOWER));
+        assert_eq!(Some("0,0".to_string()), pos(&state));
+
+        // In Visual mode, a click chooses a vertex.
+        state.evaluate(Input::Key(V_LOWER));
+        mouse(&mut state, MouseEvent::Press, 2.0, 0.0);
+        let chosen = [ElementRef::Vertex(1)].iter().copied().collect();
+        assert_eq!(
+            EditorMode::Visual(Selection {
+                elements: chosen,
+                ..Selection::default()
+            }),
+            state.mode
+        );
+
+        for key in "\u{1b}i".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        mouse(&mut state, MouseEvent::Press, 2.0, 0.0);
+        assert_eq!(Severity::Error, state.take_messages()[0].severity);
+        assert_eq!(EditorMode::Insert, state.mode);
+    }
+
+    #[test]
+    fn retrace_jumps_with_the_jumplist() {
+        let mut state = EditorState::new();
+        for key in "i5v\u{1b}:mark a v3\u{e007}jj".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        // Moving a vertex at a time is not a jump.
+        for key in "G'agg".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(Some(0), state.cursor());
+        for expected in [3, 4, 1].iter() {
+            state.evaluate(Input::Key(CTRL_O));
+            assert_eq!(Some(*expected), state.cursor());
+        }
+        state.evaluate(Input::Key(CTRL_O));
+        assert_eq!("At the oldest jump", state.take_messages()[0].text);
+        for expected in [4, 3, 0].iter() {
+            state.evaluate(Input::Key(TAB));
+            assert_eq!(Some(*expected), state.cursor());
+        }
+
+        // Jumps to removed vertices are skipped.
+        for key in "Gx\u{f}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(Some(0), state.cursor());
+        for key in "'b".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!("Mark not set: b", state.take_messages()[0].text);
+    }
+
+    #[test]
+    fn search_moves_the_cursor_through_matches() {
+        let mut state = EditorState::new();
+        for key in "i4v\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        for (id, label) in [(1, "north"), (3, "northwest")].iter() {
+            let old = state.document.vertices[id].clone();
+            let new = old.clone().with_attribute("label", label);
+            state
+                .apply_interpretation(OpInterpretation::standard_op(vec![
+                    GraphOperation::ModifyVertex { old, new },
+                ]))
+                .unwrap();
+        }
+        let cursor = |state: &EditorState| state.cursor();
+
+        for key in "/north\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(vec![1, 3], state.search_matches());
+        assert_eq!(Some(1), cursor(&state));
+        state.evaluate(Input::Key(N_LOWER));
+        assert_eq!(Some(3), cursor(&state));
+        state.evaluate(Input::Key(N_LOWER));
+        assert_eq!(Some(1), cursor(&state));
+        state.evaluate(Input::Key(N_UPPER));
+        assert_eq!(Some(3), cursor(&state));
+
+        for key in "/2\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(Some(2), cursor(&state));
+    }
+
+    #[test]
+    fn dot_repeats_the_last_change() {
+        let mut state = EditorState::new();
+        state.evaluate(Input::Key(DOT));
+        assert_eq!(Graph::new(), state.document);
+
+        for key in "i2v\u{1b}:pin v0\u{e007}..".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(6, state.document.vertices.len());
+        assert_eq!(3, state.history().len());
+
+        for key in "ie0,1\u{e007}w0\u{e007}+\u{1b}\u{1b}.".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(Some("3"), state.document.edges[&0].attribute("weight"));
+        state.evaluate(Input::Key(U_LOWER));
+        assert_eq!(Some("2"), state.document.edges[&0].attribute("weight"));
+    }
+
+    #[test]
+    fn an_insert_session_is_undone_at_once() {
+        let mut state = EditorState::new();
+        for key in "ivvvve0,1\u{e007}w0\u{e007}+\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        // Edits made through the API while in Insert mode are not part of the session.
+        state.import_graph(&single_edge_graph());
+        for key in "v\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(3, state.history().len());
+
+        state.evaluate(Input::Key(U_LOWER));
+        assert_eq!(6, state.document.vertices.len());
+        state.evaluate(Input::Key(U_LOWER));
+        assert_eq!(4, state.document.vertices.len());
+        state.evaluate(Input::Key(U_LOWER));
+        assert_eq!(Graph::new(), state.document);
+    }
+
+    #[test]
+    fn explicit_undo_groups_nest() {
+        let mut state = EditorState::new();
+        state.begin_undo_group();
+        state.import_graph(&single_edge_graph());
+        state.begin_undo_group();
+        state.import_graph(&single_edge_graph());
+        state.end_undo_group();
+        for key in "iv\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        state.end_undo_group();
+        state.import_graph(&single_edge_graph());
+        assert_eq!(2, state.history().len());
+
+        state.evaluate(Input::Key(U_LOWER));
+        assert_eq!(5, state.document.vertices.len());
+        state.evaluate(Input::Key(U_LOWER));
+        assert_eq!(Graph::new(), state.document);
+    }
+
+    #[test]
+    fn ids_of_deleted_branches_are_recycled() {
+        let mut state = EditorState::new();
+        for key in "iv\u{1b}iv\u{1b}iv\u{1b}uu:ids recycle on\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        // Vertices 1 and 2 can still be redone, so their ids are not reused.
+        assert_eq!((3, 0), state.next_ids());
+
+        for key in ":history delete-branch 3\u{e007}iv\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert!(state.document.vertices.contains_key(&1));
+        assert_eq!((2, 0), state.next_ids());
+    }
+
+    #[test]
+    fn editors_for_existing_documents() {
+        let mut graph = Graph::new();
+        graph.add_vertex(Vertex::new(3));
+        graph.add_vertex(Vertex::new(7));
+        graph.add_edge(Edge::new(5, 3, 7));
+        let mut state = EditorState::from_graph(graph.clone());
+        assert_eq!((8, 6), state.next_ids());
+        assert!(state.history().is_empty());
+        for key in "iv\u{1b}u".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(graph, state.document);
+
+        let mut edited = EditorState::new();
+        for key in "iv\u{1b}iv\u{1b}u".chars() {
+            edited.evaluate(Input::Key(key));
+        }
+        let mut state = EditorState::from_document_with_history(
+            edited.document.clone(),
+            edited.history.clone(),
+        );
+        assert_eq!((2, 0), state.next_ids());
+        state.execute(Command::Redo).unwrap();
+        assert!(state.document.vertices.contains_key(&1));
+    }
+
+    #[test]
+    fn id_allocators_can_be_replaced() {
+        let mut state = EditorState::new();
+        for key in "iv\u{1b}iv\u{1b}u".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        // The undone vertex can be redone, so a new allocator does not hand out its id.
+        state.set_id_allocators(
+            Box::new(Sequential::default()),
+            Box::new(Sequential::default()),
+        );
+        assert_eq!((2, 0), state.next_ids());
+
+        state.set_id_allocators(Box::new(Prefixed::new(1)), Box::new(Random::with_seed(0)));
+        for key in "iv\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let vertex = 1 << 32;
+        assert!(state.document.vertices.contains_key(&vertex));
+        state
+            .execute(Command::AddEdgeBetween("0".to_string(), vertex.to_string()))
+            .unwrap();
+        let edge = state.document.edges.values().next().unwrap();
+        assert_eq!(Random::with_seed(0).peek(), edge.id);
+
+        for key in ":ids recycle on\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(
+            "The id allocators do not recycle ids",
+            state.take_messages()[0].text
+        );
+    }
+
+    #[test]
+    fn compact_ids_renumbers_the_document() {
+        let mut state = EditorState::new();
+        for key in "iv\u{1b}iv\u{1b}iv\u{1b}ie0,2\u{e007}\u{1b}:mark a v2\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        state
+            .apply_interpretation(OpInterpretation::standard_op(vec![
+                GraphOperation::RemoveVertex(Vertex::new(1)),
+            ]))
+            .unwrap();
+
+        let mapping = state.compact_ids();
+        assert_eq!(Some(1), mapping.vertex(2));
+        assert_eq!(single_edge_graph(), state.document);
+        assert_eq!(
+            Some(ElementRef::Vertex(1)),
+            state.references.element(&Reference::Mark('a'))
+        );
+
+        state.evaluate(Input::Key(U_LOWER));
+        let mut ids: Vec<i64> = state.document.vertices.keys().copied().collect();
+        ids.sort_unstable();
+        assert_eq!(vec![0, 2], ids);
+    }
+
+    #[test]
+    fn rejected_compaction_leaves_references_alone() {
+        let mut state = EditorState::new();
+        state.document.add_vertex(Vertex::new(5));
+        state.document.add_vertex(Vertex::new(9));
+        state.set_cursor(9);
+        for key in ":set readonly\u{e007}:ids compact\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(
+            vec!["The document is read-only"],
+            state
+                .take_messages()
+                .into_iter()
+                .map(|message| message.text)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some(9), state.cursor());
+
+        assert_eq!(IdMapping::default(), state.compact_ids());
+        assert_eq!(Some(9), state.cursor());
+        let mut ids: Vec<i64> = state.document.vertices.keys().copied().collect();
+        ids.sort_unstable();
+        assert_eq!(vec![5, 9], ids);
+    }
+
+    #[test]
+    fn undo_all_and_redo_to_tip() {
+        let mut state = EditorState::new();
+        // Two branches after the first vertex; the one adding vertex 2 is the most recent.
+        for key in "iv\u{1b}iv\u{1b}uiv\u{1b}iv\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let tip = state.history.current();
+
+        for key in ":undo-all\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(Graph::new(), state.document);
+        assert_eq!(state.history.root(), state.history.current());
+
+        for key in ":redo-all\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(tip, state.history.current());
+        assert_eq!(
+            [0, 2, 3].iter().copied().collect::<BTreeSet<_>>(),
+            state
+                .document
+                .vertices
+                .keys()
+                .copied()
+                .collect::<BTreeSet<_>>()
+        );
+        // Both moves are in the history, not new edits.
+        assert_eq!(4, state.history().len());
+    }
+
+    #[test]
+    fn redo_into_an_older_branch() {
+        let mut state = EditorState::new();
+        // Two branches after the first vertex: one adding vertex 1, then one adding vertex 2.
+        for key in "iv\u{1b}iv\u{1b}uiv\u{1b}u".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(2, state.history_children().len());
+
+        state.evaluate(Input::Key(B_UPPER));
+        assert_eq!(EditorMode::RedoBranchPending("".to_string()), state.mode);
+        for key in "1\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(EditorMode::Command, state.mode);
+        assert!(state.document.vertices.contains_key(&1));
+        assert!(!state.document.vertices.contains_key(&2));
+
+        // A branch that does not exist leaves the document as it is.
+        state.evaluate(Input::Key(U_LOWER));
+        for key in "B3\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(1, state.document.vertices.len());
+    }
+
+    #[test]
+    fn restore_a_bookmark_on_another_branch() {
+        let mut state = EditorState::new();
+        let keys =
+            "iv\u{1b}iv\u{1b}:history bookmark two\u{e007}uuiv\u{1b}iv\u{1b}ie2,3\u{e007}\u{1b}";
+        for key in keys.chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let bookmarked = state.history.bookmarked("two").unwrap();
+        assert_eq!(
+            3,
+            state.document.vertices.len() + state.document.edges.len()
+        );
+
+        for key in ":history restore two\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(bookmarked, state.history.current());
+        let mut expected = Graph::new();
+        expected.add_vertex(Vertex::new(0));
+        expected.add_vertex(Vertex::new(1));
+        assert_eq!(expected, state.document);
+
+        // The jump is a move in the history, not a new edit.
+        assert_eq!(5, state.history().len());
+        state.evaluate(Input::Key(U_LOWER));
+        assert_eq!(1, state.document.vertices.len());
+    }
+
+    #[test]
+    fn goto_any_history_node() {
+        let mut state = EditorState::new();
+        for key in "iv\u{1b}iv\u{1b}ie0,1\u{e007}\u{1b}uuuiv\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let edge_node = state.history.node(4).unwrap();
+        assert!(state.goto_history_node(edge_node));
+        assert_eq!(single_edge_graph(), state.document);
+        assert_eq!(edge_node, state.history.current());
+
+        for key in ":history goto 5\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(vec![&2], state.document.vertices.keys().collect::<Vec<_>>());
+
+        state.history = History::new();
+        assert!(!state.goto_history_node(edge_node));
+    }
+
+    #[test]
+    fn net_diff_between_history_nodes() {
+        let mut state = EditorState::new();
+        // Add two vertices and an edge, then contract the edge, which removes all but vertex 0.
+        for key in "ivve0,1\u{e007}C0\u{e007}\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let root = state.history.root();
+        let tip = state.history.current();
+
+        let diff = state.history_diff(root, tip).unwrap();
+        assert_eq!(
+            vec![GraphOperation::AddVertex(Vertex::new(0))],
+            diff.operations
+        );
+        let back = state.history_diff(tip, root).unwrap();
+        assert_eq!(1, back.summary().removed_vertices);
+        assert_eq!(Some(Graph::new()), state.document_at(root));
+        assert!(state.history_diff(tip, tip).unwrap().operations.is_empty());
+    }
+
+    #[test]
+    fn merge_two_history_branches() {
+        let mut state = EditorState::new();
+        // Vertex 1 is added on one branch and vertex 2 on another, both after vertex 0.
+        for key in "iv\u{1b}iv\u{1b}uiv\u{1b}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let other = state.history.node(3).unwrap();
+        assert_eq!(
+            Some(Vec::new()),
+            state.merge_history(other, state.history.current())
+        );
+        assert_eq!(
+            (0..3).collect::<BTreeSet<_>>(),
+            state
+                .document
+                .vertices
+                .keys()
+                .copied()
+                .collect::<BTreeSet<_>>()
+        );
+
+        // The merge is an edit of its own, and can be undone.
+        assert_eq!(4, state.history().len());
+        state.evaluate(Input::Key(U_LOWER));
+        assert_eq!(
+            [0, 2].iter().copied().collect::<BTreeSet<_>>(),
+            state
+                .document
+                .vertices
+                .keys()
+                .copied()
+                .collect::<BTreeSet<_>>()
+        );
+        for key in ":history merge 3\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(3, state.document.vertices.len());
+    }
+
+    #[test]
+    fn counted_undo_and_redo_move_in_one_step() {
+        let mut state = EditorState::new();
+        for key in format!("{}3u", "iv\u{1b}".repeat(4)).chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(vec![&0], state.document.vertices.keys().collect::<Vec<_>>());
+
+        for key in "2\u{12}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(3, state.document.vertices.len());
+        assert_eq!(state.history.node(4), Some(state.history.current()));
+
+        // Counts beyond either end of the history stop there.
+        for key in "99u".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert!(state.document.vertices.is_empty());
+        for key in "99U".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(4, state.document.vertices.len());
+    }
+
+    #[test]
+    fn history_limit_collapses_old_edits() {
+        let mut state = EditorState::new();
+        for key in format!(":history limit 2\u{e007}{}", "iv\u{1b}".repeat(4)).chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert_eq!(2, state.history().len());
+        assert_eq!(4, state.document.vertices.len());
+
+        // The oldest edits are undone together, and the newest on its own.
+        state.evaluate(Input::Key(U_LOWER));
+        assert_eq!(3, state.document.vertices.len());
+        state.evaluate(Input::Key(U_LOWER));
+        assert!(state.document.vertices.is_empty());
     }
 }
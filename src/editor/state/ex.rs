@@ -0,0 +1,681 @@
+use std::time::Duration;
+use std::time::SystemTime;
+
+use crate::anonymize::Anonymization;
+use crate::computed::ComputedAttribute;
+use crate::editor::export_filter::ElementFilter;
+use crate::editor::export_filter::ExportFilter;
+use crate::editor::fold::FoldKind;
+use crate::editor::history::History;
+use crate::editor::history::HistoryLimit;
+use crate::editor::hooks::Hook;
+use crate::editor::keymap::key_notation;
+use crate::editor::keymap::KeymapMode;
+use crate::editor::message::Message;
+use crate::editor::mode::ModalOperation;
+use crate::editor::options::NAMES as OPTION_NAMES;
+use crate::editor::plugin::PluginOperation;
+use crate::editor::references::ElementRef;
+use crate::editor::references::Reference;
+use crate::editor::state::parse_element;
+use crate::editor::state::EditorState;
+use crate::editor::state::OpInterpretation;
+use crate::schema::ElementKind;
+use crate::schema::Schema;
+
+impl EditorState {
+    fn list_autocommands(&mut self, hook: Option<Hook>) {
+        let listed: Vec<String> = self
+            .hooks
+            .autocommands()
+            .into_iter()
+            .filter(|(at, _, _)| hook.is_none_or(|hook| hook == *at))
+            .map(|(at, command, owner)| match owner {
+                Some(plugin) => format!("{} {} (plugin {})", at, command, plugin),
+                None => format!("{} {}", at, command),
+            })
+            .collect();
+        listed.into_iter().for_each(|line| self.info(line));
+    }
+
+    // Carry out the settings of `:set`, or list every option without any.
+    fn interpret_set_command<'a, I: Iterator<Item = &'a str>>(&mut self, words: I) {
+        let mut words = words.peekable();
+        if words.peek().is_none() {
+            let listed: Vec<String> = OPTION_NAMES
+                .iter()
+                .filter_map(|name| self.options.apply(&format!("{}?", name)).ok().flatten())
+                .collect();
+            return listed.into_iter().for_each(|line| self.info(line));
+        }
+        for setting in words {
+            match self.options.apply(setting) {
+                Ok(Some(shown)) => self.info(shown),
+                Ok(None) => {}
+                Err(err) => return self.error(err.to_string()),
+            }
+        }
+    }
+
+    // Map a key with `:map <mode> <key> <keys>`, or list mappings with `:map [mode]`.
+    fn interpret_map_command<'a, I: Iterator<Item = &'a str>>(&mut self, mut words: I) {
+        let mode = match words.next().map(|name| (name, KeymapMode::parse(name))) {
+            Some((_, Some(mode))) => Some(mode),
+            Some((name, None)) => return self.error(format!("Unknown mode: {}", name)),
+            None => None,
+        };
+        let keys = match words.next().map(|keys| self.keymap.parse_keys(keys)) {
+            Some(Ok(keys)) => keys,
+            Some(Err(err)) => return self.error(err),
+            None => {
+                let listed: Vec<String> = self
+                    .keymap
+                    .mappings()
+                    .filter(|(mapped_mode, _, _)| mode.is_none_or(|mode| mode == *mapped_mode))
+                    .map(|(mapped_mode, keys, to)| {
+                        format!(
+                            "{} {} {}",
+                            mapped_mode,
+                            key_notation(keys),
+                            key_notation(to)
+                        )
+                    })
+                    .collect();
+                return listed.into_iter().for_each(|line| self.info(line));
+            }
+        };
+        let to = words.collect::<Vec<&str>>().join(" ");
+        let owner = keys
+            .split_first()
+            .filter(|(first, _)| **first == self.keymap.leader())
+            .and_then(|(_, namespaced)| self.plugins.namespace_owner(namespaced))
+            .map(str::to_string);
+        match (mode, self.keymap.parse_keys(&to)) {
+            (Some(mode), Ok(to)) => {
+                if let Some(owner) = owner {
+                    self.warn(format!(
+                        "{} is in the leader namespace of plugin {}",
+                        key_notation(&keys),
+                        owner
+                    ));
+                }
+                self.keymap.map(mode, keys, to)
+            }
+            (_, Err(err)) => self.error(err),
+            (None, _) => {}
+        }
+    }
+
+    fn show_bindings(&mut self, mode: KeymapMode) {
+        for binding in self.bindings(mode) {
+            self.info(binding.to_string());
+        }
+    }
+
+    // Fold the given vertices behind the first of them.
+    fn fold_cluster<'a, I: Iterator<Item = &'a str>>(&mut self, vertices: I) {
+        let mut ids = Vec::new();
+        for vertex in vertices {
+            match self.resolve_vertex(vertex) {
+                Ok(id) => ids.push(id),
+                Err(err) => return self.error(err.to_string()),
+            }
+        }
+        match ids.first() {
+            Some(anchor) => self.folds.create(
+                &self.document,
+                *anchor,
+                FoldKind::Cluster(ids.iter().copied().collect()),
+            ),
+            None => self.error("No vertices to fold".to_string()),
+        }
+    }
+
+    fn reference_element(&mut self, reference: Reference, element: &str) {
+        match parse_element(element) {
+            Some((kind, id)) => {
+                if !self.add_reference(reference, ElementRef::new(kind, id)) {
+                    self.error(format!("Could not find {}", element));
+                }
+            }
+            None => self.error(format!("Not an element: {}", element)),
+        }
+    }
+
+    pub(super) fn interpret_ex_command(&mut self, command: &str) -> OpInterpretation {
+        let mut words = command.split_whitespace();
+        match words.next() {
+            Some("infer-schema") => {
+                let schema = Schema::infer(&self.document);
+                self.info(format!(
+                    "Inferred {} vertex and {} edge attribute types",
+                    schema.vertex_attributes.len(),
+                    schema.edge_attributes.len()
+                ));
+                self.schema = Some(schema);
+            }
+            Some("compute") => match words.next() {
+                Some(name) => match ComputedAttribute::builtin(name) {
+                    Some(attribute) => self.computed.declare(name, attribute),
+                    None => self.error(format!("Unknown computed attribute: {}", name)),
+                },
+                None => self.error("No computed attribute name".to_string()),
+            },
+            Some("write") | Some("w") => match words.next() {
+                Some(path) => match parse_write_options(words) {
+                    Ok((filter, anonymization)) => {
+                        self.write_document(path, &filter, anonymization.as_ref())
+                    }
+                    Err(err) => self.error(err),
+                },
+                None => self.error("No file name".to_string()),
+            },
+            Some("fold-cluster") => self.fold_cluster(words),
+            Some("ids") => match (words.next(), words.next()) {
+                (Some("compact"), _) => match self.try_compact_ids() {
+                    Ok(mapping) => self.info(format!(
+                        "Renumbered {} vertices and {} edges",
+                        mapping
+                            .vertices
+                            .iter()
+                            .filter(|(old, new)| old != new)
+                            .count(),
+                        mapping.edges.iter().filter(|(old, new)| old != new).count()
+                    )),
+                    Err(err) => self.error(err.to_string()),
+                },
+                (Some("recycle"), Some("on")) => {
+                    if !self.set_id_recycling(true) {
+                        self.error("The id allocators do not recycle ids".to_string());
+                    }
+                }
+                (Some("recycle"), Some("off")) => {
+                    self.set_id_recycling(false);
+                }
+                (Some("recycle"), _) => self.error("Usage: ids recycle on|off".to_string()),
+                (Some(other), _) => self.error(format!("Unknown ids command: {}", other)),
+                (None, _) => self.info(format!(
+                    "Next vertex id {}, next edge id {}",
+                    self.vertex_ids.peek(),
+                    self.edge_ids.peek()
+                )),
+            },
+            Some("undo-all") => return self.interpret_or_report(ModalOperation::UndoAll),
+            Some("redo-all") => return self.interpret_or_report(ModalOperation::RedoToTip),
+            #[cfg(feature = "json")]
+            Some("recover") => match words.next() {
+                None => return self.recover(false),
+                Some("discard") => return self.recover(true),
+                Some(other) => self.error(format!("Unknown recover option: {}", other)),
+            },
+            #[cfg(feature = "json")]
+            Some("oplog") => match words.next() {
+                Some("off") => self.stop_op_log(),
+                Some(path) => {
+                    if let Err(err) = self.start_op_log(path) {
+                        self.error(format!("Could not log to {}: {}", path, err));
+                    }
+                }
+                None => match &self.op_log {
+                    Some(log) => self.info(format!("Logging to {}", log.path())),
+                    None => self.info("Not logging".to_string()),
+                },
+            },
+            #[cfg(feature = "json")]
+            Some("trace") => match words.next() {
+                Some("off") => self.stop_trace(),
+                Some(path) => {
+                    if let Err(err) = self.start_trace(path) {
+                        self.error(format!("Could not trace to {}: {}", path, err));
+                    }
+                }
+                None => match &self.trace {
+                    Some(trace) => self.info(format!("Tracing to {}", trace.path())),
+                    None => self.info("Not tracing".to_string()),
+                },
+            },
+            Some("alias") => match (words.next(), words.next()) {
+                (Some(name), Some(element)) => {
+                    self.reference_element(Reference::Alias(name.to_string()), element)
+                }
+                _ => self.error("Usage: alias <name> <element>".to_string()),
+            },
+            Some("mark") => match (
+                words.next().map(|m| m.chars().collect::<Vec<_>>()),
+                words.next(),
+            ) {
+                (Some(mark), Some(element)) if mark.len() == 1 => {
+                    self.reference_element(Reference::Mark(mark[0]), element)
+                }
+                _ => self.error("Usage: mark <letter> <element>".to_string()),
+            },
+            Some("map") => self.interpret_map_command(words),
+            Some("set") => self.interpret_set_command(words),
+            // Like vim's, `:autocmd Hook command` runs an ex command at a hook, in which `<id>`
+            // stands for the element added or removed and `<path>` for the path saved to.
+            Some("autocmd") => match words.next() {
+                None => self.list_autocommands(None),
+                Some(name) => match Hook::parse(name) {
+                    Some(hook) => {
+                        let command = words.collect::<Vec<_>>().join(" ");
+                        if command.is_empty() {
+                            self.list_autocommands(Some(hook));
+                        } else {
+                            self.add_autocommand(hook, &command);
+                        }
+                    }
+                    None => self.error(format!("Unknown hook: {}", name)),
+                },
+            },
+            Some("autocmd!") => match words.next().map(|name| (name, Hook::parse(name))) {
+                None => self.hooks.remove_autocommands(None),
+                Some((_, Some(hook))) => self.hooks.remove_autocommands(Some(hook)),
+                Some((name, None)) => self.error(format!("Unknown hook: {}", name)),
+            },
+            // Like vim's, list the messages of the history again, with their severities.
+            Some("messages") => match words.next() {
+                None => {
+                    let listed: Vec<Message> = self.message_history.iter().cloned().collect();
+                    listed.into_iter().for_each(|message| self.show(message));
+                }
+                Some("clear") => self.message_history.clear(),
+                Some(other) => self.error(format!("Unknown messages command: {}", other)),
+            },
+            Some("help") => match words.next().map(|name| (name, KeymapMode::parse(name))) {
+                Some((_, Some(mode))) => self.show_bindings(mode),
+                Some((name, None)) => self.error(format!("Unknown mode: {}", name)),
+                None => self.show_bindings(KeymapMode::Command),
+            },
+            Some("unmap") => match (words.next().and_then(KeymapMode::parse), words.next()) {
+                (Some(mode), Some(keys)) => match self.keymap.parse_keys(keys) {
+                    Ok(parsed) if self.keymap.unmap(mode, &parsed) => {}
+                    _ => self.error(format!("No mapping of {} in {} mode", keys, mode)),
+                },
+                _ => self.error("Usage: unmap <mode> <keys>".to_string()),
+            },
+            Some("leader") => match words.next().map(|key| self.keymap.parse_keys(key)) {
+                Some(Ok(key)) if key.len() == 1 => self.keymap.set_leader(key[0]),
+                Some(_) => self.error("Expected a single leader key".to_string()),
+                None => self.info(key_notation(&[self.keymap.leader()])),
+            },
+            Some("timeout") => match words.next() {
+                Some("off") => self.key_timeout = None,
+                Some(millis) => match millis.parse() {
+                    Ok(millis) => self.key_timeout = Some(Duration::from_millis(millis)),
+                    Err(_) => self.error("Usage: timeout <milliseconds>|off".to_string()),
+                },
+                None => match self.key_timeout {
+                    Some(timeout) => self.info(format!("{}ms", timeout.as_millis())),
+                    None => self.info("off".to_string()),
+                },
+            },
+            Some("keymap") => match words.next() {
+                Some(path) => {
+                    if let Err(err) = self.load_keymap(path) {
+                        self.error(format!("Could not read keymap {}: {}", path, err));
+                    }
+                }
+                None => self.error("No file name".to_string()),
+            },
+            Some("pin") => {
+                words.for_each(|element| self.reference_element(Reference::Pin, element))
+            }
+            Some("unpin") => {
+                for element in words {
+                    match parse_element(element) {
+                        Some((kind, id)) => {
+                            self.references
+                                .remove(&Reference::Pin, ElementRef::new(kind, id));
+                        }
+                        None => self.error(format!("Not an element: {}", element)),
+                    }
+                }
+            }
+            Some("scenario") => return self.interpret_scenario_command(words),
+            Some("history") => return self.interpret_history_command(words),
+            Some("edit") | Some("e") => match words.next() {
+                Some(path) => self.open_document(path, false),
+                None => self.error("No file name".to_string()),
+            },
+            // Like vim's `:view`, open a document with the `readonly` option set.
+            Some("view") => match words.next() {
+                Some(path) => self.open_document(path, true),
+                None => self.error("No file name".to_string()),
+            },
+            Some("read") | Some("r") => match words.next() {
+                Some(path) => return self.read_document(path),
+                None => self.error("No file name".to_string()),
+            },
+            Some("layout") => match words.next() {
+                Some(path) => return self.read_layout(path),
+                None => self.error("No file name".to_string()),
+            },
+            Some(other) => match self.plugins.command_owner(other) {
+                Some(plugin) => {
+                    let operation = PluginOperation {
+                        plugin: plugin.to_string(),
+                        name: other.to_string(),
+                        args: words.map(str::to_string).collect(),
+                    };
+                    return self.interpret_or_report(ModalOperation::Plugin(operation));
+                }
+                None => self.error(format!("Unknown command: {}", other)),
+            },
+            None => {}
+        }
+        OpInterpretation::default()
+    }
+
+    fn interpret_history_command<'a, I: Iterator<Item = &'a str>>(
+        &mut self,
+        mut words: I,
+    ) -> OpInterpretation {
+        match (words.next(), words.next()) {
+            (Some("branches"), _) => {
+                let now = SystemTime::now();
+                for branch in self.history.branches() {
+                    let age = now
+                        .duration_since(branch.last_touched)
+                        .map_or(0, |d| d.as_secs());
+                    self.info(format!(
+                        "Branch {}: {} edits, ~{} bytes, last touched {}s ago",
+                        branch.id, branch.node_count, branch.estimated_bytes, age
+                    ));
+                }
+            }
+            (Some("delete-branch"), Some(id)) => match id.parse::<usize>() {
+                Ok(id) => match self.history.delete_branch(id) {
+                    Ok(branch) => self.info(format!(
+                        "Deleted {} edits, ~{} bytes",
+                        branch.node_count, branch.estimated_bytes
+                    )),
+                    Err(err) => self.error(err.to_string()),
+                },
+                Err(_) => self.error(format!("Invalid history node id: {}", id)),
+            },
+            (Some("delete-branch"), None) => self.error("No history node id".to_string()),
+            (Some("attr"), Some(element)) => match (parse_element(element), words.next()) {
+                (Some((kind, id)), Some(key)) => self.show_attribute_history(kind, id, key),
+                (None, _) => self.error(format!("Invalid element: {}", element)),
+                (_, None) => self.error("No attribute name".to_string()),
+            },
+            (Some("attr"), None) => self.error("No element".to_string()),
+            (Some("dot"), path) => {
+                let dot = self.history.to_dot();
+                match path {
+                    Some(path) => {
+                        if let Err(err) = std::fs::write(path, dot) {
+                            self.error(format!("Could not write {}: {}", path, err));
+                        }
+                    }
+                    None => self.info(dot),
+                }
+            }
+            (Some("log"), _) => {
+                // The edits that make up the current document, oldest first, like a change log.
+                for view in self
+                    .history
+                    .view()
+                    .into_iter()
+                    .filter(|view| view.is_applied)
+                {
+                    if view.parent.is_some() {
+                        self.info(format!(
+                            "node {}: {}{}",
+                            view.id,
+                            view.summary,
+                            view.message
+                                .map_or(String::new(), |message| format!(": {}", message))
+                        ));
+                    }
+                }
+            }
+            (Some("children"), _) => {
+                let now = SystemTime::now();
+                for (i, child) in self.history_children().into_iter().enumerate() {
+                    let entry = self.history.entry(child).unwrap();
+                    let age = now
+                        .duration_since(entry.last_touched)
+                        .map_or(0, |d| d.as_secs());
+                    self.info(format!(
+                        "{}: node {}, {}, last touched {}s ago{}",
+                        i + 1,
+                        History::id_of(child),
+                        entry.diff().summary(),
+                        age,
+                        entry
+                            .message
+                            .as_ref()
+                            .map_or(String::new(), |message| format!(": {}", message))
+                    ));
+                }
+            }
+            (Some("message"), Some(first)) => {
+                let message = std::iter::once(first)
+                    .chain(words)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let current = self.history.current();
+                self.history.set_message(current, Some(message));
+            }
+            (Some("message"), None) => {
+                let current = self.history.current();
+                match self.history.entry(current).and_then(|e| e.message.clone()) {
+                    Some(message) => self.info(message.to_string()),
+                    None => self.error(format!("No message for node {}", History::id_of(current))),
+                }
+            }
+            (Some("delete-message"), _) => {
+                let current = self.history.current();
+                self.history.set_message(current, None);
+            }
+            (Some("limit"), Some("off")) => self.history.set_limit(HistoryLimit::default()),
+            (Some("limit"), Some(nodes)) => match nodes.parse::<usize>() {
+                Ok(nodes) => self.history.set_limit(HistoryLimit {
+                    max_nodes: Some(nodes),
+                    ..self.history.limit()
+                }),
+                Err(_) => self.error(format!("Invalid number of history nodes: {}", nodes)),
+            },
+            (Some("limit"), None) => {
+                let limit = self.history.limit();
+                self.info(format!(
+                    "{} edits (limit {:?}), ~{} bytes (limit {:?})",
+                    self.history.len(),
+                    limit.max_nodes,
+                    self.history.estimated_bytes(),
+                    limit.max_bytes
+                ));
+            }
+            (Some("goto"), Some(id)) => match id
+                .parse::<usize>()
+                .ok()
+                .and_then(|id| self.history.node(id))
+            {
+                Some(node) => return self.history_jump(node),
+                None => self.error(format!("Invalid history node id: {}", id)),
+            },
+            (Some("goto"), None) => self.error("No history node id".to_string()),
+            (Some("diff"), Some(from)) => {
+                let node = |id: &str| {
+                    id.parse::<usize>()
+                        .ok()
+                        .and_then(|id| self.history.node(id))
+                };
+                let to = words.next();
+                match (node(from), to.map_or(Some(self.history.current()), node)) {
+                    (Some(from), Some(to)) => {
+                        if let Some(diff) = self.history_diff(from, to) {
+                            self.info(format!("{}", diff.summary()));
+                        }
+                    }
+                    (None, _) => self.error(format!("Invalid history node id: {}", from)),
+                    (_, None) => {
+                        self.error(format!("Invalid history node id: {}", to.unwrap_or("")))
+                    }
+                }
+            }
+            (Some("diff"), None) => self.error("No history node id".to_string()),
+            (Some("merge"), Some(ours)) => {
+                let node = |id: &str| {
+                    id.parse::<usize>()
+                        .ok()
+                        .and_then(|id| self.history.node(id))
+                };
+                let theirs = words.next();
+                match (
+                    node(ours),
+                    theirs.map_or(Some(self.history.current()), node),
+                ) {
+                    (Some(ours), Some(theirs)) => {
+                        if let Some((interpretation, conflicts)) = self.history_merge(ours, theirs)
+                        {
+                            for conflict in conflicts {
+                                self.warn(conflict.to_string());
+                            }
+                            return interpretation;
+                        }
+                    }
+                    (None, _) => self.error(format!("Invalid history node id: {}", ours)),
+                    (_, None) => {
+                        self.error(format!("Invalid history node id: {}", theirs.unwrap_or("")))
+                    }
+                }
+            }
+            (Some("merge"), None) => self.error("No history node id".to_string()),
+            (Some("bookmark"), Some(name)) => self.history.bookmark(name),
+            (Some("bookmarks"), _) => {
+                let current = self.history.current();
+                let listed: Vec<String> = self
+                    .history
+                    .bookmarks()
+                    .map(|(name, node)| {
+                        let marker = if node == current { "*" } else { " " };
+                        format!("{} {}: node {}", marker, name, History::id_of(node))
+                    })
+                    .collect();
+                listed.into_iter().for_each(|line| self.info(line));
+            }
+            (Some("restore"), Some(name)) => match self.history.bookmarked(name) {
+                Some(node) => return self.history_jump(node),
+                None => self.error(format!("No bookmark {}", name)),
+            },
+            (Some("delete-bookmark"), Some(name)) => {
+                if !self.history.remove_bookmark(name) {
+                    self.error(format!("No bookmark {}", name));
+                }
+            }
+            (Some("bookmark"), None)
+            | (Some("restore"), None)
+            | (Some("delete-bookmark"), None) => self.error("No bookmark name".to_string()),
+            (Some(other), _) => self.error(format!("Unknown history command: {}", other)),
+            (None, _) => self.error("No history command".to_string()),
+        }
+        OpInterpretation::default()
+    }
+
+    fn interpret_scenario_command<'a, I: Iterator<Item = &'a str>>(
+        &mut self,
+        mut words: I,
+    ) -> OpInterpretation {
+        match (words.next(), words.next(), words.next()) {
+            (Some("save"), Some(name), _) => self.scenarios.save(name, &self.document),
+            (Some("switch"), Some(name), _) => match self.scenarios.graph(name) {
+                Some(graph) => {
+                    // Applied here, so that the scenario is active only if the switch is.
+                    let switch =
+                        OpInterpretation::standard_op(self.document.difference(&graph).operations);
+                    match self.apply_interpretation(switch) {
+                        Ok(()) => self.scenarios.set_active(name),
+                        Err(err) => self.error(err.to_string()),
+                    }
+                }
+                None => self.error(format!("No scenario {}", name)),
+            },
+            (Some("delete"), Some(name), _) => {
+                if !self.scenarios.remove(name) {
+                    self.error(format!("No scenario {}", name));
+                }
+            }
+            (Some("base"), _, _) => self.scenarios.rebase(&self.document),
+            (Some("list"), _, _) => {
+                let listed: Vec<String> = self
+                    .scenarios
+                    .names()
+                    .map(|name| {
+                        let marker = if Some(name) == self.scenarios.active() {
+                            "*"
+                        } else {
+                            " "
+                        };
+                        format!("{} {}", marker, name)
+                    })
+                    .collect();
+                listed.into_iter().for_each(|line| self.info(line));
+            }
+            (Some("compare"), Some(from), Some(to)) => match self.scenarios.compare(from, to) {
+                Some(comparison) => self.info(format!("{} -> {}: {:?}", from, to, comparison)),
+                None => self.error(format!("No scenarios {} and {}", from, to)),
+            },
+            (Some("compare"), None, _) => {
+                for (from, to, comparison) in self.scenarios.compare_all() {
+                    self.info(format!("{} -> {}: {:?}", from, to, comparison));
+                }
+            }
+            (Some("save"), None, _) | (Some("switch"), None, _) | (Some("delete"), None, _) => {
+                self.error("No scenario name".to_string())
+            }
+            (Some(other), _, _) => self.error(format!("Unknown scenario command: {}", other)),
+            (None, _, _) => self.error("No scenario command".to_string()),
+        }
+        OpInterpretation::default()
+    }
+
+    fn show_attribute_history(&mut self, kind: ElementKind, id: i64, key: &str) {
+        let attribute_history = match &self.attribute_history {
+            Some(attribute_history) => attribute_history,
+            None => return self.error("Attribute history is not being recorded".to_string()),
+        };
+        let now = SystemTime::now();
+        let describe = |value: &Option<String>| match value {
+            Some(value) => format!("'{}'", value),
+            None => "(none)".to_string(),
+        };
+        let listed: Vec<String> = attribute_history
+            .changes(kind, id, key)
+            .iter()
+            .map(|change| {
+                let age = now.duration_since(change.at).map_or(0, |d| d.as_secs());
+                format!(
+                    "{} -> {}, {}s ago",
+                    describe(&change.old),
+                    describe(&change.new),
+                    age
+                )
+            })
+            .collect();
+        listed.into_iter().for_each(|line| self.info(line));
+    }
+}
+
+/**
+ * Parse the options of `:write`: `--only=<filter>` to write only the vertices passing the filter,
+ * `--edges=<filter>` to write only the edges passing it, and `--anonymize` followed by
+ * anonymization options.
+ */
+fn parse_write_options<'a, I: Iterator<Item = &'a str>>(
+    mut options: I,
+) -> Result<(ExportFilter, Option<Anonymization>), String> {
+    let mut filter = ExportFilter::default();
+    while let Some(option) = options.next() {
+        match option.split_once('=') {
+            Some(("--only", value)) => filter.vertices = Some(ElementFilter::parse(value)?),
+            Some(("--edges", value)) => filter.edges = Some(ElementFilter::parse(value)?),
+            None if option == "--anonymize" => {
+                return Ok((filter, Some(Anonymization::parse(options)?)))
+            }
+            _ => return Err(format!("Unknown write option: {}", option)),
+        }
+    }
+    Ok((filter, None))
+}
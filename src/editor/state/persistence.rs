@@ -0,0 +1,817 @@
+#[cfg(feature = "json")]
+use std::time::SystemTime;
+
+use crate::anonymize::anonymize;
+use crate::anonymize::Anonymization;
+use crate::editor::event::EditorEvent;
+use crate::editor::export_filter::ExportFilter;
+use crate::editor::fold::Folds;
+use crate::editor::history::History;
+use crate::editor::history::HistoryEvent;
+use crate::editor::hooks::HookEvent;
+#[cfg(feature = "json")]
+use crate::editor::journal::journal_path;
+#[cfg(feature = "json")]
+use crate::editor::journal::RecoveryJournal;
+#[cfg(feature = "json")]
+use crate::editor::jumplist::Jumplist;
+#[cfg(feature = "json")]
+use crate::editor::mode::EditorMode;
+#[cfg(feature = "json")]
+use crate::editor::op_log;
+#[cfg(feature = "json")]
+use crate::editor::op_log::read_log;
+#[cfg(feature = "json")]
+use crate::editor::op_log::OpLog;
+use crate::editor::references::References;
+#[cfg(feature = "json")]
+use crate::editor::session::Session;
+use crate::editor::state::EditorState;
+#[cfg(feature = "json")]
+use crate::editor::state::Input;
+use crate::editor::state::OpInterpretation;
+#[cfg(feature = "json")]
+use crate::editor::trace::TraceRecorder;
+#[cfg(feature = "json")]
+use crate::editor::undo_file::document_hash;
+#[cfg(feature = "json")]
+use crate::editor::undo_file::undo_path;
+#[cfg(feature = "json")]
+use crate::editor::undo_file::UndoFile;
+use crate::format::plain::from_plain;
+use crate::format::Format;
+#[cfg(feature = "json")]
+use crate::graph::Diff;
+use crate::graph::Graph;
+use crate::graph::GraphOperation;
+
+impl EditorState {
+    // Journal unsaved changes every N document changes, or never if None.
+    #[cfg(feature = "json")]
+    pub fn set_autosave_interval(&mut self, interval: Option<usize>) {
+        self.autosave.set_interval(interval);
+    }
+
+    // Journal the unsaved changes not yet journaled, e.g., when the frontend is idle.
+    #[cfg(feature = "json")]
+    pub fn autosave(&mut self) {
+        if let Err(err) = self.autosave.flush() {
+            self.error(format!("Could not autosave: {}", err));
+        }
+    }
+
+    /**
+     * Append every change to the document to the operation log at the path. If the log already
+     * exists, the changes that turn the document it records into the current document are
+     * appended first, so that replaying the log always reconstructs the document.
+     */
+    #[cfg(feature = "json")]
+    pub fn start_op_log(&mut self, path: &str) -> Result<(), String> {
+        let logged = if std::path::Path::new(path).exists() {
+            let logged = read_log(path).map_err(|err| err.to_string())?;
+            op_log::replay(&logged, None).map_err(|err| err.to_string())?
+        } else {
+            Graph::new()
+        };
+        let mut log = OpLog::open(path).map_err(|err| err.to_string())?;
+        let diff = logged.difference(&self.document);
+        if !diff.operations.is_empty() {
+            log.append(&diff, SystemTime::now())
+                .map_err(|err| err.to_string())?;
+        }
+        self.op_log = Some(log);
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    pub fn stop_op_log(&mut self) {
+        self.op_log = None;
+    }
+
+    #[cfg(feature = "json")]
+    pub(super) fn log_operations(&mut self, diff: &Diff) {
+        if let Some(log) = &mut self.op_log {
+            if let Err(err) = log.append(diff, SystemTime::now()) {
+                let message = format!("Could not append to {}: {}", log.path(), err);
+                self.error(message);
+            }
+        }
+    }
+
+    /**
+     * Trace every input from now on, with the time it was evaluated, to a file at the path, e.g.,
+     * to reproduce a bug with `Trace::read` and `Trace::replay`. The trace reproduces the editor
+     * if it is new when tracing starts.
+     */
+    #[cfg(feature = "json")]
+    pub fn start_trace(&mut self, path: &str) -> Result<(), String> {
+        let recorder =
+            TraceRecorder::create(path, SystemTime::now()).map_err(|err| err.to_string())?;
+        self.trace = Some(recorder);
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    pub fn stop_trace(&mut self) {
+        self.trace = None;
+    }
+
+    #[cfg(feature = "json")]
+    pub(super) fn trace_input(&mut self, input: &Input) {
+        if let Some(trace) = &mut self.trace {
+            if let Err(err) = trace.record(input, SystemTime::now()) {
+                let message = format!("Stopped tracing to {}: {}", trace.path(), err);
+                self.trace = None;
+                self.error(message);
+            }
+        }
+    }
+
+    pub(super) fn write_document(
+        &mut self,
+        path: &str,
+        filter: &ExportFilter,
+        anonymization: Option<&Anonymization>,
+    ) {
+        let format = match Format::from_path(path) {
+            Some(format) => format,
+            None => {
+                self.error(format!("Unknown file format: {}", path));
+                return;
+            }
+        };
+        if let Err(err) = self.run_hooks(&HookEvent::PreSave(path.to_string())) {
+            self.error(format!("Did not write {}: {}", path, err));
+            return;
+        }
+        // Computed attributes are derived from the document, so only exports include them.
+        let exported = if format.is_native() {
+            self.document.clone()
+        } else {
+            self.computed.materialize(&self.document)
+        };
+        let exported = if filter.is_empty() {
+            exported
+        } else {
+            filter.apply(&exported, &self.references)
+        };
+        let exported = match anonymization {
+            Some(anonymization) => anonymize(&exported, anonymization),
+            None => exported,
+        };
+        if let Err(err) = std::fs::write(path, format.write(&exported)) {
+            self.error(format!("Could not write {}: {}", path, err));
+            return;
+        }
+        self.info(format!(
+            "Wrote {} vertices and {} edges to {}",
+            exported.vertices.len(),
+            exported.edges.len(),
+            path
+        ));
+        // Only a file that stores the document exactly can have its history restored.
+        #[cfg(feature = "json")]
+        if format.is_native() && filter.is_empty() && anonymization.is_none() {
+            self.write_undo_file(path);
+            self.autosave.saved(path, document_hash(&self.document));
+        }
+    }
+
+    #[cfg(feature = "json")]
+    fn write_undo_file(&mut self, path: &str) {
+        let undo_file = UndoFile {
+            document_hash: document_hash(&self.document),
+            next_vertex_id: self.vertex_ids.next_unused(),
+            next_edge_id: self.edge_ids.next_unused(),
+            history: self.history.clone(),
+            options: Some(self.options.clone()),
+        };
+        if let Err(err) = std::fs::write(undo_path(path), undo_file.to_json()) {
+            self.error(format!(
+                "Could not write the undo file for {}: {}",
+                path, err
+            ));
+        }
+    }
+
+    // Look for a journal left by a session that did not save the document at the path.
+    #[cfg(feature = "json")]
+    fn check_recovery_journal(&mut self, path: &str, graph: &Graph) {
+        if let Err(err) = self.autosave.opened(path, document_hash(graph)) {
+            self.error(format!("Could not autosave: {}", err));
+        }
+        self.recovery = None;
+        if !std::path::Path::new(&journal_path(path)).exists() {
+            return;
+        }
+        match RecoveryJournal::read(&journal_path(path)) {
+            Ok(journal) if journal.document_hash == document_hash(graph) => {
+                self.info(format!(
+                    "Found {} unsaved edits of {}: use :recover to replay them, or \
+                     :recover discard to delete them",
+                    journal.diffs.len(),
+                    path
+                ));
+                self.recovery = Some(journal);
+            }
+            Ok(_) => self.warn(format!(
+                "Found unsaved edits of an older version of {}: use :recover discard to delete \
+                 them",
+                path
+            )),
+            Err(err) => self.error(format!("Could not read the journal of {}: {}", path, err)),
+        }
+    }
+
+    #[cfg(feature = "json")]
+    pub(super) fn recover(&mut self, discard: bool) -> OpInterpretation {
+        let journal = self.recovery.take();
+        if discard {
+            self.autosave.discard();
+            return OpInterpretation::default();
+        }
+        match journal {
+            Some(journal) => {
+                self.autosave.resume();
+                OpInterpretation::standard_op(journal.operations())
+            }
+            None => {
+                self.info("Nothing to recover".to_string());
+                OpInterpretation::default()
+            }
+        }
+    }
+
+    // The saved history of the document at the path, if it was saved with the document as it is.
+    #[cfg(feature = "json")]
+    fn read_undo_file(&mut self, path: &str, graph: &Graph) -> Option<UndoFile> {
+        let text = std::fs::read_to_string(undo_path(path)).ok()?;
+        match UndoFile::from_json(&text) {
+            Ok(undo_file) if undo_file.document_hash == document_hash(graph) => {
+                if let Some(options) = &undo_file.options {
+                    self.options = options.clone();
+                }
+                Some(undo_file)
+            }
+            Ok(_) => {
+                self.warn(format!("{} changed since its undo file was written", path));
+                None
+            }
+            Err(err) => {
+                self.error(format!(
+                    "Could not read the undo file for {}: {}",
+                    path, err
+                ));
+                None
+            }
+        }
+    }
+
+    fn load_file(&mut self, path: &str) -> Option<Graph> {
+        let format = match Format::from_path(path) {
+            Some(format) => format,
+            None => {
+                self.error(format!("Unknown file format: {}", path));
+                return None;
+            }
+        };
+        let loaded = match std::fs::read(path) {
+            Ok(bytes) => format.read(&bytes),
+            Err(err) => {
+                self.error(format!("Could not read {}: {}", path, err));
+                return None;
+            }
+        };
+        match loaded {
+            Ok(graph) => Some(graph),
+            Err(err) => {
+                self.error(format!("Could not read {}: {}", path, err));
+                None
+            }
+        }
+    }
+
+    // Add the contents of a file to the document as a single edit.
+    pub(super) fn read_document(&mut self, path: &str) -> OpInterpretation {
+        match self.load_file(path) {
+            Some(graph) => {
+                let (interpretation, mapping) = self.import(&graph);
+                self.info(format!(
+                    "Read {} vertices and {} edges from {}",
+                    mapping.vertices.len(),
+                    mapping.edges.len(),
+                    path
+                ));
+                interpretation
+            }
+            None => OpInterpretation::default(),
+        }
+    }
+
+    // Position the document's vertices as laid out by Graphviz, e.g., by `dot -Tplain` run on an
+    // export of the document, as a single edit.
+    pub(super) fn read_layout(&mut self, path: &str) -> OpInterpretation {
+        let parsed = match std::fs::read_to_string(path) {
+            Ok(text) => from_plain(&text).map_err(|err| err.to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+        let layout = match parsed {
+            Ok(layout) => layout,
+            Err(err) => {
+                self.error(format!("Could not read {}: {}", path, err));
+                return OpInterpretation::default();
+            }
+        };
+        let mut unmatched = 0;
+        let mut ops = Vec::new();
+        for (id, laid_out) in layout.vertices.iter() {
+            let old = match self.document.vertices.get(id) {
+                Some(old) => old.clone(),
+                None => {
+                    unmatched += 1;
+                    continue;
+                }
+            };
+            let pos = laid_out.attribute("pos").unwrap_or_default();
+            if old.attribute("pos") != Some(pos) {
+                let new = old.clone().with_attribute("pos", pos);
+                ops.push(GraphOperation::ModifyVertex { old, new });
+            }
+        }
+        self.info(format!("Positioned {} vertices from {}", ops.len(), path));
+        if unmatched > 0 {
+            self.warn(format!(
+                "{} vertices in {} are not in the document",
+                unmatched, path
+            ));
+        }
+        OpInterpretation::standard_op(ops)
+    }
+
+    // Replace the document with the contents of a file, starting a new history.
+    pub(super) fn open_document(&mut self, path: &str, readonly: bool) {
+        let graph = match self.load_file(path) {
+            Some(graph) => graph,
+            None => return,
+        };
+        self.vertex_ids
+            .reset(&graph.vertices.keys().copied().collect());
+        self.edge_ids.reset(&graph.edges.keys().copied().collect());
+        self.info(format!(
+            "Opened {} vertices and {} edges from {}",
+            graph.vertices.len(),
+            graph.edges.len(),
+            path
+        ));
+        let limit = self.history.limit();
+        self.history = History::new();
+        #[cfg(feature = "json")]
+        if let Some(undo_file) = self.read_undo_file(path, &graph) {
+            self.info(format!(
+                "Restored {} edits of history",
+                undo_file.history.len()
+            ));
+            self.history = undo_file.history;
+            self.observe_saved_ids(undo_file.next_vertex_id, undo_file.next_edge_id);
+        }
+        self.subscribers
+            .emit(EditorEvent::History(HistoryEvent::Reset));
+        self.history.set_limit(limit);
+        #[cfg(feature = "json")]
+        self.check_recovery_journal(path, &graph);
+        let opened = self.document.difference(&graph);
+        #[cfg(feature = "json")]
+        self.log_operations(&opened);
+        if !opened.operations.is_empty() {
+            self.subscribers.emit(EditorEvent::Document(opened));
+        }
+        self.document = graph;
+        self.coalescing = None;
+        self.folds = Folds::new();
+        self.references = References::new();
+        if let Some(attribute_history) = &mut self.attribute_history {
+            attribute_history.clear();
+        }
+        self.recent_vertices.clear();
+        self.recycle_ids();
+        self.options.readonly = readonly;
+    }
+
+    // Keep the ids of a restored history, and any below the next ids saved with it, from being
+    // allocated again.
+    #[cfg(feature = "json")]
+    fn observe_saved_ids(&mut self, next_vertex_id: i64, next_edge_id: i64) {
+        self.vertex_ids.observe(next_vertex_id - 1);
+        self.edge_ids.observe(next_edge_id - 1);
+        self.observe_history_ids();
+    }
+
+    // Allocators that do not hand out ids in order are told of each id in the history.
+    pub(super) fn observe_history_ids(&mut self) {
+        let (vertices, edges) = self.history.element_ids();
+        vertices
+            .into_iter()
+            .for_each(|id| self.vertex_ids.observe(id));
+        edges.into_iter().for_each(|id| self.edge_ids.observe(id));
+    }
+
+    // A snapshot of the editing session, e.g., for a frontend to save when it quits.
+    #[cfg(feature = "json")]
+    pub fn session(&self) -> Session {
+        Session {
+            document: self.document.clone(),
+            history: self.history.clone(),
+            next_vertex_id: self.vertex_ids.next_unused(),
+            next_edge_id: self.edge_ids.next_unused(),
+            options: self.options.clone(),
+            references: self.references.clone(),
+            views: self.views.clone(),
+            last_change: self.last_change.clone(),
+            search: self.search.clone(),
+        }
+    }
+
+    /**
+     * Replace the editing session with a saved one, as if it had never been left: the document,
+     * the history and the position in it, the options, marks and other references, and the views
+     * are restored. The editor returns to Command mode, and folds, jumps and pending keys are
+     * cleared, like when a document is opened. The history keeps its current limit.
+     */
+    #[cfg(feature = "json")]
+    pub fn restore_session(&mut self, session: Session) {
+        let restored = self.document.difference(&session.document);
+        self.log_operations(&restored);
+        if !restored.operations.is_empty() {
+            self.subscribers.emit(EditorEvent::Document(restored));
+        }
+        self.document = session.document;
+        let limit = self.history.limit();
+        self.history = session.history;
+        self.history.set_limit(limit);
+        self.subscribers
+            .emit(EditorEvent::History(HistoryEvent::Reset));
+        self.vertex_ids
+            .reset(&self.document.vertices.keys().copied().collect());
+        self.edge_ids
+            .reset(&self.document.edges.keys().copied().collect());
+        self.observe_saved_ids(session.next_vertex_id, session.next_edge_id);
+
+        self.options = session.options;
+        self.references = session.references;
+        self.views = session.views;
+        self.last_change = session.last_change;
+        self.search = session.search;
+        self.coalescing = None;
+        self.folds = Folds::new();
+        self.jumps = Jumplist::new();
+        self.pending_keys.clear();
+        if let Some(attribute_history) = &mut self.attribute_history {
+            attribute_history.clear();
+        }
+        self.recent_vertices.clear();
+        self.recycle_ids();
+        let before = std::mem::replace(&mut self.mode, EditorMode::Command);
+        self.emit_mode_change(before);
+        self.update_completions();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "json")]
+    use crate::computed::ComputedAttribute;
+    #[cfg(feature = "json")]
+    use crate::editor::command::Command;
+    #[cfg(feature = "json")]
+    use crate::editor::ids::Sequential;
+    use crate::editor::keys::*;
+    #[cfg(feature = "json")]
+    use crate::editor::options::EditorOptions;
+    #[cfg(feature = "json")]
+    use crate::editor::references::ElementRef;
+    #[cfg(feature = "json")]
+    use crate::editor::references::Reference;
+    use crate::editor::state::tests::single_edge_graph;
+    use crate::editor::state::Input;
+    #[cfg(feature = "json")]
+    use crate::editor::trace::Trace;
+    use crate::graph::Edge;
+    use crate::graph::Vertex;
+
+    #[test]
+    fn write_command_exports_dot() {
+        let path = std::env::temp_dir().join(format!("gri-write-{}.dot", std::process::id()));
+        let mut state = EditorState::new();
+        state.document = single_edge_graph();
+        for key in format!(":write {}", path.display()).chars() {
+            state.evaluate(Input::Key(key));
+        }
+        state.evaluate(Input::Key(ENTER));
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(crate::format::dot::to_dot(&state.document), written);
+    }
+
+    #[test]
+    fn write_command_exports_the_pinned_subgraph() {
+        let path = std::env::temp_dir().join(format!("gri-filter-{}.dot", std::process::id()));
+        let mut state = EditorState::new();
+        for key in format!(
+            "ivvve0,1\u{e007}e1,2\u{e007}\u{1b}:pin v0 v1\u{e007}:w {} --only=@pins\u{e007}",
+            path.display()
+        )
+        .chars()
+        {
+            state.evaluate(Input::Key(key));
+        }
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            crate::format::dot::to_dot(&state.document.induced_subgraph(vec![0, 1])),
+            written
+        );
+    }
+
+    #[test]
+    fn read_command_imports_dot_as_one_edit() {
+        let path = std::env::temp_dir().join(format!("gri-read-{}.dot", std::process::id()));
+        std::fs::write(&path, "digraph { a -> b [weight=3] }").unwrap();
+        let mut state = EditorState::new();
+        for key in "ivv".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        state.evaluate(Input::Key(ESC));
+        for key in format!(":read {}", path.display()).chars() {
+            state.evaluate(Input::Key(key));
+        }
+        state.evaluate(Input::Key(ENTER));
+        std::fs::remove_file(&path).unwrap();
+
+        let mut expected = Graph::new();
+        expected.add_vertex(Vertex::new(0));
+        expected.add_vertex(Vertex::new(1));
+        expected.add_vertex(Vertex::new(2).with_attribute("label", "a"));
+        expected.add_vertex(Vertex::new(3).with_attribute("label", "b"));
+        expected.add_edge(Edge::new(0, 2, 3).with_attribute("weight", "3"));
+        assert_eq!(expected, state.document);
+
+        state.evaluate(Input::Key(U_LOWER));
+        let mut before = Graph::new();
+        before.add_vertex(Vertex::new(0));
+        before.add_vertex(Vertex::new(1));
+        assert_eq!(before, state.document);
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn saved_session_can_be_opened() {
+        let path = std::env::temp_dir().join(format!("gri-session-{}.json", std::process::id()));
+        let mut state = EditorState::new();
+        state.document = single_edge_graph();
+        state.computed.declare("degree", ComputedAttribute::Degree);
+        for key in format!(":set weight-step=0.5\u{e007}:w {}\u{e007}", path.display()).chars() {
+            state.evaluate(Input::Key(key));
+        }
+
+        let mut reopened = EditorState::new();
+        for key in format!(":e {}\u{e007}", path.display()).chars() {
+            reopened.evaluate(Input::Key(key));
+        }
+        // The options saved for a document that has since changed are not for that document.
+        let format = Format::from_path(&path.display().to_string()).unwrap();
+        std::fs::write(&path, format.write(&Graph::new())).unwrap();
+        let mut changed = EditorState::new();
+        for key in format!(":e {}\u{e007}", path.display()).chars() {
+            changed.evaluate(Input::Key(key));
+        }
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(undo_path(&path.display().to_string())).unwrap();
+        assert_eq!(
+            EditorOptions::default().weight_step,
+            changed.options().weight_step
+        );
+        assert!(changed.take_messages().iter().any(|message| message
+            .text
+            .ends_with("changed since its undo file was written")));
+
+        // Computed attributes are not saved with the document, but options are.
+        assert_eq!(single_edge_graph(), reopened.document);
+        assert_eq!(0.5, reopened.options().weight_step);
+        assert!(reopened.history().is_empty());
+        for key in "iv".chars() {
+            reopened.evaluate(Input::Key(key));
+        }
+        assert!(reopened.document.vertices.contains_key(&2));
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn view_opens_a_document_readonly() {
+        let path = std::env::temp_dir().join(format!("gri-view-{}.json", std::process::id()));
+        std::fs::write(&path, single_edge_graph().to_json()).unwrap();
+        let mut state = EditorState::new();
+        for key in format!(":view {}\u{e007}", path.display()).chars() {
+            state.evaluate(Input::Key(key));
+        }
+        assert!(state.options().readonly);
+        for key in format!(":e {}\u{e007}", path.display()).chars() {
+            state.evaluate(Input::Key(key));
+        }
+        std::fs::remove_file(&path).unwrap();
+        assert!(!state.options().readonly);
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn sessions_are_restored_where_they_were_left() {
+        let mut state = EditorState::new();
+        for key in "iv\u{1b}iv\u{1b}iv\u{1b}u:mark a v0\u{e007}:set strict\u{e007}v".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let saved = state.session().to_json();
+
+        let mut restored = EditorState::new();
+        let receiver = restored.subscribe();
+        restored.restore_session(Session::from_json(&saved).unwrap());
+        assert_eq!(state.document, restored.document);
+        assert_eq!(state.cursor(), restored.cursor());
+        assert_eq!(
+            Some(ElementRef::Vertex(0)),
+            restored.references().element(&Reference::Mark('a'))
+        );
+        assert!(restored.options().strict);
+        assert_eq!(EditorMode::Command, *restored.mode());
+        assert!(receiver
+            .try_iter()
+            .any(|event| event == EditorEvent::History(HistoryEvent::Reset)));
+
+        // The undone vertex can be redone, and its id is not given to a new one.
+        restored.execute(Command::Redo).unwrap();
+        assert!(restored.document.vertices.contains_key(&2));
+        restored.evaluate(Input::Key('.'));
+        assert!(restored.document.vertices.contains_key(&3));
+    }
+
+    #[test]
+    fn layout_positions_vertices_in_one_edit() {
+        let path = std::env::temp_dir().join(format!("gri-layout-{}.plain", std::process::id()));
+        std::fs::write(
+            &path,
+            "graph 1 2 2\n\
+             node 0 0.5 1.5 0.75 0.5 0 solid ellipse black lightgrey\n\
+             node 1 1.5 0.5 0.75 0.5 1 solid ellipse black lightgrey\n\
+             node 7 1 1 0.75 0.5 7 solid ellipse black lightgrey\n\
+             edge 0 1 4 0.5 1.2 0.7 1 1 0.8 1.3 0.7 solid black\n\
+             stop\n",
+        )
+        .unwrap();
+        let mut state = EditorState::new();
+        state.document = single_edge_graph();
+        for key in format!(":layout {}\u{e007}", path.display()).chars() {
+            state.evaluate(Input::Key(key));
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            Some("0.5,1.5"),
+            state.document.vertices[&0].attribute("pos")
+        );
+        assert_eq!(
+            Some("1.5,0.5"),
+            state.document.vertices[&1].attribute("pos")
+        );
+        assert!(!state.document.vertices.contains_key(&7));
+        state.evaluate(Input::Key('u'));
+        assert_eq!(single_edge_graph(), state.document);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn undo_history_persists_with_the_document() {
+        let path = std::env::temp_dir().join(format!("gri-undo-{}.json", std::process::id()));
+        let mut state = EditorState::new();
+        for key in format!("iv\u{1b}iv\u{1b}iv\u{1b}u:w {}\u{e007}", path.display()).chars() {
+            state.evaluate(Input::Key(key));
+        }
+
+        let mut reopened = EditorState::new();
+        for key in format!(":e {}\u{e007}iv\u{1b}uu", path.display()).chars() {
+            reopened.evaluate(Input::Key(key));
+        }
+        assert_eq!(1, reopened.document.vertices.len());
+        // The vertex created after reopening did not reuse the id of the undone one.
+        assert_eq!(4, reopened.vertex_ids.next_unused());
+
+        // A document changed by other means does not get the stale history.
+        std::fs::write(&path, Graph::new().to_json()).unwrap();
+        let mut changed = EditorState::new();
+        for key in format!(":e {}\u{e007}", path.display()).chars() {
+            changed.evaluate(Input::Key(key));
+        }
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(undo_path(&path.display().to_string())).unwrap();
+        assert!(changed.history().is_empty());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn op_log_replays_to_the_document() {
+        let path = std::env::temp_dir().join(format!("gri-oplog-{}.jsonl", std::process::id()));
+        let path = path.display().to_string();
+        let mut state = EditorState::new();
+        state.document = single_edge_graph();
+        state.vertex_ids = Box::new(Sequential::starting_at(2));
+        state.edge_ids = Box::new(Sequential::starting_at(1));
+        for key in format!(":oplog {}\u{e007}ivv\u{1b}u:oplog off\u{e007}", path).chars() {
+            state.evaluate(Input::Key(key));
+        }
+        // Edits made while not logging are caught up on when logging starts again.
+        for key in format!("iv\u{1b}:oplog {}\u{e007}ie0,1\u{e007}", path).chars() {
+            state.evaluate(Input::Key(key));
+        }
+
+        let logged = read_log(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(6, logged.len());
+        assert_eq!(Ok(state.document.clone()), op_log::replay(&logged, None));
+
+        // A log whose diffs do not apply is left alone rather than appended to.
+        for key in "\u{1b}:oplog off\u{e007}".chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let damaged = "{\"at\":0,\"diff\":{\"operations\":[{\"AddEdge\":{\"id\":0,\"source\":0,\
+                       \"target\":1,\"attributes\":{}}}]}}\n";
+        std::fs::write(&path, damaged).unwrap();
+        for key in format!(":oplog {}\u{e007}", path).chars() {
+            state.evaluate(Input::Key(key));
+        }
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            format!("Could not log to {}: Line 1: Unknown vertex 0", path),
+            state.take_messages()[0].text
+        );
+        assert_eq!(damaged, written);
+        assert!(state.op_log.is_none());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn traced_inputs_reproduce_the_editor() {
+        let path = std::env::temp_dir().join(format!("gri-trace-{}.jsonl", std::process::id()));
+        let path = path.display().to_string();
+        let mut state = EditorState::new();
+        for key in format!(":trace {}\u{e007}", path).chars() {
+            state.evaluate(Input::Key(key));
+        }
+        state.evaluate(Input::Press(KeyEvent::new(KeyCode::Char('i'))));
+        state.evaluate(Input::Paste("0,1\n1,2".to_string()));
+        for key in "vv\u{1b}u:trace off\u{e007}iv".chars() {
+            state.evaluate(Input::Key(key));
+        }
+
+        let trace = Trace::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        // Keys are traced as the editor encodes them, up to the one that stops tracing.
+        assert_eq!(Input::Key('i'), trace.inputs[0].input);
+        assert_eq!(17, trace.inputs.len());
+        let mut replayed = trace.replay();
+        replayed.evaluate(Input::Key('i'));
+        replayed.evaluate(Input::Key('v'));
+        assert_eq!(state.document, replayed.document);
+        assert_eq!(state.next_ids(), replayed.next_ids());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn unsaved_edits_are_recovered_after_a_crash() {
+        let path = std::env::temp_dir().join(format!("gri-crash-{}.json", std::process::id()));
+        let path = path.display().to_string();
+        let mut crashed = EditorState::new();
+        crashed.set_autosave_interval(Some(1));
+        for key in format!(":w {}\u{e007}ivve0,1\u{e007}", path).chars() {
+            crashed.evaluate(Input::Key(key));
+        }
+        let unsaved = crashed.document.clone();
+        drop(crashed);
+
+        let mut recovered = EditorState::new();
+        recovered.set_autosave_interval(Some(1));
+        for key in format!(":e {}\u{e007}:recover\u{e007}", path).chars() {
+            recovered.evaluate(Input::Key(key));
+        }
+        assert_eq!(unsaved, recovered.document);
+        assert_eq!(1, recovered.history().len());
+        assert!(std::path::Path::new(&journal_path(&path)).exists());
+
+        for key in format!(":w {}\u{e007}", path).chars() {
+            recovered.evaluate(Input::Key(key));
+        }
+        assert!(!std::path::Path::new(&journal_path(&path)).exists());
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(undo_path(&path)).unwrap();
+    }
+}
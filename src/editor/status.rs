@@ -0,0 +1,56 @@
+use std::fmt;
+
+use crate::editor::keymap::key_notation;
+use crate::editor::keys::QUOTE;
+use crate::editor::message::Message;
+use crate::editor::mode::EditorMode;
+use crate::editor::mode::Operator;
+
+/**
+ * What a frontend shows in a vim-style status line: the mode, what has been typed toward the
+ * command in progress, and the last message.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Status<'a> {
+    pub mode: &'a EditorMode,
+    // The text typed in a pending mode, e.g., the vertices of a new edge.
+    pub pending_text: Option<&'a str>,
+    // The count typed before a command.
+    pub count: Option<usize>,
+    // Keys that begin a mapping, awaiting the rest of it.
+    pub pending_keys: &'a [char],
+    // The last error or notice, until the next key is typed.
+    pub message: Option<&'a Message>,
+}
+
+/**
+ * The parts of a command typed so far, like vim's `showcmd`, e.g., the register and operator of
+ * `"ad` or the vertices of `e0,1`. Its display is the keys as they were typed, for a frontend to
+ * echo while the command is incomplete.
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PendingCommand {
+    // The register named with `"`.
+    pub register: Option<char>,
+    pub count: Option<usize>,
+    pub operator: Option<Operator>,
+    // The key that began the command, if it takes text, and the text typed after it.
+    pub keys: String,
+    // Keys that begin a mapping, awaiting the rest of it.
+    pub pending_keys: Vec<char>,
+}
+
+impl fmt::Display for PendingCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(register) = self.register {
+            write!(f, "{}{}", QUOTE, register)?;
+        }
+        if let Some(count) = self.count {
+            write!(f, "{}", count)?;
+        }
+        if let Some(operator) = self.operator {
+            write!(f, "{}", operator.key())?;
+        }
+        write!(f, "{}{}", self.keys, key_notation(&self.pending_keys))
+    }
+}
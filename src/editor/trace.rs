@@ -0,0 +1,187 @@
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::editor::replay::InputLog;
+use crate::editor::state::EditorState;
+use crate::editor::state::Input;
+use crate::format::ParseError;
+
+// The version of the trace layout written by `TraceRecorder`.
+pub const VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    version: u32,
+    // Milliseconds since the Unix epoch.
+    started: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Line {
+    // Milliseconds since the trace started.
+    at: u64,
+    input: Input,
+}
+
+// An input an editor evaluated, and how long after the trace started.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TracedInput {
+    pub at: Duration,
+    pub input: Input,
+}
+
+/**
+ * Every input an editor evaluated while it was traced, with the time of each, e.g., for a user to
+ * attach to a bug report. Like an `InputLog`, replaying the trace in a new editor reproduces the
+ * editor that was traced, provided it was new when the trace started. The times are only for the
+ * reader: time passing matters to the editor only as `Input::Tick`, which is traced like any other
+ * input.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    pub started: SystemTime,
+    pub inputs: Vec<TracedInput>,
+}
+
+impl Trace {
+    // Read the trace at the path. A partial last line, left by a crash while tracing, is ignored.
+    pub fn read(path: &str) -> Result<Trace, ParseError> {
+        let text =
+            std::fs::read_to_string(path).map_err(|err| ParseError::new(1, &err.to_string()))?;
+        let mut lines = text.lines().enumerate();
+        let header: Header = match lines.next() {
+            Some((_, line)) => {
+                serde_json::from_str(line).map_err(|err| ParseError::new(1, &err.to_string()))?
+            }
+            None => return Err(ParseError::new(1, "Trace has no header")),
+        };
+        if header.version > VERSION {
+            return Err(ParseError::new(
+                1,
+                &format!(
+                    "Trace version {} is newer than the supported version {}",
+                    header.version, VERSION
+                ),
+            ));
+        }
+        let count = text.lines().count();
+        let mut inputs = Vec::new();
+        for (i, line) in lines {
+            match serde_json::from_str::<Line>(line) {
+                Ok(line) => inputs.push(TracedInput {
+                    at: Duration::from_millis(line.at),
+                    input: line.input,
+                }),
+                Err(_) if i + 1 == count && !text.ends_with('\n') => break,
+                Err(err) => return Err(ParseError::new(i + 1, &err.to_string())),
+            }
+        }
+        Ok(Trace {
+            started: UNIX_EPOCH + Duration::from_millis(header.started),
+            inputs,
+        })
+    }
+
+    // The inputs, without their times.
+    pub fn input_log(&self) -> InputLog {
+        InputLog {
+            inputs: self
+                .inputs
+                .iter()
+                .map(|traced| traced.input.clone())
+                .collect(),
+        }
+    }
+
+    pub fn replay(&self) -> EditorState {
+        self.input_log().replay()
+    }
+}
+
+/**
+ * Writes a trace to a file as it is recorded, with a line of JSON per input after a header, so
+ * that the trace survives a crash of the editor it records. Starting a trace replaces any file at
+ * the path.
+ */
+#[derive(Debug)]
+pub struct TraceRecorder {
+    path: String,
+    file: File,
+    started: SystemTime,
+}
+
+impl TraceRecorder {
+    pub fn create(path: &str, started: SystemTime) -> io::Result<TraceRecorder> {
+        let mut file = File::create(path)?;
+        let mut header = serde_json::to_string(&Header {
+            version: VERSION,
+            started: millis(started.duration_since(UNIX_EPOCH).unwrap_or_default()),
+        })
+        .unwrap();
+        header.push('\n');
+        file.write_all(header.as_bytes())?;
+        Ok(TraceRecorder {
+            path: path.to_string(),
+            file,
+            started,
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    // Append an input, evaluated at the time given. Inputs are not synced to disk one by one,
+    // which would slow typing down, but are written before the next input is evaluated.
+    pub fn record(&mut self, input: &Input, at: SystemTime) -> io::Result<()> {
+        let mut line = serde_json::to_string(&Line {
+            at: millis(at.duration_since(self.started).unwrap_or_default()),
+            input: input.clone(),
+        })
+        .unwrap();
+        line.push('\n');
+        self.file.write_all(line.as_bytes())
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn traces_are_read_back_with_their_times() {
+        let path = std::env::temp_dir()
+            .join(format!("gri-trace-recorder-{}.jsonl", std::process::id()))
+            .display()
+            .to_string();
+        let started = UNIX_EPOCH + Duration::from_secs(60);
+        let mut recorder = TraceRecorder::create(&path, started).unwrap();
+        for (i, key) in "iv\u{1b}".chars().enumerate() {
+            recorder
+                .record(
+                    &Input::Key(key),
+                    started + Duration::from_millis(250 * i as u64),
+                )
+                .unwrap();
+        }
+        recorder.file.write_all(b"{\"at\": 900, \"inp").unwrap();
+        let trace = Trace::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(started, trace.started);
+        assert_eq!(3, trace.inputs.len());
+        assert_eq!(Duration::from_millis(500), trace.inputs[2].at);
+        assert_eq!(1, trace.replay().document().vertices.len());
+    }
+}
@@ -0,0 +1,172 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::editor::history::FlatHistory;
+use crate::editor::history::History;
+use crate::editor::history::HistoryEntry;
+use crate::editor::history::HistoryLimit;
+use crate::editor::options::EditorOptions;
+use crate::format::binary::to_binary;
+use crate::format::ParseError;
+use crate::graph::Graph;
+
+// The version of the undo file layout written by `UndoFile::to_json`.
+pub const VERSION: u32 = 1;
+
+// The undo file kept alongside a document saved at the path, like vim's undofile.
+pub fn undo_path(document_path: &str) -> String {
+    format!("{}.undo", document_path)
+}
+
+// FNV-1a of the document's binary encoding, which is canonical, so equal documents hash equally.
+pub fn document_hash(graph: &Graph) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in to_binary(graph) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Node {
+    parent: Option<usize>,
+    entry: HistoryEntry,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Layout {
+    version: u32,
+    document_hash: u64,
+    next_vertex_id: i64,
+    next_edge_id: i64,
+    current: usize,
+    nodes: Vec<Node>,
+    // Bookmarked nodes by position in `nodes`. Undo files written before bookmarks were saved
+    // have none, and no history limit.
+    #[serde(default)]
+    bookmarks: Vec<(String, usize)>,
+    #[serde(default)]
+    history_limit: HistoryLimit,
+    // Undo files written before options were saved have none.
+    #[serde(default)]
+    options: Option<EditorOptions>,
+}
+
+/**
+ * The undo tree of a document, saved so that edits can still be undone after the document is
+ * reopened. The file records a hash of the document it was saved with, and is only used if the
+ * document still has that hash, since the history cannot be applied to a document changed by
+ * other means. It also records the next ids to allocate, so that new edits cannot reuse the ids
+ * of undone ones, and the editor's options, which are restored whether or not the history is.
+ */
+#[derive(Debug)]
+pub struct UndoFile {
+    pub document_hash: u64,
+    pub next_vertex_id: i64,
+    pub next_edge_id: i64,
+    pub history: History,
+    pub options: Option<EditorOptions>,
+}
+
+impl UndoFile {
+    pub fn to_json(&self) -> String {
+        let history = self.history.flatten();
+        let layout = Layout {
+            version: VERSION,
+            document_hash: self.document_hash,
+            next_vertex_id: self.next_vertex_id,
+            next_edge_id: self.next_edge_id,
+            current: history.current,
+            nodes: history
+                .nodes
+                .into_iter()
+                .map(|(parent, entry)| Node { parent, entry })
+                .collect(),
+            bookmarks: history.bookmarks,
+            history_limit: history.limit,
+            options: self.options.clone(),
+        };
+        serde_json::to_string(&layout).unwrap()
+    }
+
+    pub fn from_json(text: &str) -> Result<UndoFile, ParseError> {
+        let layout: Layout = serde_json::from_str(text)
+            .map_err(|err| ParseError::new(err.line(), &err.to_string()))?;
+        if layout.version > VERSION {
+            return Err(ParseError::new(
+                1,
+                &format!(
+                    "Undo file version {} is newer than the supported version {}",
+                    layout.version, VERSION
+                ),
+            ));
+        }
+        let nodes = layout
+            .nodes
+            .into_iter()
+            .map(|node| (node.parent, node.entry))
+            .collect();
+        let history = History::unflatten(FlatHistory {
+            nodes,
+            current: layout.current,
+            bookmarks: layout.bookmarks,
+            limit: layout.history_limit,
+        })
+        .ok_or_else(|| ParseError::new(1, "Undo file does not describe a history tree"))?;
+        Ok(UndoFile {
+            document_hash: layout.document_hash,
+            next_vertex_id: layout.next_vertex_id,
+            next_edge_id: layout.next_edge_id,
+            history,
+            options: layout.options,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Diff;
+    use crate::graph::GraphOperation;
+    use crate::graph::Vertex;
+
+    #[test]
+    fn round_trip() {
+        let mut history = History::new();
+        history.record(Diff {
+            operations: vec![GraphOperation::AddVertex(Vertex::new(0))],
+        });
+        history.set_message(history.current(), Some("first".to_string()));
+        history.bookmark("first");
+        let mut graph = Graph::new();
+        graph.add_vertex(Vertex::new(0));
+        let undo_file = UndoFile {
+            document_hash: document_hash(&graph),
+            next_vertex_id: 3,
+            next_edge_id: 0,
+            history,
+            options: Some(EditorOptions {
+                directed: false,
+                ..EditorOptions::default()
+            }),
+        };
+
+        let restored = UndoFile::from_json(&undo_file.to_json()).unwrap();
+        assert_eq!(document_hash(&graph), restored.document_hash);
+        assert_ne!(document_hash(&Graph::new()), restored.document_hash);
+        assert_eq!(3, restored.next_vertex_id);
+        assert_eq!(
+            Some(false),
+            restored.options.map(|options| options.directed)
+        );
+        assert_eq!(1, restored.history.len());
+        let current = restored.history.current();
+        assert_eq!(
+            Some("first"),
+            restored.history.entry(current).unwrap().message.as_deref()
+        );
+        assert_eq!(Some(current), restored.history.bookmarked("first"));
+        assert!(UndoFile::from_json(r#"{"version": 2}"#).is_err());
+    }
+}
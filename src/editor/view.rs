@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+
+use crate::editor::mouse::Position;
+
+pub type ViewId = usize;
+
+// The part of the layout a view shows: the position at its center, and how far it is zoomed in.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Viewport {
+    pub center: Position,
+    pub zoom: f64,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Viewport {
+            center: Position::new(0.0, 0.0),
+            zoom: 1.0,
+        }
+    }
+}
+
+/**
+ * The views open on a document, like vim's windows, of which one is active. Each view has its own
+ * viewport, kept here, and its own cursor and selection, kept as references so that they follow
+ * the document as it changes. There is always at least one view.
+ */
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Views {
+    viewports: BTreeMap<ViewId, Viewport>,
+    active: ViewId,
+    next_id: ViewId,
+}
+
+impl Default for Views {
+    fn default() -> Self {
+        let mut viewports = BTreeMap::new();
+        viewports.insert(0, Viewport::default());
+        Views {
+            viewports,
+            active: 0,
+            next_id: 1,
+        }
+    }
+}
+
+impl Views {
+    pub fn new() -> Views {
+        Views::default()
+    }
+
+    pub fn active(&self) -> ViewId {
+        self.active
+    }
+
+    // The open views, in the order they were opened.
+    pub fn ids(&self) -> impl Iterator<Item = ViewId> + '_ {
+        self.viewports.keys().copied()
+    }
+
+    pub fn contains(&self, id: ViewId) -> bool {
+        self.viewports.contains_key(&id)
+    }
+
+    // Open a view showing the same part of the layout as the active one, without activating it.
+    pub fn open(&mut self) -> ViewId {
+        let id = self.next_id;
+        self.next_id += 1;
+        let viewport = self.viewports[&self.active];
+        self.viewports.insert(id, viewport);
+        id
+    }
+
+    // Close a view, unless it is the last one. Closing the active view activates the view opened
+    // before it, or else the first.
+    pub fn close(&mut self, id: ViewId) -> bool {
+        if self.viewports.len() == 1 || self.viewports.remove(&id).is_none() {
+            return false;
+        }
+        if self.active == id {
+            self.active = match self.viewports.range(..id).next_back() {
+                Some((before, _)) => *before,
+                None => *self.viewports.keys().next().unwrap(),
+            };
+        }
+        true
+    }
+
+    pub fn activate(&mut self, id: ViewId) -> bool {
+        if !self.contains(id) {
+            return false;
+        }
+        self.active = id;
+        true
+    }
+
+    pub fn viewport(&self, id: ViewId) -> Option<Viewport> {
+        self.viewports.get(&id).copied()
+    }
+
+    pub fn set_viewport(&mut self, id: ViewId, viewport: Viewport) -> bool {
+        match self.viewports.get_mut(&id) {
+            Some(current) => {
+                *current = viewport;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_and_close_views() {
+        let mut views = Views::new();
+        let zoomed = Viewport {
+            center: Position::new(1.0, 2.0),
+            zoom: 3.0,
+        };
+        assert!(views.set_viewport(0, zoomed));
+        let split = views.open();
+        assert_eq!(Some(zoomed), views.viewport(split));
+        assert_eq!(0, views.active());
+
+        let other = views.open();
+        assert!(views.activate(other));
+        assert!(views.set_viewport(other, Viewport::default()));
+        assert_eq!(Some(zoomed), views.viewport(split));
+
+        assert!(views.close(other));
+        assert_eq!(split, views.active());
+        assert!(views.close(0));
+        assert!(!views.close(split));
+        assert!(!views.activate(other));
+        assert_eq!(vec![split], views.ids().collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,311 @@
+use crate::format::ParseError;
+use crate::graph::Attributes;
+use crate::graph::Edge;
+use crate::graph::Graph;
+use crate::graph::Vertex;
+
+pub const MAGIC: &[u8; 4] = b"GRIB";
+
+// The version written by `to_binary`. Readers reject documents of a newer version, so bump it
+// only for changes older readers could not skip, and add new data as optional sections instead.
+pub const VERSION: u32 = 1;
+
+// Marks the end of the document, so that a truncated document is detected.
+const END: u8 = 0;
+const PROPERTIES: u8 = 1;
+const VERTICES: u8 = 2;
+const EDGES: u8 = 3;
+// Sections whose tag has this bit set may be skipped by readers that do not know them.
+const OPTIONAL: u8 = 0x80;
+
+/**
+ * Encode the graph in the editor's binary format, which is more compact and faster to read than
+ * JSON for large documents. The layout is:
+ *
+ *   magic "GRIB", version (u32, little endian), then sections, then an empty END section
+ *   section: tag (u8), length of the payload in bytes (varint), payload
+ *   properties payload: count, then key and value strings
+ *   vertices payload: count, then for each vertex its id and attributes as above
+ *   edges payload: count, then for each edge its id, source, target and attributes
+ *
+ * Counts and string lengths are unsigned LEB128 varints, ids are zigzag-encoded varints, and
+ * strings are UTF-8. Vertices and edges are written in order of id.
+ */
+pub fn to_binary(graph: &Graph) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    out.extend_from_slice(&VERSION.to_le_bytes());
+
+    let mut payload = Vec::new();
+    write_attributes(&mut payload, &graph.properties);
+    write_section(&mut out, PROPERTIES, &payload);
+
+    let mut vertices: Vec<_> = graph.vertices.values().collect();
+    vertices.sort_by_key(|v| v.id);
+    let mut payload = Vec::new();
+    write_varint(&mut payload, vertices.len() as u64);
+    for v in vertices {
+        write_id(&mut payload, v.id);
+        write_attributes(&mut payload, &v.attributes);
+    }
+    write_section(&mut out, VERTICES, &payload);
+
+    let mut edges: Vec<_> = graph.edges.values().collect();
+    edges.sort_by_key(|e| e.id);
+    let mut payload = Vec::new();
+    write_varint(&mut payload, edges.len() as u64);
+    for e in edges {
+        write_id(&mut payload, e.id);
+        write_id(&mut payload, e.source);
+        write_id(&mut payload, e.target);
+        write_attributes(&mut payload, &e.attributes);
+    }
+    write_section(&mut out, EDGES, &payload);
+    write_section(&mut out, END, &[]);
+    out
+}
+
+fn write_section(out: &mut Vec<u8>, tag: u8, payload: &[u8]) {
+    out.push(tag);
+    write_varint(out, payload.len() as u64);
+    out.extend_from_slice(payload);
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_id(out: &mut Vec<u8>, id: i64) {
+    write_varint(out, ((id << 1) ^ (id >> 63)) as u64);
+}
+
+fn write_string(out: &mut Vec<u8>, s: &str) {
+    write_varint(out, s.len() as u64);
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn write_attributes(out: &mut Vec<u8>, attributes: &Attributes) {
+    write_varint(out, attributes.len() as u64);
+    for (key, value) in attributes.iter() {
+        write_string(out, key);
+        write_string(out, value);
+    }
+}
+
+// Reads a document, reporting errors with the byte offset they were detected at.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn error(&self, message: &str) -> ParseError {
+        ParseError::new(1, &format!("Byte {}: {}", self.offset, message))
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ParseError> {
+        if self.bytes.len() - self.offset < n {
+            return Err(self.error("Unexpected end of document"));
+        }
+        let taken = &self.bytes[self.offset..self.offset + n];
+        self.offset += n;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, ParseError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<u64, ParseError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(self.error("Varint is too long"))
+    }
+
+    // A count of items that each take at least one byte, so that a corrupt count cannot make the
+    // reader allocate more than the document's size.
+    fn count(&mut self) -> Result<usize, ParseError> {
+        let count = self.varint()?;
+        if count > (self.bytes.len() - self.offset) as u64 {
+            return Err(self.error("Count exceeds the size of the document"));
+        }
+        Ok(count as usize)
+    }
+
+    fn id(&mut self) -> Result<i64, ParseError> {
+        let zigzag = self.varint()?;
+        Ok((zigzag >> 1) as i64 ^ -((zigzag & 1) as i64))
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        let len = self.count()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| self.error("String is not UTF-8"))
+    }
+
+    fn attributes(&mut self) -> Result<Attributes, ParseError> {
+        let mut attributes = Attributes::new();
+        for _ in 0..self.count()? {
+            let key = self.string()?;
+            attributes.insert(key, self.string()?);
+        }
+        Ok(attributes)
+    }
+
+    fn at_end(&self) -> bool {
+        self.offset == self.bytes.len()
+    }
+}
+
+// Read a document written by `to_binary`, rejecting duplicate ids and dangling edges.
+pub fn from_binary(bytes: &[u8]) -> Result<Graph, ParseError> {
+    let mut header = Reader { bytes, offset: 0 };
+    if header.take(4).ok() != Some(&MAGIC[..]) {
+        return Err(ParseError::new(1, "Not a binary document"));
+    }
+    let mut version = [0u8; 4];
+    version.copy_from_slice(header.take(4)?);
+    let version = u32::from_le_bytes(version);
+    if version > VERSION {
+        return Err(ParseError::new(
+            1,
+            &format!(
+                "Document version {} is newer than the supported version {}",
+                version, VERSION
+            ),
+        ));
+    }
+
+    let mut graph = Graph::new();
+    // Vertices are kept until every section has been read, so that edges can be checked
+    // against them whatever order a future writer puts sections in.
+    let mut edges = Vec::new();
+    let mut reader = header;
+    loop {
+        let tag = reader.byte()?;
+        let len = reader.count()?;
+        if tag == END {
+            break;
+        }
+        let mut section = Reader {
+            bytes: &bytes[..reader.offset + len],
+            offset: reader.offset,
+        };
+        match tag {
+            PROPERTIES => graph.properties = section.attributes()?,
+            VERTICES => {
+                for _ in 0..section.count()? {
+                    let mut v = Vertex::new(section.id()?);
+                    v.attributes = section.attributes()?;
+                    let id = v.id;
+                    if graph.add_vertex(v).operations.is_empty() {
+                        return Err(section.error(&format!("Duplicate vertex {}", id)));
+                    }
+                }
+            }
+            EDGES => {
+                for _ in 0..section.count()? {
+                    let mut e = Edge::new(section.id()?, section.id()?, section.id()?);
+                    e.attributes = section.attributes()?;
+                    edges.push((section.offset, e));
+                }
+            }
+            _ if tag & OPTIONAL != 0 => section.offset += len,
+            _ => return Err(reader.error(&format!("Unknown required section {}", tag))),
+        }
+        if !section.at_end() {
+            return Err(section.error("Section has trailing bytes"));
+        }
+        reader.offset = section.offset;
+    }
+    if !reader.at_end() {
+        return Err(reader.error("Trailing bytes after the end of the document"));
+    }
+
+    for (offset, e) in edges {
+        let error = |message: String| ParseError::new(1, &format!("Byte {}: {}", offset, message));
+        for endpoint in [e.source, e.target].iter() {
+            if !graph.vertices.contains_key(endpoint) {
+                return Err(error(format!(
+                    "Edge {} refers to unknown vertex {}",
+                    e.id, endpoint
+                )));
+            }
+        }
+        let id = e.id;
+        if graph.add_edge(e).operations.is_empty() {
+            return Err(error(format!("Duplicate edge {}", id)));
+        }
+    }
+    Ok(graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Graph {
+        let mut g = Graph::new();
+        g.properties
+            .insert("name".to_string(), "cities".to_string());
+        g.add_vertex(Vertex::new(-300).with_attribute("label", "Zürich"));
+        g.add_vertex(Vertex::new(i64::MAX));
+        g.add_edge(Edge::new(0, -300, i64::MAX).with_attribute("weight", "2.5"));
+        g
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes = to_binary(&sample());
+        assert_eq!(b"GRIB\x01\x00\x00\x00", &bytes[..8]);
+        assert_eq!(Ok(sample()), from_binary(&bytes));
+    }
+
+    #[test]
+    fn unknown_sections_and_versions() {
+        let document = to_binary(&sample());
+        let (sections, end) = document.split_at(document.len() - 2);
+        let with_section = |section: &[u8]| [sections, section, end].concat();
+        assert_eq!(
+            Ok(sample()),
+            from_binary(&with_section(&[OPTIONAL | 9, 2, 0xff, 0xff]))
+        );
+        assert!(from_binary(&with_section(&[9, 0])).is_err());
+
+        let mut newer = to_binary(&sample());
+        newer[4] = 2;
+        assert_eq!(
+            Err(ParseError::new(
+                1,
+                "Document version 2 is newer than the supported version 1"
+            )),
+            from_binary(&newer)
+        );
+    }
+
+    #[test]
+    fn corrupt_documents_are_rejected() {
+        let bytes = to_binary(&sample());
+        for len in 0..bytes.len() {
+            assert!(
+                from_binary(&bytes[..len]).is_err(),
+                "prefix of {} bytes",
+                len
+            );
+        }
+        let mut corrupt = bytes.clone();
+        // A vertex count far larger than the document.
+        let vertices = corrupt.iter().position(|b| *b == VERTICES).unwrap();
+        corrupt[vertices + 2] = 0x7f;
+        assert!(from_binary(&corrupt).is_err());
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::format::ParseError;
+use crate::graph::Attributes;
+use crate::graph::Edge;
+use crate::graph::Graph;
+use crate::graph::Vertex;
+
+// The first line of clipboard text, which also tells other programs' text apart.
+const HEADER: &str = "gri-clipboard 1";
+
+/**
+ * Render a subgraph for the clipboard. Ids are not kept, since they only mean something in the
+ * document the subgraph was copied from: vertices are written in order of id and numbered from 0,
+ * and edges refer to their endpoints by that number. Edges with an endpoint outside the subgraph
+ * are left out.
+ *
+ *   gri-clipboard 1
+ *   v "label"="a"
+ *   v
+ *   e 0 1 "weight"="2"
+ */
+pub fn to_clipboard(graph: &Graph) -> String {
+    let mut out = format!("{}\n", HEADER);
+
+    let mut vertices: Vec<_> = graph.vertices.values().collect();
+    vertices.sort_by_key(|v| v.id);
+    let positions: BTreeMap<i64, usize> = vertices
+        .iter()
+        .enumerate()
+        .map(|(position, v)| (v.id, position))
+        .collect();
+    for v in vertices {
+        out.push('v');
+        write_attributes(&mut out, &v.attributes);
+    }
+
+    let mut edges: Vec<_> = graph.edges.values().collect();
+    edges.sort_by_key(|e| e.id);
+    for e in edges {
+        if let (Some(source), Some(target)) = (positions.get(&e.source), positions.get(&e.target)) {
+            write!(out, "e {} {}", source, target).unwrap();
+            write_attributes(&mut out, &e.attributes);
+        }
+    }
+    out
+}
+
+fn write_attributes(out: &mut String, attributes: &Attributes) {
+    let sorted: BTreeMap<&String, &String> = attributes.iter().collect();
+    for (key, value) in sorted {
+        write!(out, " {}={}", quote(key), quote(value)).unwrap();
+    }
+    out.push('\n');
+}
+
+fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/**
+ * Read clipboard text written by `to_clipboard`. Vertices are given ids from 0 in the order they
+ * are listed, and so are edges; pasting renumbers them for the document anyway.
+ */
+pub fn from_clipboard(text: &str) -> Result<Graph, ParseError> {
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, header)) if header.trim_end() == HEADER => {}
+        _ => return Err(ParseError::new(1, "Not a graph on the clipboard")),
+    }
+    let mut graph = Graph::new();
+    for (i, line) in lines {
+        let number = i + 1;
+        let mut words = Words {
+            rest: line.trim(),
+            line: number,
+        };
+        match words.bare() {
+            "" => {}
+            "v" => {
+                let id = graph.vertices.len() as i64;
+                graph.add_vertex(Vertex {
+                    id,
+                    attributes: words.attributes()?,
+                });
+            }
+            "e" => {
+                let mut endpoint = || match words.bare().parse::<i64>() {
+                    Ok(position) if graph.vertices.contains_key(&position) => Ok(position),
+                    _ => Err(ParseError::new(number, "Expected a vertex number")),
+                };
+                let (source, target) = (endpoint()?, endpoint()?);
+                let edge = Edge {
+                    attributes: words.attributes()?,
+                    ..Edge::new(graph.edges.len() as i64, source, target)
+                };
+                graph.add_edge(edge);
+            }
+            other => {
+                return Err(ParseError::new(
+                    number,
+                    &format!("Unexpected line '{}'", other),
+                ))
+            }
+        }
+    }
+    Ok(graph)
+}
+
+struct Words<'a> {
+    rest: &'a str,
+    line: usize,
+}
+
+impl<'a> Words<'a> {
+    fn bare(&mut self) -> &'a str {
+        let (word, rest) = self
+            .rest
+            .split_once(char::is_whitespace)
+            .unwrap_or((self.rest, ""));
+        self.rest = rest.trim_start();
+        word
+    }
+
+    fn quoted(&mut self) -> Result<String, ParseError> {
+        let mut chars = self.rest.char_indices();
+        if chars.next().map(|(_, c)| c) != Some('"') {
+            return Err(ParseError::new(self.line, "Expected a quoted string"));
+        }
+        let mut text = String::new();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.rest = &self.rest[i + 1..];
+                    return Ok(text);
+                }
+                '\\' => match chars.next().map(|(_, c)| c) {
+                    Some('n') => text.push('\n'),
+                    Some(c) => text.push(c),
+                    None => break,
+                },
+                c => text.push(c),
+            }
+        }
+        Err(ParseError::new(self.line, "Unterminated string"))
+    }
+
+    fn attributes(&mut self) -> Result<Attributes, ParseError> {
+        let mut attributes = Attributes::new();
+        while !self.rest.is_empty() {
+            let key = self.quoted()?;
+            match self.rest.strip_prefix('=') {
+                Some(rest) => self.rest = rest,
+                None => return Err(ParseError::new(self.line, "Expected '='")),
+            }
+            let value = self.quoted()?;
+            attributes.insert(key, value);
+            self.rest = self.rest.trim_start();
+        }
+        Ok(attributes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn structure_and_attributes_survive_the_clipboard() {
+        let mut g = Graph::new();
+        g.add_vertex(Vertex::new(7).with_attribute("label", "say \"hi\"\n"));
+        g.add_vertex(Vertex::new(3));
+        g.add_vertex(Vertex::new(9));
+        g.add_edge(Edge::new(4, 7, 3).with_attribute("weight", "2"));
+        g.add_edge(Edge::new(5, 3, 3));
+        g.edges.insert(6, Edge::new(6, 7, 12));
+
+        let text = to_clipboard(&g);
+        assert_eq!(
+            "gri-clipboard 1\nv\nv \"label\"=\"say \\\"hi\\\"\\n\"\nv\n\
+             e 1 0 \"weight\"=\"2\"\ne 0 0\n",
+            text
+        );
+
+        let pasted = from_clipboard(&text).unwrap();
+        assert_eq!(Some("say \"hi\"\n"), pasted.vertices[&1].attribute("label"));
+        assert_eq!(
+            Edge::new(0, 1, 0).with_attribute("weight", "2"),
+            pasted.edges[&0]
+        );
+        assert_eq!(2, pasted.edges.len());
+    }
+
+    #[test]
+    fn other_text_is_rejected() {
+        assert!(from_clipboard("hello").is_err());
+        assert_eq!(
+            Err(ParseError::new(3, "Expected a vertex number")),
+            from_clipboard("gri-clipboard 1\nv\ne 0 1\n")
+        );
+        assert_eq!(
+            2,
+            from_clipboard("gri-clipboard 1\nv \"a\"=\"b\n")
+                .unwrap_err()
+                .line
+        );
+    }
+}
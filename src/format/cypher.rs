@@ -0,0 +1,107 @@
+use std::fmt::Write;
+
+use crate::graph::Attributes;
+use crate::graph::Graph;
+
+/**
+ * Render the graph as a Cypher statement that creates it, e.g., for loading into Neo4j. Each
+ * vertex becomes a `Vertex` node and each edge an `EDGE` relationship, with the element's id as
+ * its `id` property and its attributes as string properties. Everything is created by a single
+ * statement, since Cypher variables do not outlive the statement they are bound in.
+ */
+pub fn to_cypher(graph: &Graph) -> String {
+    let mut patterns = Vec::new();
+
+    let mut vertices: Vec<_> = graph.vertices.values().collect();
+    vertices.sort_by_key(|v| v.id);
+    for v in vertices {
+        patterns.push(format!(
+            "({}:Vertex {})",
+            variable(v.id),
+            properties(v.id, &v.attributes)
+        ));
+    }
+
+    let mut edges: Vec<_> = graph.edges.values().collect();
+    edges.sort_by_key(|e| e.id);
+    for e in edges {
+        patterns.push(format!(
+            "({})-[:EDGE {}]->({})",
+            variable(e.source),
+            properties(e.id, &e.attributes),
+            variable(e.target)
+        ));
+    }
+
+    if patterns.is_empty() {
+        return String::new();
+    }
+    let mut out = String::from("CREATE\n");
+    writeln!(out, "  {};", patterns.join(",\n  ")).unwrap();
+    out
+}
+
+fn variable(id: i64) -> String {
+    if id < 0 {
+        format!("vm{}", -(id as i128))
+    } else {
+        format!("v{}", id)
+    }
+}
+
+// An attribute named `id` would clash with the element's id, so it is left out.
+fn properties(id: i64, attributes: &Attributes) -> String {
+    let mut out = format!("{{id: {}", id);
+    for (key, value) in attributes.iter().filter(|(key, _)| key.as_str() != "id") {
+        write!(out, ", {}: '{}'", key_name(key), escape(value)).unwrap();
+    }
+    out.push('}');
+    out
+}
+
+// Keys that are not identifiers are quoted with backticks, which are escaped by doubling.
+fn key_name(key: &str) -> String {
+    let is_identifier = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if is_identifier {
+        key.to_string()
+    } else {
+        format!("`{}`", key.replace('`', "``"))
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\'', "\\'")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Edge;
+    use crate::graph::Vertex;
+
+    #[test]
+    fn vertices_edges_and_attributes() {
+        let mut g = Graph::new();
+        g.add_vertex(Vertex::new(1).with_attribute("label", "O'Hare"));
+        g.add_vertex(Vertex::new(-1).with_attribute("hub.size", "3"));
+        g.add_edge(
+            Edge::new(0, -1, 1)
+                .with_attribute("weight", "2")
+                .with_attribute("id", "x"),
+        );
+
+        let expected = "CREATE
+  (vm1:Vertex {id: -1, `hub.size`: '3'}),
+  (v1:Vertex {id: 1, label: 'O\\'Hare'}),
+  (vm1)-[:EDGE {id: 0, weight: '2'}]->(v1);
+";
+        assert_eq!(expected, to_cypher(&g));
+        assert_eq!("", to_cypher(&Graph::new()));
+    }
+}
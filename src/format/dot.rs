@@ -0,0 +1,557 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::format::ParseError;
+use crate::graph::Attributes;
+use crate::graph::Edge;
+use crate::graph::Graph;
+use crate::graph::Vertex;
+
+/**
+ * Render the graph in Graphviz's DOT language. Edges are directed, so the graph is written as a
+ * digraph. Every attribute (such as `label` or `weight`) is written as a DOT attribute of the
+ * same name, and graph properties are written as graph attributes. Vertices and edges are
+ * written in order of id, so the output is deterministic.
+ */
+pub fn to_dot(graph: &Graph) -> String {
+    let mut out = String::from("digraph {\n");
+    if !graph.properties.is_empty() {
+        writeln!(out, "  graph{};", attribute_list(&graph.properties)).unwrap();
+    }
+
+    let mut vertices: Vec<_> = graph.vertices.values().collect();
+    vertices.sort_by_key(|v| v.id);
+    for v in vertices {
+        writeln!(out, "  {}{};", v.id, attribute_list(&v.attributes)).unwrap();
+    }
+
+    let mut edges: Vec<_> = graph.edges.values().collect();
+    edges.sort_by_key(|e| e.id);
+    for e in edges {
+        writeln!(
+            out,
+            "  {} -> {}{};",
+            e.source,
+            e.target,
+            attribute_list(&e.attributes)
+        )
+        .unwrap();
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+fn attribute_list(attributes: &Attributes) -> String {
+    if attributes.is_empty() {
+        return String::new();
+    }
+    let pairs: Vec<String> = attributes
+        .iter()
+        .map(|(key, value)| format!("{}={}", quote(key), quote(value)))
+        .collect();
+    format!(" [{}]", pairs.join(", "))
+}
+
+// Quote a string as a DOT ID, escaping the characters that would end it early.
+pub(crate) fn quote(s: &str) -> String {
+    let is_plain = !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') && {
+        let first = s.chars().next().unwrap();
+        !first.is_ascii_digit() || s.chars().all(|c| c.is_ascii_digit())
+    };
+    if is_plain {
+        return s.to_string();
+    }
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/**
+ * Read a graph written in Graphviz's DOT language. Node statements, edge statements (including
+ * chains such as `a -> b -> c`), attribute lists and `node`/`edge` default attributes are
+ * supported, and graph attributes are read as graph properties. Ports are skipped, and subgraphs
+ * are rejected. Undirected edges are read as directed from the first node to the second.
+ *
+ * Nodes named by an integer keep it as their id. Other nodes are numbered after the largest such
+ * id in order of appearance, and keep their name as their `label` unless they set one. Edges are
+ * numbered from 0 in order of appearance.
+ */
+pub fn from_dot(text: &str) -> Result<Graph, ParseError> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        position: 0,
+        graph_attributes: Attributes::new(),
+        node_defaults: Attributes::new(),
+        edge_defaults: Attributes::new(),
+        names: Vec::new(),
+        nodes: HashMap::new(),
+        edges: Vec::new(),
+    };
+    parser.graph()?;
+    Ok(parser.build())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Id(String),
+    // Either `->` or `--`.
+    EdgeOp,
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
+    Equals,
+    Semicolon,
+    Comma,
+    Colon,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Id(id) => format!("'{}'", id),
+            Token::EdgeOp => "an edge operator".to_string(),
+            Token::LeftBrace => "'{'".to_string(),
+            Token::RightBrace => "'}'".to_string(),
+            Token::LeftBracket => "'['".to_string(),
+            Token::RightBracket => "']'".to_string(),
+            Token::Equals => "'='".to_string(),
+            Token::Semicolon => "';'".to_string(),
+            Token::Comma => "','".to_string(),
+            Token::Colon => "':'".to_string(),
+        }
+    }
+}
+
+// Split DOT text into tokens, each paired with the line it starts on.
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let start = line;
+        let token = match c {
+            '\n' => {
+                line += 1;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '{' => Token::LeftBrace,
+            '}' => Token::RightBrace,
+            '[' => Token::LeftBracket,
+            ']' => Token::RightBracket,
+            '=' => Token::Equals,
+            ';' => Token::Semicolon,
+            ',' => Token::Comma,
+            ':' => Token::Colon,
+            '#' => {
+                skip_line(&mut chars);
+                continue;
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                skip_line(&mut chars);
+                continue;
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if previous == '*' => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            previous = c;
+                        }
+                        None => return Err(ParseError::new(start, "Unterminated comment")),
+                    }
+                }
+                continue;
+            }
+            '-' if chars.peek() == Some(&'>') || chars.peek() == Some(&'-') => {
+                chars.next();
+                Token::EdgeOp
+            }
+            '"' => {
+                let mut id = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('"') => id.push('"'),
+                            Some('\\') => id.push('\\'),
+                            // A backslash before a newline continues the string on the next line.
+                            Some('\n') => line += 1,
+                            Some(c) => {
+                                id.push('\\');
+                                id.push(c);
+                            }
+                            None => return Err(ParseError::new(start, "Unterminated string")),
+                        },
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            id.push(c);
+                        }
+                        None => return Err(ParseError::new(start, "Unterminated string")),
+                    }
+                }
+                Token::Id(id)
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '-' || c == '.' => {
+                let mut id = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_' || c == '.') {
+                        break;
+                    }
+                    id.push(c);
+                    chars.next();
+                }
+                Token::Id(id)
+            }
+            other => {
+                return Err(ParseError::new(
+                    line,
+                    &format!("Unexpected character '{}'", other),
+                ))
+            }
+        };
+        tokens.push((token, start));
+    }
+    Ok(tokens)
+}
+
+fn skip_line(chars: &mut Peekable<Chars>) {
+    while let Some(&c) = chars.peek() {
+        if c == '\n' {
+            break;
+        }
+        chars.next();
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    graph_attributes: Attributes,
+    node_defaults: Attributes,
+    edge_defaults: Attributes,
+    // Node names in order of first appearance.
+    names: Vec<String>,
+    nodes: HashMap<String, Attributes>,
+    edges: Vec<(String, String, Attributes)>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or_else(|| self.tokens.last())
+            .map_or(1, |(_, line)| *line)
+    }
+
+    fn error(&self, expected: &str) -> ParseError {
+        let found = match self.peek() {
+            Some(token) => token.describe(),
+            None => "the end of the file".to_string(),
+        };
+        ParseError::new(
+            self.line(),
+            &format!("Expected {}, found {}", expected, found),
+        )
+    }
+
+    fn next_is(&self, token: &Token) -> bool {
+        self.peek() == Some(token)
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), ParseError> {
+        if !self.next_is(&token) {
+            return Err(self.error(&token.describe()));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn id(&mut self, expected: &str) -> Result<String, ParseError> {
+        match self.peek() {
+            Some(Token::Id(id)) => {
+                let id = id.clone();
+                self.position += 1;
+                Ok(id)
+            }
+            _ => Err(self.error(expected)),
+        }
+    }
+
+    fn graph(&mut self) -> Result<(), ParseError> {
+        let mut keyword = self.id("'graph' or 'digraph'")?;
+        if keyword.eq_ignore_ascii_case("strict") {
+            keyword = self.id("'graph' or 'digraph'")?;
+        }
+        if !keyword.eq_ignore_ascii_case("graph") && !keyword.eq_ignore_ascii_case("digraph") {
+            self.position -= 1;
+            return Err(self.error("'graph' or 'digraph'"));
+        }
+        if let Some(Token::Id(_)) = self.peek() {
+            self.position += 1;
+        }
+        self.expect(Token::LeftBrace)?;
+        loop {
+            match self.peek() {
+                Some(Token::RightBrace) => break,
+                Some(Token::Semicolon) => self.position += 1,
+                Some(Token::LeftBrace) => {
+                    return Err(ParseError::new(self.line(), "Subgraphs are not supported"))
+                }
+                Some(_) => self.statement()?,
+                None => return Err(self.error("'}'")),
+            }
+        }
+        self.expect(Token::RightBrace)?;
+        if self.peek().is_some() {
+            return Err(self.error("the end of the file"));
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self) -> Result<(), ParseError> {
+        let line = self.line();
+        let id = self.id("a statement")?;
+        let keyword = id.to_lowercase();
+        if keyword == "subgraph" {
+            return Err(ParseError::new(line, "Subgraphs are not supported"));
+        }
+        if self.next_is(&Token::LeftBracket) {
+            match keyword.as_str() {
+                "node" => {
+                    let attributes = self.attribute_lists()?;
+                    self.node_defaults.extend(attributes);
+                    return Ok(());
+                }
+                "edge" => {
+                    let attributes = self.attribute_lists()?;
+                    self.edge_defaults.extend(attributes);
+                    return Ok(());
+                }
+                "graph" => {
+                    let attributes = self.attribute_lists()?;
+                    self.graph_attributes.extend(attributes);
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+        if self.next_is(&Token::Equals) {
+            // A graph attribute.
+            self.position += 1;
+            let value = self.id("an attribute value")?;
+            self.graph_attributes.insert(id, value);
+            return Ok(());
+        }
+        self.port()?;
+
+        let mut chain = vec![id];
+        while self.next_is(&Token::EdgeOp) {
+            self.position += 1;
+            if self.next_is(&Token::LeftBrace) {
+                return Err(ParseError::new(self.line(), "Subgraphs are not supported"));
+            }
+            chain.push(self.id("a node")?);
+            self.port()?;
+        }
+        let attributes = self.attribute_lists()?;
+
+        if chain.len() == 1 {
+            self.node(&chain[0]).extend(attributes);
+            return Ok(());
+        }
+        for name in chain.iter() {
+            self.node(name);
+        }
+        for pair in chain.windows(2) {
+            let mut edge_attributes = self.edge_defaults.clone();
+            edge_attributes.extend(attributes.clone());
+            self.edges
+                .push((pair[0].clone(), pair[1].clone(), edge_attributes));
+        }
+        Ok(())
+    }
+
+    // Ports only affect drawing, so they are skipped.
+    fn port(&mut self) -> Result<(), ParseError> {
+        while self.next_is(&Token::Colon) {
+            self.position += 1;
+            self.id("a port")?;
+        }
+        Ok(())
+    }
+
+    fn attribute_lists(&mut self) -> Result<Attributes, ParseError> {
+        let mut attributes = Attributes::new();
+        while self.next_is(&Token::LeftBracket) {
+            self.position += 1;
+            loop {
+                match self.peek() {
+                    Some(Token::RightBracket) => {
+                        self.position += 1;
+                        break;
+                    }
+                    Some(Token::Comma) | Some(Token::Semicolon) => self.position += 1,
+                    _ => {
+                        let key = self.id("an attribute name or ']'")?;
+                        self.expect(Token::Equals)?;
+                        let value = self.id("an attribute value")?;
+                        attributes.insert(key, value);
+                    }
+                }
+            }
+        }
+        Ok(attributes)
+    }
+
+    // The attributes of the named node, creating it with the current defaults if it is new.
+    fn node(&mut self, name: &str) -> &mut Attributes {
+        if !self.nodes.contains_key(name) {
+            self.names.push(name.to_string());
+            self.nodes
+                .insert(name.to_string(), self.node_defaults.clone());
+        }
+        self.nodes.get_mut(name).unwrap()
+    }
+
+    fn build(mut self) -> Graph {
+        // Names such as "7" and "07" denote different nodes, so only the first keeps the id.
+        let mut used = HashSet::new();
+        let numeric: Vec<Option<i64>> = self
+            .names
+            .iter()
+            .map(|name| name.parse::<i64>().ok().filter(|id| used.insert(*id)))
+            .collect();
+        let mut next_id = used.iter().max().map_or(0, |max| max + 1);
+
+        let mut graph = Graph::new();
+        graph.properties = self.graph_attributes;
+        let mut ids = HashMap::new();
+        for (name, numeric_id) in self.names.into_iter().zip(numeric) {
+            let mut v = Vertex::new(numeric_id.unwrap_or(next_id));
+            v.attributes = self.nodes.remove(&name).unwrap();
+            if numeric_id.is_none() {
+                next_id += 1;
+                v.attributes
+                    .entry("label".to_string())
+                    .or_insert_with(|| name.clone());
+            }
+            ids.insert(name, v.id);
+            graph.add_vertex(v);
+        }
+        for (id, (source, target, attributes)) in self.edges.into_iter().enumerate() {
+            let mut e = Edge::new(id as i64, ids[&source], ids[&target]);
+            e.attributes = attributes;
+            graph.add_edge(e);
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_graph() {
+        assert_eq!("digraph {\n}\n", to_dot(&Graph::new()));
+    }
+
+    #[test]
+    fn labels_and_weights() {
+        let mut g = Graph::new();
+        g.add_vertex(Vertex::new(1).with_attribute("label", "New York"));
+        g.add_vertex(Vertex::new(0));
+        g.add_edge(Edge::new(0, 1, 0).with_attribute("weight", "2.5"));
+        g.add_edge(Edge::new(1, 0, 0).with_attribute("label", "say \"hi\""));
+
+        let expected = "digraph {
+  0;
+  1 [label=\"New York\"];
+  1 -> 0 [weight=\"2.5\"];
+  0 -> 0 [label=\"say \\\"hi\\\"\"];
+}
+";
+        assert_eq!(expected, to_dot(&g));
+    }
+
+    #[test]
+    fn read_what_was_written() {
+        let mut g = Graph::new();
+        g.add_vertex(Vertex::new(1).with_attribute("label", "New York"));
+        g.add_vertex(Vertex::new(0));
+        g.add_edge(Edge::new(0, 1, 0).with_attribute("weight", "2.5"));
+        g.add_edge(Edge::new(1, 0, 0).with_attribute("label", "say \"hi\""));
+        g.properties
+            .insert("label".to_string(), "Cities".to_string());
+
+        assert_eq!(Ok(g.clone()), from_dot(&to_dot(&g)));
+    }
+
+    #[test]
+    fn named_nodes_chains_and_defaults() {
+        let text = "strict digraph G {
+  rankdir=LR; // a graph attribute
+  node [shape=box]
+  a -> b -> 3 [weight=2];
+  /* b is already
+     declared */
+  b [label=\"B\"]
+  edge [color=red]
+  3:n -> a
+}";
+        let mut expected = Graph::new();
+        expected
+            .properties
+            .insert("rankdir".to_string(), "LR".to_string());
+        expected.add_vertex(
+            Vertex::new(4)
+                .with_attribute("label", "a")
+                .with_attribute("shape", "box"),
+        );
+        expected.add_vertex(
+            Vertex::new(5)
+                .with_attribute("label", "B")
+                .with_attribute("shape", "box"),
+        );
+        expected.add_vertex(Vertex::new(3).with_attribute("shape", "box"));
+        expected.add_edge(Edge::new(0, 4, 5).with_attribute("weight", "2"));
+        expected.add_edge(Edge::new(1, 5, 3).with_attribute("weight", "2"));
+        expected.add_edge(Edge::new(2, 3, 4).with_attribute("color", "red"));
+
+        assert_eq!(Ok(expected), from_dot(text));
+    }
+
+    #[test]
+    fn errors_report_the_line() {
+        assert_eq!(
+            Err(ParseError::new(2, "Expected an attribute value, found ']'")),
+            from_dot("digraph {\n  a [color=]\n}")
+        );
+        assert_eq!(
+            Err(ParseError::new(1, "Subgraphs are not supported")),
+            from_dot("graph { a -- { b c } }")
+        );
+        assert_eq!(
+            Err(ParseError::new(
+                1,
+                "Expected '}', found the end of the file"
+            )),
+            from_dot("digraph { a -> b")
+        );
+    }
+}
@@ -0,0 +1,651 @@
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use crate::format::ParseError;
+use crate::graph::Attributes;
+use crate::graph::Edge;
+use crate::graph::Graph;
+use crate::graph::Vertex;
+
+/**
+ * Render the graph in the Graph Modelling Language. Every attribute is written as a key of its
+ * node or edge, such as `label` or `weight`, and graph properties as keys of the graph. Keys
+ * containing dots are written as nested lists, so `graphics.x` becomes `graphics [ x ... ]`.
+ * Other characters GML does not allow in keys are replaced by underscores. Numeric values are
+ * written as numbers and all others as strings.
+ */
+pub fn to_gml(graph: &Graph) -> String {
+    let mut out = String::from("graph [\n  directed 1\n");
+    let properties: Attributes = graph
+        .properties
+        .iter()
+        .filter(|(key, _)| key.as_str() != "directed")
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    write_attributes(&mut out, 1, &properties);
+
+    let mut vertices: Vec<_> = graph.vertices.values().collect();
+    vertices.sort_by_key(|v| v.id);
+    for v in vertices {
+        writeln!(out, "  node [\n    id {}", v.id).unwrap();
+        write_attributes(&mut out, 2, &without(&v.attributes, &["id"]));
+        out.push_str("  ]\n");
+    }
+
+    let mut edges: Vec<_> = graph.edges.values().collect();
+    edges.sort_by_key(|e| e.id);
+    for e in edges {
+        writeln!(
+            out,
+            "  edge [\n    id {}\n    source {}\n    target {}",
+            e.id, e.source, e.target
+        )
+        .unwrap();
+        write_attributes(
+            &mut out,
+            2,
+            &without(&e.attributes, &["id", "source", "target"]),
+        );
+        out.push_str("  ]\n");
+    }
+
+    out.push_str("]\n");
+    out
+}
+
+// The attributes other than those GML reserves for the element's structure.
+fn without(attributes: &Attributes, reserved: &[&str]) -> Attributes {
+    attributes
+        .iter()
+        .filter(|(key, _)| !reserved.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+fn write_attributes(out: &mut String, depth: usize, attributes: &Attributes) {
+    // Group dotted keys by their first component, to be written as nested lists.
+    let mut nested: BTreeMap<String, Attributes> = BTreeMap::new();
+    for (key, value) in attributes.iter() {
+        match key.split_once('.') {
+            Some((list, rest)) if !list.is_empty() && !rest.is_empty() => {
+                nested
+                    .entry(list.to_string())
+                    .or_default()
+                    .insert(rest.to_string(), value.clone());
+            }
+            _ => {
+                writeln!(
+                    out,
+                    "{}{} {}",
+                    "  ".repeat(depth),
+                    gml_key(key),
+                    gml_value(value)
+                )
+                .unwrap();
+            }
+        }
+    }
+    for (list, attributes) in nested.iter() {
+        writeln!(out, "{}{} [", "  ".repeat(depth), gml_key(list)).unwrap();
+        write_attributes(out, depth + 1, attributes);
+        writeln!(out, "{}]", "  ".repeat(depth)).unwrap();
+    }
+}
+
+fn gml_key(key: &str) -> String {
+    let mut sanitized: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if !sanitized.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        sanitized.insert(0, '_');
+    }
+    sanitized
+}
+
+fn gml_value(value: &str) -> String {
+    if is_number(value) {
+        value.to_string()
+    } else {
+        format!("\"{}\"", value.replace('&', "&amp;").replace('"', "&quot;"))
+    }
+}
+
+// Whether the text is a GML integer or real, i.e., digits with an optional sign, fraction and
+// exponent.
+fn is_number(text: &str) -> bool {
+    let digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    let unsigned = text.strip_prefix(['+', '-']).unwrap_or(text);
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (unsigned, None),
+    };
+    let mantissa_ok = match mantissa.split_once('.') {
+        Some((whole, fraction)) => digits(whole) && digits(fraction),
+        None => digits(mantissa),
+    };
+    let exponent_ok = exponent.is_none_or(|e| digits(e.strip_prefix(['+', '-']).unwrap_or(e)));
+    mantissa_ok && exponent_ok
+}
+
+/**
+ * Read a graph written in the Graph Modelling Language. The first top-level `graph` list is read;
+ * its `node` and `edge` lists become vertices and edges, and its other keys become graph
+ * properties. Each node needs an integer `id`, and each edge a `source` and `target` naming one.
+ * Edges without an `id` are numbered after the largest id given. Other keys of nodes and edges,
+ * such as `label` and `weight`, become attributes, with nested lists flattened into dotted keys.
+ * Edges are read as directed whether or not the graph is.
+ */
+pub fn from_gml(text: &str) -> Result<Graph, ParseError> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        position: 0,
+        depth: 0,
+    };
+    let top = parser.list(None)?;
+    let items = top
+        .into_iter()
+        .find_map(|item| match item {
+            (key, Value::List(items), _) if key == "graph" => Some(items),
+            _ => None,
+        })
+        .ok_or_else(|| ParseError::new(1, "No graph list"))?;
+
+    let mut graph = Graph::new();
+    let mut edges = Vec::new();
+    for (key, value, line) in items {
+        match (key.as_str(), value) {
+            ("node", Value::List(items)) => {
+                let mut attributes = flatten(items);
+                let id = integer(&mut attributes, "id", "Node", line)?;
+                let mut v = Vertex::new(id);
+                v.attributes = attributes;
+                if graph.add_vertex(v).operations.is_empty() {
+                    return Err(ParseError::new(line, &format!("Duplicate node {}", id)));
+                }
+            }
+            ("edge", Value::List(items)) => edges.push((flatten(items), line)),
+            ("directed", Value::Scalar(_)) => {}
+            (_, Value::Scalar(value)) => {
+                graph.properties.insert(key, value);
+            }
+            (_, value) => {
+                graph.properties.extend(flatten(vec![(key, value, line)]));
+            }
+        }
+    }
+
+    let edge_ids: HashSet<i64> = edges
+        .iter()
+        .filter_map(|(attributes, _)| attributes.get("id"))
+        .filter_map(|id| id.parse::<i64>().ok())
+        .collect();
+    let mut next_id = edge_ids.iter().max().map_or(0, |max| max + 1);
+    for (mut attributes, line) in edges {
+        let id = if attributes.contains_key("id") {
+            integer(&mut attributes, "id", "Edge", line)?
+        } else {
+            next_id += 1;
+            next_id - 1
+        };
+        let source = integer(&mut attributes, "source", "Edge", line)?;
+        let target = integer(&mut attributes, "target", "Edge", line)?;
+        for endpoint in [source, target].iter() {
+            if !graph.vertices.contains_key(endpoint) {
+                return Err(ParseError::new(
+                    line,
+                    &format!("Edge {} refers to unknown node {}", id, endpoint),
+                ));
+            }
+        }
+        let mut e = Edge::new(id, source, target);
+        e.attributes = attributes;
+        if graph.add_edge(e).operations.is_empty() {
+            return Err(ParseError::new(line, &format!("Duplicate edge {}", id)));
+        }
+    }
+
+    Ok(graph)
+}
+
+// Remove and parse the integer stored under a key that an element requires.
+fn integer(
+    attributes: &mut Attributes,
+    key: &str,
+    element: &str,
+    line: usize,
+) -> Result<i64, ParseError> {
+    match attributes.remove(key) {
+        Some(value) => value.parse::<i64>().map_err(|_| {
+            ParseError::new(
+                line,
+                &format!(
+                    "{} {} must be an integer, but was '{}'",
+                    element, key, value
+                ),
+            )
+        }),
+        None => Err(ParseError::new(
+            line,
+            &format!("{} has no {}", element, key),
+        )),
+    }
+}
+
+fn flatten(items: Vec<(String, Value, usize)>) -> Attributes {
+    let mut attributes = Attributes::new();
+    for (key, value, _) in items {
+        match value {
+            Value::Scalar(value) => {
+                attributes.insert(key, value);
+            }
+            Value::List(items) => {
+                for (nested_key, value) in flatten(items) {
+                    attributes.insert(format!("{}.{}", key, nested_key), value);
+                }
+            }
+        }
+    }
+    attributes
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Key(String),
+    // A number or string, as the text it denotes.
+    Scalar(String),
+    LeftBracket,
+    RightBracket,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    Scalar(String),
+    // Key-value pairs, each with the line its key is on.
+    List(Vec<(String, Value, usize)>),
+}
+
+// Split GML text into tokens, each paired with the line it starts on.
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let start = line;
+        let token = match c {
+            '\n' => {
+                line += 1;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '[' => Token::LeftBracket,
+            ']' => Token::RightBracket,
+            '#' => {
+                while let Some(&c) = chars.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    chars.next();
+                }
+                continue;
+            }
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            value.push(c);
+                        }
+                        None => return Err(ParseError::new(start, "Unterminated string")),
+                    }
+                }
+                Token::Scalar(unescape(&value))
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut key = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    key.push(c);
+                    chars.next();
+                }
+                Token::Key(key)
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' => {
+                let mut number = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    let continues_exponent = (c == '-' || c == '+') && number.ends_with(['e', 'E']);
+                    if !(c.is_ascii_digit()
+                        || c == '.'
+                        || c == 'e'
+                        || c == 'E'
+                        || continues_exponent)
+                    {
+                        break;
+                    }
+                    number.push(c);
+                    chars.next();
+                }
+                if !is_number(&number) {
+                    return Err(ParseError::new(
+                        start,
+                        &format!("Invalid number '{}'", number),
+                    ));
+                }
+                Token::Scalar(number)
+            }
+            other => {
+                return Err(ParseError::new(
+                    line,
+                    &format!("Unexpected character '{}'", other),
+                ))
+            }
+        };
+        tokens.push((token, start));
+    }
+    Ok(tokens)
+}
+
+// Replace the character entities GML uses in strings by the characters they stand for.
+fn unescape(s: &str) -> String {
+    let mut out = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest.find(';').map(|end| (&rest[1..end], end));
+        let decoded = entity.and_then(|(name, end)| {
+            let c = match name {
+                "quot" => Some('"'),
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "apos" => Some('\''),
+                _ => name
+                    .strip_prefix('#')
+                    .and_then(|code| code.parse::<u32>().ok())
+                    .and_then(std::char::from_u32),
+            };
+            c.map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// How deeply lists may nest. Lists are parsed, flattened and dropped recursively, so without a
+// limit a document of nothing but opening brackets would overflow the stack.
+const MAX_DEPTH: usize = 64;
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    // The number of lists open at the position.
+    depth: usize,
+}
+
+impl Parser {
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or_else(|| self.tokens.last())
+            .map_or(1, |(_, line)| *line)
+    }
+
+    // Key-value pairs up to the end of the list begun on the given line, or the end of the text
+    // at the top level.
+    fn list(&mut self, opened: Option<usize>) -> Result<Vec<(String, Value, usize)>, ParseError> {
+        let mut items = Vec::new();
+        loop {
+            let (token, line) = match self.tokens.get(self.position) {
+                Some((token, line)) => (token.clone(), *line),
+                None => match opened {
+                    Some(opened) => {
+                        return Err(ParseError::new(opened, "Unterminated list"));
+                    }
+                    None => return Ok(items),
+                },
+            };
+            self.position += 1;
+            let key = match token {
+                Token::Key(key) => key,
+                Token::RightBracket if opened.is_some() => return Ok(items),
+                Token::RightBracket => return Err(ParseError::new(line, "Unexpected ']'")),
+                Token::Scalar(value) => {
+                    return Err(ParseError::new(
+                        line,
+                        &format!("Expected a key, found '{}'", value),
+                    ))
+                }
+                Token::LeftBracket => {
+                    return Err(ParseError::new(line, "Expected a key, found '['"))
+                }
+            };
+            let value = match self.tokens.get(self.position).cloned() {
+                Some((Token::Scalar(value), _)) => {
+                    self.position += 1;
+                    Value::Scalar(value)
+                }
+                Some((Token::LeftBracket, _)) => {
+                    if self.depth == MAX_DEPTH {
+                        return Err(ParseError::new(
+                            line,
+                            &format!("Lists nest more than {} deep", MAX_DEPTH),
+                        ));
+                    }
+                    self.position += 1;
+                    self.depth += 1;
+                    let items = self.list(Some(line))?;
+                    self.depth -= 1;
+                    Value::List(items)
+                }
+                _ => {
+                    return Err(ParseError::new(
+                        self.line(),
+                        &format!("Expected a value for '{}'", key),
+                    ))
+                }
+            };
+            items.push((key, value, line));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weighted_graph() -> Graph {
+        let mut g = Graph::new();
+        g.properties
+            .insert("label".to_string(), "Karate & \"friends\"".to_string());
+        g.add_vertex(Vertex::new(1).with_attribute("label", "New York"));
+        g.add_vertex(
+            Vertex::new(0)
+                .with_attribute("graphics.x", "1.5")
+                .with_attribute("graphics.y", "-2"),
+        );
+        g.add_edge(Edge::new(0, 1, 0).with_attribute("weight", "2.5"));
+        g.add_edge(Edge::new(3, 0, 0).with_attribute("value", "4"));
+        g
+    }
+
+    #[test]
+    fn write_gml() {
+        let expected = "graph [
+  directed 1
+  label \"Karate &amp; &quot;friends&quot;\"
+  node [
+    id 0
+    graphics [
+      x 1.5
+      y -2
+    ]
+  ]
+  node [
+    id 1
+    label \"New York\"
+  ]
+  edge [
+    id 0
+    source 1
+    target 0
+    weight 2.5
+  ]
+  edge [
+    id 3
+    source 0
+    target 0
+    value 4
+  ]
+]
+";
+        assert_eq!(expected, to_gml(&weighted_graph()));
+    }
+
+    #[test]
+    fn read_what_was_written() {
+        let g = weighted_graph();
+        assert_eq!(Ok(g.clone()), from_gml(&to_gml(&g)));
+    }
+
+    #[test]
+    fn read_network_science_dataset() {
+        let text = "Creator \"someone\"
+graph
+[
+  # A comment
+  directed 0
+  node [ id 1 label \"a\" ]
+  node [ id 2 label \"b\" ]
+  edge [ source 1 target 2 weight 3 ]
+  edge [ source 2 target 1 id 5 ]
+]";
+        let mut expected = Graph::new();
+        expected.add_vertex(Vertex::new(1).with_attribute("label", "a"));
+        expected.add_vertex(Vertex::new(2).with_attribute("label", "b"));
+        expected.add_edge(Edge::new(6, 1, 2).with_attribute("weight", "3"));
+        expected.add_edge(Edge::new(5, 2, 1));
+        assert_eq!(Ok(expected), from_gml(text));
+    }
+
+    #[test]
+    fn errors_report_the_line() {
+        assert_eq!(
+            Err(ParseError::new(3, "Edge 0 refers to unknown node 2")),
+            from_gml("graph [\n  node [ id 1 ]\n  edge [ source 1 target 2 ]\n]")
+        );
+        assert_eq!(
+            Err(ParseError::new(2, "Node has no id")),
+            from_gml("graph [\n  node [ label \"a\" ]\n]")
+        );
+        assert_eq!(
+            Err(ParseError::new(1, "Unterminated list")),
+            from_gml("graph [ node [ id 1 ]")
+        );
+        assert_eq!(
+            Err(ParseError::new(1, "No graph list")),
+            from_gml("Creator \"nobody\"")
+        );
+    }
+
+    // A reproducible stream of pseudo-random numbers below a bound.
+    fn generator(seed: u64) -> impl FnMut(usize) -> usize {
+        let mut state = seed;
+        move |bound| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((state >> 33) as usize) % bound
+        }
+    }
+
+    #[test]
+    fn fuzz_mutated_documents_never_panic() {
+        let valid = to_gml(&weighted_graph());
+        let alphabet: Vec<char> = "[]\"#&;-+.eE0123456789 \nabcdefghijklmnopqrstuvwxyz"
+            .chars()
+            .collect();
+        for seed in 0..500 {
+            let mut next = generator(seed);
+            let mut chars: Vec<char> = valid.chars().collect();
+            for _ in 0..1 + next(8) {
+                let position = next(chars.len() + 1);
+                match next(3) {
+                    0 if position < chars.len() => {
+                        chars.remove(position);
+                    }
+                    1 if position < chars.len() => chars[position] = alphabet[next(alphabet.len())],
+                    _ => chars.insert(position, alphabet[next(alphabet.len())]),
+                }
+            }
+            let mutated: String = chars.into_iter().collect();
+            // Any document that is read must survive a round trip.
+            if let Ok(g) = from_gml(&mutated) {
+                assert_eq!(Ok(g.clone()), from_gml(&to_gml(&g)), "{}", mutated);
+            }
+        }
+    }
+
+    #[test]
+    fn deeply_nested_lists_are_rejected() {
+        let nested = |depth: usize| {
+            format!(
+                "graph [ {}x 1{} ]",
+                "a [ ".repeat(depth),
+                " ]".repeat(depth)
+            )
+        };
+        let g = from_gml(&nested(MAX_DEPTH - 1)).unwrap();
+        assert_eq!(1, g.properties.len());
+        assert_eq!(
+            Err(ParseError::new(
+                1,
+                &format!("Lists nest more than {} deep", MAX_DEPTH)
+            )),
+            from_gml(&nested(100_000))
+        );
+    }
+
+    #[test]
+    fn fuzz_random_graphs_round_trip() {
+        let values = ["0", "-1.5", "1e3", "a b", "&amp;", "\"", "", "x\ny"];
+        for seed in 0..100 {
+            let mut next = generator(seed);
+            let mut g = Graph::new();
+            for id in 0..next(6) as i64 {
+                g.add_vertex(Vertex::new(id).with_attribute("label", values[next(values.len())]));
+            }
+            let n = g.vertices.len() as i64;
+            for id in 0..if n == 0 { 0 } else { next(6) } {
+                g.add_edge(
+                    Edge::new(id as i64, next(n as usize) as i64, next(n as usize) as i64)
+                        .with_attribute("weight", values[next(values.len())]),
+                );
+            }
+            assert_eq!(Ok(g.clone()), from_gml(&to_gml(&g)));
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::format::ParseError;
+use crate::graph::Attributes;
+use crate::graph::Edge;
+use crate::graph::Graph;
+use crate::graph::Vertex;
+
+// The version of the document schema written by `to_json`. Bump it whenever the schema changes
+// in a way older readers would misinterpret.
+pub const VERSION: u32 = 1;
+
+/**
+ * The on-disk layout of a document. It is kept separate from Graph so that the file format stays
+ * stable as the in-memory representation changes:
+ *
+ * {
+ *   "version": 1,
+ *   "properties": { "name": "..." },
+ *   "vertices": [ { "id": 0, "attributes": { "label": "..." } } ],
+ *   "edges": [ { "id": 0, "source": 0, "target": 1, "attributes": { "weight": "2" } } ]
+ * }
+ *
+ * Empty attribute maps may be omitted.
+ */
+#[derive(Debug, Serialize, Deserialize)]
+struct Document {
+    version: u32,
+    #[serde(default, skip_serializing_if = "Attributes::is_empty")]
+    properties: Attributes,
+    #[serde(default)]
+    vertices: Vec<DocumentVertex>,
+    #[serde(default)]
+    edges: Vec<DocumentEdge>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DocumentVertex {
+    id: i64,
+    #[serde(default, skip_serializing_if = "Attributes::is_empty")]
+    attributes: Attributes,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DocumentEdge {
+    id: i64,
+    source: i64,
+    target: i64,
+    #[serde(default, skip_serializing_if = "Attributes::is_empty")]
+    attributes: Attributes,
+}
+
+impl Graph {
+    // Serialize the whole document, with vertices and edges in order of id.
+    pub fn to_json(&self) -> String {
+        let mut vertices: Vec<&Vertex> = self.vertices.values().collect();
+        vertices.sort_by_key(|v| v.id);
+        let mut edges: Vec<&Edge> = self.edges.values().collect();
+        edges.sort_by_key(|e| e.id);
+
+        let document = Document {
+            version: VERSION,
+            properties: self.properties.clone(),
+            vertices: vertices
+                .into_iter()
+                .map(|v| DocumentVertex {
+                    id: v.id,
+                    attributes: v.attributes.clone(),
+                })
+                .collect(),
+            edges: edges
+                .into_iter()
+                .map(|e| DocumentEdge {
+                    id: e.id,
+                    source: e.source,
+                    target: e.target,
+                    attributes: e.attributes.clone(),
+                })
+                .collect(),
+        };
+        let mut json = serde_json::to_string_pretty(&document).unwrap();
+        json.push('\n');
+        json
+    }
+
+    // Read a document written by `to_json`, rejecting duplicate ids and dangling edges.
+    pub fn from_json(text: &str) -> Result<Graph, ParseError> {
+        let document: Document = serde_json::from_str(text)
+            .map_err(|err| ParseError::new(err.line(), &err.to_string()))?;
+        if document.version > VERSION {
+            return Err(ParseError::new(
+                1,
+                &format!(
+                    "Document version {} is newer than the supported version {}",
+                    document.version, VERSION
+                ),
+            ));
+        }
+
+        let mut graph = Graph::new();
+        graph.properties = document.properties;
+        for v in document.vertices {
+            let mut vertex = Vertex::new(v.id);
+            vertex.attributes = v.attributes;
+            if graph.add_vertex(vertex).operations.is_empty() {
+                return Err(ParseError::new(1, &format!("Duplicate vertex {}", v.id)));
+            }
+        }
+        for e in document.edges {
+            for endpoint in [e.source, e.target].iter() {
+                if !graph.vertices.contains_key(endpoint) {
+                    return Err(ParseError::new(
+                        1,
+                        &format!("Edge {} refers to unknown vertex {}", e.id, endpoint),
+                    ));
+                }
+            }
+            let mut edge = Edge::new(e.id, e.source, e.target);
+            edge.attributes = e.attributes;
+            if graph.add_edge(edge).operations.is_empty() {
+                return Err(ParseError::new(1, &format!("Duplicate edge {}", e.id)));
+            }
+        }
+        Ok(graph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut g = Graph::new();
+        g.properties
+            .insert("name".to_string(), "cities".to_string());
+        g.add_vertex(Vertex::new(1).with_attribute("label", "New York"));
+        g.add_vertex(Vertex::new(0));
+        g.add_edge(Edge::new(0, 1, 0).with_attribute("weight", "2.5"));
+
+        let json = g.to_json();
+        assert_eq!(Ok(g), Graph::from_json(&json));
+        assert!(json.contains("\"version\": 1"));
+    }
+
+    #[test]
+    fn minimal_document() {
+        let json = r#"{"version": 1, "vertices": [{"id": 3}]}"#;
+        let mut expected = Graph::new();
+        expected.add_vertex(Vertex::new(3));
+        assert_eq!(Ok(expected), Graph::from_json(json));
+    }
+
+    #[test]
+    fn invalid_documents_are_rejected() {
+        assert_eq!(
+            Err(ParseError::new(
+                1,
+                "Document version 2 is newer than the supported version 1"
+            )),
+            Graph::from_json(r#"{"version": 2}"#)
+        );
+        assert_eq!(
+            Err(ParseError::new(1, "Edge 0 refers to unknown vertex 1")),
+            Graph::from_json(
+                r#"{"version": 1, "vertices": [{"id": 0}], "edges": [{"id": 0, "source": 0, "target": 1}]}"#
+            )
+        );
+        assert_eq!(
+            2,
+            Graph::from_json("{\n  \"version\": \"one\"\n}")
+                .unwrap_err()
+                .line
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+use crate::graph::Graph;
+
+// The distance between neighbouring vertices of the automatic layout.
+const SPACING: f64 = 1.5;
+
+// Parse a `pos` attribute of the form "x,y".
+pub fn parse_position(pos: &str) -> Option<(f64, f64)> {
+    let (x, y) = pos.split_once(',')?;
+    let (x, y) = (x.trim().parse::<f64>().ok()?, y.trim().parse::<f64>().ok()?);
+    if x.is_finite() && y.is_finite() {
+        Some((x, y))
+    } else {
+        None
+    }
+}
+
+/**
+ * The position of every vertex, for formats that draw the graph. Vertices with a valid `pos`
+ * attribute are placed there, and the rest are spaced evenly on a circle, in order of id, so that
+ * a graph without positions is still drawn legibly.
+ */
+pub fn layout(graph: &Graph) -> BTreeMap<i64, (f64, f64)> {
+    let mut positions = BTreeMap::new();
+    let mut unplaced = Vec::new();
+    for v in graph.vertices.values() {
+        match v.attribute("pos").and_then(parse_position) {
+            Some(position) => {
+                positions.insert(v.id, position);
+            }
+            None => unplaced.push(v.id),
+        }
+    }
+    unplaced.sort_unstable();
+
+    let n = unplaced.len();
+    let radius = if n < 2 {
+        0.0
+    } else {
+        SPACING / (2.0 * (PI / n as f64).sin())
+    };
+    for (i, id) in unplaced.into_iter().enumerate() {
+        // Start at the top and go clockwise, as people usually draw by hand.
+        let angle = PI / 2.0 - 2.0 * PI * i as f64 / n as f64;
+        positions.insert(id, (radius * angle.cos(), radius * angle.sin()));
+    }
+    positions
+}
+
+// Where new vertices go so as not to overlap the graph's: in a row to the right of its rightmost
+// vertex, as laid out.
+pub fn positions_after(graph: &Graph, count: usize) -> Vec<(f64, f64)> {
+    let start = layout(graph)
+        .values()
+        .map(|(x, _)| x + SPACING)
+        .fold(None, |right: Option<f64>, x| {
+            Some(right.map_or(x, |r| r.max(x)))
+        })
+        .unwrap_or(0.0);
+    (0..count)
+        .map(|i| (start + SPACING * i as f64, 0.0))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Vertex;
+
+    #[test]
+    fn positions_are_kept_and_the_rest_go_on_a_circle() {
+        let mut g = Graph::new();
+        g.add_vertex(Vertex::new(0).with_attribute("pos", "3, -1.5"));
+        g.add_vertex(Vertex::new(1).with_attribute("pos", "left"));
+        for id in 2..4 {
+            g.add_vertex(Vertex::new(id));
+        }
+
+        let positions = layout(&g);
+        assert_eq!((3.0, -1.5), positions[&0]);
+        let (x1, y1) = positions[&1];
+        let (x3, y3) = positions[&3];
+        assert!(x1.abs() < 1e-9 && y1 > 0.0);
+        assert!(((x1 - x3).hypot(y1 - y3) - SPACING).abs() < 1e-9);
+        assert_eq!(None, parse_position("1,inf"));
+    }
+}
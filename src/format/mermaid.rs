@@ -0,0 +1,86 @@
+use std::fmt::Write;
+
+use crate::graph::Graph;
+
+/**
+ * Render the graph as a Mermaid flowchart, e.g., for pasting into Markdown. Each vertex is shown
+ * with its `label`, or its id if it has none, and each edge with its `label`, or else its
+ * `weight`. Vertices and edges are written in order of id, so the output is deterministic.
+ */
+pub fn to_mermaid(graph: &Graph) -> String {
+    let mut out = String::from("graph TD\n");
+
+    let mut vertices: Vec<_> = graph.vertices.values().collect();
+    vertices.sort_by_key(|v| v.id);
+    for v in vertices {
+        let label = v
+            .attribute("label")
+            .map_or_else(|| v.id.to_string(), str::to_string);
+        writeln!(out, "  {}[\"{}\"]", node_id(v.id), escape(&label)).unwrap();
+    }
+
+    let mut edges: Vec<_> = graph.edges.values().collect();
+    edges.sort_by_key(|e| e.id);
+    for e in edges {
+        match e.attribute("label").or_else(|| e.attribute("weight")) {
+            Some(label) => writeln!(
+                out,
+                "  {} -->|\"{}\"| {}",
+                node_id(e.source),
+                escape(label),
+                node_id(e.target)
+            ),
+            None => writeln!(out, "  {} --> {}", node_id(e.source), node_id(e.target)),
+        }
+        .unwrap();
+    }
+    out
+}
+
+// Mermaid node ids must not start with a digit or a minus sign.
+fn node_id(id: i64) -> String {
+    if id < 0 {
+        format!("vm{}", -(id as i128))
+    } else {
+        format!("v{}", id)
+    }
+}
+
+// Mermaid has no escape character in quoted text, but understands entity codes.
+fn escape(text: &str) -> String {
+    text.replace('#', "#35;")
+        .replace('"', "#quot;")
+        .replace('\n', "<br>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Edge;
+    use crate::graph::Vertex;
+
+    #[test]
+    fn labels_and_weights() {
+        let mut g = Graph::new();
+        g.add_vertex(Vertex::new(1).with_attribute("label", "say \"hi\""));
+        g.add_vertex(Vertex::new(0));
+        g.add_vertex(Vertex::new(-2));
+        g.add_edge(Edge::new(0, 1, 0).with_attribute("weight", "2.5"));
+        g.add_edge(Edge::new(1, 0, -2));
+        g.add_edge(
+            Edge::new(2, 0, 1)
+                .with_attribute("label", "#1")
+                .with_attribute("weight", "3"),
+        );
+
+        let expected = "graph TD
+  vm2[\"-2\"]
+  v0[\"0\"]
+  v1[\"say #quot;hi#quot;\"]
+  v1 -->|\"2.5\"| v0
+  v0 --> vm2
+  v0 -->|\"#35;1\"| v1
+";
+        assert_eq!(expected, to_mermaid(&g));
+    }
+}
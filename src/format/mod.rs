@@ -0,0 +1,161 @@
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+use crate::graph::Graph;
+
+pub mod binary;
+pub mod clipboard;
+pub mod cypher;
+pub mod dot;
+pub mod gml;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod layout;
+pub mod mermaid;
+pub mod plain;
+pub mod svg;
+pub mod tgf;
+pub mod tikz;
+
+// A document could not be read because its text is malformed or unsupported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    // The 1-based line the error was detected on.
+    pub line: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(line: usize, message: &str) -> ParseError {
+        ParseError {
+            line,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+// The file formats a document can be written in, as chosen by file extension.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    // Cypher statements, for Neo4j, can only be written.
+    Cypher,
+    Dot,
+    Gml,
+    // Mermaid flowcharts can only be written.
+    Mermaid,
+    // SVG images can only be written.
+    Svg,
+    // The Trivial Graph Format, which only has labels.
+    Tgf,
+    // TikZ pictures, for LaTeX documents, can only be written.
+    Tikz,
+    // The editor's own format, which stores the document exactly.
+    #[cfg(feature = "json")]
+    Json,
+    // A compact encoding of the editor's own format, for large documents.
+    Binary,
+}
+
+impl Format {
+    pub fn from_path(path: &str) -> Option<Format> {
+        let extension = Path::new(path).extension()?.to_str()?;
+        match extension.to_lowercase().as_str() {
+            "cypher" | "cql" => Some(Format::Cypher),
+            "dot" | "gv" => Some(Format::Dot),
+            "gml" => Some(Format::Gml),
+            "mmd" | "mermaid" => Some(Format::Mermaid),
+            "svg" => Some(Format::Svg),
+            "tgf" => Some(Format::Tgf),
+            "tex" | "tikz" => Some(Format::Tikz),
+            #[cfg(feature = "json")]
+            "json" => Some(Format::Json),
+            "grib" => Some(Format::Binary),
+            _ => None,
+        }
+    }
+
+    // Whether the format is meant for saving documents, rather than exporting them to other tools.
+    pub fn is_native(self) -> bool {
+        match self {
+            Format::Cypher
+            | Format::Dot
+            | Format::Gml
+            | Format::Mermaid
+            | Format::Svg
+            | Format::Tgf
+            | Format::Tikz => false,
+            #[cfg(feature = "json")]
+            Format::Json => true,
+            Format::Binary => true,
+        }
+    }
+
+    pub fn write(self, graph: &Graph) -> Vec<u8> {
+        let text = match self {
+            Format::Cypher => cypher::to_cypher(graph),
+            Format::Dot => dot::to_dot(graph),
+            Format::Gml => gml::to_gml(graph),
+            Format::Mermaid => mermaid::to_mermaid(graph),
+            Format::Svg => svg::to_svg(graph),
+            Format::Tgf => tgf::to_tgf(graph),
+            Format::Tikz => tikz::to_tikz(graph),
+            #[cfg(feature = "json")]
+            Format::Json => graph.to_json(),
+            Format::Binary => return binary::to_binary(graph),
+        };
+        text.into_bytes()
+    }
+
+    pub fn read(self, bytes: &[u8]) -> Result<Graph, ParseError> {
+        if self == Format::Binary {
+            return binary::from_binary(bytes);
+        }
+        let text = std::str::from_utf8(bytes).map_err(|err| {
+            let valid = &bytes[..err.valid_up_to()];
+            let line = 1 + valid.iter().filter(|b| **b == b'\n').count();
+            ParseError::new(line, "Text is not UTF-8")
+        })?;
+        match self {
+            Format::Cypher => Err(ParseError::new(1, "Cypher files cannot be read")),
+            Format::Dot => dot::from_dot(text),
+            Format::Gml => gml::from_gml(text),
+            Format::Mermaid => Err(ParseError::new(1, "Mermaid files cannot be read")),
+            Format::Svg => Err(ParseError::new(1, "SVG files cannot be read")),
+            Format::Tgf => tgf::from_tgf(text),
+            Format::Tikz => Err(ParseError::new(1, "TikZ files cannot be read")),
+            #[cfg(feature = "json")]
+            Format::Json => Graph::from_json(text),
+            Format::Binary => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(Some(Format::Dot), Format::from_path("foo.dot"));
+        assert_eq!(Some(Format::Dot), Format::from_path("dir/foo.GV"));
+        assert_eq!(Some(Format::Gml), Format::from_path("karate.gml"));
+        assert_eq!(Some(Format::Mermaid), Format::from_path("README.mmd"));
+        assert_eq!(Some(Format::Tikz), Format::from_path("figure.tikz"));
+        assert_eq!(Some(Format::Cypher), Format::from_path("import.cql"));
+        assert_eq!(Some(Format::Tgf), Format::from_path("yed.tgf"));
+        #[cfg(feature = "json")]
+        assert_eq!(Some(Format::Json), Format::from_path("foo.json"));
+        assert_eq!(Some(Format::Binary), Format::from_path("large.grib"));
+        assert_eq!(None, Format::from_path("foo.txt"));
+        assert_eq!(None, Format::from_path("foo"));
+    }
+}
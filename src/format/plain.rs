@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use crate::format::ParseError;
+use crate::graph::Edge;
+use crate::graph::Graph;
+use crate::graph::Vertex;
+
+// Split a line into words, where a double-quoted word may contain spaces and `\"` escapes.
+fn words(line: &str, number: usize) -> Result<Vec<String>, ParseError> {
+    let mut words = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        let mut word = String::new();
+        if c == '"' {
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('"') => word.push('"'),
+                        Some(other) => {
+                            word.push('\\');
+                            word.push(other);
+                        }
+                        None => return Err(ParseError::new(number, "Unterminated string")),
+                    },
+                    Some(other) => word.push(other),
+                    None => return Err(ParseError::new(number, "Unterminated string")),
+                }
+            }
+        } else {
+            word.push(c);
+            while let Some(next) = chars.peek().copied() {
+                if next.is_whitespace() {
+                    break;
+                }
+                word.push(next);
+                chars.next();
+            }
+        }
+        words.push(word);
+    }
+    Ok(words)
+}
+
+fn number(word: Option<&String>, line: usize) -> Result<f64, ParseError> {
+    word.and_then(|w| w.parse::<f64>().ok())
+        .filter(|x| x.is_finite())
+        .ok_or_else(|| ParseError::new(line, "Expected a number"))
+}
+
+/**
+ * Read the output of Graphviz's `-Tplain` format, i.e., the layout Graphviz computed for a graph:
+ *
+ *   graph scale width height
+ *   node name x y width height label style shape color fillcolor
+ *   edge tail head n x1 y1 ... xn yn [label xl yl] style color
+ *   stop
+ *
+ * Each node becomes a vertex whose `pos` is its center, in inches. As in `from_dot`, nodes named
+ * by an integer keep it as their id and others are numbered after the largest such id, and a
+ * label other than the node's name is kept as its `label`. Edges are numbered from 0, and keep
+ * their label if they have one.
+ */
+pub fn from_plain(text: &str) -> Result<Graph, ParseError> {
+    let mut nodes: Vec<(String, Vertex)> = Vec::new();
+    let mut edges: Vec<(usize, String, String, Option<String>)> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let number_of_line = i + 1;
+        let words = words(line, number_of_line)?;
+        match words.first().map(String::as_str) {
+            Some("graph") | None => {}
+            Some("stop") => break,
+            Some("node") => {
+                if words.len() < 7 {
+                    return Err(ParseError::new(number_of_line, "Truncated node"));
+                }
+                let x = number(words.get(2), number_of_line)?;
+                let y = number(words.get(3), number_of_line)?;
+                let mut v = Vertex::new(0).with_attribute("pos", &format!("{},{}", x, y));
+                if words[6] != words[1] {
+                    v = v.with_attribute("label", &words[6]);
+                }
+                nodes.push((words[1].clone(), v));
+            }
+            Some("edge") => {
+                if words.len() < 4 {
+                    return Err(ParseError::new(number_of_line, "Truncated edge"));
+                }
+                let points = number(words.get(3), number_of_line)? as usize;
+                // After the control points come either the style and color, or a label, its
+                // position, the style and the color.
+                let rest = words.len().saturating_sub(4 + 2 * points);
+                let label = if rest >= 5 {
+                    Some(words[4 + 2 * points].clone())
+                } else {
+                    None
+                };
+                edges.push((number_of_line, words[1].clone(), words[2].clone(), label));
+            }
+            Some(other) => {
+                return Err(ParseError::new(
+                    number_of_line,
+                    &format!("Unexpected statement '{}'", other),
+                ))
+            }
+        }
+    }
+
+    let mut next_id = nodes
+        .iter()
+        .filter_map(|(name, _)| name.parse::<i64>().ok())
+        .max()
+        .map_or(0, |id| id + 1);
+    let mut graph = Graph::new();
+    let mut ids = HashMap::new();
+    for (name, mut v) in nodes {
+        v.id = match name.parse::<i64>() {
+            Ok(id) => id,
+            Err(_) => {
+                next_id += 1;
+                if !v.attributes.contains_key("label") {
+                    v = v.with_attribute("label", &name);
+                }
+                next_id - 1
+            }
+        };
+        ids.insert(name, v.id);
+        graph.add_vertex(v);
+    }
+    for (id, (line, tail, head, label)) in edges.into_iter().enumerate() {
+        let endpoint = |name: &String| {
+            ids.get(name).copied().ok_or_else(|| {
+                ParseError::new(line, &format!("Edge refers to unknown node '{}'", name))
+            })
+        };
+        let mut e = Edge::new(id as i64, endpoint(&tail)?, endpoint(&head)?);
+        if let Some(label) = label {
+            e = e.with_attribute("label", &label);
+        }
+        graph.add_edge(e);
+    }
+    Ok(graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_layout() {
+        let plain = "graph 1 2.5 3
+node 0 0.75 2.5 0.75 0.5 0 solid ellipse black lightgrey
+node \"New York\" 1.75 0.5 0.75 0.5 \"New \\\"York\\\"\" solid ellipse black lightgrey
+edge 0 \"New York\" 4 0.75 2.2 0.75 1.9 1.2 1.1 1.6 0.8 300km 1.1 1.5 solid black
+edge \"New York\" 0 4 1.6 0.8 1.4 1.2 1.2 1.6 0.9 2.2 solid black
+stop
+";
+        let g = from_plain(plain).unwrap();
+        assert_eq!(Some("0.75,2.5"), g.vertices[&0].attribute("pos"));
+        assert_eq!(None, g.vertices[&0].attribute("label"));
+        assert_eq!(Some("New \"York\""), g.vertices[&1].attribute("label"));
+        assert_eq!(Some("300km"), g.edges[&0].attribute("label"));
+        assert_eq!((1, 0), (g.edges[&1].source, g.edges[&1].target));
+        assert_eq!(None, g.edges[&1].attribute("label"));
+    }
+
+    #[test]
+    fn errors_report_the_line() {
+        assert_eq!(
+            Err(ParseError::new(2, "Expected a number")),
+            from_plain("graph 1 1 1\nnode a x 0 1 1 a solid ellipse black white\n")
+        );
+        assert_eq!(3, from_plain("\n\nrank 1\n").unwrap_err().line);
+    }
+}
@@ -0,0 +1,190 @@
+use std::fmt::Write;
+
+use crate::format::layout::layout;
+use crate::graph::Graph;
+
+// Pixels per unit of layout distance.
+const SCALE: f64 = 50.0;
+const RADIUS: f64 = 15.0;
+const MARGIN: f64 = 30.0;
+
+/**
+ * Render the graph as a standalone SVG image. Vertices are placed by their `pos` attribute, or
+ * an automatic layout if they have none, with y pointing up as in TikZ and Graphviz, and drawn as
+ * circles labelled with their `label`, or their id. Edges are arrows labelled with their `label`,
+ * or else their `weight`. The output is deterministic, so it can be compared in tests.
+ */
+pub fn to_svg(graph: &Graph) -> String {
+    let positions = layout(graph);
+    let bound = |coordinate: fn(&(f64, f64)) -> f64, min: bool| {
+        let values = positions.values().map(coordinate);
+        let bound = if min {
+            values.fold(f64::INFINITY, f64::min)
+        } else {
+            values.fold(f64::NEG_INFINITY, f64::max)
+        };
+        if bound.is_finite() {
+            bound
+        } else {
+            0.0
+        }
+    };
+    let (min_x, max_x) = (bound(|p| p.0, true), bound(|p| p.0, false));
+    let (min_y, max_y) = (bound(|p| p.1, true), bound(|p| p.1, false));
+    let pad = MARGIN + RADIUS;
+    let width = (max_x - min_x) * SCALE + 2.0 * pad;
+    let height = (max_y - min_y) * SCALE + 2.0 * pad;
+    let point = |id: &i64| {
+        let (x, y) = positions[id];
+        ((x - min_x) * SCALE + pad, (max_y - y) * SCALE + pad)
+    };
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+         viewBox=\"0 0 {} {}\">",
+        number(width),
+        number(height),
+        number(width),
+        number(height)
+    )
+    .unwrap();
+    out.push_str(
+        "  <defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" \
+         markerWidth=\"6\" markerHeight=\"6\" orient=\"auto\">\
+         <path d=\"M 0 0 L 10 5 L 0 10 z\"/></marker></defs>\n",
+    );
+
+    let mut edges: Vec<_> = graph.edges.values().collect();
+    edges.sort_by_key(|e| e.id);
+    for e in edges {
+        let (sx, sy) = point(&e.source);
+        let (tx, ty) = point(&e.target);
+        let (label_x, label_y) = if e.source == e.target {
+            writeln!(
+                out,
+                "  <path d=\"M {} {} C {} {} {} {} {} {}\" fill=\"none\" stroke=\"black\" \
+                 marker-end=\"url(#arrow)\"/>",
+                number(sx - RADIUS / 2.0),
+                number(sy - RADIUS),
+                number(sx - 2.0 * RADIUS),
+                number(sy - 4.0 * RADIUS),
+                number(sx + 2.0 * RADIUS),
+                number(sy - 4.0 * RADIUS),
+                number(sx + RADIUS / 2.0),
+                number(sy - RADIUS)
+            )
+            .unwrap();
+            (sx, sy - 3.5 * RADIUS)
+        } else {
+            // Stop the line at the circles' edges, so that the arrowhead is visible.
+            let (dx, dy) = (tx - sx, ty - sy);
+            let length = dx.hypot(dy).max(f64::EPSILON);
+            let (ux, uy) = (dx / length, dy / length);
+            writeln!(
+                out,
+                "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"black\" \
+                 marker-end=\"url(#arrow)\"/>",
+                number(sx + ux * RADIUS),
+                number(sy + uy * RADIUS),
+                number(tx - ux * RADIUS),
+                number(ty - uy * RADIUS)
+            )
+            .unwrap();
+            ((sx + tx) / 2.0, (sy + ty) / 2.0 - 4.0)
+        };
+        if let Some(label) = e.attribute("label").or_else(|| e.attribute("weight")) {
+            writeln!(
+                out,
+                "  <text x=\"{}\" y=\"{}\" text-anchor=\"middle\" font-size=\"12\">{}</text>",
+                number(label_x),
+                number(label_y),
+                escape(label)
+            )
+            .unwrap();
+        }
+    }
+
+    for id in positions.keys() {
+        let v = &graph.vertices[id];
+        let (x, y) = point(id);
+        let label = v
+            .attribute("label")
+            .map_or_else(|| id.to_string(), str::to_string);
+        writeln!(
+            out,
+            "  <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"white\" stroke=\"black\"/>",
+            number(x),
+            number(y),
+            number(RADIUS)
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "  <text x=\"{}\" y=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\" \
+             font-size=\"12\">{}</text>",
+            number(x),
+            number(y),
+            escape(&label)
+        )
+        .unwrap();
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+fn number(value: f64) -> String {
+    let rounded = format!("{:.2}", value);
+    let trimmed = rounded.trim_end_matches('0').trim_end_matches('.');
+    match trimmed {
+        "-0" => "0".to_string(),
+        _ => trimmed.to_string(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Edge;
+    use crate::graph::Vertex;
+
+    #[test]
+    fn positioned_vertices_and_labelled_edges() {
+        let mut g = Graph::new();
+        g.add_vertex(Vertex::new(0).with_attribute("pos", "0,0"));
+        g.add_vertex(
+            Vertex::new(1)
+                .with_attribute("pos", "2,1")
+                .with_attribute("label", "a<b"),
+        );
+        g.add_edge(Edge::new(0, 0, 1).with_attribute("weight", "3"));
+
+        let svg = to_svg(&g);
+        assert!(svg
+            .starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"190\" height=\"140\""));
+        // Vertex 1 is up and to the right of vertex 0.
+        assert!(svg.contains("<circle cx=\"45\" cy=\"95\""));
+        assert!(svg.contains("<circle cx=\"145\" cy=\"45\""));
+        assert!(svg.contains(">a&lt;b</text>"));
+        assert!(svg.contains("font-size=\"12\">3</text>"));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+
+    #[test]
+    fn empty_graph_and_self_loop() {
+        assert!(to_svg(&Graph::new()).contains("width=\"90\" height=\"90\""));
+
+        let mut g = Graph::new();
+        g.add_vertex(Vertex::new(0));
+        g.add_edge(Edge::new(0, 0, 0));
+        assert!(to_svg(&g).contains("<path d=\"M 37.5 30 C"));
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::format::ParseError;
+use crate::graph::Edge;
+use crate::graph::Graph;
+use crate::graph::Vertex;
+
+/**
+ * Render the graph in the Trivial Graph Format: a line per vertex with its id and `label`, a `#`
+ * line, then a line per edge with its endpoints and `label`. Other attributes and graph
+ * properties cannot be written.
+ */
+pub fn to_tgf(graph: &Graph) -> String {
+    let mut out = String::new();
+
+    let mut vertices: Vec<_> = graph.vertices.values().collect();
+    vertices.sort_by_key(|v| v.id);
+    for v in vertices {
+        write_line(&mut out, &v.id.to_string(), v.attribute("label"));
+    }
+    out.push_str("#\n");
+
+    let mut edges: Vec<_> = graph.edges.values().collect();
+    edges.sort_by_key(|e| e.id);
+    for e in edges {
+        write_line(
+            &mut out,
+            &format!("{} {}", e.source, e.target),
+            e.attribute("label"),
+        );
+    }
+    out
+}
+
+// Labels cannot span lines, so line breaks are written as spaces.
+fn write_line(out: &mut String, ids: &str, label: Option<&str>) {
+    match label.map(|label| label.replace(['\r', '\n'], " ")) {
+        Some(label) if !label.trim().is_empty() => writeln!(out, "{} {}", ids, label.trim()),
+        _ => writeln!(out, "{}", ids),
+    }
+    .unwrap();
+}
+
+/**
+ * Read the Trivial Graph Format. As in `from_dot`, vertices with integer ids keep them and others
+ * are numbered after the largest such id, with their TGF id as their `label` unless they have
+ * one. Edges are numbered from 0 in the order they are listed.
+ */
+pub fn from_tgf(text: &str) -> Result<Graph, ParseError> {
+    let mut vertices: Vec<(usize, String, Option<String>)> = Vec::new();
+    let mut edges: Vec<(usize, String, String, Option<String>)> = Vec::new();
+    let mut in_edges = false;
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('#') {
+            if in_edges {
+                return Err(ParseError::new(i + 1, "Unexpected second '#'"));
+            }
+            in_edges = true;
+            continue;
+        }
+        let (first, rest) = split_word(line);
+        let label = |rest: &str| Some(rest.to_string()).filter(|label| !label.is_empty());
+        if in_edges {
+            let (second, rest) = split_word(rest);
+            if second.is_empty() {
+                return Err(ParseError::new(i + 1, "Edge has no target"));
+            }
+            edges.push((i + 1, first.to_string(), second.to_string(), label(rest)));
+        } else {
+            vertices.push((i + 1, first.to_string(), label(rest)));
+        }
+    }
+
+    let mut next_id = vertices
+        .iter()
+        .filter_map(|(_, id, _)| id.parse::<i64>().ok())
+        .max()
+        .map_or(0, |id| id + 1);
+    let mut graph = Graph::new();
+    let mut ids = HashMap::new();
+    for (line, name, label) in vertices {
+        if ids.contains_key(&name) {
+            return Err(ParseError::new(
+                line,
+                &format!("Vertex '{}' is listed twice", name),
+            ));
+        }
+        let mut v = match name.parse::<i64>() {
+            Ok(id) => Vertex::new(id),
+            Err(_) => {
+                next_id += 1;
+                Vertex::new(next_id - 1).with_attribute("label", &name)
+            }
+        };
+        if let Some(label) = label {
+            v = v.with_attribute("label", &label);
+        }
+        ids.insert(name, v.id);
+        graph.add_vertex(v);
+    }
+    for (id, (line, source, target, label)) in edges.into_iter().enumerate() {
+        let endpoint = |name: &String| {
+            ids.get(name).copied().ok_or_else(|| {
+                ParseError::new(line, &format!("Edge refers to unknown vertex '{}'", name))
+            })
+        };
+        let mut e = Edge::new(id as i64, endpoint(&source)?, endpoint(&target)?);
+        if let Some(label) = label {
+            e = e.with_attribute("label", &label);
+        }
+        graph.add_edge(e);
+    }
+    Ok(graph)
+}
+
+fn split_word(text: &str) -> (&str, &str) {
+    match text.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (text, ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_and_read_back() {
+        let mut g = Graph::new();
+        g.add_vertex(Vertex::new(0).with_attribute("label", "New York"));
+        g.add_vertex(Vertex::new(1));
+        g.add_vertex(Vertex::new(-3).with_attribute("label", "two\nlines"));
+        g.add_edge(Edge::new(0, 0, 1).with_attribute("label", "I-95"));
+        g.add_edge(Edge::new(1, 1, -3));
+
+        let expected = "-3 two lines\n0 New York\n1\n#\n0 1 I-95\n1 -3\n";
+        assert_eq!(expected, to_tgf(&g));
+
+        let read = from_tgf(expected).unwrap();
+        assert_eq!(Some("New York"), read.vertices[&0].attribute("label"));
+        assert_eq!(Some("I-95"), read.edges[&0].attribute("label"));
+        assert_eq!((1, -3), (read.edges[&1].source, read.edges[&1].target));
+    }
+
+    #[test]
+    fn named_vertices_and_errors() {
+        let read = from_tgf("a\n7 Seven\n# comment\na 7\n").unwrap();
+        assert_eq!(Some("a"), read.vertices[&8].attribute("label"));
+        assert_eq!(Edge::new(0, 8, 7), read.edges[&0]);
+
+        assert_eq!(
+            Err(ParseError::new(3, "Edge refers to unknown vertex 'b'")),
+            from_tgf("a\n#\na b\n")
+        );
+        assert_eq!(3, from_tgf("a\n#\n#\n").unwrap_err().line);
+        assert_eq!(2, from_tgf("a\na\n").unwrap_err().line);
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt::Write;
+
+use crate::format::layout::layout;
+use crate::graph::Graph;
+
+/**
+ * Render the graph as a TikZ picture, for including in LaTeX documents. Vertices are placed by
+ * their `pos` attribute, or an automatic layout if they have none, and labelled with their
+ * `label`, or their id. Edges are labelled with their `label`, or else their `weight`.
+ */
+pub fn to_tikz(graph: &Graph) -> String {
+    let positions = layout(graph);
+    let mut out = String::from("\\begin{tikzpicture}[every node/.style={draw, circle}]\n");
+
+    for (id, (x, y)) in positions.iter() {
+        let v = &graph.vertices[id];
+        let label = v
+            .attribute("label")
+            .map_or_else(|| id.to_string(), str::to_string);
+        writeln!(
+            out,
+            "  \\node ({}) at ({}, {}) {{{}}};",
+            node_name(*id),
+            coordinate(*x),
+            coordinate(*y),
+            escape(&label)
+        )
+        .unwrap();
+    }
+
+    let mut edges: Vec<_> = graph.edges.values().collect();
+    edges.sort_by_key(|e| e.id);
+    for e in edges {
+        let path = if e.source == e.target {
+            "to[loop above]"
+        } else {
+            "--"
+        };
+        let label = match e.attribute("label").or_else(|| e.attribute("weight")) {
+            Some(label) => format!(" node[draw=none, auto] {{{}}}", escape(label)),
+            None => String::new(),
+        };
+        writeln!(
+            out,
+            "  \\draw[->] ({}) {}{} ({});",
+            node_name(e.source),
+            path,
+            label,
+            node_name(e.target)
+        )
+        .unwrap();
+    }
+    out.push_str("\\end{tikzpicture}\n");
+    out
+}
+
+// TikZ node names must not contain a minus sign, which would be read as a coordinate.
+fn node_name(id: i64) -> String {
+    if id < 0 {
+        format!("vm{}", -(id as i128))
+    } else {
+        format!("v{}", id)
+    }
+}
+
+fn coordinate(value: f64) -> String {
+    let rounded = format!("{:.3}", value);
+    let trimmed = rounded.trim_end_matches('0').trim_end_matches('.');
+    match trimmed {
+        "-0" => "0".to_string(),
+        _ => trimmed.to_string(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push(' '),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Edge;
+    use crate::graph::Vertex;
+
+    #[test]
+    fn nodes_at_positions_with_escaped_labels() {
+        let mut g = Graph::new();
+        g.add_vertex(
+            Vertex::new(0)
+                .with_attribute("pos", "0,0")
+                .with_attribute("label", "$x_1$"),
+        );
+        g.add_vertex(Vertex::new(-1).with_attribute("pos", "2.5,-1"));
+        g.add_edge(Edge::new(0, 0, -1).with_attribute("weight", "50%"));
+        g.add_edge(Edge::new(1, -1, -1));
+
+        let expected = "\\begin{tikzpicture}[every node/.style={draw, circle}]
+  \\node (vm1) at (2.5, -1) {-1};
+  \\node (v0) at (0, 0) {\\$x\\_1\\$};
+  \\draw[->] (v0) -- node[draw=none, auto] {50\\%} (vm1);
+  \\draw[->] (vm1) to[loop above] (vm1);
+\\end{tikzpicture}
+";
+        assert_eq!(expected, to_tikz(&g));
+    }
+}
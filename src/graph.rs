@@ -1,48 +1,337 @@
+use std::collections::hash_map::Entry;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+// Free-form key/value data attached to a vertex or edge, e.g., a label or a weight.
+pub type Attributes = BTreeMap<String, String>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vertex {
     pub id: i64,
+    pub attributes: Attributes,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Edge {
     pub id: i64,
     pub source: i64,
     pub target: i64,
+    pub attributes: Attributes,
+}
+
+impl Vertex {
+    pub fn new(id: i64) -> Vertex {
+        Vertex {
+            id,
+            attributes: Attributes::new(),
+        }
+    }
+
+    pub fn with_attribute(mut self, key: &str, value: &str) -> Vertex {
+        self.attributes.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.get(key).map(String::as_str)
+    }
+}
+
+impl Edge {
+    pub fn new(id: i64, source: i64, target: i64) -> Edge {
+        Edge {
+            id,
+            source,
+            target,
+            attributes: Attributes::new(),
+        }
+    }
+
+    pub fn with_attribute(mut self, key: &str, value: &str) -> Edge {
+        self.attributes.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.get(key).map(String::as_str)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Graph {
     pub vertices: HashMap<i64, Vertex>,
     pub edges: HashMap<i64, Edge>,
+    // Attributes of the graph as a whole, such as its name.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub properties: Attributes,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GraphOperation {
     AddVertex(Vertex),
     RemoveVertex(Vertex),
     AddEdge(Edge),
     RemoveEdge(Edge),
+    // Replace the vertex (or edge) with the same id as `new`. `old` is what is replaced, which
+    // makes the operation invertible.
+    ModifyVertex { old: Vertex, new: Vertex },
+    ModifyEdge { old: Edge, new: Edge },
+    // Compound operations. When applied, these are expanded into the primitive operations above,
+    // so they never appear in a Diff.
+
+    // Remove the edge and merge its target into its source.
+    ContractEdge(Edge),
+    // Rewire every edge incident to `remove` onto `keep`, then remove `remove`.
+    MergeVertices { keep: Vertex, remove: Vertex },
 }
 
-#[derive(Debug, Clone)]
+// The primitive operations that an operation actually performed, in the order they happened.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Diff {
     pub operations: Vec<GraphOperation>,
 }
 
+// The reasons an operation can fail when it is applied strictly, i.e., when an operation that
+// would otherwise be a silent no-op is treated as a mistake by the caller.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GraphError {
+    DuplicateVertex(i64),
+    UnknownVertex(i64),
+    DuplicateEdge(i64),
+    UnknownEdge(i64),
+    // A vertex cannot be merged into itself.
+    SelfMerge(i64),
+    // A modification that would leave the vertex or edge as it is.
+    UnchangedVertex(i64),
+    UnchangedEdge(i64),
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GraphError::DuplicateVertex(id) => write!(f, "Vertex {} already exists", id),
+            GraphError::UnknownVertex(id) => write!(f, "Unknown vertex {}", id),
+            GraphError::DuplicateEdge(id) => write!(f, "Edge {} already exists", id),
+            GraphError::UnknownEdge(id) => write!(f, "Unknown edge {}", id),
+            GraphError::SelfMerge(id) => write!(f, "Cannot merge vertex {} into itself", id),
+            GraphError::UnchangedVertex(id) => write!(f, "Vertex {} is unchanged", id),
+            GraphError::UnchangedEdge(id) => write!(f, "Edge {} is unchanged", id),
+        }
+    }
+}
+
+impl Error for GraphError {}
+
+// A single way in which a graph's internal structure is inconsistent.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum InvariantViolation {
+    // A vertex is stored under a key that differs from its own id.
+    VertexIdMismatch { key: i64, id: i64 },
+    // An edge is stored under a key that differs from its own id.
+    EdgeIdMismatch { key: i64, id: i64 },
+    // An edge refers to a vertex that is not in the graph.
+    DanglingEdge { edge: i64, vertex: i64 },
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct InvariantReport {
+    pub violations: Vec<InvariantViolation>,
+}
+
+impl InvariantReport {
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl fmt::Display for InvariantReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_ok() {
+            return write!(f, "No invariant violations");
+        }
+        writeln!(f, "{} invariant violation(s):", self.violations.len())?;
+        for violation in self.violations.iter() {
+            match violation {
+                InvariantViolation::VertexIdMismatch { key, id } => {
+                    writeln!(f, "  vertex {} is stored under id {}", id, key)?
+                }
+                InvariantViolation::EdgeIdMismatch { key, id } => {
+                    writeln!(f, "  edge {} is stored under id {}", id, key)?
+                }
+                InvariantViolation::DanglingEdge { edge, vertex } => {
+                    writeln!(f, "  edge {} refers to unknown vertex {}", edge, vertex)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
 
 use GraphOperation::*;
 
 impl GraphOperation {
-    pub fn invert(self) -> GraphOperation {
+    /**
+     * The operation that undoes this one. Compound operations have none of their own, since what
+     * they undo depends on the document they were applied to; invert the Diff one produced
+     * instead.
+     */
+    pub fn invert(self) -> Option<GraphOperation> {
         match self {
-            AddVertex(v) => RemoveVertex(v),
-            RemoveVertex(v) => AddVertex(v),
-            AddEdge(e) => RemoveEdge(e),
-            RemoveEdge(e) => AddEdge(e),
+            AddVertex(v) => Some(RemoveVertex(v)),
+            RemoveVertex(v) => Some(AddVertex(v)),
+            AddEdge(e) => Some(RemoveEdge(e)),
+            RemoveEdge(e) => Some(AddEdge(e)),
+            ModifyVertex { old, new } => Some(ModifyVertex { old: new, new: old }),
+            ModifyEdge { old, new } => Some(ModifyEdge { old: new, new: old }),
+            ContractEdge(_) | MergeVertices { .. } => None,
+        }
+    }
+
+    // Compound operations are expanded when applied, so they never appear in a diff.
+    pub fn is_compound(&self) -> bool {
+        matches!(self, ContractEdge(_) | MergeVertices { .. })
+    }
+}
+
+impl Diff {
+    // The diff that undoes this one: each operation inverted, in reverse order.
+    pub fn invert(&self) -> Diff {
+        Diff {
+            operations: self
+                .primitives()
+                .rev()
+                .filter_map(|op| op.clone().invert())
+                .collect(),
+        }
+    }
+
+    /**
+     * The operations of the diff, without any compound operation a caller put in one by hand, so
+     * that code reading a diff need only handle the primitive operations.
+     */
+    pub fn primitives(&self) -> impl DoubleEndedIterator<Item = &GraphOperation> {
+        self.operations.iter().filter(|op| !op.is_compound())
+    }
+
+    /**
+     * The diff of performing this diff and then a later one, where consecutive modifications of
+     * the same element are combined into one, and dropped if they cancel out.
+     */
+    pub fn coalesce(mut self, later: Diff) -> Diff {
+        use GraphOperation::*;
+        for op in later.operations {
+            let combined = match (self.operations.last(), &op) {
+                (Some(ModifyVertex { old, new: a }), ModifyVertex { new: b, .. })
+                    if a.id == b.id =>
+                {
+                    Some(ModifyVertex {
+                        old: old.clone(),
+                        new: b.clone(),
+                    })
+                }
+                (Some(ModifyEdge { old, new: a }), ModifyEdge { new: b, .. }) if a.id == b.id => {
+                    Some(ModifyEdge {
+                        old: old.clone(),
+                        new: b.clone(),
+                    })
+                }
+                _ => None,
+            };
+            match combined {
+                Some(combined) => {
+                    self.operations.pop();
+                    let cancels_out = match &combined {
+                        ModifyVertex { old, new } => old == new,
+                        ModifyEdge { old, new } => old == new,
+                        _ => false,
+                    };
+                    if !cancels_out {
+                        self.operations.push(combined);
+                    }
+                }
+                None => self.operations.push(op),
+            }
+        }
+        self
+    }
+}
+
+// How many elements a diff changes, by kind of change. Compound operations are not counted,
+// since applying them produces the simple operations they stand for.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiffSummary {
+    pub added_vertices: usize,
+    pub removed_vertices: usize,
+    pub modified_vertices: usize,
+    pub added_edges: usize,
+    pub removed_edges: usize,
+    pub modified_edges: usize,
+}
+
+impl DiffSummary {
+    pub fn is_empty(&self) -> bool {
+        *self == DiffSummary::default()
+    }
+}
+
+impl fmt::Display for DiffSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let counts = [
+            ("added", self.added_vertices, "vertex", "vertices"),
+            ("removed", self.removed_vertices, "vertex", "vertices"),
+            ("modified", self.modified_vertices, "vertex", "vertices"),
+            ("added", self.added_edges, "edge", "edges"),
+            ("removed", self.removed_edges, "edge", "edges"),
+            ("modified", self.modified_edges, "edge", "edges"),
+        ];
+        let parts: Vec<String> = counts
+            .iter()
+            .filter(|(_, count, _, _)| *count > 0)
+            .map(|(change, count, one, many)| {
+                format!(
+                    "{} {} {}",
+                    change,
+                    count,
+                    if *count == 1 { one } else { many }
+                )
+            })
+            .collect();
+        if parts.is_empty() {
+            write!(f, "no changes")
+        } else {
+            write!(f, "{}", parts.join(", "))
+        }
+    }
+}
+
+impl Diff {
+    pub fn summary(&self) -> DiffSummary {
+        let mut summary = DiffSummary::default();
+        for op in self.primitives() {
+            let count = match op {
+                AddVertex(_) => &mut summary.added_vertices,
+                RemoveVertex(_) => &mut summary.removed_vertices,
+                ModifyVertex { .. } => &mut summary.modified_vertices,
+                AddEdge(_) => &mut summary.added_edges,
+                RemoveEdge(_) => &mut summary.removed_edges,
+                ModifyEdge { .. } => &mut summary.modified_edges,
+                _ => continue,
+            };
+            *count += 1;
         }
+        summary
     }
 }
 
@@ -57,18 +346,90 @@ impl Graph {
         Graph {
             vertices: HashMap::new(),
             edges: HashMap::new(),
+            properties: Attributes::new(),
         }
     }
 
     pub fn apply_all(&mut self, operations: Vec<GraphOperation>) -> Diff {
         Diff {
             operations: operations
-                .iter()
-                .flat_map(|operation| self.apply(*operation).operations)
+                .into_iter()
+                .flat_map(|operation| self.apply(operation).operations)
                 .collect(),
         }
     }
 
+    /**
+     * Apply a batch of operations, in parallel where possible.
+     *
+     * A batch consisting only of AddVertex and AddEdge operations with fresh, distinct ids and
+     * edges whose endpoints exist (or are added in the same batch) cannot conflict, so it is
+     * split into a vertex shard and an edge shard that are built and inserted concurrently.
+     * Any other batch falls back to the sequential `apply_all`.
+     */
+    #[cfg(feature = "rayon")]
+    pub fn par_apply_all(&mut self, operations: Vec<GraphOperation>) -> Diff {
+        if !self.is_independent_batch(&operations) {
+            return self.apply_all(operations);
+        }
+
+        let diff = Diff {
+            operations: operations.clone(),
+        };
+        let (vertex_shard, edge_shard): (Vec<GraphOperation>, Vec<GraphOperation>) = operations
+            .into_iter()
+            .partition(|operation| matches!(operation, AddVertex(_)));
+
+        let vertices = &mut self.vertices;
+        let edges = &mut self.edges;
+        rayon::join(
+            || {
+                vertices.par_extend(vertex_shard.par_iter().map(|operation| match operation {
+                    AddVertex(v) => (v.id, v.clone()),
+                    _ => unreachable!(),
+                }))
+            },
+            || {
+                edges.par_extend(edge_shard.par_iter().map(|operation| match operation {
+                    AddEdge(e) => (e.id, e.clone()),
+                    _ => unreachable!(),
+                }))
+            },
+        );
+
+        diff
+    }
+
+    // Whether every operation in the batch is an addition that is guaranteed to succeed
+    // regardless of the order in which the batch is applied.
+    #[cfg(feature = "rayon")]
+    fn is_independent_batch(&self, operations: &[GraphOperation]) -> bool {
+        let mut new_vertices: HashSet<i64> = HashSet::new();
+        let mut new_edges: HashSet<i64> = HashSet::new();
+        for operation in operations {
+            match operation {
+                AddVertex(v) => {
+                    if self.vertices.contains_key(&v.id) || !new_vertices.insert(v.id) {
+                        return false;
+                    }
+                }
+                AddEdge(e) => {
+                    if self.edges.contains_key(&e.id) || !new_edges.insert(e.id) {
+                        return false;
+                    }
+                }
+                _ => return false,
+            }
+        }
+
+        operations.iter().all(|operation| match operation {
+            AddEdge(e) => [e.source, e.target]
+                .iter()
+                .all(|id| self.vertices.contains_key(id) || new_vertices.contains(id)),
+            _ => true,
+        })
+    }
+
     // TODO: change return type to Result<Diff, Error>
     // and define new Error class that can be used to report errors to user
     pub fn apply(&mut self, operation: GraphOperation) -> Diff {
@@ -77,7 +438,237 @@ impl Graph {
             RemoveVertex(v) => self.remove_vertex(v),
             AddEdge(e) => self.add_edge(e),
             RemoveEdge(e) => self.remove_edge(e),
+            ModifyVertex { new, .. } => self.modify_vertex(new),
+            ModifyEdge { new, .. } => self.modify_edge(new),
+            ContractEdge(e) => self.contract_edge(e),
+            MergeVertices { keep, remove } => self.merge_vertices(keep, remove),
+        }
+    }
+
+    /**
+     * Apply an operation, failing instead of silently doing nothing when the operation would not
+     * change the document (or, for edges, when an endpoint does not exist).
+     */
+    pub fn try_apply(&mut self, operation: GraphOperation) -> Result<Diff, GraphError> {
+        self.check(&operation)?;
+        Ok(self.apply(operation))
+    }
+
+    /**
+     * Apply a batch of operations strictly. The batch is atomic: if any operation fails, the
+     * operations already applied are rolled back and the document is left unchanged.
+     */
+    pub fn try_apply_all(&mut self, operations: Vec<GraphOperation>) -> Result<Diff, GraphError> {
+        let mut applied = Diff {
+            operations: Vec::new(),
+        };
+        for operation in operations {
+            match self.try_apply(operation) {
+                Ok(diff) => applied.operations.extend(diff.operations),
+                Err(err) => {
+                    self.apply_all(applied.invert().operations);
+                    return Err(err);
+                }
+            }
+        }
+        Ok(applied)
+    }
+
+    fn check(&self, operation: &GraphOperation) -> Result<(), GraphError> {
+        match operation {
+            AddVertex(v) if self.vertices.contains_key(&v.id) => {
+                Err(GraphError::DuplicateVertex(v.id))
+            }
+            RemoveVertex(v) if !self.vertices.contains_key(&v.id) => {
+                Err(GraphError::UnknownVertex(v.id))
+            }
+            AddEdge(e) if self.edges.contains_key(&e.id) => Err(GraphError::DuplicateEdge(e.id)),
+            AddEdge(e) => [e.source, e.target]
+                .iter()
+                .find(|id| !self.vertices.contains_key(id))
+                .map_or(Ok(()), |id| Err(GraphError::UnknownVertex(*id))),
+            RemoveEdge(e) | ContractEdge(e) if !self.edges.contains_key(&e.id) => {
+                Err(GraphError::UnknownEdge(e.id))
+            }
+            ModifyVertex { new, .. } => match self.vertices.get(&new.id) {
+                None => Err(GraphError::UnknownVertex(new.id)),
+                Some(stored) if stored == new => Err(GraphError::UnchangedVertex(new.id)),
+                Some(_) => Ok(()),
+            },
+            ModifyEdge { new, .. } => match self.edges.get(&new.id) {
+                None => Err(GraphError::UnknownEdge(new.id)),
+                Some(stored) if stored == new => Err(GraphError::UnchangedEdge(new.id)),
+                Some(_) => [new.source, new.target]
+                    .iter()
+                    .find(|id| !self.vertices.contains_key(id))
+                    .map_or(Ok(()), |id| Err(GraphError::UnknownVertex(*id))),
+            },
+            MergeVertices { keep, remove } if keep.id == remove.id => {
+                Err(GraphError::SelfMerge(keep.id))
+            }
+            MergeVertices { keep, remove } => [keep.id, remove.id]
+                .iter()
+                .find(|id| !self.vertices.contains_key(id))
+                .map_or(Ok(()), |id| Err(GraphError::UnknownVertex(*id))),
+            _ => Ok(()),
+        }
+    }
+
+    /**
+     * Verify the structural invariants of the graph: every vertex and edge is stored under its
+     * own id (so ids are unique), and every edge endpoint exists. Violations are sorted so that
+     * reports are deterministic.
+     */
+    pub fn check_invariants(&self) -> InvariantReport {
+        let mut violations = Vec::new();
+        for (key, v) in self.vertices.iter() {
+            if *key != v.id {
+                violations.push(InvariantViolation::VertexIdMismatch {
+                    key: *key,
+                    id: v.id,
+                });
+            }
+        }
+        for (key, e) in self.edges.iter() {
+            if *key != e.id {
+                violations.push(InvariantViolation::EdgeIdMismatch {
+                    key: *key,
+                    id: e.id,
+                });
+            }
+            for vertex in [e.source, e.target].iter() {
+                if !self.vertices.contains_key(vertex) {
+                    violations.push(InvariantViolation::DanglingEdge {
+                        edge: e.id,
+                        vertex: *vertex,
+                    });
+                }
+            }
         }
+        violations.sort();
+        violations.dedup();
+        InvariantReport { violations }
+    }
+
+    // A copy of the given vertices and all edges among them. Unknown ids are ignored.
+    pub fn induced_subgraph<I: IntoIterator<Item = i64>>(&self, vertex_ids: I) -> Graph {
+        let ids: HashSet<i64> = vertex_ids.into_iter().collect();
+        self.subgraph_by(|v| ids.contains(&v.id))
+    }
+
+    // A copy of the vertices satisfying the predicate and all edges among them, with the same
+    // graph properties.
+    pub fn subgraph_by<P: Fn(&Vertex) -> bool>(&self, pred: P) -> Graph {
+        self.filtered(pred, |_| true)
+    }
+
+    // A copy of the vertices satisfying the vertex predicate, and the edges among them satisfying
+    // the edge predicate, with the same graph properties.
+    pub fn filtered<P: Fn(&Vertex) -> bool, Q: Fn(&Edge) -> bool>(
+        &self,
+        vertex_pred: P,
+        edge_pred: Q,
+    ) -> Graph {
+        let vertices: HashMap<i64, Vertex> = self
+            .vertices
+            .iter()
+            .filter(|(_, v)| vertex_pred(v))
+            .map(|(id, v)| (*id, v.clone()))
+            .collect();
+        let edges = self
+            .edges
+            .iter()
+            .filter(|(_, e)| vertices.contains_key(&e.source) && vertices.contains_key(&e.target))
+            .filter(|(_, e)| edge_pred(e))
+            .map(|(id, e)| (*id, e.clone()))
+            .collect();
+        Graph {
+            vertices,
+            edges,
+            properties: self.properties.clone(),
+        }
+    }
+
+    /**
+     * The operations that turn this graph into the other one, in an order in which they can be
+     * applied: removed edges, added vertices, modified vertices and edges, removed vertices,
+     * then added edges, each in order of id. Graph properties are not compared.
+     */
+    pub fn difference(&self, other: &Graph) -> Diff {
+        let sorted = |ids: Vec<i64>| {
+            let mut ids = ids;
+            ids.sort_unstable();
+            ids
+        };
+        let vertex_ids = sorted(
+            self.vertices
+                .keys()
+                .chain(
+                    other
+                        .vertices
+                        .keys()
+                        .filter(|id| !self.vertices.contains_key(id)),
+                )
+                .copied()
+                .collect(),
+        );
+        let edge_ids = sorted(
+            self.edges
+                .keys()
+                .chain(other.edges.keys().filter(|id| !self.edges.contains_key(id)))
+                .copied()
+                .collect(),
+        );
+
+        let mut operations = Vec::new();
+        for id in edge_ids.iter() {
+            if let (Some(e), None) = (self.edges.get(id), other.edges.get(id)) {
+                operations.push(RemoveEdge(e.clone()));
+            }
+        }
+        for id in vertex_ids.iter() {
+            if let (None, Some(v)) = (self.vertices.get(id), other.vertices.get(id)) {
+                operations.push(AddVertex(v.clone()));
+            }
+        }
+        for id in vertex_ids.iter() {
+            if let (Some(old), Some(new)) = (self.vertices.get(id), other.vertices.get(id)) {
+                if old != new {
+                    operations.push(ModifyVertex {
+                        old: old.clone(),
+                        new: new.clone(),
+                    });
+                }
+            }
+        }
+        for id in edge_ids.iter() {
+            if let (Some(old), Some(new)) = (self.edges.get(id), other.edges.get(id)) {
+                if old != new {
+                    operations.push(ModifyEdge {
+                        old: old.clone(),
+                        new: new.clone(),
+                    });
+                }
+            }
+        }
+        for id in vertex_ids.iter() {
+            if let (Some(v), None) = (self.vertices.get(id), other.vertices.get(id)) {
+                operations.push(RemoveVertex(v.clone()));
+            }
+        }
+        for id in edge_ids.iter() {
+            if let (None, Some(e)) = (self.edges.get(id), other.edges.get(id)) {
+                operations.push(AddEdge(e.clone()));
+            }
+        }
+        Diff { operations }
+    }
+
+    pub fn resolve_edge(&self, edge: &str) -> Option<i64> {
+        edge.trim()
+            .parse::<i64>()
+            .ok()
+            .filter(|x| self.edges.contains_key(x))
     }
 
     pub fn resolve_vertex(&self, vertex: &str) -> Option<i64> {
@@ -85,16 +676,28 @@ impl Graph {
             .trim()
             .parse::<i64>()
             .ok()
-            .filter(|x| self.vertices.contains_key(&x))
+            .filter(|x| self.vertices.contains_key(x))
+    }
+
+    // The ids of the vertices with the label, in order.
+    pub fn vertices_labeled(&self, label: &str) -> Vec<i64> {
+        let mut ids: Vec<i64> = self
+            .vertices
+            .values()
+            .filter(|v| v.attribute("label") == Some(label))
+            .map(|v| v.id)
+            .collect();
+        ids.sort_unstable();
+        ids
     }
 
     pub fn add_vertex(&mut self, v: Vertex) -> Diff {
         let mut ops = Vec::new();
-        let result = self.vertices.insert(v.id, v);
-
-        if result.is_none() {
+        if let Entry::Vacant(entry) = self.vertices.entry(v.id) {
+            entry.insert(v.clone());
             ops.push(AddVertex(v));
         } else {
+            // An existing vertex is left untouched, since the diff could not restore it.
             // TODO: add a "modify vertex"?
         }
 
@@ -104,24 +707,28 @@ impl Graph {
     pub fn remove_vertex(&mut self, v: Vertex) -> Diff {
         let mut ops = Vec::new();
         let result = self.vertices.remove(&v.id);
-        if result.is_some() {
-            ops.push(RemoveVertex(v));
-
+        if let Some(removed) = result {
             // Each edge referring to this vertex is now
             // invalid and must be removed.
             // TODO: make more efficient with an index
             // from vertex to incident edges.
-            let mut edges_to_remove: HashSet<Edge> = HashSet::new();
-            for edge in self.edges.values() {
-                if edge.source == v.id || edge.target == v.id {
-                    edges_to_remove.insert(*edge);
-                }
-            }
+            let mut edges_to_remove: Vec<Edge> = self
+                .edges
+                .values()
+                .filter(|edge| edge.source == v.id || edge.target == v.id)
+                .cloned()
+                .collect();
+            edges_to_remove.sort_by_key(|edge| edge.id);
 
-            for edge in edges_to_remove.iter() {
+            // The edges are recorded as removed first, so that the inverted diff adds the vertex
+            // back before the edges that refer to it.
+            for edge in edges_to_remove.into_iter() {
                 self.edges.remove(&edge.id);
-                ops.push(RemoveEdge(*edge));
+                ops.push(RemoveEdge(edge));
             }
+
+            // Record the stored vertex, so that undoing the removal restores its attributes.
+            ops.push(RemoveVertex(removed));
         }
 
         Diff { operations: ops }
@@ -136,10 +743,11 @@ impl Graph {
         }
 
         let mut ops = Vec::new();
-        let result = self.edges.insert(e.id, e);
-        if result.is_none() {
+        if let Entry::Vacant(entry) = self.edges.entry(e.id) {
+            entry.insert(e.clone());
             ops.push(AddEdge(e));
         } else {
+            // An existing edge is left untouched, since the diff could not restore it.
             // TODO: add an edge edit operation?
         }
 
@@ -149,9 +757,94 @@ impl Graph {
     pub fn remove_edge(&mut self, e: Edge) -> Diff {
         let mut ops = Vec::new();
         let result = self.edges.remove(&e.id);
-        if result.is_some() {
-            ops.push(RemoveEdge(e));
+        if let Some(removed) = result {
+            ops.push(RemoveEdge(removed));
+        }
+
+        Diff { operations: ops }
+    }
+
+    pub fn modify_vertex(&mut self, new: Vertex) -> Diff {
+        let mut ops = Vec::new();
+        if let Some(stored) = self.vertices.get_mut(&new.id) {
+            if *stored != new {
+                let old = std::mem::replace(stored, new.clone());
+                ops.push(ModifyVertex { old, new });
+            }
+        }
+
+        Diff { operations: ops }
+    }
+
+    pub fn modify_edge(&mut self, new: Edge) -> Diff {
+        if !self.vertices.contains_key(&new.source) {
+            panic!("Unknown vertex {:?}", new.source);
+        }
+        if !self.vertices.contains_key(&new.target) {
+            panic!("Unknown vertex {:?}", new.target);
+        }
+
+        let mut ops = Vec::new();
+        if let Some(stored) = self.edges.get_mut(&new.id) {
+            if *stored != new {
+                let old = std::mem::replace(stored, new.clone());
+                ops.push(ModifyEdge { old, new });
+            }
+        }
+
+        Diff { operations: ops }
+    }
+
+    pub fn contract_edge(&mut self, e: Edge) -> Diff {
+        // Use the stored edge, so that the diff restores exactly what was removed.
+        let e = match self.edges.get(&e.id) {
+            Some(stored) => stored.clone(),
+            None => {
+                return Diff {
+                    operations: Vec::new(),
+                }
+            }
+        };
+
+        let mut diff = self.remove_edge(e.clone());
+        if e.source != e.target {
+            let keep = self.vertices[&e.source].clone();
+            let remove = self.vertices[&e.target].clone();
+            diff.operations
+                .extend(self.merge_vertices(keep, remove).operations);
+        }
+        diff
+    }
+
+    pub fn merge_vertices(&mut self, keep: Vertex, remove: Vertex) -> Diff {
+        let mut ops = Vec::new();
+        if keep.id == remove.id
+            || !self.vertices.contains_key(&keep.id)
+            || !self.vertices.contains_key(&remove.id)
+        {
+            return Diff { operations: ops };
+        }
+
+        let mut incident: Vec<Edge> = self
+            .edges
+            .values()
+            .filter(|e| e.source == remove.id || e.target == remove.id)
+            .cloned()
+            .collect();
+        incident.sort_by_key(|e| e.id);
+
+        let rewire = |id: i64| if id == remove.id { keep.id } else { id };
+        for old in incident {
+            let new = Edge {
+                source: rewire(old.source),
+                target: rewire(old.target),
+                ..old.clone()
+            };
+            ops.extend(self.remove_edge(old).operations);
+            ops.extend(self.add_edge(new).operations);
         }
+        let removed = self.vertices[&remove.id].clone();
+        ops.extend(self.remove_vertex(removed).operations);
 
         Diff { operations: ops }
     }
@@ -171,26 +864,18 @@ mod tests {
     #[test]
     fn new_construct_small_graph() {
         let mut g = Graph::new();
-        let v1 = Vertex { id: 1 };
-        let v2 = Vertex { id: 2 };
-        let v3 = Vertex { id: 3 };
-
-        let e1 = Edge {
-            id: 1,
-            source: v1.id,
-            target: v2.id,
-        };
-        let e2 = Edge {
-            id: 2,
-            source: v2.id,
-            target: v3.id,
-        };
+        let v1 = Vertex::new(1);
+        let v2 = Vertex::new(2);
+        let v3 = Vertex::new(3);
+
+        let e1 = Edge::new(1, v1.id, v2.id);
+        let e2 = Edge::new(2, v2.id, v3.id);
 
-        g.add_vertex(v1);
-        g.add_vertex(v2);
-        g.add_vertex(v3);
-        g.add_edge(e1);
-        g.add_edge(e2);
+        g.add_vertex(v1.clone());
+        g.add_vertex(v2.clone());
+        g.add_vertex(v3.clone());
+        g.add_edge(e1.clone());
+        g.add_edge(e2.clone());
 
         assert_eq!(HashMap::from([(1, v1), (2, v2), (3, v3)]), g.vertices);
         assert_eq!(HashMap::from([(1, e1), (2, e2)]), g.edges);
@@ -200,29 +885,21 @@ mod tests {
     fn undo_operations() {
         let mut g = Graph::new();
         let mut history = Vec::new();
-        let v1 = Vertex { id: 1 };
-        let v2 = Vertex { id: 2 };
-        let v3 = Vertex { id: 3 };
-
-        history.extend(g.add_vertex(v1).operations);
-        history.extend(g.add_vertex(v2).operations);
-        history.extend(g.add_vertex(v3).operations);
-        let e1 = Edge {
-            id: 1,
-            source: v1.id,
-            target: v2.id,
-        };
-        let e2 = Edge {
-            id: 2,
-            source: v2.id,
-            target: v3.id,
-        };
+        let v1 = Vertex::new(1);
+        let v2 = Vertex::new(2);
+        let v3 = Vertex::new(3);
 
-        history.extend(g.add_edge(e1).operations);
-        history.extend(g.add_edge(e2).operations);
+        history.extend(g.add_vertex(v1.clone()).operations);
+        history.extend(g.add_vertex(v2.clone()).operations);
+        history.extend(g.add_vertex(v3.clone()).operations);
+        let e1 = Edge::new(1, v1.id, v2.id);
+        let e2 = Edge::new(2, v2.id, v3.id);
+
+        history.extend(g.add_edge(e1.clone()).operations);
+        history.extend(g.add_edge(e2.clone()).operations);
 
         for op in history.into_iter() {
-            g.apply(op.invert());
+            g.apply(op.invert().unwrap());
         }
 
         assert_eq!(g.vertices, HashMap::new());
@@ -232,32 +909,371 @@ mod tests {
     #[test]
     fn remove_vertex_removes_all_incident_edges() {
         let mut g = Graph::new();
-        let v1 = Vertex { id: 1 };
-        let v2 = Vertex { id: 2 };
-        let v3 = Vertex { id: 3 };
-        let e1 = Edge {
-            id: 1,
-            source: v1.id,
-            target: v2.id,
-        };
-        let e2 = Edge {
-            id: 2,
-            source: v1.id,
-            target: v3.id,
-        };
+        let v1 = Vertex::new(1);
+        let v2 = Vertex::new(2);
+        let v3 = Vertex::new(3);
+        let e1 = Edge::new(1, v1.id, v2.id);
+        let e2 = Edge::new(2, v1.id, v3.id);
 
-        g.add_vertex(v1);
-        g.add_vertex(v2);
-        g.add_vertex(v3);
-        g.add_edge(e1);
-        g.add_edge(e2);
+        g.add_vertex(v1.clone());
+        g.add_vertex(v2.clone());
+        g.add_vertex(v3.clone());
+        g.add_edge(e1.clone());
+        g.add_edge(e2.clone());
 
-        assert_eq!(HashMap::from([(1, v1), (2, v2), (3, v3)]), g.vertices);
+        assert_eq!(
+            HashMap::from([(1, v1.clone()), (2, v2.clone()), (3, v3.clone())]),
+            g.vertices
+        );
         assert_eq!(HashMap::from([(1, e1), (2, e2)]), g.edges);
 
-        g.remove_vertex(v1);
+        g.remove_vertex(v1.clone());
 
         assert_eq!(HashMap::from([(2, v2), (3, v3)]), g.vertices);
         assert_eq!(HashMap::new(), g.edges);
     }
+
+    #[test]
+    fn try_apply_rejects_no_ops() {
+        let mut g = Graph::new();
+        let v1 = Vertex::new(1);
+        let e1 = Edge::new(1, 1, 2);
+
+        assert!(g.try_apply(AddVertex(v1.clone())).is_ok());
+        assert_eq!(
+            Err(GraphError::DuplicateVertex(1)),
+            g.try_apply(AddVertex(v1.clone())).map(|_| ())
+        );
+        assert_eq!(
+            Err(GraphError::UnknownVertex(2)),
+            g.try_apply(AddEdge(e1.clone())).map(|_| ())
+        );
+        assert_eq!(
+            Err(GraphError::UnknownEdge(1)),
+            g.try_apply(RemoveEdge(e1.clone())).map(|_| ())
+        );
+        assert_eq!(
+            Err(GraphError::UnknownVertex(3)),
+            g.try_apply(RemoveVertex(Vertex::new(3))).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn try_apply_all_is_atomic() {
+        let mut g = Graph::new();
+        g.add_vertex(Vertex::new(1));
+        let before = g.clone();
+
+        let result = g.try_apply_all(vec![
+            AddVertex(Vertex::new(2)),
+            AddEdge(Edge::new(1, 1, 2)),
+            AddVertex(Vertex::new(1)),
+        ]);
+
+        assert_eq!(Err(GraphError::DuplicateVertex(1)), result.map(|_| ()));
+        assert_eq!(before, g);
+    }
+
+    #[test]
+    fn check_invariants_reports_violations() {
+        let mut g = Graph::new();
+        g.add_vertex(Vertex::new(1));
+        g.add_vertex(Vertex::new(2));
+        g.add_edge(Edge::new(1, 1, 2));
+        assert!(g.check_invariants().is_ok());
+
+        // Corrupt the graph behind its back.
+        g.vertices.remove(&2);
+        g.vertices.insert(3, Vertex::new(4));
+
+        assert_eq!(
+            vec![
+                InvariantViolation::VertexIdMismatch { key: 3, id: 4 },
+                InvariantViolation::DanglingEdge { edge: 1, vertex: 2 },
+            ],
+            g.check_invariants().violations
+        );
+    }
+
+    #[test]
+    fn add_edge_does_not_replace_existing_edge() {
+        let mut g = Graph::new();
+        let e1 = Edge::new(1, 1, 2);
+        g.add_vertex(Vertex::new(1));
+        g.add_vertex(Vertex::new(2));
+        g.add_edge(e1.clone());
+
+        let diff = g.add_edge(Edge::new(1, 2, 1));
+
+        assert!(diff.operations.is_empty());
+        assert_eq!(HashMap::from([(1, e1)]), g.edges);
+    }
+
+    fn path_graph() -> Graph {
+        // 1 -> 2 -> 3, plus 4 -> 2
+        let mut g = Graph::new();
+        for id in 1..=4 {
+            g.add_vertex(Vertex::new(id));
+        }
+        for (id, source, target) in [(1, 1, 2), (2, 2, 3), (3, 4, 2)].iter() {
+            g.add_edge(Edge::new(*id, *source, *target));
+        }
+        g
+    }
+
+    #[test]
+    fn coalesce_combines_consecutive_modifications() {
+        let e = Edge::new(0, 1, 2);
+        let weighted = |w: &str| e.clone().with_attribute("weight", w);
+        let modify = |old: Edge, new: Edge| Diff {
+            operations: vec![GraphOperation::ModifyEdge { old, new }],
+        };
+
+        let coalesced =
+            modify(e.clone(), weighted("2")).coalesce(modify(weighted("2"), weighted("3")));
+        assert_eq!(
+            vec![GraphOperation::ModifyEdge {
+                old: e.clone(),
+                new: weighted("3")
+            }],
+            coalesced.operations
+        );
+
+        let cancelled = coalesced.coalesce(modify(weighted("3"), e.clone()));
+        assert!(cancelled.operations.is_empty());
+
+        let added = Diff {
+            operations: vec![GraphOperation::AddEdge(e.clone())],
+        };
+        assert_eq!(
+            2,
+            added
+                .coalesce(modify(e.clone(), weighted("2")))
+                .operations
+                .len()
+        );
+    }
+
+    #[test]
+    fn difference_turns_one_graph_into_another() {
+        let before = path_graph();
+        let mut after = path_graph();
+        after.apply(AddVertex(Vertex::new(5).with_attribute("label", "new")));
+        // Repoint every edge of vertex 2 that survives its removal.
+        for (id, source, target) in [(1, 1, 5), (3, 4, 5)].iter() {
+            after.apply(ModifyEdge {
+                old: Edge::new(*id, 0, 0),
+                new: Edge::new(*id, *source, *target),
+            });
+        }
+        after.apply(RemoveVertex(Vertex::new(2)));
+        after.apply(AddEdge(Edge::new(7, 5, 3)));
+        assert_eq!(3, after.edges.len());
+
+        let diff = before.difference(&after);
+        let mut g = before.clone();
+        assert_eq!(
+            diff.operations,
+            g.try_apply_all(diff.operations.clone()).unwrap().operations
+        );
+        assert_eq!(after, g);
+        assert!(after.difference(&g).operations.is_empty());
+    }
+
+    #[test]
+    fn remove_vertex_with_edges_can_be_undone() {
+        let mut g = path_graph();
+        let before = g.clone();
+        let diff = g.remove_vertex(Vertex::new(2));
+        assert_eq!(3, g.vertices.len());
+        g.apply_all(diff.invert().operations);
+        assert_eq!(before, g);
+    }
+
+    #[test]
+    fn modify_and_remove_restore_attributes_on_undo() {
+        let mut g = Graph::new();
+        let v1 = Vertex::new(1).with_attribute("label", "a");
+        g.add_vertex(v1.clone());
+        let original = g.clone();
+
+        let modified = g.apply(ModifyVertex {
+            old: v1.clone(),
+            new: v1.clone().with_attribute("label", "b"),
+        });
+        assert_eq!(Some("b"), g.vertices[&1].attribute("label"));
+        g.apply_all(modified.invert().operations);
+        assert_eq!(original, g);
+
+        // Removing a vertex by id alone still records its attributes.
+        let removed = g.apply(RemoveVertex(Vertex::new(1)));
+        g.apply_all(removed.invert().operations);
+        assert_eq!(original, g);
+    }
+
+    #[test]
+    fn induced_subgraph_keeps_edges_among_selected_vertices() {
+        let g = path_graph();
+
+        let sub = g.induced_subgraph(vec![1, 2, 5]);
+
+        assert_eq!(
+            HashMap::from([(1, Vertex::new(1)), (2, Vertex::new(2))]),
+            sub.vertices
+        );
+        assert_eq!(HashMap::from([(1, Edge::new(1, 1, 2))]), sub.edges);
+    }
+
+    #[test]
+    fn subgraph_by_predicate() {
+        let mut g = path_graph();
+        g.modify_vertex(Vertex::new(3).with_attribute("color", "red"));
+        g.modify_vertex(Vertex::new(2).with_attribute("color", "red"));
+
+        let sub = g.subgraph_by(|v| v.attribute("color") == Some("red"));
+
+        assert_eq!(
+            HashSet::from([2, 3]),
+            sub.vertices.keys().copied().collect::<HashSet<i64>>()
+        );
+        assert_eq!(HashMap::from([(2, Edge::new(2, 2, 3))]), sub.edges);
+    }
+
+    #[test]
+    fn diff_summary_counts_and_describes_changes() {
+        let mut g = path_graph();
+        let diff = g.apply_all(vec![
+            AddVertex(Vertex::new(5)),
+            AddEdge(Edge::new(4, 5, 1)),
+            RemoveVertex(Vertex::new(2)),
+        ]);
+        let summary = diff.summary();
+        assert_eq!(1, summary.added_vertices);
+        assert_eq!(3, summary.removed_edges);
+        assert_eq!(
+            "added 1 vertex, removed 1 vertex, added 1 edge, removed 3 edges",
+            summary.to_string()
+        );
+        let empty = Diff {
+            operations: Vec::new(),
+        };
+        assert_eq!("no changes", empty.summary().to_string());
+    }
+
+    #[test]
+    fn filtered_by_vertex_and_edge_predicates() {
+        let g = path_graph();
+
+        let sub = g.filtered(|v| v.id != 4, |e| e.id != 2);
+
+        assert_eq!(3, sub.vertices.len());
+        assert_eq!(HashMap::from([(1, Edge::new(1, 1, 2))]), sub.edges);
+    }
+
+    #[test]
+    fn merge_vertices_rewires_incident_edges() {
+        let mut g = path_graph();
+        let original = g.clone();
+
+        let diff = g.apply(MergeVertices {
+            keep: Vertex::new(3),
+            remove: Vertex::new(2),
+        });
+
+        assert_eq!(
+            HashMap::from([
+                (1, Vertex::new(1)),
+                (3, Vertex::new(3)),
+                (4, Vertex::new(4))
+            ]),
+            g.vertices
+        );
+        assert_eq!(
+            HashMap::from([
+                (1, Edge::new(1, 1, 3)),
+                (2, Edge::new(2, 3, 3)),
+                (3, Edge::new(3, 4, 3)),
+            ]),
+            g.edges
+        );
+
+        g.apply_all(diff.invert().operations);
+        assert_eq!(original, g);
+    }
+
+    #[test]
+    fn contract_edge_merges_target_into_source() {
+        let mut g = path_graph();
+        let original = g.clone();
+
+        let diff = g.apply(ContractEdge(Edge::new(1, 1, 2)));
+
+        assert_eq!(
+            HashMap::from([
+                (1, Vertex::new(1)),
+                (3, Vertex::new(3)),
+                (4, Vertex::new(4))
+            ]),
+            g.vertices
+        );
+        assert_eq!(
+            HashMap::from([(2, Edge::new(2, 1, 3)), (3, Edge::new(3, 4, 1)),]),
+            g.edges
+        );
+
+        // Only the diff the contraction produced can undo it.
+        assert_eq!(None, ContractEdge(Edge::new(1, 1, 2)).invert());
+        let by_hand = Diff {
+            operations: vec![ContractEdge(Edge::new(1, 1, 2))],
+        };
+        assert_eq!(0, by_hand.primitives().count());
+        assert!(by_hand.invert().operations.is_empty());
+        g.apply_all(diff.invert().operations);
+        assert_eq!(original, g);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_apply_all_matches_sequential_apply() {
+        let mut ops: Vec<GraphOperation> = (0..100).map(|id| AddVertex(Vertex::new(id))).collect();
+        ops.extend((0..99).map(|id| AddEdge(Edge::new(id, id, id + 1))));
+
+        let mut sequential = Graph::new();
+        let sequential_diff = sequential.apply_all(ops.clone());
+        let mut parallel = Graph::new();
+        let parallel_diff = parallel.par_apply_all(ops);
+
+        assert_eq!(sequential, parallel);
+        assert_eq!(sequential_diff.operations, parallel_diff.operations);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_apply_all_falls_back_on_conflicts() {
+        let v1 = Vertex::new(1);
+        let mut g = Graph::new();
+        g.add_vertex(v1.clone());
+
+        // Re-adding an existing vertex is a no-op and must not be reported in the diff.
+        let diff = g.par_apply_all(vec![AddVertex(v1.clone()), AddVertex(Vertex::new(2))]);
+
+        assert_eq!(vec![AddVertex(Vertex::new(2))], diff.operations);
+        assert_eq!(2, g.vertices.len());
+    }
+
+    #[cfg(all(feature = "serde", feature = "json"))]
+    #[test]
+    fn graphs_and_diffs_round_trip_through_serde() {
+        let mut g = path_graph();
+        g.properties.insert("name".to_string(), "path".to_string());
+        let diff = g.clone().apply(RemoveVertex(Vertex::new(2)));
+
+        let json = serde_json::to_string(&g).unwrap();
+        assert_eq!(g, serde_json::from_str::<Graph>(&json).unwrap());
+        let json = serde_json::to_string(&diff).unwrap();
+        assert_eq!(
+            diff.operations,
+            serde_json::from_str::<Diff>(&json).unwrap().operations
+        );
+    }
 }
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+
+use crate::graph::Edge;
+use crate::graph::Graph;
+use crate::graph::Vertex;
+
+/**
+ * Conversions to and from petgraph, so that its algorithms can be run on a document. Each node
+ * weight is the vertex and each edge weight is the edge, so that ids and attributes survive the
+ * round trip. Graph properties are not carried over.
+ */
+pub type PetGraph = petgraph::Graph<Vertex, Edge>;
+
+impl From<&Graph> for PetGraph {
+    // Vertices and edges are added in order of id, so node and edge indices follow id order.
+    fn from(graph: &Graph) -> PetGraph {
+        let mut vertices: Vec<&Vertex> = graph.vertices.values().collect();
+        vertices.sort_by_key(|v| v.id);
+        let mut edges: Vec<&Edge> = graph.edges.values().collect();
+        edges.sort_by_key(|e| e.id);
+
+        let mut converted = PetGraph::with_capacity(vertices.len(), edges.len());
+        let mut indices = HashMap::new();
+        for v in vertices {
+            indices.insert(v.id, converted.add_node(v.clone()));
+        }
+        for e in edges {
+            converted.add_edge(indices[&e.source], indices[&e.target], e.clone());
+        }
+        converted
+    }
+}
+
+impl From<&PetGraph> for Graph {
+    /**
+     * Edges connect the vertices petgraph says they do, whatever their weights' source and
+     * target, so results of restructuring algorithms come back as petgraph sees them. If several
+     * nodes or edges share an id, the first one is kept.
+     */
+    fn from(converted: &PetGraph) -> Graph {
+        let mut graph = Graph::new();
+        for v in converted.node_weights() {
+            graph.add_vertex(v.clone());
+        }
+        for edge in converted.edge_references() {
+            let mut e = edge.weight().clone();
+            e.source = converted[edge.source()].id;
+            e.target = converted[edge.target()].id;
+            graph.add_edge(e);
+        }
+        graph
+    }
+}
+
+// The index of each vertex's node, by vertex id, for calling algorithms that take a start node.
+pub fn node_indices(converted: &PetGraph) -> HashMap<i64, NodeIndex> {
+    converted
+        .node_indices()
+        .map(|index| (converted[index].id, index))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use petgraph::algo::dijkstra;
+    use petgraph::visit::Dfs;
+
+    fn network() -> Graph {
+        let mut g = Graph::new();
+        for id in [3, 1, 2, 7].iter() {
+            g.add_vertex(Vertex::new(*id).with_attribute("label", &format!("v{}", id)));
+        }
+        g.add_edge(Edge::new(0, 1, 2).with_attribute("weight", "2"));
+        g.add_edge(Edge::new(1, 2, 3).with_attribute("weight", "1"));
+        g.add_edge(Edge::new(4, 7, 1));
+        g
+    }
+
+    #[test]
+    fn round_trip() {
+        let g = network();
+        let converted = PetGraph::from(&g);
+        assert_eq!(1, converted[NodeIndex::new(0)].id);
+        assert_eq!(g, Graph::from(&converted));
+    }
+
+    #[test]
+    fn run_algorithms_and_bring_results_back() {
+        let converted = PetGraph::from(&network());
+        let indices = node_indices(&converted);
+
+        let distances = dijkstra(&converted, indices[&1], None, |e| {
+            e.weight()
+                .attribute("weight")
+                .map_or(1.0, |w| w.parse().unwrap())
+        });
+        assert_eq!(Some(&3.0), distances.get(&indices[&3]));
+
+        // The subgraph reachable from vertex 2.
+        let mut reachable = Vec::new();
+        let mut dfs = Dfs::new(&converted, indices[&2]);
+        while let Some(index) = dfs.next(&converted) {
+            reachable.push(index);
+        }
+        let subgraph = converted.filter_map(
+            |index, v| Some(v.clone()).filter(|_| reachable.contains(&index)),
+            |_, e| Some(e.clone()),
+        );
+        let back = Graph::from(&subgraph);
+        let mut ids: Vec<i64> = back.vertices.keys().copied().collect();
+        ids.sort_unstable();
+        assert_eq!(vec![2, 3], ids);
+        assert_eq!(vec![&1], back.edges.keys().collect::<Vec<_>>());
+    }
+}
@@ -1,3 +1,10 @@
-pub mod graph;
+pub mod anonymize;
+pub mod backend;
+pub mod computed;
 pub mod editor;
-
+pub mod format;
+pub mod graph;
+#[cfg(feature = "petgraph")]
+pub mod interop;
+pub mod schema;
+pub mod testkit;
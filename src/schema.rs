@@ -0,0 +1,240 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::graph::Attributes;
+use crate::graph::Graph;
+use crate::graph::GraphOperation;
+
+// Attribute values are stored as strings; a schema records which values are meaningful.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AttributeType {
+    Integer,
+    Float,
+    Boolean,
+    Text,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ElementKind {
+    Vertex,
+    Edge,
+}
+
+impl AttributeType {
+    // The most specific type that describes the value.
+    pub fn of(value: &str) -> AttributeType {
+        if value.parse::<i64>().is_ok() {
+            AttributeType::Integer
+        } else if value.parse::<f64>().is_ok() {
+            AttributeType::Float
+        } else if value == "true" || value == "false" {
+            AttributeType::Boolean
+        } else {
+            AttributeType::Text
+        }
+    }
+
+    pub fn accepts(self, value: &str) -> bool {
+        self.unify(AttributeType::of(value)) == self
+    }
+
+    // The most specific type that describes values of both types.
+    pub fn unify(self, other: AttributeType) -> AttributeType {
+        use AttributeType::*;
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Integer, Float) | (Float, Integer) => Float,
+            _ => Text,
+        }
+    }
+}
+
+impl fmt::Display for AttributeType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            AttributeType::Integer => "integer",
+            AttributeType::Float => "float",
+            AttributeType::Boolean => "boolean",
+            AttributeType::Text => "text",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for ElementKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ElementKind::Vertex => write!(f, "vertex"),
+            ElementKind::Edge => write!(f, "edge"),
+        }
+    }
+}
+
+/**
+ * A Schema declares the type of each attribute, separately for vertices and edges. Attributes it
+ * does not mention are unconstrained.
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Schema {
+    pub vertex_attributes: BTreeMap<String, AttributeType>,
+    pub edge_attributes: BTreeMap<String, AttributeType>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    pub kind: ElementKind,
+    pub id: i64,
+    pub key: String,
+    pub value: String,
+    pub expected: AttributeType,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Attribute {} of {} {} must be {}, but was '{}'",
+            self.key, self.kind, self.id, self.expected, self.value
+        )
+    }
+}
+
+impl Error for SchemaError {}
+
+impl Schema {
+    // Derive the narrowest schema that every attribute currently in the graph conforms to.
+    pub fn infer(graph: &Graph) -> Schema {
+        let mut schema = Schema::default();
+        for v in graph.vertices.values() {
+            Schema::widen(&mut schema.vertex_attributes, &v.attributes);
+        }
+        for e in graph.edges.values() {
+            Schema::widen(&mut schema.edge_attributes, &e.attributes);
+        }
+        schema
+    }
+
+    fn widen(types: &mut BTreeMap<String, AttributeType>, attributes: &Attributes) {
+        for (key, value) in attributes.iter() {
+            let observed = AttributeType::of(value);
+            types
+                .entry(key.clone())
+                .and_modify(|t| *t = t.unify(observed))
+                .or_insert(observed);
+        }
+    }
+
+    pub fn attributes(&self, kind: ElementKind) -> &BTreeMap<String, AttributeType> {
+        match kind {
+            ElementKind::Vertex => &self.vertex_attributes,
+            ElementKind::Edge => &self.edge_attributes,
+        }
+    }
+
+    // Check that every attribute an operation introduces conforms to the schema.
+    pub fn validate(&self, operation: &GraphOperation) -> Result<(), SchemaError> {
+        match operation {
+            GraphOperation::AddVertex(v) | GraphOperation::ModifyVertex { new: v, .. } => {
+                self.validate_attributes(ElementKind::Vertex, v.id, &v.attributes)
+            }
+            GraphOperation::AddEdge(e) | GraphOperation::ModifyEdge { new: e, .. } => {
+                self.validate_attributes(ElementKind::Edge, e.id, &e.attributes)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_attributes(
+        &self,
+        kind: ElementKind,
+        id: i64,
+        attributes: &Attributes,
+    ) -> Result<(), SchemaError> {
+        let types = self.attributes(kind);
+        for (key, value) in attributes.iter() {
+            if let Some(expected) = types.get(key) {
+                if !expected.accepts(value) {
+                    return Err(SchemaError {
+                        kind,
+                        id,
+                        key: key.clone(),
+                        value: value.clone(),
+                        expected: *expected,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Edge;
+    use crate::graph::Vertex;
+
+    fn imported_graph() -> Graph {
+        let mut g = Graph::new();
+        g.add_vertex(
+            Vertex::new(1)
+                .with_attribute("label", "a")
+                .with_attribute("size", "3"),
+        );
+        g.add_vertex(Vertex::new(2).with_attribute("size", "2.5"));
+        g.add_edge(
+            Edge::new(1, 1, 2)
+                .with_attribute("weight", "4")
+                .with_attribute("directed", "true"),
+        );
+        g
+    }
+
+    #[test]
+    fn infer_unifies_attribute_types() {
+        let schema = Schema::infer(&imported_graph());
+
+        assert_eq!(
+            BTreeMap::from([
+                ("label".to_string(), AttributeType::Text),
+                ("size".to_string(), AttributeType::Float),
+            ]),
+            schema.vertex_attributes
+        );
+        assert_eq!(
+            BTreeMap::from([
+                ("directed".to_string(), AttributeType::Boolean),
+                ("weight".to_string(), AttributeType::Integer),
+            ]),
+            schema.edge_attributes
+        );
+    }
+
+    #[test]
+    fn validate_rejects_mistyped_attributes() {
+        let schema = Schema::infer(&imported_graph());
+
+        let ok = GraphOperation::AddVertex(Vertex::new(3).with_attribute("size", "7"));
+        assert_eq!(Ok(()), schema.validate(&ok));
+
+        let unconstrained =
+            GraphOperation::AddVertex(Vertex::new(3).with_attribute("color", "red"));
+        assert_eq!(Ok(()), schema.validate(&unconstrained));
+
+        let bad = GraphOperation::AddEdge(Edge::new(2, 1, 2).with_attribute("weight", "heavy"));
+        assert_eq!(
+            Err(SchemaError {
+                kind: ElementKind::Edge,
+                id: 2,
+                key: "weight".to_string(),
+                value: "heavy".to_string(),
+                expected: AttributeType::Integer,
+            }),
+            schema.validate(&bad)
+        );
+    }
+}
@@ -0,0 +1,215 @@
+// Helpers for testing frontends and plugins against the editor: scripting input, recording
+// events, and comparing documents and history trees in a readable form. Like the backend
+// assertions, the assertions here panic with a description of the mismatch.
+use std::sync::mpsc::Receiver;
+
+use indextree::NodeId;
+
+use crate::editor::event::EditorEvent;
+use crate::editor::history::History;
+use crate::editor::keys::ENTER;
+use crate::editor::keys::ESC;
+use crate::editor::state::EditorState;
+use crate::editor::state::Input;
+use crate::graph::Attributes;
+use crate::graph::Graph;
+
+/**
+ * Parse a script of keys, written as the keys themselves except for special keys, which are
+ * named in angle brackets as in vim mappings: `<Esc>`, `<CR>` (or `<Enter>`) and `<lt>` for `<`
+ * itself. For example, `ie0,1<CR><Esc>` creates an edge from 0 to 1 and returns to Command mode.
+ */
+pub fn keys(script: &str) -> Vec<Input> {
+    let mut inputs = Vec::new();
+    let mut rest = script;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            if let Some(end) = rest.find('>') {
+                let key = match &rest[1..end] {
+                    "Esc" => Some(ESC),
+                    "CR" | "Enter" => Some(ENTER),
+                    "lt" => Some('<'),
+                    _ => None,
+                };
+                if let Some(key) = key {
+                    inputs.push(Input::Key(key));
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+            }
+        }
+        inputs.push(Input::Key(c));
+        rest = &rest[c.len_utf8()..];
+    }
+    inputs
+}
+
+// Evaluate every key of a script, as written for `keys`.
+pub fn type_keys(state: &mut EditorState, script: &str) {
+    for input in keys(script) {
+        state.evaluate(input);
+    }
+}
+
+// Collects the events an editor emits from the moment it is created.
+pub struct EventRecorder {
+    receiver: Receiver<EditorEvent>,
+}
+
+impl EventRecorder {
+    pub fn new(state: &mut EditorState) -> EventRecorder {
+        EventRecorder {
+            receiver: state.subscribe(),
+        }
+    }
+
+    // The events emitted since the last call, oldest first.
+    pub fn drain(&self) -> Vec<EditorEvent> {
+        self.receiver.try_iter().collect()
+    }
+
+    // Assert that exactly these events were emitted since the last call.
+    pub fn assert_emitted(&self, expected: &[EditorEvent]) {
+        let actual = self.drain();
+        assert_eq!(expected, actual.as_slice(), "Unexpected events");
+    }
+}
+
+fn write_attributes(out: &mut String, attributes: &Attributes) {
+    for (key, value) in attributes.iter() {
+        out.push_str(&format!(" {}={:?}", key, value));
+    }
+}
+
+/**
+ * The document as canonical text, one line per element in order of id, which stays the same
+ * however the document was built:
+ *
+ *   graph name="cities"
+ *   v0 label="a"
+ *   e0 0->1 weight="2"
+ *
+ * The graph line is omitted if the graph has no properties.
+ */
+pub fn snapshot(graph: &Graph) -> String {
+    let mut out = String::new();
+    if !graph.properties.is_empty() {
+        out.push_str("graph");
+        write_attributes(&mut out, &graph.properties);
+        out.push('\n');
+    }
+    let mut vertices: Vec<_> = graph.vertices.values().collect();
+    vertices.sort_by_key(|v| v.id);
+    for v in vertices {
+        out.push_str(&format!("v{}", v.id));
+        write_attributes(&mut out, &v.attributes);
+        out.push('\n');
+    }
+    let mut edges: Vec<_> = graph.edges.values().collect();
+    edges.sort_by_key(|e| e.id);
+    for e in edges {
+        out.push_str(&format!("e{} {}->{}", e.id, e.source, e.target));
+        write_attributes(&mut out, &e.attributes);
+        out.push('\n');
+    }
+    out
+}
+
+// Assert that the editor's document has the snapshot, ignoring indentation and blank lines.
+pub fn assert_snapshot(state: &EditorState, expected: &str) {
+    let normalize = |text: &str| {
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let actual = snapshot(state.document());
+    assert_eq!(
+        normalize(expected),
+        normalize(&actual),
+        "Document snapshot differs"
+    );
+}
+
+/**
+ * The shape of a history tree, ignoring what each edit did: every node is written as its children
+ * in parentheses, in time order, and the current node is marked with `*`. For example, `(()*)` is
+ * a single edit, which is current, and `(()(*))` is two edits made after the root, where the
+ * second has a child that is current.
+ */
+pub fn history_shape(history: &History) -> String {
+    fn write(history: &History, node: NodeId, out: &mut String) {
+        out.push('(');
+        for child in history.children(node) {
+            write(history, child, out);
+        }
+        out.push(')');
+        if node == history.current() {
+            out.push('*');
+        }
+    }
+    let mut out = String::new();
+    write(history, history.root(), &mut out);
+    out
+}
+
+pub fn assert_history_shape(state: &EditorState, expected: &str) {
+    assert_eq!(
+        expected,
+        history_shape(state.history()),
+        "History tree shape differs"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::completion::Completion;
+
+    #[test]
+    fn keys_with_special_names() {
+        assert_eq!(
+            vec![
+                Input::Key('i'),
+                Input::Key(ESC),
+                Input::Key('<'),
+                Input::Key(ENTER),
+                Input::Key('<'),
+                Input::Key('x'),
+                Input::Key('>'),
+            ],
+            keys("i<Esc><lt><CR><x>")
+        );
+    }
+
+    #[test]
+    fn script_a_session() {
+        let mut state = EditorState::new();
+        let events = EventRecorder::new(&mut state);
+        type_keys(&mut state, "iv<Esc>iv<Esc>ie0,");
+        let completions = match events.drain().pop() {
+            Some(EditorEvent::Completions(completions)) => completions,
+            other => panic!("Unexpected event {:?}", other),
+        };
+        assert_eq!(
+            vec![1, 0],
+            completions
+                .iter()
+                .map(|c: &Completion| c.vertex)
+                .collect::<Vec<_>>()
+        );
+
+        type_keys(&mut state, "1<CR><Esc>uuiv");
+        events.drain();
+        assert_snapshot(
+            &state,
+            "
+            v0
+            v2
+            ",
+        );
+        assert_history_shape(&state, "(((())()*))");
+        events.assert_emitted(&[]);
+    }
+}